@@ -21,12 +21,15 @@
 
 use primal::is_prime;
 use std::cell::Cell;
+use std::cmp;
 use std::mem;
 use std::ptr;
 use sha3;
 use std::slice;
-use std::path::PathBuf;
-use std::io::{self, Read, Write};
+use std::thread;
+use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::io::{self, Read, Write, BufWriter};
 use std::fs::{self, File};
 
 use parking_lot::Mutex;
@@ -46,6 +49,8 @@ const NODE_BYTES: usize = 64;
 const MIX_WORDS: usize = ETHASH_MIX_BYTES / 4;
 const MIX_NODES: usize = MIX_WORDS / NODE_WORDS;
 const FNV_PRIME: u32 = 0x01000193;
+// Number of DAG nodes each generating thread computes in one round.
+const DAG_CHUNK_NODES: usize = 1 << 14;
 
 /// Computation result
 pub struct ProofOfWork {
@@ -158,6 +163,173 @@ impl Light {
 	}
 }
 
+/// Full dataset for an epoch, memory mapped from disk where supported.
+pub struct Full {
+	block_number: u64,
+	dataset: Dataset,
+}
+
+impl Full {
+	/// Path of the DAG file for the epoch with given seed hash.
+	pub fn file_path(seed_hash: H256) -> PathBuf {
+		let mut home = ::std::env::home_dir().unwrap();
+		home.push(".ethash");
+		home.push("full");
+		home.push(to_hex(&seed_hash));
+		home
+	}
+
+	/// Check if a complete DAG file for the block's epoch is on disk.
+	pub fn exists(block_number: u64) -> bool {
+		let path = Full::file_path(slow_get_seedhash(block_number));
+		fs::metadata(path).map(|m| m.len() == get_data_size(block_number) as u64).unwrap_or(false)
+	}
+
+	/// Load the DAG for the block's epoch from disk.
+	pub fn from_file(block_number: u64) -> io::Result<Full> {
+		let path = Full::file_path(slow_get_seedhash(block_number));
+		let dataset = try!(Dataset::load(&path, get_data_size(block_number)));
+		Ok(Full {
+			block_number: block_number,
+			dataset: dataset,
+		})
+	}
+
+	/// Generate the DAG for the epoch of the given light cache and write it to disk,
+	/// spreading the work over `threads` threads. Removes the DAG from two epochs before.
+	pub fn generate(light: Arc<Light>, threads: usize) -> io::Result<PathBuf> {
+		let block_number = light.block_number;
+		let path = Full::file_path(slow_get_seedhash(block_number));
+		if block_number >= ETHASH_EPOCH_LENGTH * 2 {
+			let deprecated = Full::file_path(slow_get_seedhash(block_number - ETHASH_EPOCH_LENGTH * 2));
+			if deprecated.exists() {
+				debug!(target: "ethash", "removing: {:?}", &deprecated);
+				try!(fs::remove_file(deprecated));
+			}
+		}
+
+		try!(fs::create_dir_all(path.parent().unwrap()));
+		// write under a temporary name so an interrupted run never leaves a DAG that looks complete.
+		let tmp_path = path.with_extension("tmp");
+		{
+			let mut file = BufWriter::new(try!(File::create(&tmp_path)));
+			let num_nodes = get_data_size(block_number) / NODE_BYTES;
+			let threads = cmp::max(threads, 1);
+			let mut start = 0;
+			let mut reported = 0;
+			while start < num_nodes {
+				let end = cmp::min(start + DAG_CHUNK_NODES * threads, num_nodes);
+				let per_thread = (end - start + threads - 1) / threads;
+				let workers: Vec<_> = (0..threads).map(|t| {
+					let light = light.clone();
+					let from = cmp::min(start + t * per_thread, end);
+					let to = cmp::min(from + per_thread, end);
+					thread::spawn(move || generate_dag_items(&light, from, to))
+				}).collect();
+				for worker in workers {
+					let nodes = try!(worker.join().map_err(|_| io::Error::new(io::ErrorKind::Other, "DAG generation thread panicked")));
+					let buf = unsafe { slice::from_raw_parts(nodes.as_ptr() as *const u8, nodes.len() * NODE_BYTES) };
+					try!(file.write_all(buf));
+				}
+				start = end;
+				let percent = start * 100 / num_nodes;
+				if percent >= reported + 10 {
+					reported = percent;
+					info!(target: "ethash", "Generating DAG for epoch {}: {}%", block_number / ETHASH_EPOCH_LENGTH, percent);
+				}
+			}
+			try!(file.flush());
+		}
+		try!(fs::rename(&tmp_path, &path));
+		Ok(path)
+	}
+
+	/// Calculate the boundary data using the full dataset
+	/// `header_hash` - The header hash to pack into the mix
+	/// `nonce` - The nonce to pack into the mix
+	pub fn compute(&self, header_hash: &H256, nonce: u64) -> ProofOfWork {
+		full_compute(self, header_hash, nonce)
+	}
+
+	/// Block number the dataset was loaded for.
+	pub fn block_number(&self) -> u64 {
+		self.block_number
+	}
+}
+
+enum Dataset {
+	#[cfg(unix)]
+	Mapped(Mmap),
+	#[cfg_attr(unix, allow(dead_code))]
+	Memory(Vec<Node>),
+}
+
+impl Dataset {
+	#[cfg(unix)]
+	fn load(path: &Path, size: usize) -> io::Result<Dataset> {
+		let file = try!(File::open(path));
+		if try!(file.metadata()).len() != size as u64 {
+			return Err(io::Error::new(io::ErrorKind::Other, "DAG file size mismatch"));
+		}
+		Mmap::open(&file, size).map(Dataset::Mapped)
+	}
+
+	#[cfg(not(unix))]
+	fn load(path: &Path, size: usize) -> io::Result<Dataset> {
+		let mut file = try!(File::open(path));
+		if try!(file.metadata()).len() != size as u64 {
+			return Err(io::Error::new(io::ErrorKind::Other, "DAG file size mismatch"));
+		}
+		let mut nodes: Vec<Node> = Vec::new();
+		nodes.resize(size / NODE_BYTES, Node::default());
+		let buf = unsafe { slice::from_raw_parts_mut(nodes.as_mut_ptr() as *mut u8, size) };
+		try!(file.read_exact(buf));
+		Ok(Dataset::Memory(nodes))
+	}
+
+	fn nodes(&self) -> &[Node] {
+		match *self {
+			#[cfg(unix)]
+			Dataset::Mapped(ref map) => unsafe { slice::from_raw_parts(map.ptr as *const Node, map.len / NODE_BYTES) },
+			Dataset::Memory(ref nodes) => nodes,
+		}
+	}
+}
+
+/// Read-only shared mapping of a whole file.
+#[cfg(unix)]
+struct Mmap {
+	ptr: *mut ::libc::c_void,
+	len: usize,
+}
+
+#[cfg(unix)]
+unsafe impl Send for Mmap {}
+#[cfg(unix)]
+unsafe impl Sync for Mmap {}
+
+#[cfg(unix)]
+impl Mmap {
+	fn open(file: &File, len: usize) -> io::Result<Mmap> {
+		use std::os::unix::io::AsRawFd;
+		let ptr = unsafe { ::libc::mmap(ptr::null_mut(), len, ::libc::PROT_READ, ::libc::MAP_SHARED, file.as_raw_fd(), 0) };
+		if ptr == ::libc::MAP_FAILED {
+			return Err(io::Error::last_os_error());
+		}
+		Ok(Mmap {
+			ptr: ptr,
+			len: len,
+		})
+	}
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+	fn drop(&mut self) {
+		unsafe { ::libc::munmap(self.ptr, self.len) };
+	}
+}
+
 pub struct SeedHashCompute {
 	prev_epoch: Cell<u64>,
 	prev_seedhash: Cell<H256>,
@@ -263,10 +435,20 @@ pub fn quick_get_difficulty(header_hash: &H256, nonce: u64, mix_hash: &H256) ->
 /// `nonce` - The nonce to pack into the mix
 pub fn light_compute(light: &Light, header_hash: &H256, nonce: u64) -> ProofOfWork {
 	let full_size = get_data_size(light.block_number);
-	hash_compute(light, full_size, header_hash, nonce)
+	let cache: &[Node] = &light.cache;  // deref once for better performance
+	hash_compute(full_size, header_hash, nonce, |index| calculate_dag_item(index, cache))
+}
+
+/// Calculate the boundary data with DAG items read from the full dataset
+/// `full` - The full dataset
+/// `header_hash` - The header hash to pack into the mix
+/// `nonce` - The nonce to pack into the mix
+pub fn full_compute(full: &Full, header_hash: &H256, nonce: u64) -> ProofOfWork {
+	let nodes = full.dataset.nodes();
+	hash_compute(nodes.len() * NODE_BYTES, header_hash, nonce, |index| unsafe { nodes.get_unchecked(index as usize).clone() })
 }
 
-fn hash_compute(light: &Light, full_size: usize, header_hash: &H256, nonce: u64) -> ProofOfWork {
+fn hash_compute<F>(full_size: usize, header_hash: &H256, nonce: u64, dag_item: F) -> ProofOfWork where F: Fn(u32) -> Node {
 	if full_size % MIX_WORDS != 0 {
 		panic!("Unaligned full size");
 	}
@@ -285,12 +467,11 @@ fn hash_compute(light: &Light, full_size: usize, header_hash: &H256, nonce: u64)
 
 		let page_size = 4 * MIX_WORDS;
 		let num_full_pages = (full_size / page_size) as u32;
-		let cache: &[Node] = &light.cache;  // deref once for better performance
 
 		for i in 0..(ETHASH_ACCESSES as u32) {
 			let index = fnv_hash(f_mix.get_unchecked(0).as_words().get_unchecked(0) ^ i, *mix.get_unchecked(0).as_words().get_unchecked((i as usize) % MIX_WORDS)) % num_full_pages;
 			for n in 0..MIX_NODES {
-				let tmp_node = calculate_dag_item(index * MIX_NODES as u32 + n as u32, cache);
+				let tmp_node = dag_item(index * MIX_NODES as u32 + n as u32);
 				for w in 0..NODE_WORDS {
 					*mix.get_unchecked_mut(n).as_words_mut().get_unchecked_mut(w) = fnv_hash(*mix.get_unchecked(n).as_words().get_unchecked(w), *tmp_node.as_words().get_unchecked(w));
 				}
@@ -341,6 +522,10 @@ fn calculate_dag_item(node_index: u32, cache: &[Node]) -> Node {
 	}
 }
 
+fn generate_dag_items(light: &Light, from: usize, to: usize) -> Vec<Node> {
+	(from..to).map(|i| calculate_dag_item(i as u32, &light.cache)).collect()
+}
+
 fn light_new(block_number: u64) -> Light {
 
 	let seed_compute = SeedHashCompute::new();
@@ -484,3 +669,38 @@ fn test_drop_old_data() {
 	let _ = Light::new(ETHASH_EPOCH_LENGTH * 3).to_file();
 	assert!(fs::metadata(&second).is_err());
 }
+
+#[test]
+fn test_full_compute_matches_light() {
+	let hash = [0xf5, 0x7e, 0x6f, 0x3a, 0xcf, 0xc0, 0xdd, 0x4b, 0x5b, 0xf2, 0xbe, 0xe4, 0x0a, 0xb3, 0x35, 0x8a, 0xa6, 0x87, 0x73, 0xa8, 0xd0, 0x9f, 0x5e, 0x59, 0x5e, 0xab, 0x55, 0x94, 0x05, 0x52, 0x7d, 0x72];
+	let nonce = 0xd7b3ac70a301a249;
+	let light = Light::new(0);
+	// a truncated dataset is enough to check that lookups agree with light computation.
+	let num_nodes = MIX_NODES * 1024;
+	let full = Full {
+		block_number: 0,
+		dataset: Dataset::Memory(generate_dag_items(&light, 0, num_nodes)),
+	};
+	let expected = hash_compute(num_nodes * NODE_BYTES, &hash, nonce, |index| calculate_dag_item(index, &light.cache));
+	let result = full.compute(&hash, nonce);
+	assert_eq!(result.mix_hash[..], expected.mix_hash[..]);
+	assert_eq!(result.value[..], expected.value[..]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_dataset_load() {
+	let light = Light::new(0);
+	let nodes = generate_dag_items(&light, 0, 256);
+	let path = ::std::env::temp_dir().join("ethash-test-dataset");
+	{
+		let mut file = File::create(&path).unwrap();
+		let buf = unsafe { slice::from_raw_parts(nodes.as_ptr() as *const u8, nodes.len() * NODE_BYTES) };
+		file.write_all(buf).unwrap();
+	}
+	assert!(Dataset::load(&path, 255 * NODE_BYTES).is_err());
+	let dataset = Dataset::load(&path, 256 * NODE_BYTES).unwrap();
+	assert_eq!(dataset.nodes().len(), 256);
+	assert_eq!(dataset.nodes()[255].bytes[..], nodes[255].bytes[..]);
+	fs::remove_file(&path).unwrap();
+}
@@ -19,18 +19,26 @@
 extern crate primal;
 extern crate sha3;
 extern crate parking_lot;
+extern crate libc;
 
 #[macro_use]
 extern crate log;
 mod compute;
 
+use std::io;
 use std::mem;
+use std::thread;
+use std::path::PathBuf;
+use std::collections::HashSet;
 use compute::Light;
-pub use compute::{ETHASH_EPOCH_LENGTH, H256, ProofOfWork, SeedHashCompute, quick_get_difficulty, slow_get_seedhash};
+pub use compute::{ETHASH_EPOCH_LENGTH, H256, ProofOfWork, SeedHashCompute, Full, quick_get_difficulty, slow_get_seedhash};
 
 use std::sync::Arc;
 use parking_lot::Mutex;
 
+/// Number of blocks before the end of an epoch when the DAG for the next one starts generating.
+pub const DAG_PRECOMPUTE_BLOCKS: u64 = 3000;
+
 struct LightCache {
 	recent_epoch: Option<u64>,
 	recent: Option<Arc<Light>>,
@@ -38,9 +46,25 @@ struct LightCache {
 	prev: Option<Arc<Light>>,
 }
 
+/// Generate the DAG for the block's epoch on the calling thread using `threads` worker threads
+/// and store it on disk. Returns the path of the DAG file.
+pub fn generate_dag(block_number: u64, threads: usize) -> io::Result<PathBuf> {
+	let light = Arc::new(Light::from_file(block_number).unwrap_or_else(|_| Light::new(block_number)));
+	Full::generate(light, threads)
+}
+
+struct FullCache {
+	/// Datasets of at most two adjacent epochs, so work on both sides of an epoch boundary doesn't remap them.
+	loaded: Vec<(u64, Arc<Full>)>,
+	/// Epochs for which no DAG file was found, so the disk isn't checked on every call.
+	missing: HashSet<u64>,
+}
+
 /// Light/Full cache manager.
 pub struct EthashManager {
 	cache: Mutex<LightCache>,
+	full: Arc<Mutex<FullCache>>,
+	generating: Arc<Mutex<HashSet<u64>>>,
 }
 
 impl EthashManager {
@@ -53,6 +77,82 @@ impl EthashManager {
 				prev_epoch: None,
 				prev: None,
 			}),
+			full: Arc::new(Mutex::new(FullCache {
+				loaded: Vec::new(),
+				missing: HashSet::new(),
+			})),
+			generating: Arc::new(Mutex::new(HashSet::new())),
+		}
+	}
+
+	/// Calculate the proof of work using the full dataset if its DAG file is on disk
+	/// and the light cache otherwise. Both give the same result.
+	/// `block_number` - Block number to check
+	/// `header_hash` - The header hash to pack into the mix
+	/// `nonce` - The nonce to pack into the mix
+	pub fn compute(&self, block_number: u64, header_hash: &H256, nonce: u64) -> ProofOfWork {
+		match self.full(block_number) {
+			Some(full) => full.compute(header_hash, nonce),
+			None => self.compute_light(block_number, header_hash, nonce),
+		}
+	}
+
+	/// Get the full dataset for the block's epoch if its DAG is on disk.
+	pub fn full(&self, block_number: u64) -> Option<Arc<Full>> {
+		let epoch = block_number / ETHASH_EPOCH_LENGTH;
+		let mut cache = self.full.lock();
+		if let Some(&(_, ref full)) = cache.loaded.iter().find(|&&(e, _)| e == epoch) {
+			return Some(full.clone());
+		}
+		if cache.missing.contains(&epoch) {
+			return None;
+		}
+		match Full::from_file(block_number) {
+			Ok(full) => {
+				let full = Arc::new(full);
+				cache.loaded.retain(|&(e, _)| e + 1 == epoch || e == epoch + 1);
+				if cache.loaded.len() > 1 {
+					cache.loaded.remove(0);
+				}
+				cache.loaded.push((epoch, full.clone()));
+				Some(full)
+			},
+			Err(e) => {
+				trace!(target: "ethash", "DAG not available for epoch {}: {}", epoch, e);
+				cache.missing.insert(epoch);
+				None
+			}
+		}
+	}
+
+	/// Make sure the DAG for the block's epoch gets generated in the background unless it's on disk already.
+	/// Within `DAG_PRECOMPUTE_BLOCKS` of the epoch end, the DAG for the next epoch is generated as well.
+	pub fn prepare_dag(&self, block_number: u64, threads: usize) {
+		let epoch = block_number / ETHASH_EPOCH_LENGTH;
+		self.generate_in_background(epoch, threads);
+		if block_number % ETHASH_EPOCH_LENGTH >= ETHASH_EPOCH_LENGTH - DAG_PRECOMPUTE_BLOCKS {
+			self.generate_in_background(epoch + 1, threads);
+		}
+	}
+
+	fn generate_in_background(&self, epoch: u64, threads: usize) {
+		let block_number = epoch * ETHASH_EPOCH_LENGTH;
+		if Full::exists(block_number) || !self.generating.lock().insert(epoch) {
+			return;
+		}
+		let generating = self.generating.clone();
+		let full = self.full.clone();
+		let spawned = thread::Builder::new().name(format!("ethash-dag-{}", epoch)).spawn(move || {
+			match generate_dag(block_number, threads) {
+				Ok(path) => info!(target: "ethash", "DAG for epoch {} written to {:?}", epoch, path),
+				Err(e) => warn!(target: "ethash", "DAG generation for epoch {} failed: {}", epoch, e),
+			}
+			full.lock().missing.remove(&epoch);
+			generating.lock().remove(&epoch);
+		});
+		if let Err(e) = spawned {
+			warn!(target: "ethash", "Unable to start DAG generation: {}", e);
+			self.generating.lock().remove(&epoch);
 		}
 	}
 
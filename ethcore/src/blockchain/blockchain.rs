@@ -31,7 +31,7 @@ use blockchain::best_block::BestBlock;
 use types::tree_route::TreeRoute;
 use blockchain::update::ExtrasUpdate;
 use blockchain::{CacheSize, ImportRoute, Config};
-use db::{self, Writable, Readable, CacheUpdatePolicy};
+use db::{self, Key, Writable, Readable, CacheUpdatePolicy};
 use cache_manager::CacheManager;
 
 const LOG_BLOOMS_LEVELS: usize = 3;
@@ -175,6 +175,9 @@ pub struct BlockChain {
 	blocks_blooms: RwLock<HashMap<LogGroupPosition, BloomGroup>>,
 	block_receipts: RwLock<HashMap<H256, BlockReceipts>>,
 
+	// number of the last block with pruned body and receipts
+	pruned_number: RwLock<Option<BlockNumber>>,
+
 	db: Arc<Database>,
 
 	cache_man: Mutex<CacheManager<CacheID>>,
@@ -182,6 +185,8 @@ pub struct BlockChain {
 	pending_best_block: RwLock<Option<BestBlock>>,
 	pending_block_hashes: RwLock<HashMap<BlockNumber, H256>>,
 	pending_transaction_addresses: RwLock<HashMap<H256, Option<TransactionAddress>>>,
+	// set when the chain was rewound and extras caches have to be cleared on commit
+	pending_rewind: RwLock<bool>,
 }
 
 impl BlockProvider for BlockChain {
@@ -223,7 +228,7 @@ impl BlockProvider for BlockChain {
 		// Check if it's the best block
 		{
 			let best_block = self.best_block.read();
-			if &best_block.hash == hash {
+			if &best_block.hash == hash && !best_block.block.is_empty() {
 				return Some(Rlp::new(&best_block.block).at(0).as_raw().to_vec());
 			}
 		}
@@ -259,7 +264,7 @@ impl BlockProvider for BlockChain {
 		// Check if it's the best block
 		{
 			let best_block = self.best_block.read();
-			if &best_block.hash == hash {
+			if &best_block.hash == hash && !best_block.block.is_empty() {
 				return Some(Self::block_to_body(&best_block.block));
 			}
 		}
@@ -388,6 +393,45 @@ impl<'a> Iterator for AncestryIter<'a> {
 impl BlockChain {
 	/// Create new instance of blockchain from given Genesis
 	pub fn new(config: Config, genesis: &[u8], db: Arc<Database>) -> BlockChain {
+		if db.get(db::COL_EXTRA, b"best").expect("Low level database error. Some issue with disk?").is_none() {
+			// best block does not exist
+			// we need to insert genesis into the database
+			let block = BlockView::new(genesis);
+			let header = block.header_view();
+			let hash = block.sha3();
+
+			let details = BlockDetails {
+				number: header.number(),
+				total_difficulty: header.difficulty(),
+				parent: header.parent_hash(),
+				children: vec![]
+			};
+
+			let mut batch = DBTransaction::new(&db);
+			batch.put(db::COL_HEADERS, &hash, block.header_rlp().as_raw());
+			batch.put(db::COL_BODIES, &hash, &Self::block_to_body(genesis));
+
+			batch.write(db::COL_EXTRA, &hash, &details);
+			batch.write(db::COL_EXTRA, &header.number(), &hash);
+
+			batch.put(db::COL_EXTRA, b"best", &hash);
+			batch.put(db::COL_EXTRA, b"first", &hash);
+			db.write(batch).expect("Low level database error. Some issue with disk?");
+		}
+
+		let bc = Self::open(config, db.clone()).expect("Best block was inserted or is in the database; qed");
+		if db.get(db::COL_EXTRA, b"first").expect("Low level database error. Some issue with disk?").is_none() {
+			let mut batch = db.transaction();
+			batch.put(db::COL_EXTRA, b"first", &bc.first_block);
+			db.write(batch).expect("Low level database error.");
+		}
+		bc
+	}
+
+	/// Opens the blockchain of an existing database without writing to it.
+	/// Missing data of the best block is tolerated, so that a damaged database can be checked
+	/// and repaired. Fails if the database contains no blockchain.
+	pub fn open(config: Config, db: Arc<Database>) -> Result<BlockChain, String> {
 		// 400 is the avarage size of the key
 		let cache_man = CacheManager::new(config.pref_cache_size, config.max_cache_size, 400);
 
@@ -405,91 +449,84 @@ impl BlockChain {
 			transaction_addresses: RwLock::new(HashMap::new()),
 			blocks_blooms: RwLock::new(HashMap::new()),
 			block_receipts: RwLock::new(HashMap::new()),
+			pruned_number: RwLock::new(None),
 			db: db.clone(),
 			cache_man: Mutex::new(cache_man),
 			pending_best_block: RwLock::new(None),
 			pending_block_hashes: RwLock::new(HashMap::new()),
 			pending_transaction_addresses: RwLock::new(HashMap::new()),
+			pending_rewind: RwLock::new(false),
 		};
 
 		// load best block
-		let best_block_hash = match bc.db.get(db::COL_EXTRA, b"best").unwrap() {
-			Some(best) => {
-				H256::from_slice(&best)
-			}
-			None => {
-				// best block does not exist
-				// we need to insert genesis into the cache
-				let block = BlockView::new(genesis);
-				let header = block.header_view();
-				let hash = block.sha3();
-
-				let details = BlockDetails {
-					number: header.number(),
-					total_difficulty: header.difficulty(),
-					parent: header.parent_hash(),
-					children: vec![]
-				};
-
-				let mut batch = DBTransaction::new(&db);
-				batch.put(db::COL_HEADERS, &hash, block.header_rlp().as_raw());
-				batch.put(db::COL_BODIES, &hash, &Self::block_to_body(genesis));
-
-				batch.write(db::COL_EXTRA, &hash, &details);
-				batch.write(db::COL_EXTRA, &header.number(), &hash);
-
-				batch.put(db::COL_EXTRA, b"best", &hash);
-				batch.put(db::COL_EXTRA, b"first", &hash);
-				bc.db.write(batch).expect("Low level database error. Some issue with disk?");
-				hash
-			}
+		let best_block_hash = match try!(db.get(db::COL_EXTRA, b"best")) {
+			Some(best) => H256::from_slice(&best),
+			None => return Err("Database contains no blockchain".into()),
 		};
 
-		{
-			// Fetch best block details
-			let best_block_number = bc.block_number(&best_block_hash).unwrap();
-			let best_block_total_difficulty = bc.block_details(&best_block_hash).unwrap().total_difficulty;
-			let best_block_rlp = bc.block(&best_block_hash).unwrap();
+		let best_block_details = bc.block_details(&best_block_hash);
+		let best_block_number = match (best_block_details.as_ref(), bc.block_header_data(&best_block_hash)) {
+			(Some(details), _) => details.number,
+			(None, Some(header)) => HeaderView::new(&header).number(),
+			(None, None) => bc.last_canonical_number(),
+		};
+		let best_block_rlp = bc.block(&best_block_hash);
+		if best_block_details.is_none() || best_block_rlp.is_none() {
+			warn!(target: "blockchain", "Data of the best block #{} ({}) is missing from the database", best_block_number, best_block_hash);
+		}
 
-			let raw_first = bc.db.get(db::COL_EXTRA, b"first").unwrap().map_or(Vec::new(), |v| v.to_vec());
+		let raw_first = try!(db.get(db::COL_EXTRA, b"first")).map_or(Vec::new(), |v| v.to_vec());
 
-			// binary search for the first block.
-			if raw_first.is_empty() {
-				let (mut f, mut hash) = (best_block_number, best_block_hash);
-				let mut l = 0;
+		// binary search for the first block.
+		if raw_first.is_empty() {
+			let (mut f, mut hash) = (best_block_number, best_block_hash);
+			let mut l = 0;
 
-				loop {
-					if l >= f { break; }
+			loop {
+				if l >= f { break; }
 
-					let step = (f - l) >> 1;
-					let m = l + step;
+				let step = (f - l) >> 1;
+				let m = l + step;
 
-					match bc.block_hash(m) {
-						Some(h) => { f = m; hash = h },
-						None => { l = m + 1 },
-					}
+				match bc.block_hash(m) {
+					Some(h) => { f = m; hash = h },
+					None => { l = m + 1 },
 				}
-
-				let mut batch = db.transaction();
-				batch.put(db::COL_EXTRA, b"first", &hash);
-				db.write(batch).expect("Low level database error.");
-
-				bc.first_block = hash;
-			} else {
-				bc.first_block = H256::from_slice(&raw_first);
 			}
 
-			// and write them
-			let mut best_block = bc.best_block.write();
-			*best_block = BestBlock {
-				number: best_block_number,
-				total_difficulty: best_block_total_difficulty,
-				hash: best_block_hash,
-				block: best_block_rlp,
-			};
+			bc.first_block = hash;
+		} else {
+			bc.first_block = H256::from_slice(&raw_first);
 		}
 
-		bc
+		*bc.pruned_number.write() = try!(db.get(db::COL_EXTRA, b"pruned")).map(|v| decode(&v));
+
+		*bc.best_block.write() = BestBlock {
+			number: best_block_number,
+			total_difficulty: best_block_details.map_or_else(U256::zero, |details| details.total_difficulty),
+			hash: best_block_hash,
+			block: best_block_rlp.unwrap_or_else(Vec::new),
+		};
+
+		Ok(bc)
+	}
+
+	/// Number of the last block in the canonical hash index, assuming the index has no gaps.
+	fn last_canonical_number(&self) -> BlockNumber {
+		let mut upper = 1;
+		while self.block_hash(upper).is_some() {
+			upper *= 2;
+		}
+		// the block at `lower` is known, the one at `upper` is not
+		let mut lower = upper / 2;
+		while upper - lower > 1 {
+			let middle = lower + (upper - lower) / 2;
+			match self.block_hash(middle).is_some() {
+				true => lower = middle,
+				false => upper = middle,
+			}
+		}
+		lower
 	}
 
 	/// Returns true if the given parent block has given child
@@ -501,52 +538,119 @@ impl BlockChain {
 	/// Rewind to a previous block
 	#[cfg(test)]
 	fn rewind(&self) -> Option<H256> {
-		use db::Key;
-		let mut batch =self.db.transaction();
-		// track back to the best block we have in the blocks database
-		if let Some(best_block_hash) = self.db.get(db::COL_EXTRA, b"best").unwrap() {
-			let best_block_hash = H256::from_slice(&best_block_hash);
-			if best_block_hash == self.genesis_hash() {
-				return None;
+		match self.best_block_number() {
+			0 => None,
+			number => {
+				let mut batch = self.db.transaction();
+				self.rewind_to(&mut batch, number - 1);
+				self.db.write(batch).unwrap();
+				self.commit();
+				Some(self.best_block_hash())
 			}
-			if let Some(extras) = self.db.read(db::COL_EXTRA, &best_block_hash) as Option<BlockDetails> {
-				type DetailsKey = Key<BlockDetails, Target=H264>;
-				batch.delete(db::COL_EXTRA, &(DetailsKey::key(&best_block_hash)));
-				let hash = extras.parent;
-				let range = extras.number as bc::Number .. extras.number as bc::Number;
-				let chain = bc::group::BloomGroupChain::new(self.blooms_config, self);
-				let changes = chain.replace(&range, vec![]);
-				for (k, v) in changes.into_iter() {
-					batch.write(db::COL_EXTRA, &LogGroupPosition::from(k), &BloomGroup::from(v));
-				}
-				batch.put(db::COL_EXTRA, b"best", &hash);
-
-				let best_block_total_difficulty = self.block_details(&hash).unwrap().total_difficulty;
-				let best_block_rlp = self.block(&hash).unwrap();
-
-				let mut best_block = self.best_block.write();
-				*best_block = BestBlock {
-					number: extras.number - 1,
-					total_difficulty: best_block_total_difficulty,
-					hash: hash,
-					block: best_block_rlp,
-				};
-				// update parent extras
-				if let Some(mut details) = self.db.read(db::COL_EXTRA, &hash) as Option<BlockDetails> {
-					details.children.clear();
-					batch.write(db::COL_EXTRA, &hash, &details);
+		}
+	}
+
+	/// Rewinds the canonical chain so that block `number` becomes the best block.
+	/// Block hashes, details, transaction addresses and log blooms of retracted blocks
+	/// are removed, their headers, bodies and receipts are kept.
+	/// Changes are added to `batch`; `commit` has to be called once it's written.
+	/// Returns hashes of retracted blocks, from the newest one.
+	pub fn rewind_to(&self, batch: &mut DBTransaction, number: BlockNumber) -> Vec<H256> {
+		type DetailsKey = Key<BlockDetails, Target=H264>;
+		type TransactionKey = Key<TransactionAddress, Target=H264>;
+		type HashKey = Key<H256, Target=BlockNumberKey>;
+
+		let best_number = self.best_block_number();
+		if number >= best_number {
+			return Vec::new();
+		}
+
+		let hash = match self.block_hash(number) {
+			Some(hash) => hash,
+			None => return Vec::new(),
+		};
+		let (total_difficulty, block) = match (self.block_details(&hash), self.block(&hash)) {
+			(Some(details), Some(block)) => (details.total_difficulty, block),
+			_ => return Vec::new(),
+		};
+
+		let mut retracted = Vec::new();
+		for n in (number + 1..best_number + 1).rev() {
+			batch.delete(db::COL_EXTRA, &(HashKey::key(&n)));
+			if let Some(retracted_hash) = self.block_hash(n) {
+				if let Some(body) = self.block_body(&retracted_hash) {
+					for tx_hash in BodyView::new(&body).transaction_hashes() {
+						batch.delete(db::COL_EXTRA, &(TransactionKey::key(&tx_hash)));
+					}
 				}
-				self.db.write(batch).expect("Writing to db failed");
-				self.block_details.write().clear();
-				self.block_hashes.write().clear();
-				self.block_headers.write().clear();
-				self.block_bodies.write().clear();
-				self.block_receipts.write().clear();
-				return Some(hash);
+				batch.delete(db::COL_EXTRA, &(DetailsKey::key(&retracted_hash)));
+				retracted.push(retracted_hash);
 			}
 		}
 
-		None
+		let range = (number + 1) as bc::Number..best_number as bc::Number;
+		let chain = bc::group::BloomGroupChain::new(self.blooms_config, self);
+		for (k, v) in chain.replace(&range, vec![]).into_iter() {
+			batch.write(db::COL_EXTRA, &LogGroupPosition::from(k), &BloomGroup::from(v));
+		}
+
+		// update new best block extras
+		if let Some(mut details) = self.block_details(&hash) {
+			details.children.retain(|child| !retracted.contains(child));
+			batch.write(db::COL_EXTRA, &hash, &details);
+		}
+		batch.put(db::COL_EXTRA, b"best", &hash);
+
+		*self.pending_best_block.write() = Some(BestBlock {
+			number: number,
+			total_difficulty: total_difficulty,
+			hash: hash,
+			block: block,
+		});
+		*self.pending_rewind.write() = true;
+		retracted
+	}
+
+	/// Rebuilds transaction addresses and log blooms of canonical blocks
+	/// from block `from` up to the best block.
+	/// Transaction addresses of blocks with pruned bodies are not restored.
+	pub fn rebuild_indexes(&self, from: BlockNumber) {
+		const BLOCKS_PER_BATCH: BlockNumber = 1024;
+
+		let best_number = self.best_block_number();
+		let mut start = from;
+		while start <= best_number {
+			let end = cmp::min(start + BLOCKS_PER_BATCH - 1, best_number);
+			let mut addresses = HashMap::new();
+			let mut blooms: Vec<bc::Bloom> = Vec::new();
+
+			for number in start..end + 1 {
+				let hash = self.block_hash(number).expect("Canonical blocks up to the best block are in database.");
+				if let Some(body) = self.block_body(&hash) {
+					for (i, tx_hash) in BodyView::new(&body).transaction_hashes().into_iter().enumerate() {
+						addresses.insert(tx_hash, TransactionAddress {
+							block_hash: hash.clone(),
+							index: i,
+						});
+					}
+				}
+				let header = self.block_header_data(&hash).expect("Canonical blocks up to the best block are in database.");
+				blooms.push(Bloom::from(HeaderView::new(&header).log_bloom()).into());
+			}
+
+			let range = start as bc::Number..end as bc::Number;
+			let chain = bc::group::BloomGroupChain::new(self.blooms_config, self);
+			let groups: HashMap<LogGroupPosition, BloomGroup> = chain.replace(&range, blooms).into_iter()
+				.map(|p| (From::from(p.0), From::from(p.1)))
+				.collect();
+
+			let mut batch = self.db.transaction();
+			batch.extend_with_cache(db::COL_EXTRA, &mut *self.transaction_addresses.write(), addresses, CacheUpdatePolicy::Remove);
+			batch.extend_with_cache(db::COL_EXTRA, &mut *self.blocks_blooms.write(), groups, CacheUpdatePolicy::Remove);
+			self.db.write(batch).expect("Low level database error. Some issue with disk?");
+
+			start = end + 1;
+		}
 	}
 
 	/// Returns a tree route between `from` and `to`, which is a tuple of:
@@ -883,6 +987,13 @@ impl BlockChain {
 
 	/// Apply pending insertion updates
 	pub fn commit(&self) {
+		// extras of retracted blocks are gone from the database
+		let rewound = mem::replace(&mut *self.pending_rewind.write(), false);
+		if rewound {
+			self.block_details.write().clear();
+			self.blocks_blooms.write().clear();
+		}
+
 		let mut pending_best_block = self.pending_best_block.write();
 		let mut pending_write_hashes = self.pending_block_hashes.write();
 		let mut pending_write_txs = self.pending_transaction_addresses.write();
@@ -895,6 +1006,11 @@ impl BlockChain {
 			*best_block = block;
 		}
 
+		if rewound {
+			write_hashes.clear();
+			write_txs.clear();
+		}
+
 		let pending_txs = mem::replace(&mut *pending_write_txs, HashMap::new());
 		let (retracted_txs, enacted_txs) = pending_txs.into_iter().partition::<HashMap<_, _>, _>(|&(_, ref value)| value.is_none());
 
@@ -1143,6 +1259,59 @@ impl BlockChain {
 		BlockView::new(&block.block).header_view().rlp().as_raw().to_vec()
 	}
 
+	/// Get the number of the most recent canonical block whose body and receipts were pruned.
+	/// Headers and details of all blocks are always kept.
+	pub fn pruned_block_number(&self) -> Option<BlockNumber> {
+		*self.pruned_number.read()
+	}
+
+	/// Returns true if body and receipts of canonical block with given number were pruned.
+	pub fn is_pruned(&self, number: BlockNumber) -> bool {
+		self.pruned_block_number().map_or(false, |pruned| number <= pruned)
+	}
+
+	/// Removes bodies, receipts and transaction addresses of canonical blocks up to and including `until`.
+	/// At most `max` blocks are removed at once, so history is pruned gradually.
+	/// Genesis block is never pruned. Returns the number of pruned blocks.
+	pub fn prune_ancient(&self, batch: &mut DBTransaction, until: BlockNumber, max: u64) -> u64 {
+		let first = match self.pruned_block_number() {
+			Some(pruned) => pruned + 1,
+			None => cmp::max(self.first_block_number(), 1),
+		};
+		if until < first || max == 0 || until >= self.best_block_number() {
+			return 0;
+		}
+
+		let last = cmp::min(until, first + max - 1);
+		let hashes: Vec<H256> = (first..last + 1).filter_map(|number| self.block_hash(number)).collect();
+		let transaction_hashes: Vec<H256> = hashes.iter()
+			.filter_map(|hash| self.block_body(hash))
+			.flat_map(|body| BodyView::new(&body).transaction_hashes())
+			.collect();
+
+		let mut block_bodies = self.block_bodies.write();
+		let mut block_receipts = self.block_receipts.write();
+		let mut transaction_addresses = self.transaction_addresses.write();
+		let mut pruned_number = self.pruned_number.write();
+		type ReceiptsKey = Key<BlockReceipts, Target=H264>;
+		type TransactionKey = Key<TransactionAddress, Target=H264>;
+		for hash in &hashes {
+			batch.delete(db::COL_BODIES, hash);
+			batch.delete(db::COL_EXTRA, &ReceiptsKey::key(hash));
+			block_bodies.remove(hash);
+			block_receipts.remove(hash);
+		}
+		for hash in &transaction_hashes {
+			batch.delete(db::COL_EXTRA, &TransactionKey::key(hash));
+			transaction_addresses.remove(hash);
+		}
+		batch.put(db::COL_EXTRA, b"pruned", &encode(&last));
+		*pruned_number = Some(last);
+
+		trace!(target: "blockchain", "Pruned bodies, receipts and transactions of blocks #{}..#{}", first, last);
+		last - first + 1
+	}
+
 	/// Get current cache size.
 	pub fn cache_size(&self) -> CacheSize {
 		CacheSize {
@@ -1972,6 +2141,58 @@ mod tests {
 		assert_eq!(bc.best_block_number(), 5);
 	}
 
+	#[test]
+	fn should_prune_ancient_bodies_and_receipts() {
+		// given
+		let mut canon_chain = ChainGenerator::default();
+		let mut finalizer = BlockFinalizer::default();
+		let genesis = canon_chain.generate(&mut finalizer).unwrap();
+		let transactions = (0..2).map(|nonce| Transaction {
+			nonce: nonce.into(),
+			gas_price: 0.into(),
+			gas: 100_000.into(),
+			action: Action::Create,
+			value: 100.into(),
+			data: vec![],
+		}.sign(&"".sha3())).collect::<Vec<_>>();
+		let mut blocks = vec![canon_chain.with_transaction(transactions[0].clone()).generate(&mut finalizer).unwrap()];
+		blocks.extend((0..2).map(|_| canon_chain.generate(&mut finalizer).unwrap()));
+		blocks.push(canon_chain.with_transaction(transactions[1].clone()).generate(&mut finalizer).unwrap());
+		let hashes = blocks.iter().map(|b| BlockView::new(b).header_view().sha3()).collect::<Vec<_>>();
+
+		let temp = RandomTempPath::new();
+		let db = new_db(temp.as_str());
+		let bc = BlockChain::new(Config::default(), &genesis, db.clone());
+		for block in &blocks {
+			insert_block(&db, &bc, block, vec![]);
+		}
+
+		// when
+		let mut batch = db.transaction();
+		let first = bc.prune_ancient(&mut batch, 3, 2);
+		let second = bc.prune_ancient(&mut batch, 3, 2);
+		db.write(batch).unwrap();
+
+		// then
+		assert_eq!(first, 2);
+		assert_eq!(second, 1);
+		assert_eq!(bc.pruned_block_number(), Some(3));
+		assert!(bc.is_pruned(1));
+		assert!(!bc.is_pruned(4));
+		assert!(bc.block_body(&hashes[0]).is_none());
+		assert!(bc.block_receipts(&hashes[2]).is_none());
+		assert!(bc.block_header(&hashes[2]).is_some());
+		assert!(bc.block_body(&hashes[3]).is_some());
+		assert!(bc.block_body(&bc.genesis_hash()).is_some());
+		assert!(bc.transaction_address(&transactions[0].hash()).is_none());
+		assert!(bc.transaction_address(&transactions[1].hash()).is_some());
+
+		// and after reopening
+		let bc = BlockChain::new(Config::default(), &genesis, db.clone());
+		assert_eq!(bc.pruned_block_number(), Some(3));
+		assert!(bc.transaction_address(&transactions[0].hash()).is_none());
+	}
+
 	#[test]
 	fn test_rewind() {
 		let mut canon_chain = ChainGenerator::default();
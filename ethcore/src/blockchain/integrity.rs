@@ -0,0 +1,369 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Canonical chain integrity check.
+
+use std::fmt;
+use util::{H256, U256};
+use header::BlockNumber;
+use views::{HeaderView, BodyView};
+use blockchain::{BlockChain, BlockProvider};
+
+/// Maximal number of index errors included in a report.
+const MAX_INDEX_ERRORS: usize = 1024;
+
+/// Inconsistency found in the canonical chain.
+#[derive(Debug, PartialEq, Clone)]
+pub enum IntegrityError {
+	/// There is no canonical hash for the block number.
+	MissingHash(BlockNumber),
+	/// Header of the canonical block is missing.
+	MissingHeader(BlockNumber, H256),
+	/// Header doesn't link to the previous canonical block.
+	BrokenLink(BlockNumber, H256),
+	/// Details of the canonical block are missing.
+	MissingDetails(BlockNumber, H256),
+	/// Details don't match the header or the total difficulty is invalid.
+	InvalidDetails(BlockNumber, H256),
+	/// Body of the canonical block is missing.
+	MissingBody(BlockNumber, H256),
+	/// Receipts of the canonical block are missing.
+	MissingReceipts(BlockNumber, H256),
+	/// Transaction address is missing or points to a different block. Contains the transaction hash.
+	InvalidTransactionAddress(BlockNumber, H256),
+	/// Log bloom of the block is missing in bloom groups.
+	MissingBloom(BlockNumber),
+	/// State of the best block is missing. Contains the state root.
+	MissingState(BlockNumber, H256),
+}
+
+impl IntegrityError {
+	/// Number of the block this error refers to.
+	pub fn block_number(&self) -> BlockNumber {
+		match *self {
+			IntegrityError::MissingHash(n) |
+			IntegrityError::MissingHeader(n, _) |
+			IntegrityError::BrokenLink(n, _) |
+			IntegrityError::MissingDetails(n, _) |
+			IntegrityError::InvalidDetails(n, _) |
+			IntegrityError::MissingBody(n, _) |
+			IntegrityError::MissingReceipts(n, _) |
+			IntegrityError::InvalidTransactionAddress(n, _) |
+			IntegrityError::MissingBloom(n) |
+			IntegrityError::MissingState(n, _) => n,
+		}
+	}
+
+	/// Returns true if the error can be fixed by rebuilding indexes, without rewinding the chain.
+	pub fn is_index_error(&self) -> bool {
+		match *self {
+			IntegrityError::InvalidTransactionAddress(..) | IntegrityError::MissingBloom(_) => true,
+			_ => false,
+		}
+	}
+}
+
+impl fmt::Display for IntegrityError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			IntegrityError::MissingHash(n) => write!(f, "#{}: canonical hash is missing", n),
+			IntegrityError::MissingHeader(n, ref hash) => write!(f, "#{} ({}): header is missing", n, hash),
+			IntegrityError::BrokenLink(n, ref hash) => write!(f, "#{} ({}): parent is not the previous canonical block", n, hash),
+			IntegrityError::MissingDetails(n, ref hash) => write!(f, "#{} ({}): block details are missing", n, hash),
+			IntegrityError::InvalidDetails(n, ref hash) => write!(f, "#{} ({}): block details are invalid", n, hash),
+			IntegrityError::MissingBody(n, ref hash) => write!(f, "#{} ({}): body is missing", n, hash),
+			IntegrityError::MissingReceipts(n, ref hash) => write!(f, "#{} ({}): receipts are missing", n, hash),
+			IntegrityError::InvalidTransactionAddress(n, ref hash) => write!(f, "#{}: transaction {} is not indexed", n, hash),
+			IntegrityError::MissingBloom(n) => write!(f, "#{}: log bloom is not indexed", n),
+			IntegrityError::MissingState(n, ref root) => write!(f, "#{}: state {} is missing", n, root),
+		}
+	}
+}
+
+/// Result of the integrity check.
+#[derive(Debug, PartialEq, Clone)]
+pub struct IntegrityReport {
+	/// Best block number at the time of the check.
+	pub best_block: BlockNumber,
+	/// Number of the last block up to which the chain is consistent.
+	/// Index errors do not count as inconsistencies here.
+	pub last_consistent: BlockNumber,
+	/// Errors found, ordered by block number.
+	pub errors: Vec<IntegrityError>,
+}
+
+impl IntegrityReport {
+	/// Returns true if no errors were found.
+	pub fn is_ok(&self) -> bool {
+		self.errors.is_empty()
+	}
+
+	/// Number of the first block with broken indexes.
+	pub fn first_index_error(&self) -> Option<BlockNumber> {
+		self.errors.iter().find(|e| e.is_index_error()).map(IntegrityError::block_number)
+	}
+}
+
+/// Walks the canonical chain from the first block to the best block and checks
+/// header links, total difficulty, presence of bodies and receipts,
+/// transaction addresses and log blooms. Bodies and receipts of pruned blocks are not checked.
+pub fn check(chain: &BlockChain) -> IntegrityReport {
+	let best_block = chain.best_block_number();
+	let first_block = chain.first_block_number();
+	let mut errors = Vec::new();
+	let mut index_errors = 0;
+	let mut parent: Option<(H256, U256)> = None;
+
+	for number in first_block..best_block + 1 {
+		if number % 100_000 == 0 && number > first_block {
+			info!(target: "integrity", "Checked {} of {} blocks", number, best_block);
+		}
+
+		let hash = match chain.block_hash(number) {
+			Some(hash) => hash,
+			None => {
+				errors.push(IntegrityError::MissingHash(number));
+				break;
+			}
+		};
+
+		let header = match chain.block_header_data(&hash) {
+			Some(header) => header,
+			None => {
+				errors.push(IntegrityError::MissingHeader(number, hash));
+				break;
+			}
+		};
+		let header = HeaderView::new(&header);
+		if header.sha3() != hash || header.number() != number {
+			errors.push(IntegrityError::MissingHeader(number, hash));
+			break;
+		}
+		if let Some((ref parent_hash, _)) = parent {
+			if header.parent_hash() != *parent_hash {
+				errors.push(IntegrityError::BrokenLink(number, hash));
+				break;
+			}
+		}
+
+		let details = match chain.block_details(&hash) {
+			Some(details) => details,
+			None => {
+				errors.push(IntegrityError::MissingDetails(number, hash));
+				break;
+			}
+		};
+		let valid_difficulty = match parent {
+			Some((_, ref parent_difficulty)) => details.total_difficulty == *parent_difficulty + header.difficulty(),
+			None => number != 0 || details.total_difficulty == header.difficulty(),
+		};
+		if details.number != number || details.parent != header.parent_hash() || !valid_difficulty {
+			errors.push(IntegrityError::InvalidDetails(number, hash));
+			break;
+		}
+
+		if !chain.is_pruned(number) {
+			let body = match chain.block_body(&hash) {
+				Some(body) => body,
+				None => {
+					errors.push(IntegrityError::MissingBody(number, hash));
+					break;
+				}
+			};
+			// receipts of the genesis block are never stored
+			if number != 0 && chain.block_receipts(&hash).is_none() {
+				errors.push(IntegrityError::MissingReceipts(number, hash));
+				break;
+			}
+
+			for (index, tx_hash) in BodyView::new(&body).transaction_hashes().into_iter().enumerate() {
+				let valid = chain.transaction_address(&tx_hash).map_or(false, |address| address.block_hash == hash && address.index == index);
+				if !valid && index_errors < MAX_INDEX_ERRORS {
+					index_errors += 1;
+					errors.push(IntegrityError::InvalidTransactionAddress(number, tx_hash));
+				}
+			}
+		}
+
+		let bloom = header.log_bloom();
+		if !bloom.is_zero() && !chain.blocks_with_bloom(&bloom, number, number).contains(&number) && index_errors < MAX_INDEX_ERRORS {
+			index_errors += 1;
+			errors.push(IntegrityError::MissingBloom(number));
+		}
+
+		parent = Some((hash, details.total_difficulty));
+	}
+
+	let last_consistent = match errors.iter().find(|e| !e.is_index_error()) {
+		Some(e) => e.block_number().saturating_sub(1),
+		None => best_block,
+	};
+
+	IntegrityReport {
+		best_block: best_block,
+		last_consistent: last_consistent,
+		errors: errors,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use util::{Database, DatabaseConfig, H264, Hashable};
+	use devtools::RandomTempPath;
+	use blockchain::{BlockProvider, BlockChain, Config};
+	use blockchain::extras::{BlockReceipts, BlockDetails, TransactionAddress};
+	use blockchain::generator::{ChainGenerator, ChainIterator, BlockFinalizer};
+	use transaction::{Transaction, Action};
+	use db::{self, Key};
+	use super::{check, IntegrityError};
+
+	fn new_db(path: &str) -> Arc<Database> {
+		Arc::new(Database::open(&DatabaseConfig::with_columns(db::NUM_COLUMNS), path).unwrap())
+	}
+
+	#[test]
+	fn should_find_missing_receipts_and_rewind() {
+		// given
+		let mut canon_chain = ChainGenerator::default();
+		let mut finalizer = BlockFinalizer::default();
+		let genesis = canon_chain.generate(&mut finalizer).unwrap();
+		let temp = RandomTempPath::new();
+		let db = new_db(temp.as_str());
+		let bc = BlockChain::new(Config::default(), &genesis, db.clone());
+		for _ in 0..3 {
+			let block = canon_chain.generate(&mut finalizer).unwrap();
+			let mut batch = db.transaction();
+			bc.insert_block(&mut batch, &block, vec![]);
+			db.write(batch).unwrap();
+			bc.commit();
+		}
+		assert!(check(&bc).is_ok());
+
+		type ReceiptsKey = Key<BlockReceipts, Target=H264>;
+		let hash = bc.block_hash(2).unwrap();
+		let mut batch = db.transaction();
+		batch.delete(db::COL_EXTRA, &(ReceiptsKey::key(&hash)));
+		db.write(batch).unwrap();
+		let bc = BlockChain::new(Config::default(), &genesis, db.clone());
+
+		// when
+		let report = check(&bc);
+
+		// then
+		assert_eq!(report.errors, vec![IntegrityError::MissingReceipts(2, hash.clone())]);
+		assert_eq!(report.best_block, 3);
+		assert_eq!(report.last_consistent, 1);
+
+		let mut batch = db.transaction();
+		assert_eq!(bc.rewind_to(&mut batch, report.last_consistent).len(), 2);
+		db.write(batch).unwrap();
+		bc.commit();
+		assert!(!bc.is_known(&hash));
+		assert_eq!(bc.block_hash(2), None);
+		assert_eq!(bc.best_block_number(), 1);
+		assert!(check(&bc).is_ok());
+	}
+
+	#[test]
+	fn should_open_chain_with_missing_best_block() {
+		// given
+		let mut canon_chain = ChainGenerator::default();
+		let mut finalizer = BlockFinalizer::default();
+		let genesis = canon_chain.generate(&mut finalizer).unwrap();
+		let temp = RandomTempPath::new();
+		let db = new_db(temp.as_str());
+		let bc = BlockChain::new(Config::default(), &genesis, db.clone());
+		for _ in 0..3 {
+			let block = canon_chain.generate(&mut finalizer).unwrap();
+			let mut batch = db.transaction();
+			bc.insert_block(&mut batch, &block, vec![]);
+			db.write(batch).unwrap();
+			bc.commit();
+		}
+
+		type DetailsKey = Key<BlockDetails, Target=H264>;
+		let hash = bc.best_block_hash();
+		let mut batch = db.transaction();
+		batch.delete(db::COL_HEADERS, &hash);
+		batch.delete(db::COL_EXTRA, &(DetailsKey::key(&hash)));
+		db.write(batch).unwrap();
+
+		// when
+		let bc = BlockChain::open(Config::default(), db.clone()).unwrap();
+		let report = check(&bc);
+
+		// then
+		assert_eq!(bc.best_block_number(), 3);
+		assert_eq!(report.errors, vec![IntegrityError::MissingHeader(3, hash)]);
+		assert_eq!(report.last_consistent, 2);
+
+		let mut batch = db.transaction();
+		bc.rewind_to(&mut batch, report.last_consistent);
+		db.write(batch).unwrap();
+		bc.commit();
+		assert!(check(&bc).is_ok());
+		assert_eq!(BlockChain::open(Config::default(), db.clone()).unwrap().best_block_number(), 2);
+	}
+
+	#[test]
+	fn should_rebuild_transaction_addresses() {
+		// given
+		let t = Transaction {
+			nonce: 0.into(),
+			gas_price: 0.into(),
+			gas: 100_000.into(),
+			action: Action::Create,
+			value: 100.into(),
+			data: vec![],
+		}.sign(&"".sha3());
+		let tx_hash = t.hash();
+
+		let mut canon_chain = ChainGenerator::default();
+		let mut finalizer = BlockFinalizer::default();
+		let genesis = canon_chain.generate(&mut finalizer).unwrap();
+		let block = canon_chain.with_transaction(t).generate(&mut finalizer).unwrap();
+		let temp = RandomTempPath::new();
+		let db = new_db(temp.as_str());
+		let bc = BlockChain::new(Config::default(), &genesis, db.clone());
+		let mut batch = db.transaction();
+		bc.insert_block(&mut batch, &block, vec![]);
+		db.write(batch).unwrap();
+		bc.commit();
+
+		type TransactionKey = Key<TransactionAddress, Target=H264>;
+		let mut batch = db.transaction();
+		batch.delete(db::COL_EXTRA, &(TransactionKey::key(&tx_hash)));
+		db.write(batch).unwrap();
+		let bc = BlockChain::new(Config::default(), &genesis, db.clone());
+
+		let report = check(&bc);
+		assert_eq!(report.errors, vec![IntegrityError::InvalidTransactionAddress(1, tx_hash.clone())]);
+		assert_eq!(report.last_consistent, 1);
+		assert_eq!(report.first_index_error(), Some(1));
+
+		// when
+		bc.rebuild_indexes(1);
+
+		// then
+		assert!(check(&bc).is_ok());
+		assert_eq!(bc.transaction_address(&tx_hash), Some(TransactionAddress {
+			block_hash: bc.block_hash(1).unwrap(),
+			index: 0,
+		}));
+	}
+}
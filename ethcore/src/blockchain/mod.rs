@@ -23,6 +23,7 @@ mod cache;
 mod config;
 pub mod extras;
 mod import_route;
+pub mod integrity;
 mod update;
 
 #[cfg(test)]
@@ -33,3 +34,4 @@ pub use self::cache::CacheSize;
 pub use self::config::Config;
 pub use types::tree_route::TreeRoute;
 pub use self::import_route::ImportRoute;
+pub use self::integrity::{IntegrityError, IntegrityReport};
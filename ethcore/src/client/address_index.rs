@@ -0,0 +1,312 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Index of canonical transactions by address.
+//!
+//! Entries are keyed by address, block number and transaction index, so all entries
+//! of a single address are stored next to each other, ordered from the oldest one.
+//!
+//! Blocks imported before the index was enabled are indexed in the background, from the
+//! most recent one down to genesis. The index is incomplete until that pass is finished.
+
+use std::sync::Arc;
+use util::{H256, Address, Database, DBTransaction, Mutex};
+use rlp::{RlpStream, Stream, UntrustedRlp, View, encode, decode};
+use header::BlockNumber;
+use blockchain::{BlockChain, BlockProvider};
+use views::BodyView;
+use transaction::{SignedTransaction, Action};
+use executive::contract_address;
+use types::address_transaction::AddressTransaction;
+use db;
+
+/// Transaction was sent by the address.
+const SENT: u8 = 1;
+/// Transaction was sent to the address.
+const RECEIVED: u8 = 2;
+/// Key of the number of old blocks which are not indexed yet.
+/// It is shorter than an address, so it never matches an address prefix.
+const UNINDEXED_KEY: &'static [u8] = b"unindexed";
+/// Maximal number of old blocks indexed in a single pass.
+const BACKFILL_BLOCKS: BlockNumber = 1000;
+
+fn key(address: &Address, number: BlockNumber, index: usize) -> H256 {
+	let mut key = H256::new();
+	key[0..20].copy_from_slice(address);
+	for i in 0..8 {
+		key[20 + i] = (number >> ((7 - i) * 8)) as u8;
+	}
+	for i in 0..4 {
+		key[28 + i] = (index >> ((3 - i) * 8)) as u8;
+	}
+	key
+}
+
+/// Returns addresses involved in the transaction together with their role.
+/// Contract creations are indexed under the created contract address.
+fn participants(transaction: &SignedTransaction) -> Vec<(Address, u8)> {
+	let sender = match transaction.sender() {
+		Ok(sender) => sender,
+		Err(_) => return Vec::new(),
+	};
+	let recipient = match transaction.action {
+		Action::Call(ref to) => to.clone(),
+		Action::Create => contract_address(&sender, &transaction.nonce),
+	};
+	match sender == recipient {
+		true => vec![(sender, SENT | RECEIVED)],
+		false => vec![(sender, SENT), (recipient, RECEIVED)],
+	}
+}
+
+/// Transactions index by sender and recipient address.
+pub struct AddressIndex {
+	db: Arc<Database>,
+	enabled: bool,
+	/// Blocks below this number are not indexed yet.
+	unindexed: Mutex<BlockNumber>,
+}
+
+impl AddressIndex {
+	/// Creates new address index. When the index is enabled for the first time, all blocks
+	/// up to `best_block` are left for the background pass.
+	pub fn new(enabled: bool, db: Arc<Database>, best_block: BlockNumber) -> Self {
+		let stored = db.get(db::COL_ADDRESS_INDEX, UNINDEXED_KEY)
+			.expect("Low level database error. Some issue with disk?")
+			.map(|bytes| decode::<BlockNumber>(&bytes));
+
+		let unindexed = match (enabled, stored) {
+			(true, Some(unindexed)) => unindexed,
+			(true, None) => Self::reset(&db, best_block + 1),
+			(false, Some(_)) => {
+				// blocks imported from now on are not indexed, so the index has to be rebuilt
+				// if it's ever enabled again.
+				let mut batch = DBTransaction::new(&db);
+				batch.delete(db::COL_ADDRESS_INDEX, UNINDEXED_KEY);
+				db.write(batch).expect("Low level database error. Some issue with disk?");
+				0
+			},
+			(false, None) => 0,
+		};
+
+		AddressIndex {
+			db: db,
+			enabled: enabled,
+			unindexed: Mutex::new(unindexed),
+		}
+	}
+
+	/// Removes entries left by a previous index and marks blocks below `unindexed` as not indexed.
+	fn reset(db: &Database, unindexed: BlockNumber) -> BlockNumber {
+		let mut batch = DBTransaction::new(db);
+		for (key, _) in db.iter(db::COL_ADDRESS_INDEX) {
+			batch.delete(db::COL_ADDRESS_INDEX, &key);
+		}
+		batch.put(db::COL_ADDRESS_INDEX, UNINDEXED_KEY, &encode(&unindexed));
+		db.write(batch).expect("Low level database error. Some issue with disk?");
+		unindexed
+	}
+
+	/// Returns true if transactions are indexed.
+	pub fn is_enabled(&self) -> bool {
+		self.enabled
+	}
+
+	/// Returns the number of old blocks which are not indexed yet.
+	pub fn unindexed(&self) -> BlockNumber {
+		match self.enabled {
+			true => *self.unindexed.lock(),
+			false => 0,
+		}
+	}
+
+	/// Indexes the next range of canonical blocks imported before the index was enabled.
+	/// Blocks without a body, e.g. the ones before a restored snapshot, are skipped.
+	/// Must not run concurrently with block import. Returns the number of blocks left.
+	pub fn backfill(&self, chain: &BlockChain) -> BlockNumber {
+		let mut unindexed = self.unindexed.lock();
+		if !self.enabled || *unindexed == 0 {
+			return 0;
+		}
+
+		let from = unindexed.saturating_sub(BACKFILL_BLOCKS);
+		let mut batch = DBTransaction::new(&self.db);
+		for number in from..*unindexed {
+			let block = chain.block_hash(number).and_then(|hash| chain.block_body(&hash).map(|body| (hash, body)));
+			if let Some((hash, body)) = block {
+				self.insert(&mut batch, number, &hash, &BodyView::new(&body).transactions());
+			}
+		}
+		batch.put(db::COL_ADDRESS_INDEX, UNINDEXED_KEY, &encode(&from));
+		self.db.write(batch).expect("Low level database error. Some issue with disk?");
+
+		*unindexed = from;
+		match from {
+			0 => info!(target: "client", "Finished indexing transactions by address"),
+			_ => debug!(target: "client", "Indexing transactions by address: {} blocks left", from),
+		}
+		from
+	}
+
+	/// Indexes transactions of a block which became canonical.
+	pub fn insert(&self, batch: &mut DBTransaction, number: BlockNumber, hash: &H256, transactions: &[SignedTransaction]) {
+		for (index, transaction) in transactions.iter().enumerate() {
+			let transaction_hash = transaction.hash();
+			for (address, flags) in participants(transaction) {
+				let mut s = RlpStream::new_list(3);
+				s.append(hash);
+				s.append(&transaction_hash);
+				s.append(&flags);
+				batch.put(db::COL_ADDRESS_INDEX, &key(&address, number, index), &s.out());
+			}
+		}
+	}
+
+	/// Removes transactions of a block retracted from the canonical chain.
+	pub fn remove(&self, batch: &mut DBTransaction, number: BlockNumber, transactions: &[SignedTransaction]) {
+		for (index, transaction) in transactions.iter().enumerate() {
+			for (address, _) in participants(transaction) {
+				batch.delete(db::COL_ADDRESS_INDEX, &key(&address, number, index));
+			}
+		}
+	}
+
+	/// Returns at most `limit` transactions of given address, skipping first `offset` of them.
+	/// Transactions are ordered from the oldest one.
+	pub fn transactions(&self, address: &Address, offset: usize, limit: usize) -> Vec<AddressTransaction> {
+		self.db.iter_from_prefix(db::COL_ADDRESS_INDEX, address)
+			.skip(offset)
+			.take(limit)
+			.filter_map(|(key, value)| Self::decode(address, &key, &value))
+			.collect()
+	}
+
+	fn decode(address: &Address, key: &[u8], value: &[u8]) -> Option<AddressTransaction> {
+		if key.len() != 32 {
+			return None;
+		}
+		let number = key[20..28].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+		let index = key[28..32].iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+		let rlp = UntrustedRlp::new(value);
+		match (rlp.val_at(0), rlp.val_at(1), rlp.val_at::<u8>(2)) {
+			(Ok(block_hash), Ok(transaction_hash), Ok(flags)) => Some(AddressTransaction {
+				address: address.clone(),
+				block_number: number,
+				block_hash: block_hash,
+				transaction_index: index,
+				transaction_hash: transaction_hash,
+				sent: flags & SENT != 0,
+				received: flags & RECEIVED != 0,
+			}),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use devtools::RandomTempPath;
+	use util::{Database, DatabaseConfig, DBTransaction, Address, U256, H256, Hashable};
+	use ethkey::{Generator, Random, KeyPair};
+	use transaction::{Transaction, Action};
+	use blockchain::{BlockChain, Config};
+	use client::{BlockChainClient, BlockID};
+	use tests::helpers::generate_dummy_client_with_data;
+	use super::AddressIndex;
+
+	fn new_db(path: &str) -> Arc<Database> {
+		Arc::new(Database::open(&DatabaseConfig::with_columns(::db::NUM_COLUMNS), path).unwrap())
+	}
+
+	#[test]
+	fn should_index_and_retract_transactions() {
+		// given
+		let temp = RandomTempPath::new();
+		let db = new_db(temp.as_str());
+		let index = AddressIndex::new(true, db.clone(), 0);
+		let keypair = Random.generate().unwrap();
+		let recipient = Address::from(5);
+		let transactions = (0..3).map(|nonce| Transaction {
+			action: Action::Call(recipient.clone()),
+			value: U256::from(100),
+			data: vec![],
+			gas: U256::from(21_000),
+			gas_price: U256::one(),
+			nonce: U256::from(nonce),
+		}.sign(keypair.secret())).collect::<Vec<_>>();
+
+		// when
+		let mut batch = DBTransaction::new(&db);
+		index.insert(&mut batch, 1, &H256::from(1), &transactions[0..2]);
+		index.insert(&mut batch, 2, &H256::from(2), &transactions[2..3]);
+		db.write(batch).unwrap();
+
+		// then
+		let sent = index.transactions(&keypair.address(), 0, 10);
+		assert_eq!(sent.len(), 3);
+		assert!(sent.iter().all(|tx| tx.sent && !tx.received));
+		assert_eq!(sent[2].block_number, 2);
+		assert_eq!(sent[2].transaction_hash, transactions[2].hash());
+		let page = index.transactions(&recipient, 1, 1);
+		assert_eq!(page.len(), 1);
+		assert_eq!((page[0].block_number, page[0].transaction_index), (1, 1));
+		assert!(page[0].received);
+
+		// and when
+		let mut batch = DBTransaction::new(&db);
+		index.remove(&mut batch, 2, &transactions[2..3]);
+		db.write(batch).unwrap();
+
+		// then
+		assert_eq!(index.transactions(&recipient, 0, 10).len(), 2);
+		assert!(index.transactions(&Address::from(6), 0, 10).is_empty());
+	}
+
+	#[test]
+	fn should_index_blocks_imported_before_the_index() {
+		// given
+		let client_result = generate_dummy_client_with_data(3, 1, &[0.into()]);
+		let client = client_result.reference();
+		let temp = RandomTempPath::new();
+		let db = new_db(temp.as_str());
+		let genesis = client.block(BlockID::Number(0)).unwrap();
+		let chain = BlockChain::new(Config::default(), &genesis, db.clone());
+		for number in 1..4 {
+			let mut batch = DBTransaction::new(&db);
+			chain.insert_block(&mut batch, &client.block(BlockID::Number(number)).unwrap(), vec![]);
+			db.write(batch).unwrap();
+			chain.commit();
+		}
+		let sender = KeyPair::from_secret("".sha3()).unwrap().address();
+
+		// when
+		let index = AddressIndex::new(true, db.clone(), chain.best_block_number());
+
+		// then
+		assert_eq!(index.unindexed(), 4);
+		assert!(index.transactions(&sender, 0, 10).is_empty());
+
+		// and when
+		assert_eq!(index.backfill(&chain), 0);
+
+		// then
+		let sent = index.transactions(&sender, 0, 10);
+		assert_eq!(sent.iter().map(|tx| tx.block_number).collect::<Vec<_>>(), vec![1, 2, 3]);
+		assert_eq!(AddressIndex::new(true, db.clone(), chain.best_block_number()).unindexed(), 0);
+	}
+}
@@ -18,22 +18,24 @@ use std::sync::{Arc, Weak};
 use std::path::{Path};
 use std::fmt;
 use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering as AtomicOrdering};
-use std::time::{Instant};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use time::precise_time_ns;
 
 // util
 use util::{Bytes, PerfTimer, Itertools, Mutex, RwLock};
 use util::journaldb;
 use util::{U256, H256, Address, H2048, Uint};
-use util::TrieFactory;
+use util::{TrieFactory, TrieError};
+use util::{HashDB, SHA3_NULL_RLP};
 use util::kvdb::*;
 
 // other
 use io::*;
-use views::{HeaderView, BodyView};
+use views::{HeaderView, BodyView, BlockView};
 use error::{ImportError, ExecutionError, CallError, BlockError, ImportResult, Error as EthcoreError};
 use header::BlockNumber;
 use state::State;
+use pod_account::PodAccount;
 use spec::Spec;
 use basic_types::Seal;
 use engines::Engine;
@@ -48,12 +50,15 @@ use types::filter::Filter;
 use log_entry::LocalizedLogEntry;
 use verification::queue::{BlockQueue, QueueInfo as BlockQueueInfo};
 use blockchain::{BlockChain, BlockProvider, TreeRoute, ImportRoute};
+use blockchain::integrity;
 use client::{
 	BlockID, TransactionID, UncleID, TraceId, ClientConfig, BlockChainClient,
 	MiningBlockChainClient, TraceFilter, CallAnalytics, BlockImportError, Mode,
 	ChainNotify
 };
 use client::Error as ClientError;
+use client::reorgs::ReorgDB;
+use client::address_index::AddressIndex;
 use env_info::EnvInfo;
 use executive::{Executive, Executed, TransactOptions, contract_address};
 use receipt::LocalizedReceipt;
@@ -64,15 +69,21 @@ use evm::Factory as EvmFactory;
 use miner::{Miner, MinerService};
 use snapshot::{self, io as snapshot_io};
 use factory::Factories;
-use rlp::{View, UntrustedRlp};
+use rlp::{encode, decode, View, UntrustedRlp};
 use state_db::StateDB;
+use types::reorg::Reorg;
+use types::address_transaction::AddressTransaction;
+use types::account_proof::{AccountProof, StateError};
 
 // re-export
 pub use types::blockchain_info::BlockChainInfo;
 pub use types::block_status::BlockStatus;
 pub use blockchain::CacheSize as BlockChainCacheSize;
+pub use blockchain::{IntegrityError, IntegrityReport};
 
 const MAX_TX_QUEUE_SIZE: usize = 4096;
+/// Maximal number of ancient blocks pruned during a single block import.
+const MAX_ANCIENT_BLOCKS_PRUNED: u64 = 256;
 const MAX_QUEUE_SIZE_TO_SLEEP_ON: usize = 2;
 
 impl fmt::Display for BlockChainInfo {
@@ -123,9 +134,12 @@ pub struct Client {
 	mode: Mode,
 	chain: RwLock<Arc<BlockChain>>,
 	tracedb: RwLock<TraceDB<BlockChain>>,
+	reorgs: RwLock<ReorgDB>,
+	address_index: RwLock<AddressIndex>,
 	engine: Arc<Engine>,
 	config: ClientConfig,
 	pruning: journaldb::Algorithm,
+	history: RwLock<u64>,
 	db: RwLock<Arc<Database>>,
 	state_db: Mutex<StateDB>,
 	block_queue: BlockQueue,
@@ -141,12 +155,20 @@ pub struct Client {
 	queue_transactions: AtomicUsize,
 	last_hashes: RwLock<VecDeque<H256>>,
 	factories: Factories,
+	/// First block retracted by the last rewind. It's not imported again until the guard is cleared.
+	rewound_block: RwLock<Option<H256>>,
 }
 
-/// The pruning constant -- how old blocks must be before we
+/// The default pruning constant -- how old blocks must be before we
 /// assume finality of a given candidate.
 pub const HISTORY: u64 = 1200;
 
+/// Default size of the shared state cache in bytes.
+pub const STATE_CACHE_SIZE: usize = 25 * 1024 * 1024;
+
+/// Key of the pruning history in effect for the database, stored in the extras column.
+const PRUNING_HISTORY_KEY: &'static [u8] = b"pruning_history";
+
 /// Append a path element to the given path and return the string.
 pub fn append_path<P>(path: P, item: &str) -> String where P: AsRef<Path> {
 	let mut p = path.as_ref().to_path_buf();
@@ -170,10 +192,13 @@ impl Client {
 		let db = Arc::new(try!(Database::open(&db_config, &path.to_str().unwrap()).map_err(ClientError::Database)));
 		let chain = Arc::new(BlockChain::new(config.blockchain.clone(), &gb, db.clone()));
 		let tracedb = RwLock::new(TraceDB::new(config.tracing.clone(), db.clone(), chain.clone()));
+		let reorgs = RwLock::new(ReorgDB::new(config.reorg_history, db.clone()));
+		let address_index = RwLock::new(AddressIndex::new(config.address_index, db.clone(), chain.best_block_number()));
 
+		let trie_factory = TrieFactory::new(config.trie_spec.clone());
 		let journal_db = journaldb::new(db.clone(), config.pruning, ::db::COL_STATE);
-		let mut state_db = StateDB::new(journal_db);
-		if state_db.journal_db().is_empty() && try!(spec.ensure_db_good(&mut state_db)) {
+		let mut state_db = StateDB::new(journal_db, config.state_cache_size.unwrap_or(STATE_CACHE_SIZE));
+		if state_db.journal_db().is_empty() && try!(spec.ensure_db_good_with_factory(&mut state_db, &trie_factory)) {
 			let mut batch = DBTransaction::new(&db);
 			try!(state_db.commit(&mut batch, 0, &spec.genesis_header().hash(), None));
 			try!(db.write(batch).map_err(ClientError::Database));
@@ -183,6 +208,26 @@ impl Client {
 			warn!("State root not found for block #{} ({})", chain.best_block_number(), chain.best_block_hash().hex());
 		}
 
+		// Pruning history in effect for the database. It can be raised gradually as new blocks come,
+		// but lowering it would leave journalled eras behind forever.
+		let target_history = config.history.unwrap_or(HISTORY);
+		let stored_history = try!(db.get(::db::COL_EXTRA, PRUNING_HISTORY_KEY).map_err(ClientError::Database))
+			.map(|h| decode::<u64>(&h));
+		let history = match stored_history {
+			Some(history) => history,
+			// databases created before the history was stored were pruned with the default one
+			None if chain.best_block_number() > 0 => HISTORY,
+			None => target_history,
+		};
+		if target_history < history {
+			warn!("Pruning history of the database is {} blocks, it can't be lowered to {} without a resync.", history, target_history);
+		}
+		if stored_history != Some(history) {
+			let mut batch = DBTransaction::new(&db);
+			batch.put(::db::COL_EXTRA, PRUNING_HISTORY_KEY, &encode(&history));
+			try!(db.write(batch).map_err(ClientError::Database));
+		}
+
 		let engine = spec.engine.clone();
 
 		let block_queue = BlockQueue::new(config.queue.clone(), engine.clone(), message_channel.clone());
@@ -193,7 +238,7 @@ impl Client {
 
 		let factories = Factories {
 			vm: EvmFactory::new(config.vm_type.clone()),
-			trie: TrieFactory::new(config.trie_spec.clone()),
+			trie: trie_factory,
 			accountdb: Default::default(),
 		};
 
@@ -203,8 +248,11 @@ impl Client {
 			mode: config.mode.clone(),
 			chain: RwLock::new(chain),
 			tracedb: tracedb,
+			reorgs: reorgs,
+			address_index: address_index,
 			engine: engine,
 			pruning: config.pruning.clone(),
+			history: RwLock::new(history),
 			verifier: verification::new(config.verifier_type.clone()),
 			config: config,
 			db: RwLock::new(db),
@@ -219,7 +267,9 @@ impl Client {
 			queue_transactions: AtomicUsize::new(0),
 			last_hashes: RwLock::new(VecDeque::new()),
 			factories: factories,
+			rewound_block: RwLock::new(None),
 		};
+		client.prepare_sealing_data();
 		Ok(Arc::new(client))
 	}
 
@@ -277,7 +327,8 @@ impl Client {
 		let chain = self.chain.read();
 		// Check the block isn't so old we won't be able to enact it.
 		let best_block_number = chain.best_block_number();
-		if best_block_number >= HISTORY && header.number() <= best_block_number - HISTORY {
+		let history = self.pruning_history();
+		if best_block_number >= history && header.number() <= best_block_number - history {
 			warn!(target: "client", "Block import failed for #{} ({})\nBlock is ancient (current best block: #{}).", header.number(), header.hash(), best_block_number);
 			return Err(());
 		}
@@ -299,8 +350,7 @@ impl Client {
 		// Enact Verified Block
 		let parent = chain_has_parent.unwrap();
 		let last_hashes = self.build_last_hashes(header.parent_hash().clone());
-		let is_canon = header.parent_hash() == &chain.best_block_hash();
-		let db = if is_canon { self.state_db.lock().boxed_clone_canon() } else { self.state_db.lock().boxed_clone() };
+		let db = self.state_db.lock().boxed_clone_canon(header.parent_hash());
 
 		let enact_result = enact_verified(block, engine, self.tracedb.read().tracing_enabled(), db, &parent, last_hashes, self.factories.clone());
 		if let Err(e) = enact_result {
@@ -318,6 +368,41 @@ impl Client {
 		Ok(locked_block)
 	}
 
+	/// Enacts `block` while `accounts` touched by the next block are loaded into the state cache
+	/// on the shared thread pool. Accounts are loaded from the current best block's state and must be
+	/// in the cache before changes made by `block` are committed. The block is handed back for the commit.
+	fn check_and_close_block_prefetching(&self, block: PreverifiedBlock, accounts: Vec<Address>) -> (PreverifiedBlock, Result<LockedBlock, ()>) {
+		if accounts.is_empty() {
+			let closed_block = self.check_and_close_block(&block);
+			return (block, closed_block);
+		}
+
+		let best_header = self.best_block_header();
+		let best_header = HeaderView::new(&best_header);
+		let db = self.state_db.lock().boxed_clone_canon(&best_header.sha3());
+		let root = best_header.state_root();
+		let state = match State::from_existing(db, root, self.engine.account_start_nonce(), self.factories.clone()) {
+			Ok(state) => state,
+			Err(_) => {
+				let closed_block = self.check_and_close_block(&block);
+				return (block, closed_block);
+			},
+		};
+
+		let ((block, closed_block), mut prefetched) = ::rayon::join(
+			move || {
+				let closed_block = self.check_and_close_block(&block);
+				(block, closed_block)
+			},
+			move || {
+				state.prefetch(&accounts);
+				state.drop().1
+			}
+		);
+		prefetched.commit_prefetched();
+		(block, closed_block)
+	}
+
 	fn calculate_enacted_retracted(&self, import_results: &[ImportRoute]) -> (Vec<H256>, Vec<H256>) {
 		fn map_to_vec(map: Vec<(H256, bool)>) -> Vec<H256> {
 			map.into_iter().map(|(k, _v)| k).collect()
@@ -355,15 +440,16 @@ impl Client {
 			let _import_lock = self.import_lock.lock();
 			let _timer = PerfTimer::new("import_verified_blocks");
 			let start = precise_time_ns();
-			let blocks = self.block_queue.drain(max_blocks_to_import);
+			let mut blocks = self.block_queue.drain(max_blocks_to_import).into_iter().peekable();
 
-			for block in blocks {
-				let header = &block.header;
-				if invalid_blocks.contains(header.parent_hash()) {
-					invalid_blocks.insert(header.hash());
+			while let Some(block) = blocks.next() {
+				if invalid_blocks.contains(block.header.parent_hash()) {
+					invalid_blocks.insert(block.header.hash());
 					continue;
 				}
-				let closed_block = self.check_and_close_block(&block);
+				let accounts = blocks.peek().map_or_else(Vec::new, touched_accounts);
+				let (block, closed_block) = self.check_and_close_block_prefetching(block, accounts);
+				let header = &block.header;
 				if let Err(_) = closed_block {
 					invalid_blocks.insert(header.hash());
 					continue;
@@ -400,6 +486,7 @@ impl Client {
 				if self.queue_info().is_empty() {
 					self.miner.chain_new_blocks(self, &imported_blocks, &invalid_blocks, &enacted, &retracted);
 				}
+				self.prepare_sealing_data();
 
 				self.notify(|notify| {
 					notify.new_blocks(
@@ -418,16 +505,36 @@ impl Client {
 		imported
 	}
 
+	fn prepare_sealing_data(&self) {
+		if self.config.sealing_data_threads > 0 {
+			let best_block = self.chain.read().best_block_number();
+			self.engine.prepare_sealing_data(best_block, self.config.sealing_data_threads);
+		}
+	}
+
 	fn commit_block<B>(&self, block: B, hash: &H256, block_data: &[u8]) -> ImportRoute where B: IsBlock + Drain {
 		let number = block.header().number();
 		let parent = block.header().parent_hash().clone();
 		let chain = self.chain.read();
-		// Are we committing an era?
-		let ancient = if number >= HISTORY {
-			let n = number - HISTORY;
-			Some((n, chain.block_hash(n).unwrap()))
-		} else {
-			None
+		// Are we committing an era? While the pruning history is being raised, eras are kept
+		// until the state of the last `history` blocks is available.
+		let (ancient, raised_history) = {
+			let mut history = self.history.write();
+			let raised_history = match *history < self.config.history.unwrap_or(HISTORY) && number >= *history {
+				true => {
+					*history += 1;
+					Some(*history)
+				},
+				false => None,
+			};
+			let ancient = match raised_history.is_none() && number >= *history {
+				true => {
+					let n = number - *history;
+					Some((n, chain.block_hash(n).unwrap()))
+				},
+				false => None,
+			};
+			(ancient, raised_history)
 		};
 
 		// Commit results
@@ -436,6 +543,10 @@ impl Client {
 		let traces: Vec<FlatTransactionTraces> = traces.into_iter()
 			.map(Into::into)
 			.collect();
+		let transactions = match self.address_index.read().is_enabled() {
+			true => block.transactions().to_vec(),
+			false => Vec::new(),
+		};
 
 		//let traces = From::from(block.traces().clone().unwrap_or_else(Vec::new));
 
@@ -445,8 +556,14 @@ impl Client {
 		// TODO: Prove it with a test.
 		let mut state = block.drain();
 		state.commit(&mut batch, number, hash, ancient).expect("DB commit failed.");
+		if let Some(history) = raised_history {
+			batch.put(::db::COL_EXTRA, PRUNING_HISTORY_KEY, &encode(&history));
+		}
 
+		let old_head = (chain.best_block_hash(), chain.best_block_number());
 		let route = chain.insert_block(&mut batch, block_data, receipts);
+		let is_best = route.enacted.last().map_or(false, |h| h == hash);
+		state.sync_cache(&route.enacted, &route.retracted, is_best);
 		self.tracedb.read().import(&mut batch, TraceImportRequest {
 			traces: traces.into(),
 			block_hash: hash.clone(),
@@ -454,6 +571,27 @@ impl Client {
 			enacted: route.enacted.clone(),
 			retracted: route.retracted.len()
 		});
+		{
+			let address_index = self.address_index.read();
+			if address_index.is_enabled() {
+				Self::update_address_index(&address_index, &mut batch, &chain, &route, hash, number, &transactions);
+			}
+		}
+		if let Some(history) = self.config.block_history {
+			if !route.enacted.is_empty() && number > history {
+				chain.prune_ancient(&mut batch, number - history, MAX_ANCIENT_BLOCKS_PRUNED);
+			}
+		}
+		{
+			let reorgs = self.reorgs.read();
+			if reorgs.is_enabled() && !route.retracted.is_empty() {
+				let reorg = Self::reorg_entry(&chain, &route, old_head, block_data);
+				let id = reorgs.import(&mut batch, reorg);
+				debug!(target: "reorg", "Chain reorganization #{}: {} blocks retracted, new best block #{} ({})", id, route.retracted.len(), number, hash.hex());
+			} else if reorgs.is_enabled() && !route.enacted.is_empty() {
+				reorgs.collect_garbage(&mut batch, number);
+			}
+		}
 		// Final commit to the DB
 		self.db.read().write_buffered(batch);
 		chain.commit();
@@ -461,6 +599,66 @@ impl Client {
 		route
 	}
 
+	/// Removes transactions of retracted blocks from the address index and adds the enacted ones.
+	/// `transactions` belong to the imported block `hash`, which is not in the database yet.
+	fn update_address_index(index: &AddressIndex, batch: &mut DBTransaction, chain: &BlockChain, route: &ImportRoute, hash: &H256, number: BlockNumber, transactions: &[SignedTransaction]) {
+		let block_transactions = |hash: &H256| chain.block_body(hash)
+			.and_then(|body| chain.block_number(hash).map(|number| (number, BodyView::new(&body).transactions())));
+
+		for retracted in &route.retracted {
+			if let Some((number, transactions)) = block_transactions(retracted) {
+				index.remove(batch, number, &transactions);
+			}
+		}
+		for enacted in &route.enacted {
+			if enacted == hash {
+				index.insert(batch, number, hash, transactions);
+			} else if let Some((number, transactions)) = block_transactions(enacted) {
+				index.insert(batch, number, enacted, &transactions);
+			}
+		}
+	}
+
+	/// Indexes the next range of blocks imported before the address index was enabled.
+	fn backfill_address_index(&self) {
+		if self.address_index.read().unindexed() == 0 {
+			return;
+		}
+		let _import_lock = self.import_lock.lock();
+		self.address_index.read().backfill(&self.chain.read());
+	}
+
+	/// Describes reorganization caused by importing `block_data` which is the new best block.
+	fn reorg_entry(chain: &BlockChain, route: &ImportRoute, old_head: (H256, BlockNumber), block_data: &[u8]) -> Reorg {
+		let block = BlockView::new(block_data);
+		let header = block.header_view();
+		let new_head = block.hash();
+		let transactions = |hash: &H256| match *hash == new_head {
+			true => block.transaction_hashes(),
+			false => chain.block_body(hash).map_or_else(Vec::new, |body| BodyView::new(&body).transaction_hashes()),
+		};
+
+		let retracted_transactions = route.retracted.iter().flat_map(|hash| transactions(hash)).collect::<Vec<_>>();
+		let enacted_transactions = route.enacted.iter().flat_map(|hash| transactions(hash)).collect::<HashSet<_>>();
+		let reincluded_transactions = retracted_transactions.iter()
+			.filter(|hash| enacted_transactions.contains(*hash))
+			.cloned()
+			.collect();
+
+		Reorg {
+			id: 0,
+			timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+			depth: route.retracted.len() as u64,
+			old_head: old_head.0,
+			old_number: old_head.1,
+			new_head: new_head,
+			new_number: header.number(),
+			retracted_blocks: route.retracted.clone(),
+			retracted_transactions: retracted_transactions,
+			reincluded_transactions: reincluded_transactions,
+		}
+	}
+
 	fn update_last_hashes(&self, parent: &H256, hash: &H256) {
 		let mut hashes = self.last_hashes.write();
 		if hashes.front().map_or(false, |h| h == parent) {
@@ -492,25 +690,35 @@ impl Client {
 			_ => {},
 		}
 
-		let block_number = match self.block_number(id.clone()) {
-			Some(num) => num,
-			None => return None,
-		};
+		// early exit for pruned blocks
+		if self.state_available(id.clone()).is_err() {
+			return None;
+		}
 
 		self.block_header(id).and_then(|header| {
-			let db = self.state_db.lock().boxed_clone();
-
-			// early exit for pruned blocks
-			if db.is_pruned() && self.chain.read().best_block_number() >= block_number + HISTORY {
-				return None;
-			}
-
-			let root = HeaderView::new(&header).state_root();
+			let header = HeaderView::new(&header);
+			let db = self.state_db.lock().boxed_clone_canon(&header.sha3());
+			let root = header.state_root();
 
 			State::from_existing(db, root, self.engine.account_start_nonce(), self.factories.clone()).ok()
 		})
 	}
 
+	/// Call `f` with the address and contents of every account in the state of the given block,
+	/// streaming them from a single view of the state.
+	/// Returns the number of accounts, or `None` if the state is not available or the database
+	/// is not a fat DB.
+	pub fn for_each_account<F>(&self, id: BlockID, f: F) -> Option<usize> where F: FnMut(Address, PodAccount) {
+		if !self.factories.trie.is_fat() {
+			trace!(target: "fatdb", "for_each_account: Not a fat DB");
+			return None;
+		}
+
+		self.state_at(id).and_then(|state| state.for_each_account(f).map_err(|e| {
+			warn!(target: "client", "Failed to walk accounts: {}", e);
+		}).ok())
+	}
+
 	/// Attempt to get a copy of a specific block's beginning state.
 	///
 	/// This will not fail if given BlockID::Latest.
@@ -528,14 +736,114 @@ impl Client {
 
 	/// Get a copy of the best block's state.
 	pub fn state(&self) -> State {
+		let header = self.best_block_header();
+		let header = HeaderView::new(&header);
 		State::from_existing(
-			self.state_db.lock().boxed_clone(),
-			HeaderView::new(&self.best_block_header()).state_root(),
+			self.state_db.lock().boxed_clone_canon(&header.sha3()),
+			header.state_root(),
 			self.engine.account_start_nonce(),
 			self.factories.clone())
 		.expect("State root of best block header always valid.")
 	}
 
+	/// Number of recent eras of state kept in the database when pruning is enabled.
+	/// This is the history in effect for the database rather than the configured one.
+	pub fn pruning_history(&self) -> u64 {
+		*self.history.read()
+	}
+
+	/// Checks integrity of the canonical chain and availability of the best block's state.
+	pub fn check_integrity(&self) -> IntegrityReport {
+		let chain = self.chain.read();
+		let state_db = self.state_db.lock();
+		Self::check_chain_and_state(&chain, state_db.journal_db())
+	}
+
+	/// Checks integrity of the database at `path` without starting the client.
+	/// Nothing is written to the database and damaged data of the best block is tolerated.
+	pub fn check_database(config: &ClientConfig, path: &Path, db_config: &DatabaseConfig) -> Result<IntegrityReport, ClientError> {
+		let db = Arc::new(try!(Database::open(db_config, &path.to_str().unwrap()).map_err(ClientError::Database)));
+		let chain = try!(BlockChain::open(config.blockchain.clone(), db.clone()).map_err(ClientError::Database));
+		let journal_db = journaldb::new(db, config.pruning, ::db::COL_STATE);
+		Ok(Self::check_chain_and_state(&chain, &*journal_db))
+	}
+
+	fn check_chain_and_state(chain: &BlockChain, journal_db: &journaldb::JournalDB) -> IntegrityReport {
+		let mut report = integrity::check(chain);
+		if report.last_consistent == report.best_block {
+			let state_root = chain.block_header_data(&chain.best_block_hash()).map(|header| HeaderView::new(&header).state_root());
+			if let Some(state_root) = state_root {
+				if state_root != SHA3_NULL_RLP && !journal_db.contains(&state_root) {
+					report.errors.push(IntegrityError::MissingState(report.best_block, state_root));
+				}
+			}
+		}
+		report
+	}
+
+	/// Repairs inconsistencies found by `check_integrity`. The best block is rewound
+	/// to the last consistent block with available state, searching back as far as needed,
+	/// and broken indexes are rebuilt.
+	/// Returns the new best block number.
+	pub fn repair_integrity(&self, report: &IntegrityReport) -> BlockNumber {
+		let _import_lock = self.import_lock.lock();
+		let chain = self.chain.read();
+
+		let mut target = report.last_consistent;
+		while target > 0 {
+			let available = chain.block_hash(target)
+				.and_then(|hash| chain.block_header_data(&hash))
+				.map_or(false, |header| self.has_state(&HeaderView::new(&header).state_root()));
+			if available {
+				break;
+			}
+			target -= 1;
+		}
+
+		if target < chain.best_block_number() {
+			self.rewind_chain(&chain, target);
+		}
+
+		if let Some(from) = report.first_index_error() {
+			if from <= chain.best_block_number() {
+				info!(target: "client", "Rebuilding indexes from #{}", from);
+				chain.rebuild_indexes(from);
+			}
+		}
+
+		chain.best_block_number()
+	}
+
+	/// Rewinds the best block to `number`, removing retracted blocks from the address index,
+	/// trace and reorganization databases and moving the state database back.
+	/// Returns hashes of retracted blocks, ordered from the newest one.
+	fn rewind_chain(&self, chain: &BlockChain, number: BlockNumber) -> Vec<H256> {
+		let best = chain.best_block_number();
+		warn!(target: "client", "Rewinding best block from #{} to #{}", best, number);
+		let db = self.db.read();
+		let mut batch = DBTransaction::new(&db);
+		let address_index = self.address_index.read();
+		if address_index.is_enabled() {
+			for n in number + 1..chain.best_block_number() + 1 {
+				if let Some(body) = chain.block_hash(n).and_then(|hash| chain.block_body(&hash)) {
+					address_index.remove(&mut batch, n, &BodyView::new(&body).transactions());
+				}
+			}
+		}
+
+		let retracted = chain.rewind_to(&mut batch, number);
+		self.tracedb.read().rewind(&mut batch, number, best, &retracted);
+		self.reorgs.read().rewind(&mut batch, number);
+		self.state_db.lock().revert_to_era(&mut batch, number);
+		db.write(batch).expect("Low level database error. Some issue with disk?");
+		chain.commit();
+		retracted
+	}
+
+	fn has_state(&self, root: &H256) -> bool {
+		*root == SHA3_NULL_RLP || self.state_db.lock().journal_db().contains(root)
+	}
+
 	/// Get info on the cache.
 	pub fn blockchain_cache_info(&self) -> BlockChainCacheSize {
 		self.chain.read().cache_size()
@@ -554,6 +862,8 @@ impl Client {
 		self.chain.read().collect_garbage();
 		self.block_queue.collect_garbage();
 		self.tracedb.read().collect_garbage();
+		self.state_db.lock().collect_garbage();
+		self.backfill_address_index();
 
 		match self.mode {
 			Mode::Dark(timeout) => {
@@ -599,12 +909,13 @@ impl Client {
 
 	/// Take a snapshot at the given block.
 	/// If the ID given is "latest", this will default to 1000 blocks behind.
-	pub fn take_snapshot<W: snapshot_io::SnapshotWriter + Send>(&self, writer: W, at: BlockID, p: &snapshot::Progress) -> Result<(), EthcoreError> {
+	/// State chunks of subtrees unchanged since the `base` snapshot are copied from it.
+	pub fn take_snapshot<W: snapshot_io::SnapshotWriter + Send>(&self, writer: W, at: BlockID, base: Option<&snapshot_io::SnapshotReader>, p: &snapshot::Progress) -> Result<(), EthcoreError> {
 		let db = self.state_db.lock().journal_db().boxed_clone();
 		let best_block_number = self.chain_info().best_block_number;
 		let block_number = try!(self.block_number(at).ok_or(snapshot::Error::InvalidStartingBlock(at)));
 
-		if best_block_number > HISTORY + block_number && db.is_pruned() {
+		if best_block_number > self.pruning_history() + block_number && db.is_pruned() {
 			return Err(snapshot::Error::OldBlockPrunedDB.into());
 		}
 
@@ -625,7 +936,7 @@ impl Client {
 			},
 		};
 
-		try!(snapshot::take_snapshot(&self.chain.read(), start_hash, db.as_hashdb(), writer, p));
+		try!(snapshot::take_snapshot(&self.chain.read(), start_hash, db.as_hashdb(), base, writer, p));
 
 		Ok(())
 	}
@@ -682,18 +993,41 @@ impl snapshot::DatabaseRestore for Client {
 		let mut state_db = self.state_db.lock();
 		let mut chain = self.chain.write();
 		let mut tracedb = self.tracedb.write();
+		let mut reorgs = self.reorgs.write();
+		let mut address_index = self.address_index.write();
 		self.miner.clear();
 		let db = self.db.write();
 		try!(db.restore(new_db));
 
-		*state_db = StateDB::new(journaldb::new(db.clone(), self.pruning, ::db::COL_STATE));
+		*state_db = StateDB::new(journaldb::new(db.clone(), self.pruning, ::db::COL_STATE), self.config.state_cache_size.unwrap_or(STATE_CACHE_SIZE));
 		*chain = Arc::new(BlockChain::new(self.config.blockchain.clone(), &[], db.clone()));
 		*tracedb = TraceDB::new(self.config.tracing.clone(), db.clone(), chain.clone());
+		*reorgs = ReorgDB::new(self.config.reorg_history, db.clone());
+		*address_index = AddressIndex::new(self.config.address_index, db.clone(), chain.best_block_number());
 		Ok(())
 	}
 }
 
 
+/// Accounts touched by transactions of the block: the author, senders and recipients.
+fn touched_accounts(block: &PreverifiedBlock) -> Vec<Address> {
+	if block.transactions.is_empty() {
+		return Vec::new();
+	}
+
+	let mut accounts = HashSet::new();
+	accounts.insert(block.header.author().clone());
+	for transaction in &block.transactions {
+		if let Ok(sender) = transaction.sender() {
+			accounts.insert(sender);
+		}
+		if let Action::Call(ref to) = transaction.action {
+			accounts.insert(to.clone());
+		}
+	}
+	accounts.into_iter().collect()
+}
+
 impl BlockChainClient for Client {
 	fn call(&self, t: &SignedTransaction, block: BlockID, analytics: CallAnalytics) -> Result<Executed, CallError> {
 		let header = try!(self.block_header(block).ok_or(CallError::StatePruned));
@@ -802,6 +1136,16 @@ impl BlockChainClient for Client {
 		})
 	}
 
+	fn is_pruned(&self, id: BlockID) -> bool {
+		if let BlockID::Pending = id {
+			return false;
+		}
+		let chain = self.chain.read();
+		Self::block_hash(&chain, id)
+			.and_then(|hash| chain.block_number(&hash).map(|number| (hash, number)))
+			.map_or(false, |(hash, number)| chain.is_pruned(number) && chain.block_body(&hash).is_none())
+	}
+
 	fn block_status(&self, id: BlockID) -> BlockStatus {
 		let chain = self.chain.read();
 		match Self::block_hash(&chain, id) {
@@ -842,6 +1186,71 @@ impl BlockChainClient for Client {
 		self.state_at(id).map(|s| s.storage_at(address, position))
 	}
 
+	fn state_available(&self, id: BlockID) -> Result<(), StateError> {
+		match id {
+			BlockID::Latest | BlockID::Pending => return Ok(()),
+			_ => {},
+		}
+
+		let is_pruned = self.state_db.lock().is_pruned();
+		let chain = self.chain.read();
+		let number = try!(Self::block_hash(&chain, id)
+			.and_then(|hash| chain.block_number(&hash))
+			.ok_or(StateError::UnknownBlock));
+
+		match is_pruned && chain.best_block_number() >= number + self.pruning_history() {
+			true => Err(StateError::StatePruned),
+			false => Ok(()),
+		}
+	}
+
+	fn rewind(&self, id: BlockID) -> Result<Vec<H256>, StateError> {
+		let _import_lock = self.import_lock.lock();
+		try!(self.state_available(id.clone()));
+
+		let retracted = {
+			let chain = self.chain.read();
+			let hash = try!(Self::block_hash(&chain, id).ok_or(StateError::UnknownBlock));
+			let number = try!(chain.block_number(&hash).ok_or(StateError::UnknownBlock));
+			if chain.block_hash(number) != Some(hash) {
+				return Err(StateError::UnknownBlock);
+			}
+			if number >= chain.best_block_number() {
+				return Ok(Vec::new());
+			}
+			self.rewind_chain(&chain, number)
+		};
+
+		// the child of the new best block is the one to blame. The guard is kept in memory only
+		// and replaced by the next rewind.
+		*self.rewound_block.write() = retracted.last().cloned();
+
+		self.miner.chain_new_blocks(self, &[], &[], &[], &retracted);
+		self.notify(|notify| {
+			notify.new_blocks(vec![], vec![], vec![], retracted.clone(), vec![], 0);
+		});
+		self.db.read().flush().expect("DB flush failed.");
+		Ok(retracted)
+	}
+
+	fn clear_rewound_block(&self) -> Option<H256> {
+		self.rewound_block.write().take()
+	}
+
+	fn prove_account(&self, address: &Address, storage_keys: Vec<H256>, id: BlockID) -> Result<AccountProof, StateError> {
+		try!(self.state_available(id.clone()));
+		let state = try!(self.state_at(id).ok_or(StateError::StatePruned));
+		let is_pruned = self.state_db.lock().is_pruned();
+		state.prove_account(address, &storage_keys).map_err(|e| match *e {
+			// the state may have been pruned in the meantime
+			TrieError::InvalidStateRoot(_) if is_pruned => StateError::StatePruned,
+			TrieError::InvalidStateRoot(hash) | TrieError::IncompleteDatabase(hash) => {
+				warn!(target: "client", "Failed to prove account {}: {}", address, e);
+				StateError::MissingTrieNode(hash)
+			},
+		})
+	}
+
 	fn transaction(&self, id: TransactionID) -> Option<LocalizedTransaction> {
 		self.transaction_address(id).and_then(|address| self.chain.read().transaction(&address))
 	}
@@ -931,6 +1340,9 @@ impl BlockChainClient for Client {
 			if self.chain.read().is_known(&unverified.hash()) {
 				return Err(BlockImportError::Import(ImportError::AlreadyInChain));
 			}
+			if self.rewound_block.read().as_ref() == Some(&unverified.hash()) {
+				return Err(BlockImportError::Import(ImportError::Rewound));
+			}
 			if self.block_status(BlockID::Hash(unverified.parent_hash())) == BlockStatus::Unknown {
 				return Err(BlockImportError::Block(BlockError::UnknownParent(unverified.parent_hash())));
 			}
@@ -1020,6 +1432,29 @@ impl BlockChainClient for Client {
 			.and_then(|number| self.tracedb.read().block_traces(number))
 	}
 
+	fn reorgs(&self, from: BlockID, to: BlockID) -> Option<Vec<Reorg>> {
+		match (self.block_number(from), self.block_number(to)) {
+			(Some(from), Some(to)) => Some(self.reorgs.read().reorgs(from, to)),
+			_ => None,
+		}
+	}
+
+	fn transaction_reorgs(&self, hash: &H256) -> Vec<Reorg> {
+		self.reorgs.read().transaction_reorgs(hash)
+	}
+
+	fn address_transactions(&self, address: &Address, offset: usize, limit: usize) -> Option<Vec<AddressTransaction>> {
+		let index = self.address_index.read();
+		match index.is_enabled() {
+			true => Some(index.transactions(address, offset, limit)),
+			false => None,
+		}
+	}
+
+	fn address_index_unindexed(&self) -> BlockNumber {
+		self.address_index.read().unindexed()
+	}
+
 	fn last_hashes(&self) -> LastHashes {
 		(*self.build_last_hashes(self.chain.read().best_block_hash())).clone()
 	}
@@ -1055,7 +1490,7 @@ impl MiningBlockChainClient for Client {
 			engine,
 			self.factories.clone(),
 			false,	// TODO: this will need to be parameterised once we want to do immediate mining insertion.
-			self.state_db.lock().boxed_clone(),
+			self.state_db.lock().boxed_clone_canon(&h),
 			&chain.block_header(&h).expect("h is best block hash: so its header must exist: qed"),
 			self.build_last_hashes(h.clone()),
 			author,
@@ -1094,6 +1529,7 @@ impl MiningBlockChainClient for Client {
 
 		let (enacted, retracted) = self.calculate_enacted_retracted(&[route]);
 		self.miner.chain_new_blocks(self, &[h.clone()], &[], &enacted, &retracted);
+		self.prepare_sealing_data();
 
 		self.notify(|notify| {
 			notify.new_blocks(
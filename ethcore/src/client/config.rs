@@ -107,6 +107,25 @@ pub struct ClientConfig {
 	pub mode: Mode,
 	/// Type of block verifier used by client.
 	pub verifier_type: VerifierType,
+	/// Number of blocks chain reorganizations are remembered for. `0` disables the history.
+	pub reorg_history: u64,
+	/// Index canonical transactions by sender and recipient address.
+	pub address_index: bool,
+	/// Number of recent blocks to keep bodies, receipts and transaction addresses for. Must be at least 1.
+	/// `None` keeps all of them.
+	pub block_history: Option<u64>,
+	/// Number of recent eras of state kept by pruning journal databases. `None` uses the default.
+	pub history: Option<u64>,
+	/// Size of the account cache shared between block import, pending block and calls, in bytes.
+	/// `None` uses the default.
+	pub state_cache_size: Option<usize>,
+	/// Number of older periodic snapshots kept in addition to the latest one.
+	pub snapshot_history: usize,
+	/// Number of incremental periodic snapshots taken between full ones. `0` disables them.
+	pub incremental_snapshots: usize,
+	/// Number of threads preparing engine data for sealing, e.g. Ethash DAGs, as the chain advances.
+	/// `0` disables the preparation.
+	pub sealing_data_threads: usize,
 }
 
 #[cfg(test)]
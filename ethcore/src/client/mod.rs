@@ -20,12 +20,17 @@ mod config;
 mod error;
 mod test_client;
 mod trace;
+mod reorgs;
+mod address_index;
 mod client;
 
 pub use self::client::*;
 pub use self::config::{Mode, ClientConfig, DatabaseCompactionProfile, BlockChainConfig, VMType};
 pub use self::error::Error;
 pub use types::ids::*;
+pub use types::reorg::Reorg;
+pub use types::address_transaction::AddressTransaction;
+pub use types::account_proof::{AccountProof, StorageProof, StateError};
 pub use self::test_client::{TestBlockChainClient, EachBlockWith};
 pub use types::trace_filter::Filter as TraceFilter;
 pub use executive::{Executed, Executive, TransactOptions};
@@ -0,0 +1,355 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Chain reorganization history.
+//!
+//! Every reorganization is stored under a sequential id together with an index
+//! of retracted transactions. Entries older than configured number of blocks are pruned.
+
+use std::ops::Deref;
+use std::collections::HashMap;
+use std::sync::Arc;
+use util::{H256, H264, Database, DBTransaction, RwLock};
+use rlp::{self, RlpStream, Encodable, Stream, UntrustedRlp, View};
+use header::BlockNumber;
+use types::reorg::Reorg;
+use db::{self, Key, Writable, Readable};
+
+#[derive(Debug, Copy, Clone)]
+enum ReorgIndex {
+	/// Reorganization entry index.
+	Reorg = 0,
+	/// Retracted transaction index.
+	Transaction = 1,
+	/// Range of stored ids.
+	Bounds = 2,
+}
+
+/// Helper data structure created cause [u8; 9] does not implement Deref to &[u8].
+pub struct ReorgKey([u8; 9]);
+
+impl Deref for ReorgKey {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl Key<Reorg> for u64 {
+	type Target = ReorgKey;
+
+	fn key(&self) -> ReorgKey {
+		let mut result = [0u8; 9];
+		result[0] = ReorgIndex::Reorg as u8;
+		for i in 0..8 {
+			result[i + 1] = (*self >> ((7 - i) * 8)) as u8;
+		}
+		ReorgKey(result)
+	}
+}
+
+/// Ids of reorganizations which retracted a transaction.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct TransactionReorgs(Vec<u64>);
+
+impl Encodable for TransactionReorgs {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.append(&self.0);
+	}
+}
+
+impl rlp::Decodable for TransactionReorgs {
+	fn decode<D>(decoder: &D) -> Result<Self, rlp::DecoderError> where D: rlp::Decoder {
+		Ok(TransactionReorgs(try!(rlp::Decodable::decode(decoder))))
+	}
+}
+
+impl Key<TransactionReorgs> for H256 {
+	type Target = H264;
+
+	fn key(&self) -> H264 {
+		let mut result = H264::default();
+		result[0] = ReorgIndex::Transaction as u8;
+		result[1..33].copy_from_slice(self);
+		result
+	}
+}
+
+/// Range of ids currently stored in the database.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Bounds {
+	/// Id of the oldest entry.
+	first: u64,
+	/// Id of the next entry.
+	next: u64,
+}
+
+impl Bounds {
+	fn key() -> [u8; 1] {
+		[ReorgIndex::Bounds as u8]
+	}
+
+	fn encode(&self) -> Vec<u8> {
+		let mut s = RlpStream::new_list(2);
+		s.append(&self.first);
+		s.append(&self.next);
+		s.out()
+	}
+
+	fn decode(bytes: &[u8]) -> Option<Self> {
+		let rlp = UntrustedRlp::new(bytes);
+		match (rlp.val_at(0), rlp.val_at(1)) {
+			(Ok(first), Ok(next)) => Some(Bounds { first: first, next: next }),
+			_ => None,
+		}
+	}
+}
+
+/// Persistent history of chain reorganizations.
+pub struct ReorgDB {
+	db: Arc<Database>,
+	/// Number of blocks the history is kept for. `0` disables the history.
+	history: u64,
+	bounds: RwLock<Bounds>,
+}
+
+impl ReorgDB {
+	/// Creates new reorganization history keeping entries for `history` blocks.
+	pub fn new(history: u64, db: Arc<Database>) -> Self {
+		let bounds = db.get(db::COL_REORGS, &Bounds::key())
+			.expect("Low-level database error. Some issue with disk?")
+			.and_then(|bytes| Bounds::decode(&bytes))
+			.unwrap_or_else(Default::default);
+
+		ReorgDB {
+			db: db,
+			history: history,
+			bounds: RwLock::new(bounds),
+		}
+	}
+
+	/// Returns true if reorganizations are recorded.
+	pub fn is_enabled(&self) -> bool {
+		self.history > 0
+	}
+
+	/// Records new reorganization, assigning it the next id, and prunes
+	/// entries older than the history. Returns the id of inserted entry.
+	pub fn import(&self, batch: &mut DBTransaction, mut reorg: Reorg) -> u64 {
+		let mut bounds = self.bounds.write();
+		reorg.id = bounds.next;
+		bounds.next += 1;
+
+		for hash in &reorg.retracted_transactions {
+			let mut reorgs: TransactionReorgs = self.db.read(db::COL_REORGS, hash).unwrap_or_else(Default::default);
+			reorgs.0.push(reorg.id);
+			batch.write(db::COL_REORGS, hash, &reorgs);
+		}
+		batch.write(db::COL_REORGS, &reorg.id, &reorg);
+
+		let best = reorg.new_number;
+		self.prune(batch, &mut bounds, best);
+		batch.put(db::COL_REORGS, &Bounds::key(), &bounds.encode());
+		reorg.id
+	}
+
+	/// Removes entries which fell out of the history.
+	/// Should be called whenever the best block changes.
+	pub fn collect_garbage(&self, batch: &mut DBTransaction, best: BlockNumber) {
+		let mut bounds = self.bounds.write();
+		let before = *bounds;
+		self.prune(batch, &mut bounds, best);
+		if *bounds != before {
+			batch.put(db::COL_REORGS, &Bounds::key(), &bounds.encode());
+		}
+	}
+
+	fn prune(&self, batch: &mut DBTransaction, bounds: &mut Bounds, best: BlockNumber) {
+		let mut pruned = Vec::new();
+		while bounds.first < bounds.next {
+			let reorg: Reorg = match self.db.read(db::COL_REORGS, &bounds.first) {
+				Some(reorg) => reorg,
+				None => {
+					bounds.first += 1;
+					continue;
+				}
+			};
+			if reorg.new_number + self.history >= best {
+				break;
+			}
+
+			trace!(target: "reorg", "Pruning reorganization #{} at block #{}", reorg.id, reorg.new_number);
+			pruned.push(reorg);
+			bounds.first += 1;
+		}
+		self.remove(batch, &pruned);
+	}
+
+	/// Removes entries of reorganizations which happened above block `number`.
+	/// Should be called when the best block is moved back to `number`.
+	pub fn rewind(&self, batch: &mut DBTransaction, number: BlockNumber) {
+		let bounds = *self.bounds.read();
+		let removed: Vec<Reorg> = (bounds.first..bounds.next)
+			.filter_map(|id| self.db.read::<Reorg, ReorgKey>(db::COL_REORGS, &id))
+			.filter(|reorg| reorg.new_number > number)
+			.collect();
+		for reorg in &removed {
+			trace!(target: "reorg", "Removing reorganization #{} at block #{}", reorg.id, reorg.new_number);
+		}
+		self.remove(batch, &removed);
+	}
+
+	/// Deletes given entries and their ids from the retracted transaction index.
+	/// The index is updated once per transaction, since all changes go to the same batch.
+	fn remove(&self, batch: &mut DBTransaction, reorgs: &[Reorg]) {
+		let mut index: HashMap<H256, Vec<u64>> = HashMap::new();
+		for reorg in reorgs {
+			for hash in &reorg.retracted_transactions {
+				index.entry(hash.clone()).or_insert_with(Vec::new).push(reorg.id);
+			}
+			Writable::delete::<Reorg, ReorgKey>(batch, db::COL_REORGS, &reorg.id);
+		}
+
+		for (hash, ids) in index {
+			let reorgs: Option<TransactionReorgs> = self.db.read(db::COL_REORGS, &hash);
+			let remaining: Vec<u64> = reorgs.map_or_else(Vec::new, |r| r.0.into_iter().filter(|id| !ids.contains(id)).collect());
+			match remaining.is_empty() {
+				true => Writable::delete::<TransactionReorgs, H264>(batch, db::COL_REORGS, &hash),
+				false => batch.write(db::COL_REORGS, &hash, &TransactionReorgs(remaining)),
+			}
+		}
+	}
+
+	/// Returns reorganizations which happened at blocks in range `from..=to`,
+	/// ordered from the oldest one.
+	pub fn reorgs(&self, from: BlockNumber, to: BlockNumber) -> Vec<Reorg> {
+		let bounds = *self.bounds.read();
+		(bounds.first..bounds.next)
+			.filter_map(|id| self.db.read::<Reorg, ReorgKey>(db::COL_REORGS, &id))
+			.filter(|reorg| reorg.new_number >= from && reorg.new_number <= to)
+			.collect()
+	}
+
+	/// Returns reorganizations which retracted given transaction.
+	pub fn transaction_reorgs(&self, hash: &H256) -> Vec<Reorg> {
+		let reorgs: TransactionReorgs = self.db.read(db::COL_REORGS, hash).unwrap_or_else(Default::default);
+		reorgs.0.into_iter()
+			.filter_map(|id| self.db.read::<Reorg, ReorgKey>(db::COL_REORGS, &id))
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use devtools::RandomTempPath;
+	use util::{Database, DatabaseConfig, DBTransaction, H256};
+	use types::reorg::Reorg;
+	use super::ReorgDB;
+
+	fn new_db(path: &str) -> Arc<Database> {
+		Arc::new(Database::open(&DatabaseConfig::with_columns(::db::NUM_COLUMNS), path).unwrap())
+	}
+
+	fn reorg(number: u64, retracted: Vec<H256>) -> Reorg {
+		Reorg {
+			id: 0,
+			timestamp: 0,
+			depth: 1,
+			old_head: H256::from(number),
+			old_number: number,
+			new_head: H256::from(number + 1000),
+			new_number: number,
+			retracted_blocks: vec![H256::from(number)],
+			retracted_transactions: retracted,
+			reincluded_transactions: vec![],
+		}
+	}
+
+	#[test]
+	fn should_record_and_query_reorgs() {
+		// given
+		let temp = RandomTempPath::new();
+		let db = new_db(temp.as_str());
+		let reorgs = ReorgDB::new(100, db.clone());
+
+		// when
+		let mut batch = DBTransaction::new(&db);
+		reorgs.import(&mut batch, reorg(10, vec![1.into(), 2.into()]));
+		reorgs.import(&mut batch, reorg(20, vec![2.into()]));
+		db.write(batch).unwrap();
+
+		// then
+		assert_eq!(reorgs.reorgs(0, 15).iter().map(|r| r.id).collect::<Vec<_>>(), vec![0]);
+		assert_eq!(reorgs.reorgs(0, 20).len(), 2);
+		assert_eq!(reorgs.transaction_reorgs(&2.into()).iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 1]);
+		assert!(reorgs.transaction_reorgs(&3.into()).is_empty());
+
+		// and after reopening
+		let reorgs = ReorgDB::new(100, db.clone());
+		assert_eq!(reorgs.reorgs(0, 20).len(), 2);
+	}
+
+	#[test]
+	fn should_prune_old_reorgs() {
+		// given
+		let temp = RandomTempPath::new();
+		let db = new_db(temp.as_str());
+		let reorgs = ReorgDB::new(100, db.clone());
+		let mut batch = DBTransaction::new(&db);
+		reorgs.import(&mut batch, reorg(10, vec![1.into(), 2.into()]));
+		reorgs.import(&mut batch, reorg(20, vec![2.into()]));
+		db.write(batch).unwrap();
+
+		// when
+		let mut batch = DBTransaction::new(&db);
+		reorgs.collect_garbage(&mut batch, 115);
+		db.write(batch).unwrap();
+
+		// then
+		assert_eq!(reorgs.reorgs(0, 200).iter().map(|r| r.id).collect::<Vec<_>>(), vec![1]);
+		assert!(reorgs.transaction_reorgs(&1.into()).is_empty());
+		assert_eq!(reorgs.transaction_reorgs(&2.into()).iter().map(|r| r.id).collect::<Vec<_>>(), vec![1]);
+	}
+
+	#[test]
+	fn should_remove_reorgs_above_rewound_block() {
+		// given
+		let temp = RandomTempPath::new();
+		let db = new_db(temp.as_str());
+		let reorgs = ReorgDB::new(100, db.clone());
+		let mut batch = DBTransaction::new(&db);
+		reorgs.import(&mut batch, reorg(10, vec![1.into(), 2.into()]));
+		reorgs.import(&mut batch, reorg(20, vec![2.into(), 3.into()]));
+		reorgs.import(&mut batch, reorg(30, vec![2.into()]));
+		db.write(batch).unwrap();
+
+		// when
+		let mut batch = DBTransaction::new(&db);
+		reorgs.rewind(&mut batch, 15);
+		db.write(batch).unwrap();
+
+		// then
+		assert_eq!(reorgs.reorgs(0, 200).iter().map(|r| r.id).collect::<Vec<_>>(), vec![0]);
+		assert_eq!(reorgs.transaction_reorgs(&2.into()).iter().map(|r| r.id).collect::<Vec<_>>(), vec![0]);
+		assert!(reorgs.transaction_reorgs(&3.into()).is_empty());
+
+		// and new entries get fresh ids
+		let mut batch = DBTransaction::new(&db);
+		assert_eq!(reorgs.import(&mut batch, reorg(16, vec![])), 3);
+	}
+}
@@ -43,6 +43,10 @@ use executive::Executed;
 use error::CallError;
 use trace::LocalizedTrace;
 use state_db::StateDB;
+use types::reorg::Reorg;
+use types::address_transaction::AddressTransaction;
+use types::account_proof::{AccountProof, StorageProof, StateError};
+use views::BlockView;
 
 /// Test client.
 pub struct TestBlockChainClient {
@@ -80,6 +84,16 @@ pub struct TestBlockChainClient {
 	pub vm_factory: EvmFactory,
 	/// Timestamp assigned to latest sealed block
 	pub latest_block_timestamp: RwLock<u64>,
+	/// Chain reorganizations.
+	pub reorgs: RwLock<Vec<Reorg>>,
+	/// Address index entries. `None` if the index is disabled.
+	pub address_transactions: RwLock<Option<Vec<AddressTransaction>>>,
+	/// Number of blocks not added to the address index yet.
+	pub address_index_unindexed: RwLock<BlockNumber>,
+	/// Number of the last block with pruned body and receipts.
+	pub pruned_number: RwLock<Option<BlockNumber>>,
+	/// Block retracted by the last rewind.
+	pub rewound_block: RwLock<Option<H256>>,
 }
 
 #[derive(Clone)]
@@ -123,6 +137,11 @@ impl TestBlockChainClient {
 			spec: spec,
 			vm_factory: EvmFactory::new(VMType::Interpreter),
 			latest_block_timestamp: RwLock::new(10_000_000),
+			reorgs: RwLock::new(Vec::new()),
+			address_transactions: RwLock::new(None),
+			address_index_unindexed: RwLock::new(0),
+			pruned_number: RwLock::new(None),
+			rewound_block: RwLock::new(None),
 		};
 		client.add_blocks(1, EachBlockWith::Nothing); // add genesis block
 		client.genesis_hash = client.last_hash.read().clone();
@@ -265,6 +284,28 @@ impl TestBlockChainClient {
 		}
 	}
 
+	fn account_rlp(&self, address: &Address) -> Option<Bytes> {
+		let exists = self.balances.read().contains_key(address)
+			|| self.nonces.read().contains_key(address)
+			|| self.code.read().contains_key(address);
+		match exists {
+			true => {
+				let mut stream = RlpStream::new_list(4);
+				stream.append(&self.latest_nonce(address));
+				stream.append(&self.latest_balance(address));
+				stream.append(&SHA3_NULL_RLP);
+				stream.append(&self.code.read().get(address).map_or(SHA3_EMPTY, |code| code.sha3()));
+				Some(stream.out())
+			},
+			false => None,
+		}
+	}
+
+	fn block_number(&self, id: BlockID) -> Option<BlockNumber> {
+		self.block_hash(id)
+			.and_then(|hash| self.blocks.read().get(&hash).map(|block| BlockView::new(block).header_view().number()))
+	}
+
 	/// Inserts a transaction to miners transactions queue.
 	pub fn insert_transaction_to_queue(&self) {
 		let keypair = Random.generate().unwrap();
@@ -288,7 +329,7 @@ pub fn get_temp_state_db() -> GuardedTempResult<StateDB> {
 	let temp = RandomTempPath::new();
 	let db = Database::open_default(temp.as_str()).unwrap();
 	let journal_db = journaldb::new(Arc::new(db), journaldb::Algorithm::EarlyMerge, None);
-	let state_db = StateDB::new(journal_db);
+	let state_db = StateDB::new(journal_db, 5 * 1024 * 1024);
 	GuardedTempResult {
 		_temp: temp,
 		result: Some(state_db)
@@ -384,6 +425,49 @@ impl BlockChainClient for TestBlockChainClient {
 		}
 	}
 
+	fn state_available(&self, id: BlockID) -> Result<(), StateError> {
+		match id {
+			BlockID::Latest | BlockID::Pending => Ok(()),
+			id if self.block_hash(id.clone()).is_some() => Err(StateError::StatePruned),
+			_ => Err(StateError::UnknownBlock),
+		}
+	}
+
+	fn rewind(&self, id: BlockID) -> Result<Vec<H256>, StateError> {
+		let number = try!(self.block_number(id).ok_or(StateError::UnknownBlock)) as usize;
+		let mut numbers = self.numbers.write();
+		let retracted: Vec<H256> = (number + 1..numbers.len()).rev().filter_map(|n| numbers.remove(&n)).collect();
+		let mut blocks = self.blocks.write();
+		for hash in &retracted {
+			blocks.remove(hash);
+		}
+		*self.last_hash.write() = numbers[&number].clone();
+		*self.rewound_block.write() = retracted.last().cloned();
+		Ok(retracted)
+	}
+
+	fn clear_rewound_block(&self) -> Option<H256> {
+		self.rewound_block.write().take()
+	}
+
+	fn prove_account(&self, address: &Address, storage_keys: Vec<H256>, id: BlockID) -> Result<AccountProof, StateError> {
+		try!(self.state_available(id));
+		Ok(AccountProof {
+			address: address.clone(),
+			balance: self.latest_balance(address),
+			nonce: self.latest_nonce(address),
+			code_hash: self.code.read().get(address).map_or(SHA3_EMPTY, |code| code.sha3()),
+			storage_root: SHA3_NULL_RLP,
+			account_rlp: self.account_rlp(address),
+			account_proof: Vec::new(),
+			storage_proof: storage_keys.into_iter().map(|key| StorageProof {
+				value: self.latest_storage_at(address, &key),
+				key: key,
+				proof: Vec::new(),
+			}).collect(),
+		})
+	}
+
 	fn transaction(&self, _id: TransactionID) -> Option<LocalizedTransaction> {
 		None	// Simple default.
 	}
@@ -422,6 +506,9 @@ impl BlockChainClient for TestBlockChainClient {
 	}
 
 	fn block_body(&self, id: BlockID) -> Option<Bytes> {
+		if self.is_pruned(id) {
+			return None;
+		}
 		self.block_hash(id).and_then(|hash| self.blocks.read().get(&hash).map(|r| {
 			let mut stream = RlpStream::new_list(2);
 			stream.append_raw(Rlp::new(r).at(1).as_raw(), 1);
@@ -431,9 +518,19 @@ impl BlockChainClient for TestBlockChainClient {
 	}
 
 	fn block(&self, id: BlockID) -> Option<Bytes> {
+		if self.is_pruned(id) {
+			return None;
+		}
 		self.block_hash(id).and_then(|hash| self.blocks.read().get(&hash).cloned())
 	}
 
+	fn is_pruned(&self, id: BlockID) -> bool {
+		match (self.block_number(id), *self.pruned_number.read()) {
+			(Some(number), Some(pruned)) => number <= pruned,
+			_ => false,
+		}
+	}
+
 	fn block_status(&self, id: BlockID) -> BlockStatus {
 		match id {
 			BlockID::Number(number) if (number as usize) < self.blocks.read().len() => BlockStatus::InChain,
@@ -590,6 +687,36 @@ impl BlockChainClient for TestBlockChainClient {
 		unimplemented!();
 	}
 
+	fn reorgs(&self, from: BlockID, to: BlockID) -> Option<Vec<Reorg>> {
+		match (self.block_number(from), self.block_number(to)) {
+			(Some(from), Some(to)) => Some(self.reorgs.read().iter()
+				.filter(|reorg| reorg.new_number >= from && reorg.new_number <= to)
+				.cloned()
+				.collect()),
+			_ => None,
+		}
+	}
+
+	fn transaction_reorgs(&self, hash: &H256) -> Vec<Reorg> {
+		self.reorgs.read().iter()
+			.filter(|reorg| reorg.retracted_transactions.contains(hash))
+			.cloned()
+			.collect()
+	}
+
+	fn address_transactions(&self, address: &Address, offset: usize, limit: usize) -> Option<Vec<AddressTransaction>> {
+		self.address_transactions.read().as_ref().map(|transactions| transactions.iter()
+			.filter(|tx| tx.address == *address)
+			.skip(offset)
+			.take(limit)
+			.cloned()
+			.collect())
+	}
+
+	fn address_index_unindexed(&self) -> BlockNumber {
+		*self.address_index_unindexed.read()
+	}
+
 	fn queue_transactions(&self, transactions: Vec<Bytes>) {
 		// import right here
 		let txs = transactions.into_iter().filter_map(|bytes| UntrustedRlp::new(&bytes).as_val().ok()).collect();
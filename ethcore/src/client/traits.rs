@@ -37,6 +37,9 @@ use block_import_error::BlockImportError;
 use ipc::IpcConfig;
 use types::blockchain_info::BlockChainInfo;
 use types::block_status::BlockStatus;
+use types::reorg::Reorg;
+use types::address_transaction::AddressTransaction;
+use types::account_proof::{AccountProof, StateError};
 
 #[derive(Ipc)]
 #[ipc(client_ident="RemoteClient")]
@@ -57,6 +60,9 @@ pub trait BlockChainClient : Sync + Send {
 	/// Get raw block data by block header hash.
 	fn block(&self, id: BlockID) -> Option<Bytes>;
 
+	/// Returns true if body and receipts of given block were removed by ancient block pruning.
+	fn is_pruned(&self, id: BlockID) -> bool;
+
 	/// Get block status by block header hash.
 	fn block_status(&self, id: BlockID) -> BlockStatus;
 
@@ -105,6 +111,24 @@ pub trait BlockChainClient : Sync + Send {
 	/// Returns None if and only if the block's root hash has been pruned from the DB.
 	fn storage_at(&self, address: &Address, position: &H256, id: BlockID) -> Option<H256>;
 
+	/// Checks if state of given block can be queried.
+	/// Fails with `StateError::StatePruned` if the state has been removed by state pruning.
+	fn state_available(&self, id: BlockID) -> Result<(), StateError>;
+
+	/// Rewind the best block back to the given canonical block.
+	/// Transactions from retracted blocks are returned to the transaction queue.
+	/// The first retracted block is not imported again until `clear_rewound_block` is called
+	/// or the chain is rewound again. The guard is not persisted.
+	/// Fails with `StateError::StatePruned` if the state of the block is not available.
+	/// Returns hashes of retracted blocks, starting from the newest one.
+	fn rewind(&self, id: BlockID) -> Result<Vec<H256>, StateError>;
+
+	/// Allow importing the block retracted by the last rewind again. Returns its hash, if any.
+	fn clear_rewound_block(&self) -> Option<H256>;
+
+	/// Get Merkle proof of the account and given storage entries at given block's state.
+	fn prove_account(&self, address: &Address, storage_keys: Vec<H256>, id: BlockID) -> Result<AccountProof, StateError>;
+
 	/// Get value of the storage at given position at the latest block's state.
 	fn latest_storage_at(&self, address: &Address, position: &H256) -> H256 {
 		self.storage_at(address, position, BlockID::Latest)
@@ -176,6 +200,19 @@ pub trait BlockChainClient : Sync + Send {
 	/// Returns traces created by transaction from block.
 	fn block_traces(&self, trace: BlockID) -> Option<Vec<LocalizedTrace>>;
 
+	/// Returns chain reorganizations which happened while importing blocks in given range.
+	fn reorgs(&self, from: BlockID, to: BlockID) -> Option<Vec<Reorg>>;
+
+	/// Returns chain reorganizations which retracted given transaction.
+	fn transaction_reorgs(&self, hash: &H256) -> Vec<Reorg>;
+
+	/// Returns at most `limit` canonical transactions sent from or to given address, skipping first `offset`
+	/// of them and ordered from the oldest one. Returns `None` if the address index is disabled.
+	fn address_transactions(&self, address: &Address, offset: usize, limit: usize) -> Option<Vec<AddressTransaction>>;
+
+	/// Returns the number of blocks imported before the address index was enabled which are not indexed yet.
+	fn address_index_unindexed(&self) -> BlockNumber;
+
 	/// Get last hashes starting from best block.
 	fn last_hashes(&self) -> LastHashes;
 
@@ -70,6 +70,9 @@ pub trait Engine : Sync + Send {
 	fn on_new_block(&self, _block: &mut ExecutedBlock) {}
 	/// Block transformation functions, after the transactions.
 	fn on_close_block(&self, _block: &mut ExecutedBlock) {}
+	/// Prepare data needed to seal blocks following `best_block`, using up to `threads` threads.
+	/// Called by the client on startup and when new blocks are imported.
+	fn prepare_sealing_data(&self, _best_block: BlockNumber, _threads: usize) {}
 
 	/// If Some(true) this author is able to generate seals, generate_seal has to be implemented.
 	/// None indicates that this Engine never seals internally regardless of author (e.g. PoW).
@@ -194,6 +194,8 @@ pub enum ImportError {
 	AlreadyQueued,
 	/// Already marked as bad from a previous import (could mean parent is bad).
 	KnownBad,
+	/// Retracted by a chain rewind and not accepted until the guard is cleared.
+	Rewound,
 }
 
 impl fmt::Display for ImportError {
@@ -202,6 +204,7 @@ impl fmt::Display for ImportError {
 			ImportError::AlreadyInChain => "block already in chain",
 			ImportError::AlreadyQueued => "block already in the block queue",
 			ImportError::KnownBad => "block known to be bad",
+			ImportError::Rewound => "block retracted by a chain rewind",
 		};
 
 		f.write_fmt(format_args!("Block import error ({})", msg))
@@ -354,6 +357,7 @@ impl From<SnapshotError> for Error {
 			SnapshotError::Io(err) => Error::StdIo(err),
 			SnapshotError::Trie(err) => Error::Trie(err),
 			SnapshotError::Decoder(err) => err.into(),
+			SnapshotError::Snappy(err) => Error::Snappy(err),
 			other => Error::Snapshot(other),
 		}
 	}
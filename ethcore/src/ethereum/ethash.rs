@@ -165,6 +165,11 @@ impl Engine for Ethash {
 		}
 	}
 
+	/// Generate the full DAG of the next block's epoch, and of the epoch after it when close to the boundary.
+	fn prepare_sealing_data(&self, best_block: BlockNumber, threads: usize) {
+		self.pow.prepare_dag(best_block + 1, threads);
+	}
+
 	/// Apply the block reward on finalisation of the block.
 	/// This assumes that all uncles are valid uncles (i.e. of at least one generation before the current).
 	fn on_close_block(&self, block: &mut ExecutedBlock) {
@@ -48,6 +48,9 @@ pub fn new_expanse() -> Spec { load(include_bytes!("../../res/ethereum/expanse.j
 /// Create a new Frontier chain spec as though it never changes to Homestead.
 pub fn new_frontier_test() -> Spec { load(include_bytes!("../../res/ethereum/frontier_test.json")) }
 
+/// Create a new Frontier chain spec with minimal difficulty, for tests mining real blocks.
+pub fn new_mining_test() -> Spec { load(include_bytes!("../../res/ethereum/mining_test.json")) }
+
 /// Create a new Homestead chain spec as though it never changed from Frontier.
 pub fn new_homestead_test() -> Spec { load(include_bytes!("../../res/ethereum/homestead_test.json")) }
 
@@ -95,6 +95,7 @@ extern crate ethash;
 extern crate ethkey;
 extern crate semver;
 extern crate ethcore_ipc_nano as nanoipc;
+extern crate ethcore_stratum;
 extern crate ethcore_devtools as devtools;
 extern crate rand;
 extern crate bit_set;
@@ -129,6 +130,7 @@ pub mod trace;
 pub mod spec;
 pub mod views;
 pub mod pod_state;
+pub mod pod_account;
 pub mod migrations;
 pub mod miner;
 pub mod snapshot;
@@ -141,7 +143,6 @@ mod blooms;
 mod common;
 mod basic_types;
 mod env_info;
-mod pod_account;
 mod state;
 mod state_db;
 mod account_db;
@@ -23,3 +23,21 @@ pub mod extras;
 mod v9;
 pub use self::v9::ToV9;
 pub use self::v9::Extract;
+
+use util::migration::ChangeColumns;
+
+/// The migration from v9 to v10.
+/// Adds a column for chain reorganization history.
+pub const TO_V10: ChangeColumns = ChangeColumns {
+	pre_columns: Some(5),
+	post_columns: Some(6),
+	version: 10,
+};
+
+/// The migration from v10 to v11.
+/// Adds a column for address transactions index.
+pub const TO_V11: ChangeColumns = ChangeColumns {
+	pre_columns: Some(6),
+	post_columns: Some(7),
+	version: 11,
+};
@@ -0,0 +1,204 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Internal multi-threaded CPU miner for Ethash chains, meant for development networks
+
+use std::thread;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use util::{H256, H64, U256, Mutex, Condvar};
+use ethash::EthashManager;
+use ethereum::ethash::Ethash;
+use client::Client;
+use miner::{Miner, MinerService};
+use miner::work_notify::NotifyWork;
+use rand;
+use rlp;
+
+// Number of nonces tried between checks for new work.
+const NONCES_PER_ROUND: u64 = 256;
+
+/// Configures the internal CPU miner.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Options {
+	/// Number of mining threads
+	pub threads: usize,
+	/// Hash with the full DAG, generated by the client ahead of each epoch, instead of the light cache
+	pub use_dag: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Job {
+	pow_hash: H256,
+	difficulty: U256,
+	number: u64,
+}
+
+struct Shared {
+	job: Mutex<Option<Job>>,
+	new_work: Condvar,
+	/// Bumped whenever the job changes, so workers notice stale work without locking.
+	generation: AtomicUsize,
+	exit: AtomicBool,
+	pow: EthashManager,
+	use_dag: bool,
+	client: Weak<Client>,
+	miner: Weak<Miner>,
+}
+
+impl Shared {
+	fn submit(&self, job: &Job, nonce: u64, mix_hash: H256) {
+		trace!(target: "miner", "CPU miner found seal for {}: nonce={:x}, mix_hash={}", job.pow_hash, nonce, mix_hash);
+		let seal = vec![rlp::encode(&mix_hash).to_vec(), rlp::encode(&H64::from(nonce)).to_vec()];
+		let result = self.client.upgrade().and_then(|client| self.miner.upgrade().map(|miner| miner.submit_seal(&*client, job.pow_hash, seal)));
+		if let Some(Err(e)) = result {
+			warn!(target: "miner", "CPU miner seal rejected: {:?}", e);
+		}
+	}
+
+	fn work(&self) {
+		loop {
+			let (job, generation) = {
+				let mut job = self.job.lock();
+				while job.is_none() && !self.exit.load(Ordering::Relaxed) {
+					self.new_work.wait(&mut job);
+				}
+				if self.exit.load(Ordering::Relaxed) {
+					return;
+				}
+				(job.clone().expect("loop above exits only with a job; qed"), self.generation.load(Ordering::SeqCst))
+			};
+
+			let mut nonce = rand::random::<u64>();
+			while self.generation.load(Ordering::Relaxed) == generation && !self.exit.load(Ordering::Relaxed) {
+				if let Some((nonce, mix_hash)) = search(&self.pow, self.use_dag, &job, nonce, NONCES_PER_ROUND) {
+					self.submit(&job, nonce, mix_hash);
+					// the job is solved, idle until the miner hands out a new one.
+					let mut current = self.job.lock();
+					if self.generation.load(Ordering::SeqCst) == generation {
+						*current = None;
+						self.generation.fetch_add(1, Ordering::SeqCst);
+					}
+					break;
+				}
+				nonce = nonce.wrapping_add(NONCES_PER_ROUND);
+			}
+		}
+	}
+}
+
+/// Tries `count` nonces from `start` and returns the first one meeting the job's difficulty, with its mix hash.
+/// Uses the full DAG when asked to and it's on disk, and the light cache otherwise.
+fn search(pow: &EthashManager, use_dag: bool, job: &Job, start: u64, count: u64) -> Option<(u64, H256)> {
+	for i in 0..count {
+		let nonce = start.wrapping_add(i);
+		let result = match use_dag {
+			true => pow.compute(job.number, &job.pow_hash.0, nonce),
+			false => pow.compute_light(job.number, &job.pow_hash.0, nonce),
+		};
+		if Ethash::boundary_to_difficulty(&H256(result.value)) >= job.difficulty {
+			return Some((nonce, H256(result.mix_hash)));
+		}
+	}
+	None
+}
+
+/// Searches for seals of the miner's work packages on local CPU threads.
+pub struct CpuMiner {
+	shared: Arc<Shared>,
+}
+
+impl NotifyWork for CpuMiner {
+	fn notify(&self, pow_hash: H256, difficulty: U256, number: u64) {
+		trace!(target: "miner", "CPU miner got new work #{}: {}", number, pow_hash);
+		let mut job = self.shared.job.lock();
+		*job = Some(Job {
+			pow_hash: pow_hash,
+			difficulty: difficulty,
+			number: number,
+		});
+		self.shared.generation.fetch_add(1, Ordering::SeqCst);
+		self.shared.new_work.notify_all();
+	}
+}
+
+impl Drop for CpuMiner {
+	fn drop(&mut self) {
+		let _job = self.shared.job.lock();
+		self.shared.exit.store(true, Ordering::SeqCst);
+		self.shared.new_work.notify_all();
+	}
+}
+
+impl CpuMiner {
+	/// Start mining threads, idle until the first work package arrives.
+	pub fn start(options: &Options, miner: Weak<Miner>, client: Weak<Client>) -> Result<CpuMiner, String> {
+		let shared = Arc::new(Shared {
+			job: Mutex::new(None),
+			new_work: Condvar::new(),
+			generation: AtomicUsize::new(0),
+			exit: AtomicBool::new(false),
+			pow: EthashManager::new(),
+			use_dag: options.use_dag,
+			client: client,
+			miner: miner,
+		});
+		for i in 0..options.threads {
+			let shared = shared.clone();
+			try!(thread::Builder::new().name(format!("cpu-miner-{}", i)).spawn(move || shared.work())
+				.map_err(|e| format!("Unable to start mining thread: {}", e)));
+		}
+		Ok(CpuMiner {
+			shared: shared,
+		})
+	}
+
+	/// Start the CPU miner and register it in the miner. Hands out the first work package right away.
+	pub fn register(options: &Options, miner: Arc<Miner>, client: Weak<Client>) -> Result<(), String> {
+		let cpu_miner = try!(CpuMiner::start(options, Arc::downgrade(&miner), client.clone()));
+		miner.add_work_listener(Box::new(cpu_miner) as Box<NotifyWork>);
+		if let Some(client) = client.upgrade() {
+			miner.update_sealing(&*client);
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Job, search};
+	use util::{H256, U256};
+	use ethash::{EthashManager, quick_get_difficulty};
+	use ethereum::ethash::Ethash;
+
+	#[test]
+	fn finds_seal_for_low_difficulty() {
+		// given
+		let pow = EthashManager::new();
+		let job = Job {
+			pow_hash: H256::from(1),
+			difficulty: U256::from(4),
+			number: 1,
+		};
+
+		// when
+		let (nonce, mix_hash) = search(&pow, false, &job, 0, 1000).expect("one in four nonces is a solution");
+
+		// then
+		let value = quick_get_difficulty(&job.pow_hash.0, nonce, &mix_hash.0);
+		assert!(Ethash::boundary_to_difficulty(&H256(value)) >= job.difficulty);
+	}
+}
@@ -30,8 +30,8 @@ use transaction::{Action, SignedTransaction};
 use receipt::{Receipt, RichReceipt};
 use spec::Spec;
 use engines::Engine;
-use miner::{MinerService, MinerStatus, TransactionQueue, AccountDetails, TransactionOrigin};
-use miner::work_notify::WorkPoster;
+use miner::{MinerService, MinerStatus, TransactionQueue, AccountDetails, TransactionOrigin, Stratum, WorkerStats};
+use miner::work_notify::{WorkPoster, NotifyWork};
 use client::TransactionImportResult;
 use miner::price_info::PriceInfo;
 use header::BlockNumber;
@@ -183,17 +183,18 @@ pub struct Miner {
 	engine: Arc<Engine>,
 
 	accounts: Option<Arc<AccountProvider>>,
-	work_poster: Option<WorkPoster>,
+	notifiers: RwLock<Vec<Box<NotifyWork>>>,
+	stratum: RwLock<Option<Stratum>>,
 	gas_pricer: Mutex<GasPricer>,
 }
 
 impl Miner {
 	/// Creates new instance of miner.
 	fn new_raw(options: MinerOptions, gas_pricer: GasPricer, spec: &Spec, accounts: Option<Arc<AccountProvider>>) -> Miner {
-		let work_poster = match options.new_work_notify.is_empty() {
-			true => None,
-			false => Some(WorkPoster::new(&options.new_work_notify))
-		};
+		let mut notifiers: Vec<Box<NotifyWork>> = Vec::new();
+		if !options.new_work_notify.is_empty() {
+			notifiers.push(Box::new(WorkPoster::new(&options.new_work_notify)));
+		}
 		let txq = Arc::new(Mutex::new(TransactionQueue::with_limits(options.tx_queue_size, options.tx_gas_limit)));
 		Miner {
 			transaction_queue: txq,
@@ -212,7 +213,8 @@ impl Miner {
 			options: options,
 			accounts: accounts,
 			engine: spec.engine.clone(),
-			work_poster: work_poster,
+			notifiers: RwLock::new(notifiers),
+			stratum: RwLock::new(None),
 			gas_pricer: Mutex::new(gas_pricer),
 		}
 	}
@@ -228,7 +230,18 @@ impl Miner {
 	}
 
 	fn forced_sealing(&self) -> bool {
-		self.options.force_sealing || !self.options.new_work_notify.is_empty()
+		self.options.force_sealing || !self.notifiers.read().is_empty()
+	}
+
+	/// Registers a listener notified about every new work package. Enables sealing.
+	pub fn add_work_listener(&self, notifier: Box<NotifyWork>) {
+		self.notifiers.write().push(notifier);
+		self.sealing_work.lock().enabled = true;
+	}
+
+	/// Sets the stratum server whose share accounting is reported by `stratum_workers`.
+	pub fn set_stratum(&self, stratum: Stratum) {
+		*self.stratum.write() = Some(stratum);
 	}
 
 	/// Clear all pending block states
@@ -424,7 +437,7 @@ impl Miner {
 				let is_new = original_work_hash.map_or(true, |h| block.block().fields().header.hash() != h);
 				sealing_work.queue.push(block);
 				// If push notifications are enabled we assume all work items are used.
-				if self.work_poster.is_some() && is_new {
+				if !self.notifiers.read().is_empty() && is_new {
 					sealing_work.queue.use_last_ref();
 				}
 				(Some((pow_hash, difficulty, number)), is_new)
@@ -435,7 +448,11 @@ impl Miner {
 			(work, is_new)
 		};
 		if is_new {
-			work.map(|(pow_hash, difficulty, number)| self.work_poster.as_ref().map(|p| p.notify(pow_hash, difficulty, number)));
+			if let Some((pow_hash, difficulty, number)) = work {
+				for notifier in self.notifiers.read().iter() {
+					notifier.notify(pow_hash, difficulty, number);
+				}
+			}
 		}
 	}
 
@@ -853,6 +870,10 @@ impl MinerService for Miner {
 		self.sealing_work.lock().queue.is_in_use()
 	}
 
+	fn stratum_workers(&self) -> HashMap<String, WorkerStats> {
+		self.stratum.read().as_ref().map_or_else(HashMap::new, |stratum| stratum.workers())
+	}
+
 	fn map_sealing_work<F, T>(&self, chain: &MiningBlockChainClient, f: F) -> Option<T> where F: FnOnce(&ClosedBlock) -> T {
 		trace!(target: "miner", "map_sealing_work: entering");
 		self.prepare_work_sealing(chain);
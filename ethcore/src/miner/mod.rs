@@ -45,14 +45,21 @@ mod miner;
 mod external;
 mod transaction_queue;
 mod work_notify;
+mod work_push;
 mod price_info;
+mod stratum;
+mod cpu;
 
 pub use self::transaction_queue::{TransactionQueue, AccountDetails, TransactionOrigin};
 pub use self::miner::{Miner, MinerOptions, PendingSet, GasPricer, GasPriceCalibratorOptions};
 pub use self::external::{ExternalMiner, ExternalMinerService};
+pub use self::work_notify::NotifyWork;
+pub use self::stratum::{Stratum, Error as StratumError, Options as StratumOptions};
+pub use self::cpu::{CpuMiner, Options as CpuMinerOptions};
+pub use ethcore_stratum::{VardiffConfig, WorkerStats};
 pub use client::TransactionImportResult;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use util::{H256, U256, Address, Bytes};
 use client::{MiningBlockChainClient, Executed, CallAnalytics};
 use block::ClosedBlock;
@@ -155,6 +162,9 @@ pub trait MinerService : Send + Sync {
 	/// Is it currently sealing?
 	fn is_sealing(&self) -> bool;
 
+	/// Share accounting of workers connected to the stratum server, by worker name.
+	fn stratum_workers(&self) -> HashMap<String, WorkerStats> { HashMap::new() }
+
 	/// Suggested gas price.
 	fn sensible_gas_price(&self) -> U256 { 20000000000u64.into() }
 
@@ -0,0 +1,180 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Client-side stratum job dispatcher and mining notifier handler
+
+use ethcore_stratum::{
+	JobDispatcher, PushWorkHandler, Stratum as StratumServer, Error as StratumServiceError,
+	Work, Share, VardiffConfig, WorkerStats,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::net::SocketAddr;
+use util::{H256, H64, U256, Mutex, FixedHash};
+use ethash::{EthashManager, SeedHashCompute};
+use ethereum::ethash::Ethash;
+use client::Client;
+use miner::{Miner, MinerService};
+use miner::work_notify::NotifyWork;
+use rlp;
+
+/// Configures stratum server options.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Options {
+	/// Network address
+	pub listen_addr: String,
+	/// Port
+	pub port: u16,
+	/// Hash of the secret workers have to provide
+	pub secret: Option<H256>,
+	/// Share difficulty settings
+	pub vardiff: VardiffConfig,
+}
+
+/// Job dispatcher for stratum service
+pub struct StratumJobDispatcher {
+	seed_compute: Mutex<SeedHashCompute>,
+	pow: EthashManager,
+	client: Weak<Client>,
+	miner: Weak<Miner>,
+}
+
+impl JobDispatcher for StratumJobDispatcher {
+	fn work(&self) -> Option<Work> {
+		self.with_core(|client, miner| miner.map_sealing_work(&*client, |b| {
+			let header = &b.block().fields().header;
+			self.payload(header.hash(), *header.difficulty(), header.number())
+		})).and_then(|work| work)
+	}
+
+	fn submit(&self, share: Share) -> Result<U256, StratumServiceError> {
+		// The mix hash supplied by the worker can't be trusted, recompute it with the light cache.
+		let pow = self.pow.compute_light(share.number, &share.pow_hash.0, share.nonce);
+		if H256(pow.mix_hash) != share.mix_hash {
+			trace!(target: "stratum", "submit: Invalid mix hash: expected={}, got={}", H256(pow.mix_hash), share.mix_hash);
+			return Err(StratumServiceError::Dispatch("Invalid mix hash".into()));
+		}
+		let difficulty = Ethash::boundary_to_difficulty(&H256(pow.value));
+		if difficulty < share.difficulty {
+			return Ok(difficulty);
+		}
+
+		trace!(target: "stratum", "submit: Block solution found: nonce={:x}, pow_hash={}, mix_hash={}", share.nonce, share.pow_hash, share.mix_hash);
+		let seal = vec![rlp::encode(&share.mix_hash).to_vec(), rlp::encode(&H64::from(share.nonce)).to_vec()];
+		match self.with_core(|client, miner| miner.submit_seal(&*client, share.pow_hash, seal)) {
+			Some(Ok(_)) => Ok(difficulty),
+			Some(Err(e)) => {
+				warn!(target: "stratum", "submit_seal error: {:?}", e);
+				Err(StratumServiceError::Dispatch(format!("{:?}", e)))
+			},
+			None => Err(StratumServiceError::Dispatch("Client or miner is not available".into())),
+		}
+	}
+}
+
+impl StratumJobDispatcher {
+	/// New stratum job dispatcher given the miner and client
+	fn new(miner: Weak<Miner>, client: Weak<Client>) -> StratumJobDispatcher {
+		StratumJobDispatcher {
+			seed_compute: Mutex::new(SeedHashCompute::new()),
+			pow: EthashManager::new(),
+			client: client,
+			miner: miner,
+		}
+	}
+
+	/// Serializes payload for stratum service
+	fn payload(&self, pow_hash: H256, difficulty: U256, number: u64) -> Work {
+		let seed_hash = self.seed_compute.lock().get_seedhash(number);
+		Work {
+			pow_hash: pow_hash,
+			seed_hash: H256::from_slice(&seed_hash[..]),
+			difficulty: difficulty,
+			number: number,
+		}
+	}
+
+	fn with_core<F, R>(&self, f: F) -> Option<R> where F: FnOnce(Arc<Client>, Arc<Miner>) -> R {
+		self.client.upgrade().and_then(|client| self.miner.upgrade().and_then(|miner| (Some(f(client, miner)))))
+	}
+}
+
+/// Wrapper for dedicated stratum service
+#[derive(Clone)]
+pub struct Stratum {
+	dispatcher: Arc<StratumJobDispatcher>,
+	service: Arc<StratumServer>,
+}
+
+#[derive(Debug)]
+/// Stratum error
+pub enum Error {
+	/// IPC sockets error
+	Service(StratumServiceError),
+	/// Invalid network address
+	Address(String),
+}
+
+impl From<StratumServiceError> for Error {
+	fn from(service_err: StratumServiceError) -> Error { Error::Service(service_err) }
+}
+
+impl NotifyWork for Stratum {
+	fn notify(&self, pow_hash: H256, difficulty: U256, number: u64) {
+		trace!(target: "stratum", "Notify work");
+
+		let work = self.dispatcher.payload(pow_hash, difficulty, number);
+		self.service.push_new_work(work).unwrap_or_else(
+			|e| warn!(target: "stratum", "Error while pushing work: {:?}", e)
+		);
+	}
+}
+
+impl Stratum {
+
+	/// New stratum job dispatcher, given the miner, client and dedicated stratum service
+	pub fn start(options: &Options, miner: Weak<Miner>, client: Weak<Client>) -> Result<Stratum, Error> {
+		use std::str::FromStr;
+
+		let addr = try!(SocketAddr::from_str(&format!("{}:{}", options.listen_addr, options.port))
+			.map_err(|_| Error::Address(format!("{}:{}", options.listen_addr, options.port))));
+		let dispatcher = Arc::new(StratumJobDispatcher::new(miner, client));
+		let stratum_svc = try!(StratumServer::start_with_vardiff(
+			&addr,
+			dispatcher.clone(),
+			options.secret,
+			options.vardiff.clone(),
+		).map_err(|e| Error::Service(StratumServiceError::Io(format!("{:?}", e)))));
+
+		Ok(Stratum {
+			dispatcher: dispatcher,
+			service: stratum_svc,
+		})
+	}
+
+	/// Share accounting of connected workers, by worker name
+	pub fn workers(&self) -> HashMap<String, WorkerStats> {
+		self.service.workers()
+	}
+
+	/// Start STRATUM job dispatcher and register it in the miner
+	pub fn register(cfg: &Options, miner: Arc<Miner>, client: Weak<Client>) -> Result<(), Error> {
+		let stratum = try!(Stratum::start(cfg, Arc::downgrade(&miner), client));
+		miner.add_work_listener(Box::new(stratum.clone()) as Box<NotifyWork>);
+		miner.set_stratum(stratum);
+		Ok(())
+	}
+}
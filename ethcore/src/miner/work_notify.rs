@@ -25,18 +25,130 @@ use ethash::SeedHashCompute;
 use hyper::Url;
 use util::*;
 use ethereum::ethash::Ethash;
+use miner::work_push::{PushChannel, Endpoint};
 
+/// Trait for notifying about new mining work
+pub trait NotifyWork : Send + Sync {
+	/// Fired when new mining job available
+	fn notify(&self, pow_hash: H256, difficulty: U256, number: u64);
+}
+
+/// Per-subscriber filter of work packages.
+/// Given in the URL fragment, e.g. `tcp://10.0.0.5:3000#new_blocks_only&min_difficulty_change=5`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Filter {
+	/// Skip packages for the block number that was already notified.
+	pub new_blocks_only: bool,
+	/// Skip packages for the already notified block number unless difficulty changed by this percentage.
+	pub min_difficulty_change: Option<u64>,
+}
+
+impl Filter {
+	/// Parses filter from URL fragment.
+	pub fn from_fragment(fragment: Option<&str>) -> Result<Filter, String> {
+		let mut filter = Filter::default();
+		for option in fragment.unwrap_or("").split('&').filter(|o| !o.is_empty()) {
+			let mut kv = option.splitn(2, '=');
+			match (kv.next(), kv.next()) {
+				(Some("new_blocks_only"), None) => filter.new_blocks_only = true,
+				(Some("min_difficulty_change"), Some(v)) => filter.min_difficulty_change = Some(try!(v.parse().map_err(|_| format!("Invalid difficulty change: {}", v)))),
+				_ => return Err(format!("Unknown work notification filter: {}", option)),
+			}
+		}
+		Ok(filter)
+	}
+
+	/// Checks whether package should be delivered given last delivered package.
+	pub fn accepts(&self, last: Option<&(U256, u64)>, difficulty: &U256, number: u64) -> bool {
+		let &(ref last_difficulty, last_number) = match last {
+			Some(last) => last,
+			None => return true,
+		};
+		if last_number != number {
+			return true;
+		}
+		if self.new_blocks_only {
+			return false;
+		}
+		match self.min_difficulty_change {
+			Some(percent) => {
+				let change = if difficulty > last_difficulty { *difficulty - *last_difficulty } else { *last_difficulty - *difficulty };
+				change * U256::from(100) >= *last_difficulty * U256::from(percent)
+			},
+			None => true,
+		}
+	}
+}
+
+enum Transport {
+	Http(Url),
+	Push(PushChannel),
+}
+
+struct Subscriber {
+	transport: Transport,
+	filter: Filter,
+	last: Mutex<Option<(U256, u64)>>,
+}
+
+impl Subscriber {
+	fn new(url: &str) -> Result<Subscriber, String> {
+		let mut url = try!(Url::parse(url).map_err(|e| format!("{}", e)));
+		let filter = try!(Filter::from_fragment(url.fragment()));
+		url.set_fragment(None);
+
+		let transport = match url.scheme() {
+			"http" | "https" => Transport::Http(url),
+			"tcp" | "ws" => {
+				let host = try!(url.host_str().ok_or_else(|| "Host is required".to_owned())).to_owned();
+				let port = try!(url.port_or_known_default().ok_or_else(|| "Port is required".to_owned()));
+				let addr = format!("{}:{}", host, port);
+				let endpoint = match url.scheme() {
+					"tcp" => Endpoint::Tcp(addr),
+					_ => Endpoint::WebSocket {
+						host: addr.clone(),
+						addr: addr,
+						path: match url.query() {
+							Some(query) => format!("{}?{}", url.path(), query),
+							None => url.path().to_owned(),
+						},
+					},
+				};
+				Transport::Push(PushChannel::new(endpoint))
+			},
+			scheme => return Err(format!("Unsupported scheme: {}", scheme)),
+		};
+
+		Ok(Subscriber {
+			transport: transport,
+			filter: filter,
+			last: Mutex::new(None),
+		})
+	}
+
+	/// Returns `true` if package passes the filter and marks it as delivered.
+	fn accepts(&self, difficulty: &U256, number: u64) -> bool {
+		let mut last = self.last.lock();
+		let accepts = self.filter.accepts(last.as_ref(), difficulty, number);
+		if accepts {
+			*last = Some((*difficulty, number));
+		}
+		accepts
+	}
+}
+
+/// Notifies subscribers about new work over HTTP POST, TCP or WebSocket.
 pub struct WorkPoster {
-	urls: Vec<Url>,
+	subscribers: Vec<Subscriber>,
 	client: Mutex<Client<PostHandler>>,
 	seed_compute: Mutex<SeedHashCompute>,
 }
 
 impl WorkPoster {
 	pub fn new(urls: &[String]) -> Self {
-		let urls = urls.into_iter().filter_map(|u| {
-			match Url::parse(u) {
-				Ok(url) => Some(url),
+		let subscribers = urls.into_iter().filter_map(|u| {
+			match Subscriber::new(u) {
+				Ok(subscriber) => Some(subscriber),
 				Err(e) => {
 					warn!("Error parsing URL {} : {}", u, e);
 					None
@@ -46,7 +158,7 @@ impl WorkPoster {
 		let client = WorkPoster::create_client();
 		WorkPoster {
 			client: Mutex::new(client),
-			urls: urls,
+			subscribers: subscribers,
 			seed_compute: Mutex::new(SeedHashCompute::new()),
 		}
 	}
@@ -58,7 +170,21 @@ impl WorkPoster {
 			.expect("Error creating HTTP client")
 	}
 
-	pub fn notify(&self, pow_hash: H256, difficulty: U256, number: u64) {
+	fn post(&self, u: &Url, body: &str) {
+		let mut client = self.client.lock();
+		if let Err(e) = client.request(u.clone(), PostHandler { body: body.to_owned() }) {
+			warn!("Error sending HTTP notification to {} : {}, retrying", u, e);
+			// TODO: remove this once https://github.com/hyperium/hyper/issues/848 is fixed
+			*client = WorkPoster::create_client();
+			if let Err(e) = client.request(u.clone(), PostHandler { body: body.to_owned() }) {
+				warn!("Error sending HTTP notification to {} : {}", u, e);
+			}
+		}
+	}
+}
+
+impl NotifyWork for WorkPoster {
+	fn notify(&self, pow_hash: H256, difficulty: U256, number: u64) {
 		// TODO: move this to engine
 		let target = Ethash::difficulty_to_boundary(&difficulty);
 		let seed_hash = &self.seed_compute.lock().get_seedhash(number);
@@ -67,15 +193,10 @@ impl WorkPoster {
 			r#"{{ "result": ["0x{}","0x{}","0x{}","0x{:x}"] }}"#,
 			pow_hash.hex(), seed_hash.hex(), target.hex(), number
 		);
-		let mut client = self.client.lock();
-		for u in &self.urls {
-			if let Err(e) = client.request(u.clone(), PostHandler { body: body.clone() }) {
-				warn!("Error sending HTTP notification to {} : {}, retrying", u, e);
-				// TODO: remove this once https://github.com/hyperium/hyper/issues/848 is fixed
-				*client = WorkPoster::create_client();
-				if let Err(e) = client.request(u.clone(), PostHandler { body: body.clone() }) {
-					warn!("Error sending HTTP notification to {} : {}", u, e);
-				}
+		for subscriber in self.subscribers.iter().filter(|s| s.accepts(&difficulty, number)) {
+			match subscriber.transport {
+				Transport::Http(ref u) => self.post(u, &body),
+				Transport::Push(ref channel) => channel.push(body.clone()),
 			}
 		}
 	}
@@ -115,3 +236,36 @@ impl hyper::client::Handler<HttpStream> for PostHandler {
 	}
 }
 
+
+#[cfg(test)]
+mod tests {
+	use util::U256;
+	use super::Filter;
+
+	#[test]
+	fn should_parse_filter() {
+		assert_eq!(Filter::from_fragment(None), Ok(Filter::default()));
+		assert_eq!(Filter::from_fragment(Some("new_blocks_only&min_difficulty_change=5")), Ok(Filter {
+			new_blocks_only: true,
+			min_difficulty_change: Some(5),
+		}));
+		assert!(Filter::from_fragment(Some("unknown")).is_err());
+	}
+
+	#[test]
+	fn should_filter_work_packages() {
+		// given
+		let last = (U256::from(1000), 10u64);
+		let new_blocks = Filter { new_blocks_only: true, min_difficulty_change: None };
+		let difficulty_change = Filter { new_blocks_only: false, min_difficulty_change: Some(5) };
+
+		// then
+		assert!(Filter::default().accepts(Some(&last), &1000.into(), 10));
+		assert!(new_blocks.accepts(None, &1000.into(), 10));
+		assert!(!new_blocks.accepts(Some(&last), &1100.into(), 10));
+		assert!(new_blocks.accepts(Some(&last), &1000.into(), 11));
+		assert!(!difficulty_change.accepts(Some(&last), &1040.into(), 10));
+		assert!(difficulty_change.accepts(Some(&last), &950.into(), 10));
+		assert!(difficulty_change.accepts(Some(&last), &1000.into(), 11));
+	}
+}
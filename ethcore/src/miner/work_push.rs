@@ -0,0 +1,293 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Persistent push channels for work notifications.
+//!
+//! Every channel keeps its own connection and a background thread delivering the most recent
+//! work package. When delivery fails the connection is re-established with exponential backoff;
+//! packages superseded in the meantime are never sent.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex, Condvar};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use rand::{Rng, OsRng};
+use rustc_serialize::base64::{ToBase64, STANDARD};
+
+/// Delay before the first reconnection attempt.
+const MIN_BACKOFF_MS: u64 = 100;
+/// Maximal delay between reconnection attempts.
+const MAX_BACKOFF_MS: u64 = 30_000;
+/// Maximal size of WebSocket handshake response.
+const MAX_HANDSHAKE_SIZE: usize = 4096;
+
+/// Push channel endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Endpoint {
+	/// Raw TCP connection, packages are separated by new lines.
+	Tcp(String),
+	/// WebSocket connection, every package is sent as a text frame.
+	WebSocket {
+		/// Socket address
+		addr: String,
+		/// Value of `Host` header
+		host: String,
+		/// Resource path
+		path: String,
+	},
+}
+
+impl Endpoint {
+	fn connect(&self) -> io::Result<TcpStream> {
+		let addr = match *self {
+			Endpoint::Tcp(ref addr) => addr,
+			Endpoint::WebSocket { ref addr, .. } => addr,
+		};
+		let mut stream = try!(TcpStream::connect(&addr[..]));
+		try!(stream.set_nodelay(true));
+		try!(stream.set_write_timeout(Some(Duration::from_secs(5))));
+		if let Endpoint::WebSocket { ref host, ref path, .. } = *self {
+			try!(stream.set_read_timeout(Some(Duration::from_secs(5))));
+			try!(Self::handshake(&mut stream, host, path));
+		}
+		Ok(stream)
+	}
+
+	fn handshake(stream: &mut TcpStream, host: &str, path: &str) -> io::Result<()> {
+		let mut key = [0u8; 16];
+		try!(OsRng::new()).fill_bytes(&mut key);
+		let request = format!(
+			"GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+			path, host, key.to_base64(STANDARD)
+		);
+		try!(stream.write_all(request.as_bytes()));
+
+		// read response headers byte by byte so no frame data is consumed
+		let mut response = Vec::new();
+		let mut byte = [0u8; 1];
+		while !response.ends_with(b"\r\n\r\n") {
+			if response.len() > MAX_HANDSHAKE_SIZE {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, "WebSocket handshake response too long"));
+			}
+			match try!(stream.read(&mut byte)) {
+				0 => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Connection closed during WebSocket handshake")),
+				_ => response.push(byte[0]),
+			}
+		}
+		let status = String::from_utf8_lossy(&response);
+		match status.lines().next().map_or(false, |line| line.split(' ').nth(1) == Some("101")) {
+			true => Ok(()),
+			false => Err(io::Error::new(io::ErrorKind::ConnectionRefused, format!("WebSocket handshake rejected: {}", status.lines().next().unwrap_or("")))),
+		}
+	}
+
+	fn send(&self, stream: &mut TcpStream, payload: &str) -> io::Result<()> {
+		match *self {
+			Endpoint::Tcp(_) => {
+				try!(stream.write_all(payload.as_bytes()));
+				try!(stream.write_all(b"\n"));
+			},
+			Endpoint::WebSocket { .. } => {
+				let mut mask = [0u8; 4];
+				try!(OsRng::new()).fill_bytes(&mut mask);
+				try!(stream.write_all(&text_frame(payload.as_bytes(), mask)));
+			},
+		}
+		stream.flush()
+	}
+}
+
+/// Encodes single masked WebSocket text frame.
+pub fn text_frame(payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+	let mut frame = Vec::with_capacity(payload.len() + 14);
+	// FIN + text opcode
+	frame.push(0x81);
+	let len = payload.len();
+	if len < 126 {
+		frame.push(0x80 | len as u8);
+	} else if len <= 0xffff {
+		frame.push(0x80 | 126);
+		frame.push((len >> 8) as u8);
+		frame.push(len as u8);
+	} else {
+		frame.push(0x80 | 127);
+		for i in (0..8).rev() {
+			frame.push(((len as u64) >> (i * 8)) as u8);
+		}
+	}
+	frame.extend_from_slice(&mask);
+	frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+	frame
+}
+
+struct Pending {
+	payload: Mutex<Option<String>>,
+	signal: Condvar,
+}
+
+/// Push channel to a single subscriber.
+pub struct PushChannel {
+	pending: Arc<Pending>,
+	stop: Arc<AtomicBool>,
+}
+
+impl PushChannel {
+	/// Creates channel and starts a thread delivering packages to `endpoint`.
+	pub fn new(endpoint: Endpoint) -> Self {
+		let pending = Arc::new(Pending {
+			payload: Mutex::new(None),
+			signal: Condvar::new(),
+		});
+		let stop = Arc::new(AtomicBool::new(false));
+
+		let thread_pending = pending.clone();
+		let thread_stop = stop.clone();
+		let name = format!("Work push {:?}", endpoint);
+		let _ = thread::Builder::new().name(name).spawn(move || {
+			Self::run(endpoint, thread_pending, thread_stop)
+		});
+
+		PushChannel {
+			pending: pending,
+			stop: stop,
+		}
+	}
+
+	/// Schedules delivery of the package, replacing any undelivered one.
+	pub fn push(&self, payload: String) {
+		*self.pending.payload.lock().expect("Push channel lock poisoned") = Some(payload);
+		self.pending.signal.notify_one();
+	}
+
+	fn run(endpoint: Endpoint, pending: Arc<Pending>, stop: Arc<AtomicBool>) {
+		let mut connection: Option<TcpStream> = None;
+		let mut backoff = MIN_BACKOFF_MS;
+
+		while !stop.load(Ordering::Relaxed) {
+			let payload = {
+				let mut payload = pending.payload.lock().expect("Push channel lock poisoned");
+				if payload.is_none() {
+					payload = pending.signal.wait_timeout(payload, Duration::from_millis(500)).expect("Push channel lock poisoned").0;
+				}
+				payload.take()
+			};
+			let payload = match payload {
+				Some(payload) => payload,
+				None => continue,
+			};
+
+			let result = match connection.take() {
+				Some(stream) => Ok(stream),
+				None => endpoint.connect(),
+			}.and_then(|mut stream| endpoint.send(&mut stream, &payload).map(|_| stream));
+
+			match result {
+				Ok(stream) => {
+					trace!(target: "miner", "Work package pushed to {:?}", endpoint);
+					connection = Some(stream);
+					backoff = MIN_BACKOFF_MS;
+				},
+				Err(e) => {
+					debug!(target: "miner", "Error pushing work to {:?}: {}, retrying in {}ms", endpoint, e, backoff);
+					// retry unless there is a newer package already
+					{
+						let mut current = pending.payload.lock().expect("Push channel lock poisoned");
+						if current.is_none() {
+							*current = Some(payload);
+						}
+					}
+					thread::sleep(Duration::from_millis(backoff));
+					backoff = ::std::cmp::min(backoff * 2, MAX_BACKOFF_MS);
+				},
+			}
+		}
+	}
+}
+
+impl Drop for PushChannel {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		self.pending.signal.notify_one();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{BufRead, BufReader, Read, Write};
+	use std::net::TcpListener;
+	use super::{Endpoint, PushChannel, text_frame};
+
+	#[test]
+	fn should_encode_masked_frames() {
+		assert_eq!(text_frame(b"ab", [1, 2, 3, 4]), vec![0x81, 0x82, 1, 2, 3, 4, b'a' ^ 1, b'b' ^ 2]);
+		let frame = text_frame(&[0u8; 300], [0, 0, 0, 0]);
+		assert_eq!(&frame[0..4], &[0x81, 0x80 | 126, 1, 44]);
+		assert_eq!(frame.len(), 300 + 8);
+	}
+
+	#[test]
+	fn should_push_work_over_tcp() {
+		// given
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let addr = listener.local_addr().unwrap();
+		let channel = PushChannel::new(Endpoint::Tcp(format!("{}", addr)));
+
+		// when
+		channel.push("work1".into());
+		let (stream, _) = listener.accept().unwrap();
+		let mut reader = BufReader::new(stream);
+		let mut line = String::new();
+		reader.read_line(&mut line).unwrap();
+
+		// then
+		assert_eq!(line, "work1\n".to_owned());
+	}
+
+	#[test]
+	fn should_push_work_over_websocket() {
+		// given
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let addr = listener.local_addr().unwrap();
+		let channel = PushChannel::new(Endpoint::WebSocket {
+			addr: format!("{}", addr),
+			host: format!("{}", addr),
+			path: "/work".into(),
+		});
+
+		// when
+		channel.push("work1".into());
+		let (mut stream, _) = listener.accept().unwrap();
+		let mut request = Vec::new();
+		let mut byte = [0u8; 1];
+		while !request.ends_with(b"\r\n\r\n") {
+			stream.read_exact(&mut byte).unwrap();
+			request.push(byte[0]);
+		}
+		stream.write_all(b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\r\n").unwrap();
+		let mut header = [0u8; 6];
+		stream.read_exact(&mut header).unwrap();
+		let mut payload = vec![0u8; (header[1] & 0x7f) as usize];
+		stream.read_exact(&mut payload).unwrap();
+		let payload = payload.iter().enumerate().map(|(i, b)| b ^ header[2 + i % 4]).collect::<Vec<_>>();
+
+		// then
+		assert!(String::from_utf8(request).unwrap().starts_with("GET /work HTTP/1.1\r\n"));
+		assert_eq!(header[0], 0x81);
+		assert_eq!(payload, b"work1".to_vec());
+	}
+}
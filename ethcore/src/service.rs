@@ -91,6 +91,7 @@ impl ClientService {
 		db_config.wal = config.db_wal;
 
 		let pruning = config.pruning;
+		let (snapshot_history, incremental_snapshots) = (config.snapshot_history, config.incremental_snapshots);
 		let client = try!(Client::new(config, &spec, client_path, miner, io_service.channel(), &db_config));
 
 		let snapshot_params = SnapServiceParams {
@@ -101,6 +102,8 @@ impl ClientService {
 			channel: io_service.channel(),
 			snapshot_root: snapshot_path.into(),
 			db_restore: client.clone(),
+			history: snapshot_history,
+			incremental: incremental_snapshots,
 		};
 		let snapshot = Arc::new(try!(SnapshotService::new(snapshot_params)));
 
@@ -19,7 +19,7 @@
 use account_db::{AccountDB, AccountDBMut};
 use snapshot::Error;
 
-use util::{U256, FixedHash, H256, Bytes, HashDB, SHA3_EMPTY};
+use util::{U256, FixedHash, H256, Bytes, HashDB, Hashable, SHA3_EMPTY};
 use util::trie::{TrieDB, Trie};
 use rlp::{Rlp, RlpStream, Stream, UntrustedRlp, View};
 
@@ -191,6 +191,20 @@ impl Account {
 		Ok((acc, new_code))
 	}
 
+	// get the hash of the code used by an account's fat rlp, along with
+	// whether the code itself is encoded in it.
+	pub fn code_of_fat_rlp(rlp: &UntrustedRlp) -> Result<Option<(H256, bool)>, Error> {
+		let raw: u8 = try!(rlp.val_at(2));
+		match try!(CodeState::from(raw)) {
+			CodeState::Empty => Ok(None),
+			CodeState::Inline => {
+				let code: Bytes = try!(rlp.val_at(3));
+				Ok(Some((code.sha3(), true)))
+			}
+			CodeState::Hash => Ok(Some((try!(rlp.val_at(3)), false))),
+		}
+	}
+
 	/// Get the account's code hash.
 	pub fn code_hash(&self) -> &H256 {
 		&self.code_hash
@@ -21,6 +21,7 @@ use std::fmt;
 use ids::BlockID;
 
 use util::H256;
+use util::snappy::InvalidInput;
 use util::trie::TrieError;
 use rlp::DecoderError;
 
@@ -45,6 +46,8 @@ pub enum Error {
 	Decoder(DecoderError),
 	/// Io error.
 	Io(::std::io::Error),
+	/// Snappy decompression error.
+	Snappy(InvalidInput),
 }
 
 impl fmt::Display for Error {
@@ -60,6 +63,7 @@ impl fmt::Display for Error {
 			Error::Io(ref err) => err.fmt(f),
 			Error::Decoder(ref err) => err.fmt(f),
 			Error::Trie(ref err) => err.fmt(f),
+			Error::Snappy(ref err) => err.fmt(f),
 		}
 	}
 }
@@ -76,6 +80,12 @@ impl From<TrieError> for Error {
 	}
 }
 
+impl From<InvalidInput> for Error {
+	fn from(err: InvalidInput) -> Self {
+		Error::Snappy(err)
+	}
+}
+
 impl From<DecoderError> for Error {
 	fn from(err: DecoderError) -> Self {
 		Error::Decoder(err)
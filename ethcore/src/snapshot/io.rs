@@ -118,15 +118,23 @@ impl SnapshotWriter for PackedWriter {
 	}
 
 	fn finish(mut self, manifest: ManifestData) -> io::Result<()> {
-		// we ignore the hashes fields of the manifest under the assumption that
-		// they are consistent with ours.
-		let mut stream = RlpStream::new_list(5);
+		// state chunks aren't necessarily written in manifest order, and chunks
+		// shared with a base snapshot aren't written at all.
+		let state_hashes = {
+			let written: HashMap<_, _> = self.state_hashes.drain(..).map(|info| (info.0, info)).collect();
+			manifest.state_hashes.iter().filter_map(|hash| written.get(hash)).map(|info| ChunkInfo(info.0, info.1, info.2)).collect::<Vec<_>>()
+		};
+
+		let mut stream = RlpStream::new_list(8);
 		stream
-			.append(&self.state_hashes)
+			.append(&state_hashes)
 			.append(&self.block_hashes)
 			.append(&manifest.state_root)
 			.append(&manifest.block_number)
-			.append(&manifest.block_hash);
+			.append(&manifest.block_hash)
+			.append(&manifest.base.into_iter().collect::<Vec<_>>())
+			.append(&manifest.subtree_digests)
+			.append(&manifest.subtree_chunks);
 
 		let manifest_rlp = stream.out();
 
@@ -260,12 +268,21 @@ impl PackedReader {
 		let state: Vec<ChunkInfo> = try!(rlp.val_at(0));
 		let blocks: Vec<ChunkInfo> = try!(rlp.val_at(1));
 
+		// files written before incremental snapshots have no subtree information.
+		let (base, subtree_digests, subtree_chunks) = match rlp.item_count() {
+			n if n < 8 => (Vec::new(), Vec::new(), Vec::new()),
+			_ => (try!(rlp.val_at::<Vec<H256>>(5)), try!(rlp.val_at(6)), try!(rlp.val_at(7))),
+		};
+
 		let manifest = ManifestData {
 			state_hashes: state.iter().map(|c| c.0).collect(),
 			block_hashes: blocks.iter().map(|c| c.0).collect(),
 			state_root: try!(rlp.val_at(2)),
 			block_number: try!(rlp.val_at(3)),
 			block_hash: try!(rlp.val_at(4)),
+			base: base.into_iter().next(),
+			subtree_digests: subtree_digests,
+			subtree_chunks: subtree_chunks,
 		};
 
 		Ok(Some(PackedReader {
@@ -283,8 +300,10 @@ impl SnapshotReader for PackedReader {
 	}
 
 	fn chunk(&self, hash: H256) -> io::Result<Bytes> {
-		let &(len, off) = self.state_hashes.get(&hash).or_else(|| self.block_hashes.get(&hash))
-			.expect("only chunks in the manifest can be requested; qed");
+		let &(len, off) = match self.state_hashes.get(&hash).or_else(|| self.block_hashes.get(&hash)) {
+			Some(info) => info,
+			None => return Err(io::Error::new(io::ErrorKind::NotFound, "chunk not in snapshot file")),
+		};
 
 		let mut file = &self.file;
 
@@ -342,6 +361,40 @@ impl SnapshotReader for LooseReader {
 	}
 }
 
+/// Reader for an incremental snapshot, looking up chunks shared with older
+/// snapshots in their readers.
+pub struct ChainedReader<'a> {
+	top: &'a SnapshotReader,
+	bases: Vec<&'a SnapshotReader>,
+}
+
+impl<'a> ChainedReader<'a> {
+	/// Create a new `ChainedReader` for the `top` snapshot, falling back to the
+	/// given older snapshots for chunks it doesn't contain.
+	pub fn new(top: &'a SnapshotReader, bases: Vec<&'a SnapshotReader>) -> Self {
+		ChainedReader {
+			top: top,
+			bases: bases,
+		}
+	}
+}
+
+impl<'a> SnapshotReader for ChainedReader<'a> {
+	fn manifest(&self) -> &ManifestData {
+		self.top.manifest()
+	}
+
+	fn chunk(&self, hash: H256) -> io::Result<Bytes> {
+		let mut res = self.top.chunk(hash);
+		for base in &self.bases {
+			if res.is_ok() { break }
+			res = base.chunk(hash);
+		}
+
+		res
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use devtools::RandomTempPath;
@@ -379,6 +432,9 @@ mod tests {
 			state_root: b"notarealroot".sha3(),
 			block_number: 12345678987654321,
 			block_hash: b"notarealblock".sha3(),
+			base: None,
+			subtree_digests: Vec::new(),
+			subtree_chunks: Vec::new(),
 		};
 
 		writer.finish(manifest.clone()).unwrap();
@@ -417,6 +473,9 @@ mod tests {
 			state_root: b"notarealroot".sha3(),
 			block_number: 12345678987654321,
 			block_hash: b"notarealblock".sha3(),
+			base: None,
+			subtree_digests: Vec::new(),
+			subtree_chunks: Vec::new(),
 		};
 
 		writer.finish(manifest.clone()).unwrap();
@@ -41,7 +41,7 @@ use rlp::{RlpStream, Stream, UntrustedRlp, View};
 
 use self::account::Account;
 use self::block::AbridgedBlock;
-use self::io::SnapshotWriter;
+use self::io::{SnapshotReader, SnapshotWriter};
 
 use crossbeam::{scope, ScopedJoinHandle};
 use rand::{Rng, OsRng};
@@ -56,6 +56,7 @@ pub use types::restoration_status::RestorationStatus;
 
 pub mod io;
 pub mod service;
+pub mod verify;
 
 mod account;
 mod block;
@@ -76,6 +77,10 @@ const PREFERRED_CHUNK_SIZE: usize = 4 * 1024 * 1024;
 // How many blocks to include in a snapshot, starting from the head of the chain.
 const SNAPSHOT_BLOCKS: u64 = 30000;
 
+// Accounts are grouped into subtrees by the first two bytes of their address hash.
+// State chunks never span subtrees, so unchanged subtrees can be shared between snapshots.
+const STATE_SUBTREES: usize = 65536;
+
 /// A progress indicator for snapshots.
 #[derive(Debug, Default)]
 pub struct Progress {
@@ -111,10 +116,15 @@ impl Progress {
 
 }
 /// Take a snapshot using the given blockchain, starting block hash, and database, writing into the given writer.
+///
+/// If a `base` snapshot is given, the snapshot is incremental: state subtrees which did not
+/// change since the base refer to its chunks instead of being written again, so restoring
+/// needs the base as well. The `base` reader must also provide the chunks of its own bases.
 pub fn take_snapshot<W: SnapshotWriter + Send>(
 	chain: &BlockChain,
 	block_at: H256,
 	state_db: &HashDB,
+	base: Option<&SnapshotReader>,
 	writer: W,
 	p: &Progress
 ) -> Result<(), Error> {
@@ -123,26 +133,38 @@ pub fn take_snapshot<W: SnapshotWriter + Send>(
 	let state_root = start_header.state_root();
 	let number = start_header.number();
 
-	info!("Taking snapshot starting at block {}", number);
+	// only snapshots with subtree-aligned state chunks can be used as a base.
+	let base = base.and_then(|reader| match subtree_offsets(reader.manifest()) {
+		Some(_) if reader.manifest().block_number < number => Some(reader),
+		_ => None,
+	});
+
+	match base {
+		Some(reader) => info!("Taking incremental snapshot starting at block {} on top of block {}", number, reader.manifest().block_number),
+		None => info!("Taking snapshot starting at block {}", number),
+	}
 
 	let writer = Mutex::new(writer);
-	let (state_hashes, block_hashes) = try!(scope(|scope| {
+	let (state_chunks, block_hashes) = try!(scope(|scope| {
 		let block_guard = scope.spawn(|| chunk_blocks(chain, (number, block_at), &writer, p));
-		let state_res = chunk_state(state_db, state_root, &writer, p);
+		let state_res = chunk_state(state_db, state_root, base, &writer, p);
 
-		state_res.and_then(|state_hashes| {
-			block_guard.join().map(|block_hashes| (state_hashes, block_hashes))
+		state_res.and_then(|state_chunks| {
+			block_guard.join().map(|block_hashes| (state_chunks, block_hashes))
 		})
 	}));
 
-	info!("produced {} state chunks and {} block chunks.", state_hashes.len(), block_hashes.len());
+	info!("produced {} state chunks ({} shared with base) and {} block chunks.", state_chunks.hashes.len(), state_chunks.shared, block_hashes.len());
 
 	let manifest_data = ManifestData {
-		state_hashes: state_hashes,
+		state_hashes: state_chunks.hashes,
 		block_hashes: block_hashes,
 		state_root: *state_root,
 		block_number: number,
 		block_hash: block_at,
+		base: base.map(|reader| reader.manifest().block_hash),
+		subtree_digests: state_chunks.subtree_digests,
+		subtree_chunks: state_chunks.subtree_chunks,
 	};
 
 	try!(writer.into_inner().finish(manifest_data));
@@ -292,6 +314,25 @@ struct StateChunker<'a> {
 }
 
 impl<'a> StateChunker<'a> {
+	// Chunk the given thin accounts, writing out all remaining data.
+	// Returns the hashes of the chunks written.
+	fn chunk_accounts(&mut self, db: &HashDB, accounts: &[(Bytes, Bytes)], used_code: &mut HashSet<H256>) -> Result<Vec<H256>, Error> {
+		let first = self.hashes.len();
+		for &(ref account_key, ref account_data) in accounts {
+			let account = Account::from_thin_rlp(account_data);
+			let account_db = AccountDB::from_hash(db, H256::from_slice(account_key));
+
+			let fat_rlp = try!(account.to_fat_rlp(&account_db, used_code));
+			try!(self.push(account_key.clone(), fat_rlp));
+		}
+
+		if self.cur_size != 0 {
+			try!(self.write_chunk());
+		}
+
+		Ok(self.hashes[first..].to_vec())
+	}
+
 	// Push a key, value pair to be encoded.
 	//
 	// If the buffer is greater than the desired chunk size,
@@ -341,42 +382,196 @@ impl<'a> StateChunker<'a> {
 	}
 }
 
+/// State chunks of a snapshot.
+pub struct StateChunks {
+	/// Hashes of all state chunks, ordered by subtree.
+	pub hashes: Vec<H256>,
+	/// Digest of each state subtree.
+	pub subtree_digests: Vec<H256>,
+	/// Number of chunks of each state subtree.
+	pub subtree_chunks: Vec<u64>,
+	/// Number of chunks shared with the base snapshot, which were not written out.
+	pub shared: usize,
+}
+
+/// Get the offset of the first state chunk of each subtree of the snapshot.
+/// Returns `None` if the state chunks of the snapshot are not aligned to subtrees.
+fn subtree_offsets(manifest: &ManifestData) -> Option<Vec<usize>> {
+	if manifest.subtree_digests.len() != STATE_SUBTREES || manifest.subtree_chunks.len() != STATE_SUBTREES {
+		return None;
+	}
+
+	let mut offsets = Vec::with_capacity(STATE_SUBTREES + 1);
+	offsets.push(0);
+	for count in &manifest.subtree_chunks {
+		let next = offsets[offsets.len() - 1] + *count as usize;
+		offsets.push(next);
+	}
+
+	match offsets[STATE_SUBTREES] == manifest.state_hashes.len() {
+		true => Some(offsets),
+		false => None,
+	}
+}
+
+// Get the state subtree of the account with the given address hash.
+fn subtree_of(account_key: &[u8]) -> usize {
+	(account_key[0] as usize) << 8 | account_key[1] as usize
+}
+
+// Get the digest of a state subtree from its thin accounts.
+fn subtree_digest(accounts: &[(Bytes, Bytes)]) -> H256 {
+	let mut stream = RlpStream::new_list(accounts.len());
+	for &(ref key, ref data) in accounts {
+		stream.begin_list(2).append(key).append_raw(data, 1);
+	}
+	stream.out().sha3()
+}
+
+// Get the code encoded in the given state chunks, along with the code
+// they refer to by hash.
+fn chunk_code(reader: &SnapshotReader, hashes: &[H256]) -> Result<(HashSet<H256>, HashSet<H256>), Error> {
+	let mut inline = HashSet::new();
+	let mut by_hash = HashSet::new();
+
+	for hash in hashes {
+		let chunk = try!(snappy::decompress(&try!(reader.chunk(*hash))));
+		for pair in UntrustedRlp::new(&chunk).iter() {
+			match try!(Account::code_of_fat_rlp(&try!(pair.at(1)))) {
+				Some((code_hash, true)) => { inline.insert(code_hash); }
+				Some((code_hash, false)) => { by_hash.insert(code_hash); }
+				None => {}
+			}
+		}
+	}
+
+	Ok((inline, by_hash))
+}
+
+/// Chunks the state subtree by subtree, leaving out subtrees shared with the base.
+struct SubtreeChunker<'a, 'b> {
+	db: &'b HashDB,
+	base: Option<(&'b SnapshotReader, Vec<usize>)>,
+	chunker: StateChunker<'a>,
+	// code written out in this snapshot or in shared chunks.
+	used_code: HashSet<H256>,
+	digests: Vec<H256>,
+	hashes: Vec<Vec<H256>>,
+	// code referred to by hash from the chunks of each shared subtree.
+	shared_code: Vec<(usize, HashSet<H256>)>,
+}
+
+impl<'a, 'b> SubtreeChunker<'a, 'b> {
+	// Chunk the given accounts, which make up the next subtree.
+	fn finish_subtree(&mut self, accounts: &mut Vec<(Bytes, Bytes)>) -> Result<(), Error> {
+		let subtree = self.digests.len();
+		let digest = subtree_digest(accounts);
+
+		let shared = match self.base {
+			Some((reader, ref offsets)) if reader.manifest().subtree_digests[subtree] == digest =>
+				Some((reader, &reader.manifest().state_hashes[offsets[subtree]..offsets[subtree + 1]])),
+			_ => None,
+		};
+
+		let hashes = match shared {
+			Some((reader, hashes)) => {
+				let (inline, by_hash) = try!(chunk_code(reader, hashes));
+				self.used_code.extend(inline);
+				self.shared_code.push((subtree, by_hash));
+				self.chunker.progress.accounts.fetch_add(accounts.len(), Ordering::SeqCst);
+				hashes.to_vec()
+			}
+			None => try!(self.chunker.chunk_accounts(self.db, accounts, &mut self.used_code)),
+		};
+
+		accounts.clear();
+		self.digests.push(digest);
+		self.hashes.push(hashes);
+		Ok(())
+	}
+}
+
 /// Walk the given state database starting from the given root,
 /// creating chunks and writing them out.
 ///
-/// Returns a list of hashes of chunks created, or any error it may
-/// have encountered.
-pub fn chunk_state<'a>(db: &HashDB, root: &H256, writer: &Mutex<SnapshotWriter + 'a>, progress: &'a Progress) -> Result<Vec<H256>, Error> {
+/// State chunks never span subtrees. Subtrees with the same digest as in the
+/// `base` snapshot are not written again: the manifest refers to the base's
+/// chunks for them. Each code is written out once, with the first account using it.
+///
+/// Returns the hashes of chunks created along with subtree digests,
+/// or any error it may have encountered.
+pub fn chunk_state<'a>(db: &HashDB, root: &H256, base: Option<&SnapshotReader>, writer: &Mutex<SnapshotWriter + 'a>, progress: &'a Progress) -> Result<StateChunks, Error> {
 	let account_trie = try!(TrieDB::new(db, &root));
 
-	let mut chunker = StateChunker {
-		hashes: Vec::new(),
-		rlps: Vec::new(),
-		cur_size: 0,
-		snappy_buffer: vec![0; snappy::max_compressed_len(PREFERRED_CHUNK_SIZE)],
-		writer: writer,
-		progress: progress,
+	let mut chunker = SubtreeChunker {
+		db: db,
+		base: base.and_then(|reader| subtree_offsets(reader.manifest()).map(|offsets| (reader, offsets))),
+		chunker: StateChunker {
+			hashes: Vec::new(),
+			rlps: Vec::new(),
+			cur_size: 0,
+			snappy_buffer: vec![0; snappy::max_compressed_len(PREFERRED_CHUNK_SIZE)],
+			writer: writer,
+			progress: progress,
+		},
+		used_code: HashSet::new(),
+		digests: Vec::with_capacity(STATE_SUBTREES),
+		hashes: Vec::with_capacity(STATE_SUBTREES),
+		shared_code: Vec::new(),
 	};
 
-	let mut used_code = HashSet::new();
+	// thin accounts of the current subtree.
+	let mut accounts: Vec<(Bytes, Bytes)> = Vec::new();
 
 	// account_key here is the address' hash.
 	for item in try!(account_trie.iter()) {
 		let (account_key, account_data) = try!(item);
-		let account = Account::from_thin_rlp(account_data);
-		let account_key_hash = H256::from_slice(&account_key);
+		while chunker.digests.len() < subtree_of(&account_key) {
+			try!(chunker.finish_subtree(&mut accounts));
+		}
 
-		let account_db = AccountDB::from_hash(db, account_key_hash);
+		accounts.push((account_key, account_data.to_vec()));
+	}
 
-		let fat_rlp = try!(account.to_fat_rlp(&account_db, &mut used_code));
-		try!(chunker.push(account_key, fat_rlp));
+	while chunker.digests.len() < STATE_SUBTREES {
+		try!(chunker.finish_subtree(&mut accounts));
 	}
 
-	if chunker.cur_size != 0 {
-		try!(chunker.write_chunk());
+	// shared chunks may refer by hash to code which this snapshot no longer writes out.
+	// such subtrees are chunked again, this time with all the code they use.
+	let regenerate: HashSet<usize> = chunker.shared_code.iter()
+		.filter(|&&(_, ref code)| code.iter().any(|hash| !chunker.used_code.contains(hash)))
+		.map(|&(subtree, _)| subtree)
+		.collect();
+
+	if !regenerate.is_empty() {
+		trace!(target: "snapshot", "chunking {} shared subtrees again for missing code", regenerate.len());
+
+		let mut regenerated: HashMap<usize, Vec<(Bytes, Bytes)>> = HashMap::new();
+		for item in try!(account_trie.iter()) {
+			let (account_key, account_data) = try!(item);
+			let subtree = subtree_of(&account_key);
+			if regenerate.contains(&subtree) {
+				regenerated.entry(subtree).or_insert_with(Vec::new).push((account_key, account_data.to_vec()));
+			}
+		}
+
+		for (subtree, accounts) in regenerated {
+			chunker.hashes[subtree] = try!(chunker.chunker.chunk_accounts(db, &accounts, &mut HashSet::new()));
+		}
 	}
 
-	Ok(chunker.hashes)
+	let shared = chunker.shared_code.iter()
+		.filter(|&&(subtree, _)| !regenerate.contains(&subtree))
+		.map(|&(subtree, _)| chunker.hashes[subtree].len())
+		.fold(0, |acc, len| acc + len);
+
+	Ok(StateChunks {
+		subtree_chunks: chunker.hashes.iter().map(|hashes| hashes.len() as u64).collect(),
+		hashes: chunker.hashes.into_iter().flat_map(|hashes| hashes.into_iter()).collect(),
+		subtree_digests: chunker.digests,
+		shared: shared,
+	})
 }
 
 /// Used to rebuild the state trie piece by piece.
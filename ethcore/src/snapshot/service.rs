@@ -24,7 +24,7 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use super::{ManifestData, StateRebuilder, BlockRebuilder, RestorationStatus, SnapshotService};
-use super::io::{SnapshotReader, LooseReader, SnapshotWriter, LooseWriter};
+use super::io::{ChainedReader, SnapshotReader, LooseReader, SnapshotWriter, LooseWriter};
 
 use blockchain::BlockChain;
 use client::{BlockChainClient, Client};
@@ -196,6 +196,10 @@ pub struct ServiceParams {
 	pub snapshot_root: PathBuf,
 	/// A handle for database restoration.
 	pub db_restore: Arc<DatabaseRestore>,
+	/// Number of older snapshots to keep and serve in addition to the latest one.
+	pub history: usize,
+	/// Number of incremental snapshots to take between full ones. `0` disables them.
+	pub incremental: usize,
 }
 
 /// `SnapshotService` implementation.
@@ -208,6 +212,10 @@ pub struct Service {
 	pruning: Algorithm,
 	status: Mutex<RestorationStatus>,
 	reader: RwLock<Option<LooseReader>>,
+	history: RwLock<Vec<LooseReader>>,
+	max_history: usize,
+	incremental: usize,
+	since_full: AtomicUsize,
 	engine: Arc<Engine>,
 	genesis_block: Bytes,
 	state_chunks: AtomicUsize,
@@ -228,6 +236,10 @@ impl Service {
 			pruning: params.pruning,
 			status: Mutex::new(RestorationStatus::Inactive),
 			reader: RwLock::new(None),
+			history: RwLock::new(Vec::new()),
+			max_history: params.history,
+			incremental: params.incremental,
+			since_full: AtomicUsize::new(0),
 			engine: params.engine,
 			genesis_block: params.genesis_block,
 			state_chunks: AtomicUsize::new(0),
@@ -261,9 +273,66 @@ impl Service {
 		let reader = LooseReader::new(service.snapshot_dir()).ok();
 		*service.reader.get_mut() = reader;
 
+		let latest = service.reader.read().as_ref().map(|r| r.manifest().clone());
+		let mut history = try!(service.load_history());
+		try!(service.prune_history(latest.as_ref(), &mut history));
+		*service.history.get_mut() = history;
+
 		Ok(service)
 	}
 
+	// load retained older snapshots, latest first.
+	fn load_history(&self) -> Result<Vec<LooseReader>, Error> {
+		let dir = self.history_dir();
+		if !dir.exists() {
+			return Ok(Vec::new());
+		}
+
+		let mut history = Vec::new();
+		for entry in try!(fs::read_dir(&dir)) {
+			let path = try!(entry).path();
+			if !path.is_dir() {
+				continue;
+			}
+
+			match LooseReader::new(path.clone()) {
+				Ok(reader) => history.push(reader),
+				Err(_) => {
+					warn!("Removing invalid snapshot at {}", path.to_string_lossy());
+					try!(fs::remove_dir_all(&path));
+				}
+			}
+		}
+
+		history.sort_by(|a, b| b.manifest().block_number.cmp(&a.manifest().block_number));
+		Ok(history)
+	}
+
+	// remove older snapshots beyond the limit, except those which the latest
+	// or any retained snapshot is based on.
+	fn prune_history(&self, latest: Option<&ManifestData>, history: &mut Vec<LooseReader>) -> Result<(), Error> {
+		let mut needed = HashSet::new();
+		let mut pending: Vec<H256> = latest.into_iter()
+			.chain(history.iter().take(self.max_history).map(|r| r.manifest()))
+			.filter_map(|m| m.base)
+			.collect();
+
+		while let Some(hash) = pending.pop() {
+			if needed.insert(hash) {
+				pending.extend(history.iter().find(|r| r.manifest().block_hash == hash).and_then(|r| r.manifest().base));
+			}
+		}
+
+		for (i, old) in ::std::mem::replace(history, Vec::new()).into_iter().enumerate() {
+			match i < self.max_history || needed.contains(&old.manifest().block_hash) {
+				true => history.push(old),
+				false => try!(fs::remove_dir_all(self.history_entry_dir(old.manifest().block_number))),
+			}
+		}
+
+		Ok(())
+	}
+
 	// get the current snapshot dir.
 	fn snapshot_dir(&self) -> PathBuf {
 		let mut dir = self.snapshot_root.clone();
@@ -271,6 +340,20 @@ impl Service {
 		dir
 	}
 
+	// get the dir of retained older snapshots.
+	fn history_dir(&self) -> PathBuf {
+		let mut dir = self.snapshot_root.clone();
+		dir.push("history");
+		dir
+	}
+
+	// get the dir of a retained snapshot taken at the given block.
+	fn history_entry_dir(&self, number: u64) -> PathBuf {
+		let mut dir = self.history_dir();
+		dir.push(number.to_string());
+		dir
+	}
+
 	// get the temporary snapshot dir.
 	fn temp_snapshot_dir(&self) -> PathBuf {
 		let mut dir = self.snapshot_root.clone();
@@ -312,6 +395,19 @@ impl Service {
 		self.reader.read()
 	}
 
+	/// Get the readers of the older snapshots retained, latest first.
+	pub fn history(&self) -> RwLockReadGuard<Vec<LooseReader>> {
+		self.history.read()
+	}
+
+	/// Get manifests of all snapshots served, latest first.
+	pub fn manifests(&self) -> Vec<ManifestData> {
+		self.reader.read().iter()
+			.chain(self.history.read().iter())
+			.map(|r| r.manifest().clone())
+			.collect()
+	}
+
 	/// Tick the snapshot service. This will log any active snapshot
 	/// being taken.
 	pub fn tick(&self) {
@@ -342,11 +438,22 @@ impl Service {
 		let writer = try!(LooseWriter::new(temp_dir.clone()));
 
 		let guard = Guard::new(temp_dir.clone());
-		let res = client.take_snapshot(writer, BlockID::Number(num), &self.progress);
+		let res = {
+			// build on top of the latest snapshot unless a full one is due.
+			// chunks it shares with its own bases are found in the history.
+			let reader = self.reader.read();
+			let history = self.history.read();
+			let chained = reader.as_ref().map(|r| ChainedReader::new(r, history.iter().map(|h| h as &SnapshotReader).collect()));
+			let base = match self.since_full.load(Ordering::SeqCst) < self.incremental {
+				true => chained.as_ref().map(|r| r as &SnapshotReader),
+				false => None,
+			};
+			client.take_snapshot(writer, BlockID::Number(num), base, &self.progress)
+		};
 
 		self.taking_snapshot.store(false, Ordering::SeqCst);
 		if let Err(e) = res {
-			if client.chain_info().best_block_number >= num + ::client::HISTORY {
+			if client.chain_info().best_block_number >= num + client.pruning_history() {
 				// "Cancelled" is mincing words a bit -- what really happened
 				// is that the state we were snapshotting got pruned out
 				// before we could finish.
@@ -359,10 +466,27 @@ impl Service {
 
 		info!("Finished taking snapshot at #{}", num);
 
+		let new_base = try!(LooseReader::new(temp_dir.clone())).manifest().base;
+
 		let mut reader = self.reader.write();
+		let mut history = self.history.write();
+
+		// destroy the old snapshot reader, keeping the snapshot if history is enabled
+		// or the new snapshot is based on it.
+		if let Some(old) = reader.take() {
+			if self.max_history > 0 || new_base == Some(old.manifest().block_hash) {
+				let number = old.manifest().block_number;
+				let old_dir = self.history_entry_dir(number);
+				if old_dir.exists() {
+					try!(fs::remove_dir_all(&old_dir));
+				}
 
-		// destroy the old snapshot reader.
-		*reader = None;
+				try!(fs::create_dir_all(self.history_dir()));
+				try!(fs::rename(&snapshot_dir, &old_dir));
+				history.retain(|r| r.manifest().block_number != number);
+				history.insert(0, try!(LooseReader::new(old_dir)));
+			}
+		}
 
 		if snapshot_dir.exists() {
 			try!(fs::remove_dir_all(&snapshot_dir));
@@ -370,7 +494,14 @@ impl Service {
 
 		try!(fs::rename(temp_dir, &snapshot_dir));
 
-		*reader = Some(try!(LooseReader::new(snapshot_dir)));
+		let new_reader = try!(LooseReader::new(snapshot_dir));
+		match new_reader.manifest().base {
+			Some(_) => self.since_full.fetch_add(1, Ordering::SeqCst),
+			None => self.since_full.swap(0, Ordering::SeqCst),
+		};
+		*reader = Some(new_reader);
+
+		try!(self.prune_history(reader.as_ref().map(|r| r.manifest()), &mut history));
 
 		guard.disarm();
 		Ok(())
@@ -529,8 +660,14 @@ impl SnapshotService for Service {
 		self.reader.read().as_ref().map(|r| r.manifest().clone())
 	}
 
+	fn older_manifests(&self) -> Vec<ManifestData> {
+		self.history.read().iter().map(|r| r.manifest().clone()).collect()
+	}
+
 	fn chunk(&self, hash: H256) -> Option<Bytes> {
+		// peers may still be restoring from older snapshots.
 		self.reader.read().as_ref().and_then(|r| r.chunk(hash).ok())
+			.or_else(|| self.history.read().iter().filter_map(|r| r.chunk(hash).ok()).next())
 	}
 
 	fn status(&self) -> RestorationStatus {
@@ -608,6 +745,8 @@ mod tests {
 			channel: service.channel(),
 			snapshot_root: dir,
 			db_restore: Arc::new(NoopDBRestore),
+			history: 0,
+			incremental: 0,
 		};
 
 		let service = Service::new(snapshot_params).unwrap();
@@ -622,6 +761,9 @@ mod tests {
 			state_root: Default::default(),
 			block_number: 0,
 			block_hash: Default::default(),
+			base: None,
+			subtree_digests: Vec::new(),
+			subtree_chunks: Vec::new(),
 		};
 
 		service.begin_restore(manifest);
@@ -28,6 +28,9 @@ pub trait SnapshotService : Sync + Send {
 	/// Query the most recent manifest data.
 	fn manifest(&self) -> Option<ManifestData>;
 
+	/// Query the manifest data of older snapshots still served, latest first.
+	fn older_manifests(&self) -> Vec<ManifestData>;
+
 	/// Get raw chunk for a given hash.
 	fn chunk(&self, hash: H256) -> Option<Bytes>;
 
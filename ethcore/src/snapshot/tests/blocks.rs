@@ -20,12 +20,15 @@ use devtools::RandomTempPath;
 
 use blockchain::generator::{ChainGenerator, ChainIterator, BlockFinalizer};
 use blockchain::BlockChain;
-use snapshot::{chunk_blocks, BlockRebuilder, Progress};
+use snapshot::{chunk_blocks, BlockRebuilder, ManifestData, Progress};
+use snapshot::verify::{verify, VerificationError};
 use snapshot::io::{PackedReader, PackedWriter, SnapshotReader, SnapshotWriter};
 
 use util::{Mutex, snappy};
+use util::sha3::SHA3_NULL_RLP;
 use util::kvdb::{Database, DatabaseConfig};
 
+use std::path::Path;
 use std::sync::Arc;
 
 fn chunk_and_restore(amount: u64) {
@@ -64,6 +67,9 @@ fn chunk_and_restore(amount: u64) {
 		state_root: Default::default(),
 		block_number: amount,
 		block_hash: best_hash,
+		base: None,
+		subtree_digests: Vec::new(),
+		subtree_chunks: Vec::new(),
 	}).unwrap();
 
 	// restore it.
@@ -85,6 +91,98 @@ fn chunk_and_restore(amount: u64) {
 	assert_eq!(new_chain.best_block_hash(), best_hash);
 }
 
+// pack a chain of `amount` blocks into a snapshot at `path`, returning its manifest.
+fn pack_blocks(amount: u64, path: &Path) -> ManifestData {
+	let mut canon_chain = ChainGenerator::default();
+	let mut finalizer = BlockFinalizer::default();
+	let genesis = canon_chain.generate(&mut finalizer).unwrap();
+	let db_cfg = DatabaseConfig::with_columns(::db::NUM_COLUMNS);
+
+	let orig_path = RandomTempPath::create_dir();
+	let db = Arc::new(Database::open(&db_cfg, orig_path.as_str()).unwrap());
+	let bc = BlockChain::new(Default::default(), &genesis, db.clone());
+	let mut batch = db.transaction();
+	for _ in 0..amount {
+		let block = canon_chain.generate(&mut finalizer).unwrap();
+		bc.insert_block(&mut batch, &block, vec![]);
+		bc.commit();
+	}
+	db.write(batch).unwrap();
+
+	let best_hash = bc.best_block_hash();
+	let writer = Mutex::new(PackedWriter::new(path).unwrap());
+	let block_hashes = chunk_blocks(&bc, (amount, best_hash), &writer, &Progress::default()).unwrap();
+	let manifest = ManifestData {
+		state_hashes: Vec::new(),
+		block_hashes: block_hashes,
+		state_root: SHA3_NULL_RLP,
+		block_number: amount,
+		block_hash: best_hash,
+		base: None,
+		subtree_digests: Vec::new(),
+		subtree_chunks: Vec::new(),
+	};
+	writer.into_inner().finish(manifest.clone()).unwrap();
+	manifest
+}
+
+#[test]
+fn verify_packed_blocks() {
+	let new_path = RandomTempPath::create_dir();
+	let mut snapshot_path = new_path.as_path().to_owned();
+	snapshot_path.push("SNAP");
+
+	// given
+	pack_blocks(100, &snapshot_path);
+
+	// when
+	let reader = PackedReader::new(&snapshot_path).unwrap().unwrap();
+	let report = verify(&reader).unwrap();
+
+	// then
+	assert!(report.is_valid(), "{:?}", report.errors);
+	assert_eq!(report.blocks, 100);
+	assert_eq!(report.first_block, Some(1));
+	assert_eq!(report.rebuilt_state_root, SHA3_NULL_RLP);
+}
+
+#[test]
+fn verify_detects_corrupted_block_chunk() {
+	let new_path = RandomTempPath::create_dir();
+	let mut snapshot_path = new_path.as_path().to_owned();
+	snapshot_path.push("SNAP");
+	let mut corrupted_path = new_path.as_path().to_owned();
+	corrupted_path.push("CORRUPTED");
+
+	// given
+	let manifest = pack_blocks(100, &snapshot_path);
+	let reader = PackedReader::new(&snapshot_path).unwrap().unwrap();
+	let mut writer = PackedWriter::new(&corrupted_path).unwrap();
+	for hash in &manifest.block_hashes {
+		let mut chunk = reader.chunk(*hash).unwrap();
+		let last = chunk.len() - 1;
+		chunk[last] ^= 0xff;
+		writer.write_block_chunk(*hash, &chunk).unwrap();
+	}
+	writer.finish(manifest.clone()).unwrap();
+
+	// when
+	let reader = PackedReader::new(&corrupted_path).unwrap().unwrap();
+	let report = verify(&reader).unwrap();
+
+	// then
+	assert!(!report.is_valid());
+	assert_eq!(report.blocks, 0);
+	match report.errors[0] {
+		VerificationError::ChunkHashMismatch { ref expected, .. } => assert_eq!(expected, &manifest.block_hashes[0]),
+		ref other => panic!("Expected chunk hash mismatch, got {:?}", other),
+	}
+	assert_eq!(report.errors.last(), Some(&VerificationError::LastBlockMismatch {
+		expected: (100, manifest.block_hash),
+		found: None,
+	}));
+}
+
 #[test]
 fn chunk_and_restore_500() { chunk_and_restore(500) }
 
@@ -32,7 +32,28 @@ fn manifest_rlp() {
 		block_number: 1234567,
 		state_root: Default::default(),
 		block_hash: Default::default(),
+		base: Some(Default::default()),
+		subtree_digests: vec![Default::default()],
+		subtree_chunks: vec![0],
 	};
 	let raw = manifest.clone().into_rlp();
 	assert_eq!(ManifestData::from_rlp(&raw).unwrap(), manifest);
+}
+
+#[test]
+fn legacy_manifest_rlp() {
+	use rlp::{RlpStream, Stream};
+	use util::H256;
+
+	let mut stream = RlpStream::new_list(5);
+	stream.append(&Vec::<H256>::new())
+		.append(&Vec::<H256>::new())
+		.append(&H256::default())
+		.append(&1234567u64)
+		.append(&H256::default());
+
+	let manifest = ManifestData::from_rlp(&stream.out()).unwrap();
+	assert_eq!(manifest.block_number, 1234567);
+	assert_eq!(manifest.base, None);
+	assert!(manifest.subtree_digests.is_empty());
 }
\ No newline at end of file
@@ -73,6 +73,8 @@ fn restored_is_equivalent() {
 		channel: IoChannel::disconnected(),
 		snapshot_root: path,
 		db_restore: client2.clone(),
+		history: 0,
+		incremental: 0,
 	};
 
 	let service = Service::new(service_params).unwrap();
@@ -116,6 +118,8 @@ fn guards_delete_folders() {
 		channel: IoChannel::disconnected(),
 		snapshot_root: path.clone(),
 		db_restore: Arc::new(NoopDBRestore),
+		history: 0,
+		incremental: 0,
 	};
 
 	let service = Service::new(service_params).unwrap();
@@ -127,6 +131,9 @@ fn guards_delete_folders() {
 		block_number: 0,
 		block_hash: Default::default(),
 		state_root: Default::default(),
+		base: None,
+		subtree_digests: Vec::new(),
+		subtree_chunks: Vec::new(),
 	};
 
 	service.init_restore(manifest.clone(), true).unwrap();
@@ -140,4 +147,91 @@ fn guards_delete_folders() {
 
 	drop(service);
 	assert!(!path.exists());
-}
\ No newline at end of file
+}
+
+#[test]
+fn keeps_history_and_takes_incremental_snapshots() {
+	const NUM_BLOCKS: u32 = 20;
+
+	let gas_prices = vec![1.into()];
+	let client = generate_dummy_client_with_spec_and_data(Spec::new_null, NUM_BLOCKS, 5, &gas_prices);
+
+	let spec = Spec::new_null();
+	let path = RandomTempPath::create_dir();
+	let service_params = ServiceParams {
+		engine: spec.engine.clone(),
+		genesis_block: spec.genesis_block(),
+		db_config: DatabaseConfig::with_columns(::db::NUM_COLUMNS),
+		pruning: ::util::journaldb::Algorithm::Archive,
+		channel: IoChannel::disconnected(),
+		snapshot_root: path.as_path().clone(),
+		db_restore: Arc::new(NoopDBRestore),
+		history: 1,
+		incremental: 1,
+	};
+
+	let service = Service::new(service_params).unwrap();
+
+	// when
+	service.take_snapshot(&client, 10).unwrap();
+	let first = service.manifest().unwrap();
+	service.take_snapshot(&client, 15).unwrap();
+	let second = service.manifest().unwrap();
+	service.take_snapshot(&client, 20).unwrap();
+	let third = service.manifest().unwrap();
+
+	// then
+	assert_eq!(first.base, None);
+	assert_eq!(second.base, Some(first.block_hash));
+	// a full snapshot follows every incremental one.
+	assert_eq!(third.base, None);
+
+	// the first snapshot is kept beyond the limit as the second is based on it.
+	let manifests = service.manifests();
+	assert_eq!(manifests, vec![third, second.clone(), first]);
+	for hash in second.state_hashes.iter().chain(&second.block_hashes) {
+		assert!(service.chunk(*hash).is_some());
+	}
+}
+
+#[test]
+fn keeps_base_of_incremental_snapshot() {
+	const NUM_BLOCKS: u32 = 20;
+
+	let gas_prices = vec![1.into()];
+	let client = generate_dummy_client_with_spec_and_data(Spec::new_null, NUM_BLOCKS, 5, &gas_prices);
+
+	let spec = Spec::new_null();
+	let path = RandomTempPath::create_dir();
+	let service_params = ServiceParams {
+		engine: spec.engine.clone(),
+		genesis_block: spec.genesis_block(),
+		db_config: DatabaseConfig::with_columns(::db::NUM_COLUMNS),
+		pruning: ::util::journaldb::Algorithm::Archive,
+		channel: IoChannel::disconnected(),
+		snapshot_root: path.as_path().clone(),
+		db_restore: Arc::new(NoopDBRestore),
+		history: 0,
+		incremental: 1,
+	};
+
+	let service = Service::new(service_params).unwrap();
+
+	// when
+	service.take_snapshot(&client, 10).unwrap();
+	let first = service.manifest().unwrap();
+	service.take_snapshot(&client, 15).unwrap();
+	let second = service.manifest().unwrap();
+
+	// then
+	assert_eq!(second.base, Some(first.block_hash));
+	assert_eq!(service.manifests(), vec![second.clone(), first]);
+	for hash in second.state_hashes.iter().chain(&second.block_hashes) {
+		assert!(service.chunk(*hash).is_some());
+	}
+
+	// a full snapshot no longer needs it.
+	service.take_snapshot(&client, 20).unwrap();
+	let third = service.manifest().unwrap();
+	assert_eq!(service.manifests(), vec![third]);
+}
@@ -17,11 +17,13 @@
 //! State snapshotting tests.
 
 use snapshot::{chunk_state, Progress, StateRebuilder};
-use snapshot::io::{PackedReader, PackedWriter, SnapshotReader, SnapshotWriter};
+use snapshot::io::{ChainedReader, PackedReader, PackedWriter, LooseReader, LooseWriter, SnapshotReader, SnapshotWriter};
 use super::helpers::{compare_dbs, StateProducer};
 
-use rand::{XorShiftRng, SeedableRng};
+use rand::{Rng, XorShiftRng, SeedableRng};
 use util::hash::H256;
+use util::trie::{TrieDBMut, TrieMut};
+use util::sha3::SHA3_NULL_RLP;
 use util::journaldb::{self, Algorithm};
 use util::kvdb::{Database, DatabaseConfig};
 use util::memorydb::MemoryDB;
@@ -48,14 +50,17 @@ fn snap_and_restore() {
 	let state_root = producer.state_root();
 	let writer = Mutex::new(PackedWriter::new(&snap_file).unwrap());
 
-	let state_hashes = chunk_state(&old_db, &state_root, &writer, &Progress::default()).unwrap();
+	let state_chunks = chunk_state(&old_db, &state_root, None, &writer, &Progress::default()).unwrap();
 
 	writer.into_inner().finish(::snapshot::ManifestData {
-		state_hashes: state_hashes,
+		state_hashes: state_chunks.hashes,
 		block_hashes: Vec::new(),
 		state_root: state_root,
 		block_number: 0,
 		block_hash: H256::default(),
+		base: None,
+		subtree_digests: state_chunks.subtree_digests,
+		subtree_chunks: state_chunks.subtree_chunks,
 	}).unwrap();
 
 	let mut db_path = snap_dir.as_path().to_owned();
@@ -82,3 +87,141 @@ fn snap_and_restore() {
 
 	compare_dbs(&old_db, new_db.as_hashdb());
 }
+
+#[test]
+fn incremental_snap_and_restore() {
+	let mut producer = StateProducer::new();
+	let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+	let mut old_db = MemoryDB::new();
+	let db_cfg = DatabaseConfig::with_columns(::db::NUM_COLUMNS);
+
+	for _ in 0..150 {
+		producer.tick(&mut rng, &mut old_db);
+	}
+
+	let snap_dir = RandomTempPath::create_dir();
+	let mut base_dir = snap_dir.as_path().to_owned();
+	base_dir.push("base");
+	let mut snap_path = snap_dir.as_path().to_owned();
+	snap_path.push("incremental");
+
+	// take the base snapshot.
+	let base_root = producer.state_root();
+	let writer = Mutex::new(LooseWriter::new(base_dir.clone()).unwrap());
+	let base_chunks = chunk_state(&old_db, &base_root, None, &writer, &Progress::default()).unwrap();
+	assert_eq!(base_chunks.shared, 0);
+
+	writer.into_inner().finish(::snapshot::ManifestData {
+		state_hashes: base_chunks.hashes,
+		block_hashes: Vec::new(),
+		state_root: base_root,
+		block_number: 0,
+		block_hash: H256::default(),
+		base: None,
+		subtree_digests: base_chunks.subtree_digests,
+		subtree_chunks: base_chunks.subtree_chunks,
+	}).unwrap();
+
+	// alter some accounts and take an incremental snapshot on top of the base.
+	for _ in 0..2 {
+		producer.tick(&mut rng, &mut old_db);
+	}
+
+	let state_root = producer.state_root();
+	let base = LooseReader::new(base_dir).unwrap();
+	let writer = Mutex::new(LooseWriter::new(snap_path.clone()).unwrap());
+	let state_chunks = chunk_state(&old_db, &state_root, Some(&base as &SnapshotReader), &writer, &Progress::default()).unwrap();
+	assert!(state_chunks.shared > 0);
+	assert!(state_chunks.shared < state_chunks.hashes.len());
+	let num_chunks = state_chunks.hashes.len() - state_chunks.shared;
+
+	writer.into_inner().finish(::snapshot::ManifestData {
+		state_hashes: state_chunks.hashes,
+		block_hashes: Vec::new(),
+		state_root: state_root,
+		block_number: 1,
+		block_hash: H256::default(),
+		base: Some(H256::default()),
+		subtree_digests: state_chunks.subtree_digests,
+		subtree_chunks: state_chunks.subtree_chunks,
+	}).unwrap();
+
+	// only the changed subtrees were written, besides the manifest.
+	assert_eq!(::std::fs::read_dir(&snap_path).unwrap().count(), num_chunks + 1);
+
+	let mut db_path = snap_dir.as_path().to_owned();
+	db_path.push("db");
+	let db = {
+		let new_db = Arc::new(Database::open(&db_cfg, &db_path.to_string_lossy()).unwrap());
+		let mut rebuilder = StateRebuilder::new(new_db.clone(), Algorithm::Archive);
+		let top = LooseReader::new(snap_path).unwrap();
+		let reader = ChainedReader::new(&top, vec![&base as &SnapshotReader]);
+
+		for chunk_hash in &reader.manifest().state_hashes {
+			let raw = reader.chunk(*chunk_hash).unwrap();
+			let chunk = ::util::snappy::decompress(&raw).unwrap();
+
+			rebuilder.feed(&chunk).unwrap();
+		}
+
+		assert_eq!(rebuilder.state_root(), state_root);
+		rebuilder.check_missing().unwrap();
+
+		new_db
+	};
+
+	let new_db = journaldb::new(db, Algorithm::Archive, ::db::COL_STATE);
+
+	compare_dbs(&old_db, new_db.as_hashdb());
+}
+
+#[test]
+fn incremental_snapshot_reuses_unchanged_subtrees() {
+	fn insert_accounts(rng: &mut XorShiftRng, db: &mut MemoryDB, root: &mut H256, address_hashes: &[H256]) {
+		let mut trie = TrieDBMut::from_existing(db, root).unwrap();
+		for address_hash in address_hashes {
+			let balance: usize = rng.gen();
+			let acc = ::state::Account::new_basic(balance.into(), 0.into()).rlp();
+			trie.insert(&address_hash[..], &acc).unwrap();
+		}
+	}
+
+	let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+	let mut db = MemoryDB::new();
+	let mut root = SHA3_NULL_RLP;
+	let address_hashes = (0..2000).map(|_| H256(rng.gen())).collect::<Vec<_>>();
+	insert_accounts(&mut rng, &mut db, &mut root, &address_hashes);
+
+	let snap_dir = RandomTempPath::create_dir();
+	let mut base_dir = snap_dir.as_path().to_owned();
+	base_dir.push("base");
+	let mut snap_path = snap_dir.as_path().to_owned();
+	snap_path.push("incremental");
+
+	let writer = Mutex::new(LooseWriter::new(base_dir.clone()).unwrap());
+	let base_chunks = chunk_state(&db, &root, None, &writer, &Progress::default()).unwrap();
+	writer.into_inner().finish(::snapshot::ManifestData {
+		state_hashes: base_chunks.hashes,
+		block_hashes: Vec::new(),
+		state_root: root,
+		block_number: 0,
+		block_hash: H256::default(),
+		base: None,
+		subtree_digests: base_chunks.subtree_digests,
+		subtree_chunks: base_chunks.subtree_chunks,
+	}).unwrap();
+
+	// change the balance of 100 accounts spread over the whole state.
+	let changed = address_hashes.iter().enumerate()
+		.filter(|&(i, _)| i % 20 == 0)
+		.map(|(_, hash)| hash.clone())
+		.collect::<Vec<_>>();
+	insert_accounts(&mut rng, &mut db, &mut root, &changed);
+
+	let base = LooseReader::new(base_dir).unwrap();
+	let writer = Mutex::new(LooseWriter::new(snap_path).unwrap());
+	let state_chunks = chunk_state(&db, &root, Some(&base as &SnapshotReader), &writer, &Progress::default()).unwrap();
+
+	// at least 90% of the chunks are shared with the base.
+	assert!(state_chunks.shared * 10 >= state_chunks.hashes.len() * 9);
+}
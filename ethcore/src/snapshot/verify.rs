@@ -0,0 +1,274 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Snapshot verification.
+//! Checks a snapshot without restoring it into the client database:
+//! chunk hashes, the rebuilt state root and continuity of block chunks.
+
+use std::{env, fmt, fs};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use util::{Hashable, U256, snappy};
+use util::hash::{FixedHash, H32, H256};
+use util::journaldb::Algorithm;
+use util::kvdb::{Database, DatabaseConfig};
+use util::triehash::ordered_trie_root;
+use rlp::{UntrustedRlp, View};
+
+use super::io::SnapshotReader;
+use super::block::AbridgedBlock;
+use super::{Error, StateRebuilder};
+
+/// Problem found in a snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationError {
+	/// Chunk could not be read, decompressed or decoded.
+	InvalidChunk(H256, String),
+	/// Chunk data does not hash to the hash in the manifest.
+	ChunkHashMismatch {
+		/// Hash listed in the manifest.
+		expected: H256,
+		/// Hash of the chunk data.
+		found: H256,
+	},
+	/// State root rebuilt from state chunks differs from the manifest.
+	StateRootMismatch {
+		/// State root from the manifest.
+		expected: H256,
+		/// Rebuilt state root.
+		found: H256,
+	},
+	/// Accounts reference code which is in none of the state chunks.
+	MissingCode(Vec<H256>),
+	/// Block chunk does not start where another one ends.
+	BlockGap {
+		/// Hash of the chunk.
+		chunk: H256,
+		/// Number of the parent of the first block in the chunk.
+		parent_number: u64,
+	},
+	/// Last block in block chunks is not the block the snapshot was taken at.
+	LastBlockMismatch {
+		/// Block from the manifest.
+		expected: (u64, H256),
+		/// Last block found in block chunks.
+		found: Option<(u64, H256)>,
+	},
+}
+
+impl fmt::Display for VerificationError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			VerificationError::InvalidChunk(ref hash, ref err) => write!(f, "Invalid chunk {}: {}", hash, err),
+			VerificationError::ChunkHashMismatch { ref expected, ref found } =>
+				write!(f, "Chunk {} has data hashing to {}", expected, found),
+			VerificationError::StateRootMismatch { ref expected, ref found } =>
+				write!(f, "Rebuilt state root {} differs from expected {}", found, expected),
+			VerificationError::MissingCode(ref hashes) => write!(f, "Missing code for {} code hashes", hashes.len()),
+			VerificationError::BlockGap { ref chunk, ref parent_number } =>
+				write!(f, "Block chunk {} starting after block #{} does not connect to other chunks", chunk, parent_number),
+			VerificationError::LastBlockMismatch { ref expected, ref found } => match *found {
+				Some((number, ref hash)) => write!(f, "Block chunks end at #{} ({}) instead of #{} ({})", number, hash, expected.0, expected.1),
+				None => write!(f, "Block chunks contain no blocks, expected to end at #{} ({})", expected.0, expected.1),
+			},
+		}
+	}
+}
+
+/// Result of a snapshot verification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerificationReport {
+	/// Block number the snapshot was taken at.
+	pub block_number: u64,
+	/// Block hash the snapshot was taken at.
+	pub block_hash: H256,
+	/// Expected state root.
+	pub state_root: H256,
+	/// State root rebuilt from state chunks.
+	pub rebuilt_state_root: H256,
+	/// Number of state chunks.
+	pub state_chunks: usize,
+	/// Number of block chunks.
+	pub block_chunks: usize,
+	/// Number of blocks in block chunks.
+	pub blocks: u64,
+	/// Number of the first block in block chunks.
+	pub first_block: Option<u64>,
+	/// Problems found.
+	pub errors: Vec<VerificationError>,
+}
+
+impl VerificationReport {
+	/// Whether the snapshot is valid.
+	pub fn is_valid(&self) -> bool {
+		self.errors.is_empty()
+	}
+}
+
+// temporary directory for the rebuilt state, removed when dropped.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+	fn new() -> Self {
+		let mut path = env::temp_dir();
+		path.push(format!("parity-snapshot-verification-{}", H32::random().hex()));
+		ScratchDir(path)
+	}
+}
+
+impl Drop for ScratchDir {
+	fn drop(&mut self) {
+		if !self.0.exists() {
+			return;
+		}
+		if let Err(e) = fs::remove_dir_all(&self.0) {
+			warn!("Failed to remove snapshot verification data at {}: {}", self.0.display(), e);
+		}
+	}
+}
+
+// blocks contained in a block chunk.
+struct BlockRange {
+	chunk: H256,
+	parent: (u64, H256),
+	last: (u64, H256),
+	count: u64,
+}
+
+// read a chunk, checking its hash, and decompress it.
+fn read_chunk<R: SnapshotReader>(reader: &R, hash: H256, errors: &mut Vec<VerificationError>) -> Option<Vec<u8>> {
+	let raw = match reader.chunk(hash) {
+		Ok(raw) => raw,
+		Err(e) => {
+			errors.push(VerificationError::InvalidChunk(hash, format!("{}", e)));
+			return None;
+		}
+	};
+
+	let found = raw.sha3();
+	if found != hash {
+		errors.push(VerificationError::ChunkHashMismatch { expected: hash, found: found });
+		return None;
+	}
+
+	match snappy::decompress(&raw) {
+		Ok(chunk) => Some(chunk),
+		Err(e) => {
+			errors.push(VerificationError::InvalidChunk(hash, format!("{}", e)));
+			None
+		}
+	}
+}
+
+// get the range of blocks in a block chunk, computing block hashes.
+fn block_range(hash: H256, chunk: &[u8]) -> Result<BlockRange, ::error::Error> {
+	let rlp = UntrustedRlp::new(chunk);
+	let item_count = rlp.item_count();
+
+	let parent_number: u64 = try!(rlp.val_at(0));
+	let parent_hash: H256 = try!(rlp.val_at(1));
+	let _: U256 = try!(rlp.val_at(2));
+
+	let mut last = (parent_number, parent_hash);
+	for idx in 3..item_count {
+		let pair = try!(rlp.at(idx));
+		let abridged_block = AbridgedBlock::from_raw(try!(pair.at(0)).as_raw().to_owned());
+		let receipts_root = ordered_trie_root(
+			try!(pair.at(1)).iter().map(|r| r.as_raw().to_owned())
+		);
+
+		let block = try!(abridged_block.to_block(last.1, last.0 + 1, receipts_root));
+		last = (last.0 + 1, block.header.hash());
+	}
+
+	Ok(BlockRange {
+		chunk: hash,
+		parent: (parent_number, parent_hash),
+		last: last,
+		count: item_count.saturating_sub(3) as u64,
+	})
+}
+
+/// Verify the snapshot read by `reader`.
+///
+/// State is rebuilt into a database in a temporary directory, removed once verification is done.
+pub fn verify<R: SnapshotReader>(reader: &R) -> Result<VerificationReport, Error> {
+	let manifest = reader.manifest();
+	let mut errors = Vec::new();
+
+	// the directory is dropped after the database.
+	let scratch = ScratchDir::new();
+	let db = Arc::new(try!(Database::open(&DatabaseConfig::with_columns(::db::NUM_COLUMNS), &scratch.0.to_string_lossy())
+		.map_err(|e| Error::Io(::std::io::Error::new(::std::io::ErrorKind::Other, e)))));
+
+	info!("Verifying {} state chunks", manifest.state_hashes.len());
+	let mut rebuilder = StateRebuilder::new(db, Algorithm::Archive);
+	for hash in &manifest.state_hashes {
+		if let Some(chunk) = read_chunk(reader, *hash, &mut errors) {
+			if let Err(e) = rebuilder.feed(&chunk) {
+				errors.push(VerificationError::InvalidChunk(*hash, format!("{}", e)));
+			}
+		}
+	}
+
+	let rebuilt_state_root = rebuilder.state_root();
+	if rebuilt_state_root != manifest.state_root {
+		errors.push(VerificationError::StateRootMismatch { expected: manifest.state_root, found: rebuilt_state_root });
+	}
+
+	if let Err(Error::MissingCode(missing)) = rebuilder.check_missing() {
+		errors.push(VerificationError::MissingCode(missing));
+	}
+
+	info!("Verifying {} block chunks", manifest.block_hashes.len());
+	let mut ranges = Vec::new();
+	for hash in &manifest.block_hashes {
+		if let Some(chunk) = read_chunk(reader, *hash, &mut errors) {
+			match block_range(*hash, &chunk) {
+				Ok(range) => ranges.push(range),
+				Err(e) => errors.push(VerificationError::InvalidChunk(*hash, format!("{}", e))),
+			}
+		}
+	}
+
+	// chunks may be listed in any order; every chunk but the first must continue another one.
+	ranges.sort_by(|a, b| a.parent.0.cmp(&b.parent.0));
+	for pair in ranges.windows(2) {
+		if pair[1].parent != pair[0].last {
+			errors.push(VerificationError::BlockGap { chunk: pair[1].chunk, parent_number: pair[1].parent.0 });
+		}
+	}
+
+	let last = ranges.last().map(|r| r.last);
+	let expected = (manifest.block_number, manifest.block_hash);
+	if last != Some(expected) {
+		errors.push(VerificationError::LastBlockMismatch { expected: expected, found: last });
+	}
+
+	Ok(VerificationReport {
+		block_number: manifest.block_number,
+		block_hash: manifest.block_hash,
+		state_root: manifest.state_root,
+		rebuilt_state_root: rebuilt_state_root,
+		state_chunks: manifest.state_hashes.len(),
+		block_chunks: manifest.block_hashes.len(),
+		blocks: ranges.iter().map(|r| r.count).sum(),
+		first_block: ranges.first().map(|r| r.parent.0 + 1),
+		errors: errors,
+	})
+}
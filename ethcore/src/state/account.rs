@@ -443,6 +443,14 @@ impl Account {
 	}
 }
 
+impl HeapSizeOf for Account {
+	fn heap_size_of_children(&self) -> usize {
+		// the LRU storage cache keeps a key and a value per entry.
+		let cached_storage = self.storage_cache.borrow().len() * 2 * ::std::mem::size_of::<H256>();
+		cached_storage + self.storage_changes.heap_size_of_children() + self.code_cache.heap_size_of_children()
+	}
+}
+
 impl fmt::Debug for Account {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(f, "{:?}", PodAccount::from_account(self))
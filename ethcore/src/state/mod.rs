@@ -23,7 +23,10 @@ use trace::FlatTrace;
 use pod_account::*;
 use pod_state::{self, PodState};
 use types::state_diff::StateDiff;
+use types::account_proof::{AccountProof, StorageProof};
 use state_db::StateDB;
+use util::trie::recorder::{Recorder, BasicRecorder};
+use rlp::decode;
 
 mod account;
 mod substate;
@@ -221,6 +224,14 @@ impl State {
 		}
 	}
 
+	/// Load given accounts together with their code into the local cache.
+	/// Accounts found in the shared cache are not loaded again.
+	pub fn prefetch(&self, addresses: &[Address]) {
+		for address in addresses {
+			self.ensure_cached(address, RequireCache::Code, |_| ());
+		}
+	}
+
 	/// Destroy the current object and return root and database.
 	pub fn drop(mut self) -> (H256, StateDB) {
 		self.commit_cache();
@@ -330,6 +341,98 @@ impl State {
 			|a| a.as_ref().and_then(|a| a.code_size()))
 	}
 
+	/// Get Merkle proof of the account `a` and its storage entries under `keys`.
+	/// Proof consists of the trie nodes visited during the lookups. Uncommitted changes are ignored.
+	pub fn prove_account(&self, a: &Address, keys: &[H256]) -> Result<AccountProof, Box<TrieError>> {
+		let mut recorder = BasicRecorder::new();
+		let account_rlp = {
+			let trie = try!(self.factories.trie.readonly(self.db.as_hashdb(), &self.root));
+			try!(trie.get_recorded(a, &mut recorder)).map(|rlp| rlp.to_vec())
+		};
+		let account_proof = recorder.drain().into_iter().map(|r| r.data).collect();
+
+		let account = match account_rlp {
+			Some(ref rlp) => Account::from_rlp(rlp),
+			None => return Ok(AccountProof {
+				address: a.clone(),
+				balance: U256::zero(),
+				nonce: self.account_start_nonce,
+				code_hash: SHA3_EMPTY,
+				storage_root: SHA3_NULL_RLP,
+				account_rlp: None,
+				account_proof: account_proof,
+				storage_proof: keys.iter().map(|key| StorageProof {
+					key: key.clone(),
+					value: H256::new(),
+					proof: Vec::new(),
+				}).collect(),
+			}),
+		};
+
+		let storage_root = account.storage_root().cloned().expect("account decoded from the trie has no storage changes; qed");
+		let account_db = self.factories.accountdb.readonly(self.db.as_hashdb(), account.address_hash(a));
+		let trie = try!(SecTrieDB::new(account_db.as_hashdb(), &storage_root));
+		let mut storage_proof = Vec::with_capacity(keys.len());
+		for key in keys {
+			let mut recorder = BasicRecorder::new();
+			let value: U256 = try!(trie.get_recorded(key, &mut recorder)).map_or_else(U256::zero, decode);
+			storage_proof.push(StorageProof {
+				key: key.clone(),
+				value: value.into(),
+				proof: recorder.drain().into_iter().map(|r| r.data).collect(),
+			});
+		}
+
+		Ok(AccountProof {
+			address: a.clone(),
+			balance: *account.balance(),
+			nonce: *account.nonce(),
+			code_hash: account.code_hash(),
+			storage_root: storage_root,
+			account_rlp: account_rlp,
+			account_proof: account_proof,
+			storage_proof: storage_proof,
+		})
+	}
+
+	/// Call `f` with the address and contents of every account in the state, in trie order.
+	/// Accounts are read one at a time, straight from the account trie.
+	/// Only works with a fat trie, which keeps preimages of the hashed keys.
+	/// Returns the number of accounts walked.
+	pub fn for_each_account<F>(&self, mut f: F) -> Result<usize, Box<TrieError>> where F: FnMut(Address, PodAccount) {
+		let trie = try!(self.factories.trie.readonly(self.db.as_hashdb(), &self.root));
+		let mut count = 0;
+		for item in try!(trie.iter()) {
+			let (address, rlp) = try!(item);
+			let address = Address::from_slice(&address);
+			let mut account = Account::from_rlp(&rlp);
+
+			let storage_root = account.storage_root().cloned().expect("account decoded from the trie has no storage changes; qed");
+			let account_db = self.factories.accountdb.readonly(self.db.as_hashdb(), account.address_hash(&address));
+			let mut storage = BTreeMap::new();
+			{
+				let storage_trie = try!(self.factories.trie.readonly(account_db.as_hashdb(), &storage_root));
+				for item in try!(storage_trie.iter()) {
+					let (key, value) = try!(item);
+					let value: U256 = decode(&value);
+					storage.insert(H256::from_slice(&key), value.into());
+				}
+			}
+
+			account.cache_code(account_db.as_hashdb());
+			let pod = PodAccount {
+				balance: *account.balance(),
+				nonce: *account.nonce(),
+				code: account.code().map(|code| code.to_vec()),
+				storage: storage,
+			};
+
+			f(address, pod);
+			count += 1;
+		}
+		Ok(count)
+	}
+
 	/// Add `incr` to the balance of account `a`.
 	pub fn add_balance(&mut self, a: &Address, incr: &U256) {
 		trace!(target: "state", "add_balance({}, {}): {}", a, incr, self.balance(a));
@@ -409,6 +512,7 @@ impl State {
 			}
 		}
 
+		let mut modified = Vec::new();
 		{
 			let mut trie = factories.trie.from_existing(db.as_hashdb_mut(), root).unwrap();
 			for (address, ref mut a) in accounts.iter_mut() {
@@ -416,16 +520,22 @@ impl State {
 					AccountEntry::Cached(ref mut account) if account.is_dirty() => {
 						account.set_clean();
 						try!(trie.insert(address, &account.rlp()));
+						modified.push(address.clone());
 					},
 					AccountEntry::Killed => {
 						try!(trie.remove(address));
 						**a = AccountEntry::Missing;
+						modified.push(address.clone());
 					},
 					_ => {},
 				}
 			}
 		}
 
+		// let the shared cache know which accounts are changed by this state.
+		for address in &modified {
+			db.note_modified(address);
+		}
 		Ok(())
 	}
 
@@ -650,6 +760,7 @@ use env_info::*;
 use spec::*;
 use transaction::*;
 use util::log::init_log;
+use util::trie::verify_secure_proof;
 use trace::{FlatTrace, TraceError, trace};
 use types::executed::CallType;
 
@@ -1683,6 +1794,31 @@ fn snapshot_nested() {
 	assert_eq!(state.balance(&a), U256::from(0));
 }
 
+#[test]
+fn should_prove_account_and_storage() {
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	let a = Address::from(1);
+	state.add_balance(&a, &U256::from(69u64));
+	state.set_storage(&a, H256::from(1), H256::from(42));
+	state.commit().unwrap();
+
+	let proof = state.prove_account(&a, &[H256::from(1), H256::from(2)]).unwrap();
+	assert_eq!(proof.balance, U256::from(69u64));
+	assert_eq!(proof.account_proof[0].sha3(), *state.root());
+	assert_eq!(verify_secure_proof(state.root(), &a, &proof.account_proof).unwrap(), proof.account_rlp);
+	assert_eq!(proof.storage_proof[0].value, H256::from(42));
+	assert_eq!(proof.storage_proof[0].proof[0].sha3(), proof.storage_root);
+	assert_eq!(verify_secure_proof(&proof.storage_root, &H256::from(1), &proof.storage_proof[0].proof).unwrap(), Some(::rlp::encode(&U256::from(42)).to_vec()));
+	assert_eq!(proof.storage_proof[1].value, H256::new());
+
+	let missing = state.prove_account(&Address::from(2), &[]).unwrap();
+	assert_eq!(missing.balance, U256::zero());
+	assert_eq!(missing.storage_root, SHA3_NULL_RLP);
+	assert_eq!(missing.account_rlp, None);
+	assert_eq!(verify_secure_proof(state.root(), &Address::from(2), &missing.account_proof).unwrap(), None);
+}
+
 #[test]
 fn create_empty() {
 	let mut state_result = get_temp_state();
@@ -14,56 +14,212 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use lru_cache::LruCache;
+use std::collections::{VecDeque, HashSet, HashMap};
 use util::journaldb::JournalDB;
 use util::hash::{H256};
 use util::hashdb::HashDB;
-use util::{Arc, Address, DBTransaction, UtilError, Mutex};
+use util::{Arc, Address, DBTransaction, UtilError, Mutex, HeapSizeOf};
 use state::Account;
+use header::BlockNumber;
+use cache_manager::CacheManager;
 
-const STATE_CACHE_ITEMS: usize = 65536;
+/// Number of recent blocks whose account modifications are remembered.
+/// Cache lookups for states older than that are not allowed.
+const STATE_CACHE_BLOCKS: usize = 12;
+
+/// Estimated size of a cached account, used to decide when accounts are moved to an older cache generation.
+const ACCOUNT_CACHE_ENTRY_SIZE: usize = 512;
 
 struct AccountCache {
 	/// DB Account cache. `None` indicates that account is known to be missing.
-	accounts: LruCache<Address, Option<Account>>,
+	accounts: HashMap<Address, Option<Account>>,
+	/// Tracks recently used accounts and evicts the least recently used ones.
+	cache_manager: CacheManager<Address>,
+	/// Accounts modified in recent blocks, ordered by block number, latest first.
+	modifications: VecDeque<BlockChanges>,
+}
+
+/// Account cache item, waiting to be applied to the global cache.
+struct CacheQueueItem {
+	address: Address,
+	account: Option<Account>,
+	/// Whether the account was modified by the committed block.
+	modified: bool,
+}
+
+/// Accounts modified by a single block.
+#[derive(Debug)]
+struct BlockChanges {
+	number: BlockNumber,
+	hash: H256,
+	parent: H256,
+	accounts: HashSet<Address>,
+	/// Whether the block is part of the canonical chain.
+	is_canon: bool,
 }
 
 /// State database abstraction.
-/// Manages shared global state cache.
+/// Manages shared global state cache which reflects the canonical state
+/// as it is after the latest imported best block.
 /// A clone of `StateDB` may be created as canonical or not.
-/// For canonical clones cache changes are accumulated and applied
-/// on commit.
-/// For non-canonical clones cache is cleared on commit.
+/// Canonical clones know the hash of the parent block they are built on
+/// and may only read cached accounts which were not modified since that block.
+/// Cache changes are accumulated and applied by `sync_cache` after the block
+/// has been committed and inserted into the chain.
+/// Non-canonical clones never use the cache.
 pub struct StateDB {
 	db: Box<JournalDB>,
 	account_cache: Arc<Mutex<AccountCache>>,
-	cache_overlay: Vec<(Address, Option<Account>)>,
-	is_canon: bool,
+	local_cache: Vec<CacheQueueItem>,
+	/// Accounts modified since the last commit.
+	modified: HashSet<Address>,
+	cache_size: usize,
+	/// Hash of the block on top of which this state is built.
+	parent_hash: Option<H256>,
+	/// Hash of the committed block.
+	commit_hash: Option<H256>,
+	/// Number of the committed block.
+	commit_number: Option<BlockNumber>,
 }
 
 impl StateDB {
-	/// Create a new instance wrapping `JournalDB`
-	pub fn new(db: Box<JournalDB>) -> StateDB {
+	/// Create a new instance wrapping `JournalDB` with shared account cache of at most `cache_size` bytes.
+	/// The cache is trimmed by `collect_garbage`.
+	pub fn new(db: Box<JournalDB>, cache_size: usize) -> StateDB {
 		StateDB {
 			db: db,
-			account_cache: Arc::new(Mutex::new(AccountCache { accounts: LruCache::new(STATE_CACHE_ITEMS) })),
-			cache_overlay: Vec::new(),
-			is_canon: false,
+			account_cache: Arc::new(Mutex::new(AccountCache {
+				accounts: HashMap::new(),
+				cache_manager: CacheManager::new(cache_size * 3 / 4, cache_size, ACCOUNT_CACHE_ENTRY_SIZE),
+				modifications: VecDeque::new(),
+			})),
+			local_cache: Vec::new(),
+			modified: HashSet::new(),
+			cache_size: cache_size,
+			parent_hash: None,
+			commit_hash: None,
+			commit_number: None,
 		}
 	}
 
 	/// Commit all recent insert operations and canonical historical commits' removals from the
 	/// old era to the backing database, reverting any non-canonical historical commit's inserts.
+	/// Cache changes are kept until `sync_cache` is called.
 	pub fn commit(&mut self, batch: &mut DBTransaction, now: u64, id: &H256, end: Option<(u64, H256)>) -> Result<u32, UtilError> {
 		let records = try!(self.db.commit(batch, now, id, end));
-		if self.is_canon {
-			self.commit_cache();
-		} else {
-			self.clear_cache();
-		}
+		self.commit_hash = Some(id.clone());
+		self.commit_number = Some(now);
 		Ok(records)
 	}
 
+	/// Propagate local cache into the global cache and synchronize
+	/// the global cache with the best block state.
+	/// This function updates the global cache by removing entries
+	/// that are invalidated by chain reorganization. `sync_cache`
+	/// should be called after the block has been committed and the
+	/// blockchain route has been calculated.
+	pub fn sync_cache(&mut self, enacted: &[H256], retracted: &[H256], is_best: bool) {
+		trace!(target: "state_db", "sync_cache id = (#{:?}, {:?}), parent={:?}, best={}", self.commit_number, self.commit_hash, self.parent_hash, is_best);
+		let mut cache = self.account_cache.lock();
+		let cache = &mut *cache;
+
+		// Purge changes of re-enacted and retracted blocks.
+		// The committed block itself is not in the journal yet.
+		let mut clear = false;
+		for block in enacted.iter().filter(|h| self.commit_hash.as_ref().map_or(true, |hash| hash != *h)) {
+			clear = clear || match cache.modifications.iter_mut().find(|m| &m.hash == block) {
+				Some(m) => {
+					trace!(target: "state_db", "Reverting enacted block {:?}", block);
+					m.is_canon = true;
+					for address in &m.accounts {
+						cache.accounts.remove(address);
+					}
+					false
+				},
+				None => true,
+			};
+		}
+
+		for block in retracted {
+			clear = clear || match cache.modifications.iter_mut().find(|m| &m.hash == block) {
+				Some(m) => {
+					trace!(target: "state_db", "Retracting block {:?}", block);
+					m.is_canon = false;
+					for address in &m.accounts {
+						cache.accounts.remove(address);
+					}
+					false
+				},
+				None => true,
+			};
+		}
+
+		if clear {
+			// Reorganization goes deeper than the journal, so nothing can be trusted.
+			trace!(target: "state_db", "Wiping cache");
+			cache.accounts.clear();
+			cache.modifications.clear();
+		}
+
+		// Only blocks built on top of a known parent are journaled.
+		// Cached accounts are updated only if the block becomes the new best block.
+		if let (Some(number), Some(hash), Some(parent)) = (self.commit_number, self.commit_hash.clone(), self.parent_hash.clone()) {
+			if cache.modifications.len() == STATE_CACHE_BLOCKS {
+				cache.modifications.pop_back();
+			}
+
+			let mut modifications = HashSet::new();
+			for item in self.local_cache.drain(..) {
+				if item.modified {
+					modifications.insert(item.address.clone());
+				}
+				if is_best {
+					cache.cache_manager.note_used(item.address.clone());
+					if let Some(&mut Some(ref mut existing)) = cache.accounts.get_mut(&item.address) {
+						if let Some(new) = item.account {
+							// storage cached for a modified account may be stale.
+							match item.modified {
+								true => *existing = new,
+								false => existing.merge_with(new),
+							}
+							continue;
+						}
+					}
+					cache.accounts.insert(item.address, item.account);
+				}
+			}
+			// modified accounts which were not queued for caching must not stay in the cache.
+			for address in self.modified.drain() {
+				if is_best {
+					cache.accounts.remove(&address);
+				}
+				modifications.insert(address);
+			}
+
+			let changes = BlockChanges {
+				number: number,
+				hash: hash,
+				parent: parent,
+				accounts: modifications,
+				is_canon: is_best,
+			};
+			let insert_at = cache.modifications.iter().position(|m| m.number < number);
+			match insert_at {
+				Some(index) => cache.modifications.insert(index, changes),
+				None => cache.modifications.push_back(changes),
+			}
+		}
+		self.local_cache.clear();
+		self.modified.clear();
+	}
+
+	/// Move the latest era back to `era` after the canonical chain was rewound.
+	/// Cached accounts may belong to retracted blocks, so the cache is cleared.
+	pub fn revert_to_era(&mut self, batch: &mut DBTransaction, era: u64) {
+		self.db.revert_to_era(batch, era);
+		self.clear_cache();
+	}
+
 	/// Returns an interface to HashDB.
 	pub fn as_hashdb(&self) -> &HashDB {
 		self.db.as_hashdb()
@@ -79,18 +235,26 @@ impl StateDB {
 		StateDB {
 			db: self.db.boxed_clone(),
 			account_cache: self.account_cache.clone(),
-			cache_overlay: Vec::new(),
-			is_canon: false,
+			local_cache: Vec::new(),
+			modified: HashSet::new(),
+			cache_size: self.cache_size,
+			parent_hash: None,
+			commit_hash: None,
+			commit_number: None,
 		}
 	}
 
-	/// Clone the database for a canonical state.
-	pub fn boxed_clone_canon(&self) -> StateDB {
+	/// Clone the database for a canonical state built on top of block `parent`.
+	pub fn boxed_clone_canon(&self, parent: &H256) -> StateDB {
 		StateDB {
 			db: self.db.boxed_clone(),
 			account_cache: self.account_cache.clone(),
-			cache_overlay: Vec::new(),
-			is_canon: true,
+			local_cache: Vec::new(),
+			modified: HashSet::new(),
+			cache_size: self.cache_size,
+			parent_hash: Some(parent.clone()),
+			commit_hash: None,
+			commit_number: None,
 		}
 	}
 
@@ -101,7 +265,22 @@ impl StateDB {
 
 	/// Heap size used.
 	pub fn mem_used(&self) -> usize {
-		self.db.mem_used() //TODO: + self.account_cache.lock().heap_size_of_children()
+		self.db.mem_used() + self.account_cache.lock().accounts.heap_size_of_children()
+	}
+
+	/// Evicts the least recently used accounts once the shared cache grows over its size.
+	pub fn collect_garbage(&self) {
+		let mut cache = self.account_cache.lock();
+		let cache = &mut *cache;
+		let current_size = cache.accounts.heap_size_of_children();
+		let accounts = &mut cache.accounts;
+		cache.cache_manager.collect_garbage(current_size, |addresses| {
+			for address in &addresses {
+				accounts.remove(address);
+			}
+			accounts.shrink_to_fit();
+			accounts.heap_size_of_children()
+		});
 	}
 
 	/// Returns underlying `JournalDB`.
@@ -111,51 +290,203 @@ impl StateDB {
 
 	/// Enqueue cache change.
 	pub fn cache_account(&mut self, addr: Address, data: Option<Account>) {
-		self.cache_overlay.push((addr, data));
+		let modified = self.modified.remove(&addr);
+		self.local_cache.push(CacheQueueItem {
+			address: addr,
+			account: data,
+			modified: modified,
+		});
 	}
 
-	/// Apply pending cache changes.
-	fn commit_cache(&mut self) {
+	/// Note that the account has been changed by the state built on top of this database.
+	pub fn note_modified(&mut self, addr: &Address) {
+		self.modified.insert(addr.clone());
+	}
+
+	/// Apply pending cache changes made while prefetching accounts.
+	/// Accounts which are cached already are not overwritten.
+	/// Prefetched accounts are only applied if they were loaded from
+	/// the state of the latest best block.
+	pub fn commit_prefetched(&mut self) {
 		let mut cache = self.account_cache.lock();
-		for (address, account) in self.cache_overlay.drain(..) {
-			if let Some(&mut Some(ref mut existing)) = cache.accounts.get_mut(&address) {
-				if let Some(new) = account {
-					existing.merge_with(new);
-					continue;
+		let is_best = match (cache.modifications.iter().find(|m| m.is_canon), self.parent_hash.as_ref()) {
+			(Some(best), Some(parent)) => &best.hash == parent,
+			_ => false,
+		};
+		if is_best {
+			let cache = &mut *cache;
+			for item in self.local_cache.drain(..) {
+				if !cache.accounts.contains_key(&item.address) {
+					cache.cache_manager.note_used(item.address.clone());
+					cache.accounts.insert(item.address, item.account);
 				}
 			}
-			cache.accounts.insert(address, account);
 		}
+		self.local_cache.clear();
 	}
 
 	/// Clear the cache.
 	pub fn clear_cache(&mut self) {
-		self.cache_overlay.clear();
+		self.local_cache.clear();
+		self.modified.clear();
 		let mut cache = self.account_cache.lock();
 		cache.accounts.clear();
+		cache.modifications.clear();
 	}
 
 	/// Get basic copy of the cached account. Does not include storage.
-	/// Returns 'None' if the state is non-canonical and cache is disabled
-	/// or if the account is not cached.
+	/// Returns 'None' if cache is disabled or if the account is not cached.
 	pub fn get_cached_account(&self, addr: &Address) -> Option<Option<Account>> {
-		if !self.is_canon {
+		let mut cache = self.account_cache.lock();
+		if !Self::is_allowed(addr, &self.parent_hash, &cache.modifications) {
 			return None;
 		}
-		let mut cache = self.account_cache.lock();
-		cache.accounts.get_mut(&addr).map(|a| a.as_ref().map(|a| a.clone_basic()))
+		let cache = &mut *cache;
+		match cache.accounts.get(addr) {
+			Some(account) => {
+				cache.cache_manager.note_used(addr.clone());
+				Some(account.as_ref().map(|a| a.clone_basic()))
+			},
+			None => None,
+		}
 	}
 
 	/// Get value from a cached account.
-	/// Returns 'None' if the state is non-canonical and cache is disabled
-	/// or if the account is not cached.
+	/// Returns 'None' if cache is disabled or if the account is not cached.
 	pub fn get_cached<F, U>(&self, a: &Address, f: F) -> Option<U>
 		where F: FnOnce(Option<&mut Account>) -> U {
-		if !self.is_canon {
+		let mut cache = self.account_cache.lock();
+		if !Self::is_allowed(a, &self.parent_hash, &cache.modifications) {
 			return None;
 		}
-		let mut cache = self.account_cache.lock();
-		cache.accounts.get_mut(a).map(|c| f(c.as_mut()))
+		let cache = &mut *cache;
+		match cache.accounts.get_mut(a) {
+			Some(account) => {
+				cache.cache_manager.note_used(a.clone());
+				Some(f(account.as_mut()))
+			},
+			None => None,
+		}
+	}
+
+	/// Check if the cached account `addr` is valid for the state built on top of `parent_hash`.
+	/// The cache reflects the latest best block. Walks the journal from the latest block
+	/// down to the parent and further through its ancestors until the canonical chain is reached,
+	/// rejecting accounts modified by any of the blocks on the way.
+	fn is_allowed(addr: &Address, parent_hash: &Option<H256>, modifications: &VecDeque<BlockChanges>) -> bool {
+		let mut parent = match *parent_hash {
+			None => return false,
+			Some(ref parent) => parent,
+		};
+		for m in modifications {
+			if &m.hash == parent {
+				if m.is_canon {
+					return true;
+				}
+				parent = &m.parent;
+			}
+			if m.accounts.contains(addr) {
+				trace!(target: "state_db", "Cache lookup skipped for {:?}: modified in a later block", addr);
+				return false;
+			}
+		}
+		trace!(target: "state_db", "Cache lookup skipped for {:?}: parent hash is unknown", addr);
+		false
 	}
 }
 
+
+#[cfg(test)]
+mod tests {
+	use util::{U256, H256, Address, DBTransaction};
+	use tests::helpers::get_temp_state_db;
+	use state::Account;
+	use super::StateDB;
+
+	fn balance(state_db: &StateDB, address: &Address) -> Option<Option<U256>> {
+		state_db.get_cached(address, |acc| acc.map(|acc| acc.balance().clone()))
+	}
+
+	/// Commits block `hash` on top of `parent` which changes balance of `address`.
+	fn commit(state_db: &StateDB, number: u64, parent: &H256, hash: &H256, address: &Address, balance: u64, enacted: &[H256], retracted: &[H256]) {
+		let mut db = state_db.boxed_clone_canon(parent);
+		let mut batch = DBTransaction::new(state_db.journal_db().backing());
+		db.note_modified(address);
+		db.cache_account(address.clone(), Some(Account::new_basic(U256::from(balance), U256::zero())));
+		db.commit(&mut batch, number, hash, None).unwrap();
+		db.sync_cache(enacted, retracted, enacted.last() == Some(hash));
+	}
+
+	#[test]
+	fn should_invalidate_cached_accounts_on_reorg() {
+		// given
+		let mut state_db_result = get_temp_state_db();
+		let state_db = state_db_result.reference_mut();
+		let address = Address::from(1);
+		let (h0, h1a, h1b, h2b) = (H256::from(10), H256::from(11), H256::from(12), H256::from(13));
+
+		// blocks  [ 1a(c) 1b 0(c) ]
+		// balance [ 2     3  1    ]
+		commit(state_db, 0, &H256::new(), &h0, &address, 1, &[h0.clone()], &[]);
+		commit(state_db, 1, &h0, &h1a, &address, 2, &[h1a.clone()], &[]);
+		commit(state_db, 1, &h0, &h1b, &address, 3, &[], &[]);
+
+		// then
+		assert_eq!(balance(&state_db.boxed_clone_canon(&h1a), &address), Some(Some(U256::from(2))));
+		assert_eq!(balance(&state_db.boxed_clone_canon(&h0), &address), None);
+		assert_eq!(balance(&state_db.boxed_clone_canon(&h1b), &address), None);
+		assert_eq!(balance(&state_db.boxed_clone(), &address), None);
+
+		// when
+		// blocks  [ 2b(c) 1a 1b(c) 0(c) ]
+		// balance [ 4     2  3     1    ]
+		commit(state_db, 2, &h1b, &h2b, &address, 4, &[h1b.clone(), h2b.clone()], &[h1a.clone()]);
+
+		// then
+		assert_eq!(balance(&state_db.boxed_clone_canon(&h2b), &address), Some(Some(U256::from(4))));
+		assert_eq!(balance(&state_db.boxed_clone_canon(&h1a), &address), None);
+		assert_eq!(balance(&state_db.boxed_clone_canon(&h1b), &address), None);
+	}
+
+	#[test]
+	fn should_evict_accounts_over_cache_size() {
+		// given
+		let state_db_result = get_temp_state_db();
+		let state_db = StateDB::new(state_db_result.reference().journal_db().boxed_clone(), 0);
+		let address = Address::from(1);
+		let h0 = H256::from(10);
+		commit(&state_db, 0, &H256::new(), &h0, &address, 1, &[h0.clone()], &[]);
+		assert_eq!(balance(&state_db.boxed_clone_canon(&h0), &address), Some(Some(U256::from(1))));
+
+		// when
+		state_db.collect_garbage();
+
+		// then
+		assert_eq!(balance(&state_db.boxed_clone_canon(&h0), &address), None);
+	}
+
+	#[test]
+	fn should_not_overwrite_cached_accounts_with_prefetched() {
+		// given
+		let mut state_db_result = get_temp_state_db();
+		let state_db = state_db_result.reference_mut();
+		let (a, b, c) = (Address::from(1), Address::from(2), Address::from(3));
+		let (h0, h1) = (H256::from(10), H256::from(11));
+		commit(state_db, 0, &H256::new(), &h0, &a, 1, &[h0.clone()], &[]);
+
+		// when
+		let mut prefetching = state_db.boxed_clone_canon(&h0);
+		prefetching.cache_account(a.clone(), None);
+		prefetching.cache_account(b.clone(), Some(Account::new_basic(U256::from(2), U256::zero())));
+		prefetching.commit_prefetched();
+		let mut outdated = state_db.boxed_clone_canon(&h1);
+		outdated.cache_account(c.clone(), Some(Account::new_basic(U256::from(3), U256::zero())));
+		outdated.commit_prefetched();
+
+		// then
+		let canon = state_db.boxed_clone_canon(&h0);
+		assert_eq!(balance(&canon, &a), Some(Some(U256::from(1))));
+		assert_eq!(balance(&canon, &b), Some(Some(U256::from(2))));
+		assert_eq!(balance(&canon, &c), None);
+	}
+}
@@ -15,15 +15,21 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use io::IoChannel;
-use client::{BlockChainClient, MiningBlockChainClient, Client, ClientConfig, BlockID};
+use client::{BlockChainClient, MiningBlockChainClient, Client, ClientConfig, BlockID, BlockStatus, BlockImportError, StateError};
+use error::ImportError;
 use ethereum;
 use block::IsBlock;
 use tests::helpers::*;
 use types::filter::Filter;
 use common::*;
 use devtools::*;
-use miner::Miner;
+use miner::{Miner, CpuMiner, CpuMinerOptions};
 use rlp::{Rlp, View};
+use ethkey::KeyPair;
+use util::journaldb::Algorithm;
+use util::trie::TrieSpec;
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[test]
 fn imports_from_empty() {
@@ -70,6 +76,113 @@ fn returns_state_root_basic() {
 	assert!(client.state_data(genesis_header.state_root()).is_some());
 }
 
+#[test]
+fn proves_account_state() {
+	let client_result = generate_dummy_client(6);
+	let client = client_result.reference();
+	let author = KeyPair::from_secret("".sha3()).unwrap().address();
+
+	let proof = client.prove_account(&author, vec![H256::from(1)], BlockID::Number(3)).unwrap();
+	let header = client.block_header(BlockID::Number(3)).unwrap();
+
+	assert_eq!(proof.account_proof[0].sha3(), HeaderView::new(&header).state_root());
+	assert_eq!(Some(proof.balance), client.balance(&author, BlockID::Number(3)));
+	assert_eq!(proof.storage_proof[0].value, H256::new());
+	assert_eq!(client.state_available(BlockID::Number(100)), Err(StateError::UnknownBlock));
+}
+
+#[test]
+fn keeps_pruning_history_of_the_database() {
+	let dir = RandomTempPath::new();
+	let spec = get_test_spec();
+	let db_config = DatabaseConfig::with_columns(::db::NUM_COLUMNS);
+	let open = |history| {
+		let mut config = ClientConfig::default();
+		config.history = Some(history);
+		Client::new(config, &spec, dir.as_path(), Arc::new(Miner::with_spec(&spec)), IoChannel::disconnected(), &db_config).unwrap()
+	};
+
+	let client = open(10);
+	assert_eq!(client.pruning_history(), 10);
+	drop(client);
+
+	// lowering the history would leave journalled eras behind
+	let client = open(5);
+	assert_eq!(client.pruning_history(), 10);
+}
+
+#[test]
+fn walks_accounts_of_fat_db() {
+	let dir = RandomTempPath::new();
+	let spec = get_test_spec();
+	let db_config = DatabaseConfig::with_columns(::db::NUM_COLUMNS);
+	let mut config = ClientConfig::default();
+	config.trie_spec = TrieSpec::Fat;
+	config.pruning = Algorithm::Archive;
+
+	let client = Client::new(
+		config,
+		&spec,
+		dir.as_path(),
+		Arc::new(Miner::with_spec(&spec)),
+		IoChannel::disconnected(),
+		&db_config
+	).unwrap();
+
+	let mut accounts = Vec::new();
+	let count = client.for_each_account(BlockID::Latest, |address, account| accounts.push((address, account))).unwrap();
+	let address: Address = "102e61f5d8f9bc71d0ad4a084df4e65e05ce0e1c".into();
+	let &(_, ref account) = accounts.iter().find(|&&(ref a, _)| *a == address).unwrap();
+	assert_eq!(count, 5);
+	assert_eq!(accounts.len(), 5);
+	assert_eq!(account.nonce, U256::from(1048576));
+	assert_eq!(account.code, Some(vec![]));
+	assert!(account.storage.is_empty());
+
+	let client_result = generate_dummy_client(1);
+	assert_eq!(client_result.reference().for_each_account(BlockID::Latest, |_, _| {}), None);
+}
+
+#[test]
+fn can_rewind_chain() {
+	let client_result = generate_dummy_client(6);
+	let client = client_result.reference();
+	let best = client.chain_info().best_block_number;
+	let best_hash = client.chain_info().best_block_hash;
+
+	assert_eq!(client.rewind(BlockID::Number(100)), Err(StateError::UnknownBlock));
+	let retracted = client.rewind(BlockID::Number(best - 2)).unwrap();
+
+	assert_eq!(retracted.len(), 2);
+	assert_eq!(retracted[0], best_hash);
+	assert_eq!(client.chain_info().best_block_number, best - 2);
+	assert_eq!(client.block_hash(BlockID::Number(best)), None);
+	assert!(client.check_integrity().is_ok());
+}
+
+#[test]
+fn should_not_reimport_rewound_block_until_cleared() {
+	// given
+	let client_result = generate_dummy_client(6);
+	let client = client_result.reference();
+	let best = client.chain_info().best_block_number;
+	let child = client.block(BlockID::Number(best - 1)).unwrap();
+
+	// when
+	let retracted = client.rewind(BlockID::Number(best - 2)).unwrap();
+
+	// then
+	assert_eq!(client.block_status(BlockID::Hash(retracted[1])), BlockStatus::Unknown);
+	match client.import_block(child.clone()) {
+		Err(BlockImportError::Import(ImportError::Rewound)) => {},
+		other => panic!("Expected rewound block to be rejected, got {:?}", other),
+	}
+
+	// and after clearing the guard
+	assert_eq!(client.clear_rewound_block(), Some(retracted[1]));
+	assert!(client.import_block(child).is_ok());
+}
+
 #[test]
 fn imports_good_block() {
 	let dir = RandomTempPath::new();
@@ -234,3 +347,31 @@ fn can_mine() {
 
 	assert_eq!(*b.block().header().parent_hash(), BlockView::new(&dummy_blocks[0]).header_view().sha3());
 }
+
+#[test]
+fn cpu_miner_seals_blocks() {
+	// given
+	let dir = RandomTempPath::new();
+	let spec = ethereum::new_mining_test();
+	let db_config = DatabaseConfig::with_columns(::db::NUM_COLUMNS);
+	let miner = Arc::new(Miner::with_spec(&spec));
+	let client = Client::new(
+		ClientConfig::default(),
+		&spec,
+		dir.as_path(),
+		miner.clone(),
+		IoChannel::disconnected(),
+		&db_config
+	).unwrap();
+	let options = CpuMinerOptions { threads: 2, use_dag: false };
+
+	// when
+	CpuMiner::register(&options, miner.clone(), Arc::downgrade(&client)).unwrap();
+	let started = Instant::now();
+	while client.chain_info().best_block_number < 3 && started.elapsed() < Duration::from_secs(120) {
+		thread::sleep(Duration::from_millis(100));
+	}
+
+	// then
+	assert!(client.chain_info().best_block_number >= 3);
+}
@@ -176,6 +176,31 @@ impl<T> TraceDB<T> where T: DatabaseExtras {
 		});
 	}
 
+	/// Removes traces of `retracted` blocks and resets trace blooms of blocks
+	/// above `number` up to `best`. Used when the best block is moved back.
+	pub fn rewind(&self, batch: &mut DBTransaction, number: BlockNumber, best: BlockNumber, retracted: &[H256]) {
+		if !self.tracing_enabled() || number >= best {
+			return;
+		}
+
+		{
+			let mut traces = self.traces.write();
+			for hash in retracted {
+				Writable::delete::<FlatBlockTraces, H264>(batch, db::COL_TRACE, hash);
+				traces.remove(hash);
+			}
+		}
+
+		let range = (number + 1) as Number..best as Number;
+		let chain = BloomGroupChain::new(self.bloom_config, self);
+		let blooms_to_reset = chain.replace(&range, vec![]).into_iter()
+			.map(|p| (From::from(p.0), From::from(p.1)))
+			.collect::<HashMap<TraceGroupPosition, blooms::BloomGroup>>();
+
+		let mut blooms = self.blooms.write();
+		batch.extend_with_cache(db::COL_TRACE, &mut *blooms, blooms_to_reset, CacheUpdatePolicy::Remove);
+	}
+
 	/// Returns traces for block with hash.
 	fn traces(&self, block_hash: &H256) -> Option<FlatBlockTraces> {
 		let result = self.tracesdb.read_with_cache(db::COL_TRACE, &self.traces, block_hash);
@@ -598,6 +623,45 @@ mod tests {
 		assert_eq!(tracedb.trace(1, 0, vec![]).unwrap(), create_simple_localized_trace(1, block_1.clone(), tx_1.clone()));
 	}
 
+	#[test]
+	fn test_rewind() {
+		let temp = RandomTempPath::new();
+		let db = new_db(temp.as_str());
+		let mut config = Config::default();
+		config.enabled = true;
+		let block_0 = H256::from(0xa1);
+		let block_1 = H256::from(0xa2);
+		let tx_0 = H256::from(0xff);
+		let tx_1 = H256::from(0xaf);
+
+		let mut extras = Extras::default();
+		extras.block_hashes.insert(0, block_0.clone());
+		extras.block_hashes.insert(1, block_1.clone());
+		extras.transaction_hashes.insert(0, vec![tx_0.clone()]);
+		extras.transaction_hashes.insert(1, vec![tx_1.clone()]);
+
+		let tracedb = TraceDB::new(config, db.clone(), Arc::new(extras));
+		let mut batch = DBTransaction::new(&db);
+		tracedb.import(&mut batch, create_simple_import_request(0, block_0.clone()));
+		tracedb.import(&mut batch, create_simple_import_request(1, block_1.clone()));
+		db.write(batch).unwrap();
+
+		// rewind to block 0
+		let mut batch = DBTransaction::new(&db);
+		tracedb.rewind(&mut batch, 0, 1, &[block_1.clone()]);
+		db.write(batch).unwrap();
+
+		let filter = Filter {
+			range: (0..1),
+			from_address: AddressesFilter::from(vec![Address::from(1)]),
+			to_address: AddressesFilter::from(vec![]),
+		};
+
+		assert_eq!(tracedb.filter(&filter), vec![create_simple_localized_trace(0, block_0.clone(), tx_0.clone())]);
+		assert_eq!(None, tracedb.block_traces(1));
+		assert_eq!(tracedb.block_traces(0).unwrap().len(), 1);
+	}
+
 	#[test]
 	fn query_trace_after_reopen() {
 		let temp = RandomTempPath::new();
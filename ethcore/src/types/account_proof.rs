@@ -0,0 +1,75 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Merkle proofs of account state
+
+use util::{U256, H256, Address, Bytes};
+use std::fmt;
+
+/// Reason why state of a block can't be queried.
+#[derive(Debug, PartialEq, Clone, Copy, Binary)]
+pub enum StateError {
+	/// The block is not known.
+	UnknownBlock,
+	/// State of the block has been pruned from the database.
+	StatePruned,
+	/// A trie node of state that should be available is missing from the database.
+	MissingTrieNode(H256),
+}
+
+impl fmt::Display for StateError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			StateError::UnknownBlock => f.write_str("Block is not known"),
+			StateError::StatePruned => f.write_str("State of the block has been pruned"),
+			StateError::MissingTrieNode(ref hash) => write!(f, "Database is missing trie node {}", hash),
+		}
+	}
+}
+
+/// Proof of a single storage entry.
+#[derive(Debug, PartialEq, Clone, Binary)]
+pub struct StorageProof {
+	/// Storage key.
+	pub key: H256,
+	/// Value stored under the key.
+	pub value: H256,
+	/// Storage trie nodes on the path from the storage root to the entry.
+	pub proof: Vec<Bytes>,
+}
+
+/// Proof of an account and some of its storage entries.
+#[derive(Debug, PartialEq, Clone, Binary)]
+pub struct AccountProof {
+	/// Address of the account.
+	pub address: Address,
+	/// Account balance.
+	pub balance: U256,
+	/// Account nonce.
+	pub nonce: U256,
+	/// Hash of the account code.
+	pub code_hash: H256,
+	/// Root of the account storage trie.
+	pub storage_root: H256,
+	/// RLP of the account as stored in the state trie. `None` if the account does not exist.
+	pub account_rlp: Option<Bytes>,
+	/// State trie nodes on the path from the state root to the account.
+	/// Proves absence of the account if it does not exist.
+	pub account_proof: Vec<Bytes>,
+	/// Proofs of requested storage entries.
+	pub storage_proof: Vec<StorageProof>,
+}
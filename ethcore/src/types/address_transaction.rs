@@ -0,0 +1,40 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Transaction found in the address index
+
+use util::{H256, Address};
+use header::BlockNumber;
+
+/// Canonical transaction sent from or to an address.
+#[derive(Debug, Clone, PartialEq, Binary)]
+pub struct AddressTransaction {
+	/// Address the entry was indexed for.
+	pub address: Address,
+	/// Number of the block including the transaction.
+	pub block_number: BlockNumber,
+	/// Hash of the block including the transaction.
+	pub block_hash: H256,
+	/// Position of the transaction in the block.
+	pub transaction_index: usize,
+	/// Hash of the transaction.
+	pub transaction_hash: H256,
+	/// Transaction was sent by the address.
+	pub sent: bool,
+	/// Transaction was sent to the address (or created a contract at the address).
+	pub received: bool,
+}
@@ -0,0 +1,81 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Chain reorganization record
+
+use util::H256;
+use rlp::*;
+use header::BlockNumber;
+
+/// Chain reorganization observed by the client.
+#[derive(Debug, Clone, PartialEq, Binary)]
+pub struct Reorg {
+	/// Sequential id of the reorganization.
+	pub id: u64,
+	/// Unix timestamp of the import which caused the reorganization.
+	pub timestamp: u64,
+	/// Number of blocks retracted from the canonical chain.
+	pub depth: u64,
+	/// Best block before the reorganization.
+	pub old_head: H256,
+	/// Number of the best block before the reorganization.
+	pub old_number: BlockNumber,
+	/// Best block after the reorganization.
+	pub new_head: H256,
+	/// Number of the best block after the reorganization.
+	pub new_number: BlockNumber,
+	/// Hashes of retracted blocks.
+	pub retracted_blocks: Vec<H256>,
+	/// Hashes of transactions included in retracted blocks.
+	pub retracted_transactions: Vec<H256>,
+	/// Hashes of retracted transactions included again in one of the enacted blocks.
+	pub reincluded_transactions: Vec<H256>,
+}
+
+impl Encodable for Reorg {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(10);
+		s.append(&self.id);
+		s.append(&self.timestamp);
+		s.append(&self.depth);
+		s.append(&self.old_head);
+		s.append(&self.old_number);
+		s.append(&self.new_head);
+		s.append(&self.new_number);
+		s.append(&self.retracted_blocks);
+		s.append(&self.retracted_transactions);
+		s.append(&self.reincluded_transactions);
+	}
+}
+
+impl Decodable for Reorg {
+	fn decode<D>(decoder: &D) -> Result<Self, DecoderError> where D: Decoder {
+		let d = decoder.as_rlp();
+		let reorg = Reorg {
+			id: try!(d.val_at(0)),
+			timestamp: try!(d.val_at(1)),
+			depth: try!(d.val_at(2)),
+			old_head: try!(d.val_at(3)),
+			old_number: try!(d.val_at(4)),
+			new_head: try!(d.val_at(5)),
+			new_number: try!(d.val_at(6)),
+			retracted_blocks: try!(d.val_at(7)),
+			retracted_transactions: try!(d.val_at(8)),
+			reincluded_transactions: try!(d.val_at(9)),
+		};
+		Ok(reorg)
+	}
+}
@@ -33,22 +33,33 @@ pub struct ManifestData {
 	pub block_number: u64,
 	/// Block hash this snapshot was taken at.
 	pub block_hash: H256,
+	/// Hash of the block the base snapshot was taken at, for incremental snapshots.
+	/// State chunks of subtrees which did not change since the base are shared with it.
+	pub base: Option<H256>,
+	/// Digest of each state subtree. Empty if state chunks are not aligned to subtrees.
+	pub subtree_digests: Vec<H256>,
+	/// Number of state chunks of each state subtree, in the order of `state_hashes`.
+	pub subtree_chunks: Vec<u64>,
 }
 
 impl ManifestData {
 	/// Encode the manifest data to rlp.
 	pub fn into_rlp(self) -> Bytes {
-		let mut stream = RlpStream::new_list(5);
+		let mut stream = RlpStream::new_list(8);
 		stream.append(&self.state_hashes);
 		stream.append(&self.block_hashes);
 		stream.append(&self.state_root);
 		stream.append(&self.block_number);
 		stream.append(&self.block_hash);
+		stream.append(&self.base.into_iter().collect::<Vec<_>>());
+		stream.append(&self.subtree_digests);
+		stream.append(&self.subtree_chunks);
 
 		stream.out()
 	}
 
 	/// Try to restore manifest data from raw bytes, interpreted as RLP.
+	/// Manifests written before incremental snapshots have only the first five fields.
 	pub fn from_rlp(raw: &[u8]) -> Result<Self, DecoderError> {
 		let decoder = UntrustedRlp::new(raw);
 
@@ -57,6 +68,10 @@ impl ManifestData {
 		let state_root: H256 = try!(decoder.val_at(2));
 		let block_number: u64 = try!(decoder.val_at(3));
 		let block_hash: H256 = try!(decoder.val_at(4));
+		let (base, subtree_digests, subtree_chunks) = match decoder.item_count() {
+			n if n < 8 => (Vec::new(), Vec::new(), Vec::new()),
+			_ => (try!(decoder.val_at::<Vec<H256>>(5)), try!(decoder.val_at(6)), try!(decoder.val_at(7))),
+		};
 
 		Ok(ManifestData {
 			state_hashes: state_hashes,
@@ -64,7 +79,9 @@ impl ManifestData {
 			state_root: state_root,
 			block_number: block_number,
 			block_hash: block_hash,
+			base: base.into_iter().next(),
+			subtree_digests: subtree_digests,
+			subtree_chunks: subtree_chunks,
 		})
 	}
 }
-
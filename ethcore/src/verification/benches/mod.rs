@@ -0,0 +1,83 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! benchmarking for block verification
+//! should be started with:
+//! ```bash
+//! multirust run nightly cargo bench
+//! ```
+
+extern crate test;
+
+use self::test::Bencher;
+
+use common::*;
+use ethkey::{Random, Generator};
+use spec::Spec;
+use engines::Engine;
+use verification::{verify_block_basic, verify_block_unordered};
+use tests::helpers::create_test_block_with_data;
+
+/// Returns a block with `count` signed transactions which passes the first two verification phases.
+fn signed_block(engine: &Engine, count: u64) -> Bytes {
+	let keypair = Random.generate().unwrap();
+	let transactions: Vec<_> = (0..count).map(|nonce| Transaction {
+		action: Action::Create,
+		value: U256::from(0),
+		data: Bytes::new(),
+		gas: U256::from(30_000),
+		gas_price: U256::from(40_000),
+		nonce: U256::from(nonce)
+	}.sign(keypair.secret())).collect();
+
+	let mut header = Header::new();
+	header.set_gas_limit(engine.params().min_gas_limit);
+	header.set_timestamp(40);
+	header.set_number(10);
+	header.set_transactions_root(ordered_trie_root(transactions.iter().map(|t| ::rlp::encode::<SignedTransaction>(t).to_vec())));
+	create_test_block_with_data(&header, &transactions, &[])
+}
+
+#[bench]
+fn verify_block_sequential(b: &mut Bencher) {
+	let spec = Spec::new_test();
+	let engine = &*spec.engine;
+	let bytes = signed_block(engine, 200);
+
+	b.iter(|| {
+		// decode on every iteration so that senders are not cached.
+		let header = BlockView::new(&bytes).header();
+		verify_block_basic(&header, &bytes, engine).unwrap();
+		engine.verify_block_unordered(&header, Some(&bytes)).unwrap();
+		for t in BlockView::new(&bytes).transactions() {
+			engine.verify_transaction(&t, &header).unwrap();
+		}
+	});
+}
+
+#[bench]
+fn verify_block_parallel(b: &mut Bencher) {
+	let spec = Spec::new_test();
+	let engine = &*spec.engine;
+	let bytes = signed_block(engine, 200);
+
+	b.iter(|| {
+		let header = BlockView::new(&bytes).header();
+		verify_block_basic(&header, &bytes, engine).unwrap();
+		verify_block_unordered(header, bytes.clone(), engine).unwrap();
+	});
+}
@@ -20,6 +20,9 @@ pub mod queue;
 mod canon_verifier;
 mod noop_verifier;
 
+#[cfg(all(feature="benches", test))]
+mod benches;
+
 pub use self::verification::*;
 pub use self::verifier::Verifier;
 pub use self::canon_verifier::CanonVerifier;
@@ -21,11 +21,15 @@
 /// 2. Signatures verification done in the queue.
 /// 3. Final verification against the blockchain done before enactment.
 
+use rayon::prelude::*;
 use common::*;
 use engines::Engine;
 use blockchain::*;
 use rlp::{UntrustedRlp, View};
 
+/// Minimal number of transactions in a block for their signatures to be checked on several threads.
+const PARALLEL_VERIFICATION_THRESHOLD: usize = 32;
+
 /// Preprocessed block data gathered in `verify_block_unordered` call
 pub struct PreverifiedBlock {
 	/// Populated block header
@@ -72,14 +76,8 @@ pub fn verify_block_unordered(header: Header, bytes: Bytes, engine: &Engine) ->
 		try!(engine.verify_block_unordered(&try!(u), None));
 	}
 	// Verify transactions.
-	let mut transactions = Vec::new();
-	{
-		let v = BlockView::new(&bytes);
-		for t in v.transactions() {
-			try!(engine.verify_transaction(&t, &header));
-			transactions.push(t);
-		}
-	}
+	let mut transactions = BlockView::new(&bytes).transactions();
+	try!(verify_transactions(&mut transactions, &header, engine));
 	Ok(PreverifiedBlock {
 		header: header,
 		transactions: transactions,
@@ -87,6 +85,25 @@ pub fn verify_block_unordered(header: Header, bytes: Bytes, engine: &Engine) ->
 	})
 }
 
+/// Verify transactions of the block, recovering their senders.
+/// Transactions of large blocks are verified on the shared thread pool.
+pub fn verify_transactions(transactions: &mut [SignedTransaction], header: &Header, engine: &Engine) -> Result<(), Error> {
+	if transactions.len() < PARALLEL_VERIFICATION_THRESHOLD {
+		for t in transactions.iter() {
+			try!(engine.verify_transaction(t, header));
+		}
+		return Ok(());
+	}
+
+	let chunk_size = transactions.len() / ::num_cpus::get() + 1;
+	// header caches its hash, so each chunk gets its own copy.
+	let mut chunks: Vec<_> = transactions.chunks_mut(chunk_size).map(|chunk| (chunk, header.clone())).collect();
+	chunks.par_iter_mut()
+		.map(|&mut (ref chunk, ref header)| chunk.iter().map(|t| engine.verify_transaction(t, header)).collect::<Result<Vec<_>, Error>>().map(|_| ()))
+		.reduce_with(|a, b| a.and(b))
+		.unwrap_or(Ok(()))
+}
+
 /// Phase 3 verification. Check block information against parent and uncles.
 pub fn verify_block_family(header: &Header, bytes: &[u8], engine: &Engine, bc: &BlockProvider) -> Result<(), Error> {
 	// TODO: verify timestamp
@@ -517,4 +534,28 @@ mod tests {
 
 		// TODO: some additional uncle checks
 	}
+
+	#[test]
+	fn should_verify_transactions_in_parallel() {
+		let spec = ::ethereum::new_frontier_test();
+		let engine = &*spec.engine;
+		let keypair = Random.generate().unwrap();
+		let transaction = |nonce: u64| Transaction {
+			action: Action::Create,
+			value: U256::from(0),
+			data: Bytes::new(),
+			gas: U256::from(30_000),
+			gas_price: U256::from(40_000),
+			nonce: U256::from(nonce)
+		};
+
+		let mut transactions: Vec<_> = (0..100).map(|nonce| transaction(nonce).sign(keypair.secret())).collect();
+		check_ok(verify_transactions(&mut transactions, &Header::new(), engine));
+		for t in &transactions {
+			assert_eq!(t.sender().unwrap(), keypair.address());
+		}
+
+		transactions[50] = transaction(50).invalid_sign();
+		assert!(verify_transactions(&mut transactions, &Header::new(), engine).is_err());
+	}
 }
@@ -16,6 +16,7 @@
 
 //! Spec account deserialization.
 
+use std::collections::BTreeMap;
 use uint::Uint;
 use bytes::Bytes;
 use spec::builtin::Builtin;
@@ -30,7 +31,9 @@ pub struct Account {
 	/// Nonce.
 	pub nonce: Option<Uint>,
 	/// Code.
-	pub code: Option<Bytes>
+	pub code: Option<Bytes>,
+	/// Storage.
+	pub storage: Option<BTreeMap<Uint, Uint>>,
 }
 
 impl Account {
@@ -42,6 +45,7 @@ impl Account {
 
 #[cfg(test)]
 mod tests {
+	use std::collections::BTreeMap;
 	use serde_json;
 	use spec::account::Account;
 	use util::U256;
@@ -62,4 +66,18 @@ mod tests {
 		assert_eq!(deserialized.code.unwrap(), Bytes::new(vec![0x12, 0x34]));
 		assert!(deserialized.builtin.is_some()); // Further tested in builtin.rs
 	}
+
+	#[test]
+	fn account_storage_deserialization() {
+		let s = r#"{
+			"balance": "0x1",
+			"nonce": "0x0",
+			"code": "0x1234",
+			"storage": { "0x1": "0x2a" }
+		}"#;
+		let deserialized: Account = serde_json::from_str(s).unwrap();
+		let mut storage = BTreeMap::new();
+		storage.insert(Uint(U256::from(1)), Uint(U256::from(42)));
+		assert_eq!(deserialized.storage.unwrap(), storage);
+	}
 }
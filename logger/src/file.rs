@@ -0,0 +1,175 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Log file with rotation by size and age
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Log file rotation settings.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rotation {
+	/// Rotate once the file grows to this many bytes
+	pub max_size: Option<u64>,
+	/// Rotate once the file has been written to for this long (counted from the last rotation)
+	pub max_age: Option<Duration>,
+	/// Number of rotated files to keep, named `<file>.1` (newest) to `<file>.<keep>`
+	pub keep: usize,
+}
+
+impl Default for Rotation {
+	fn default() -> Self {
+		Rotation {
+			max_size: None,
+			max_age: None,
+			keep: 5,
+		}
+	}
+}
+
+/// Log file writer rotating according to given settings.
+pub struct LogFile {
+	path: PathBuf,
+	file: File,
+	size: u64,
+	started: SystemTime,
+	rotation: Rotation,
+}
+
+/// Modification time of the file, if it exists.
+fn modified(path: &Path) -> Option<SystemTime> {
+	fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl LogFile {
+	/// Opens the log file for appending, creating it if it doesn't exist.
+	/// Size and age of an existing file are taken into account for rotation.
+	pub fn create(path: &str, rotation: Rotation) -> io::Result<LogFile> {
+		let path = PathBuf::from(path);
+		let file = try!(OpenOptions::new().create(true).append(true).open(&path));
+		let size = try!(file.metadata()).len();
+		let mut log = LogFile {
+			path: path,
+			file: file,
+			size: size,
+			started: SystemTime::now(),
+			rotation: rotation,
+		};
+		// The file was started when the previous one got rotated, i.e. when `<file>.1` was last written to.
+		// Without a rotated file use the modification time of the file itself.
+		log.started = modified(&log.rotated_path(1)).or_else(|| modified(&log.path)).unwrap_or_else(SystemTime::now);
+		Ok(log)
+	}
+
+	/// Appends a line, rotating the file first if it's due.
+	pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+		if self.should_rotate() {
+			try!(self.rotate());
+		}
+		try!(self.file.write_all(line.as_bytes()));
+		try!(self.file.write_all(b"\n"));
+		self.size += line.len() as u64 + 1;
+		Ok(())
+	}
+
+	fn should_rotate(&self) -> bool {
+		self.rotation.max_size.map_or(false, |max| self.size >= max)
+			|| self.rotation.max_age.map_or(false, |max| self.started.elapsed().map_or(false, |age| age >= max))
+	}
+
+	fn rotated_path(&self, index: usize) -> PathBuf {
+		PathBuf::from(format!("{}.{}", self.path.display(), index))
+	}
+
+	fn rotate(&mut self) -> io::Result<()> {
+		if self.rotation.keep > 0 {
+			for i in (1..self.rotation.keep).rev() {
+				let from = self.rotated_path(i);
+				if from.exists() {
+					try!(fs::rename(from, self.rotated_path(i + 1)));
+				}
+			}
+			try!(fs::rename(&self.path, self.rotated_path(1)));
+		}
+		self.file = try!(File::create(&self.path));
+		self.size = 0;
+		self.started = SystemTime::now();
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+	use std::fs::{self, File};
+	use std::io::Read;
+	use super::{LogFile, Rotation};
+
+	fn read(path: &str) -> String {
+		let mut s = String::new();
+		File::open(path).unwrap().read_to_string(&mut s).unwrap();
+		s
+	}
+
+	#[test]
+	fn rotates_by_size() {
+		// given
+		let path = env::temp_dir().join("parity-log-rotation-test.log");
+		let path = path.to_str().unwrap();
+		let _ = fs::remove_file(path);
+		let rotation = Rotation { max_size: Some(10), max_age: None, keep: 2 };
+		let mut file = LogFile::create(path, rotation).unwrap();
+
+		// when
+		for line in &["line 001", "line 002", "line 003", "line 004", "line 005", "line 006", "line 007"] {
+			file.write_line(line).unwrap();
+		}
+
+		// then
+		assert_eq!(read(path), "line 007\n");
+		assert_eq!(read(&format!("{}.1", path)), "line 005\nline 006\n");
+		assert_eq!(read(&format!("{}.2", path)), "line 003\nline 004\n");
+		assert!(fs::metadata(format!("{}.3", path)).is_err());
+		for p in &[path.to_owned(), format!("{}.1", path), format!("{}.2", path)] {
+			fs::remove_file(p).unwrap();
+		}
+	}
+
+	#[test]
+	fn appends_to_existing_file() {
+		// given
+		let path = env::temp_dir().join("parity-log-append-test.log");
+		let path = path.to_str().unwrap();
+		let _ = fs::remove_file(path);
+		let _ = fs::remove_file(format!("{}.1", path));
+		let rotation = Rotation { max_size: Some(15), max_age: None, keep: 1 };
+		LogFile::create(path, rotation.clone()).unwrap().write_line("line 001").unwrap();
+
+		// when
+		let mut file = LogFile::create(path, rotation).unwrap();
+		file.write_line("line 002").unwrap();
+		file.write_line("line 003").unwrap();
+
+		// then
+		assert_eq!(read(path), "line 003\n");
+		assert_eq!(read(&format!("{}.1", path)), "line 001\nline 002\n");
+		for p in &[path.to_owned(), format!("{}.1", path)] {
+			fs::remove_file(p).unwrap();
+		}
+	}
+}
@@ -21,26 +21,35 @@ extern crate ethcore_util as util;
 extern crate log as rlog;
 extern crate isatty;
 extern crate regex;
-extern crate env_logger;
 extern crate time;
 #[macro_use]
 extern crate lazy_static;
 
-use std::{env, thread};
+mod file;
+
+use std::{env, thread, io};
 use std::sync::Arc;
-use std::fs::File;
 use std::io::Write;
 use isatty::{stderr_isatty, stdout_isatty};
-use env_logger::LogBuilder;
+use rlog::{Log, LogRecord, LogMetadata, LogLevel, LogLevelFilter, max_log_level};
 use regex::Regex;
-use util::RotatingLogger;
-use util::log::Colour;
+use util::{RotatingLogger, Mutex};
+use util::log::{Colour, parse_log_directives};
+use file::LogFile;
+pub use file::Rotation;
+
+// Levels in effect unless overridden by RUST_LOG or the configured mode.
+const DEFAULT_LEVELS: &'static str = "ws=warn,rustls=warn,info";
 
 #[derive(Debug, PartialEq)]
 pub struct Config {
 	pub mode: Option<String>,
 	pub color: bool,
 	pub file: Option<String>,
+	/// Write each record as a single line JSON object
+	pub json: bool,
+	/// Log file rotation
+	pub rotation: Rotation,
 }
 
 impl Default for Config {
@@ -49,45 +58,30 @@ impl Default for Config {
 			mode: None,
 			color: !cfg!(windows),
 			file: None,
+			json: false,
+			rotation: Rotation::default(),
 		}
 	}
 }
 
-/// Sets up the logger
-pub fn setup_log(config: &Config) -> Result<Arc<RotatingLogger>, String> {
-	use rlog::*;
-
-	let mut levels = String::new();
-	let mut builder = LogBuilder::new();
-	// Disable ws info logging by default.
-	builder.filter(Some("ws"), LogLevelFilter::Warn);
-	// Disable rustls info logging by default.
-	builder.filter(Some("rustls"), LogLevelFilter::Warn);
-	builder.filter(None, LogLevelFilter::Info);
-
-	if env::var("RUST_LOG").is_ok() {
-		let lvl = &env::var("RUST_LOG").unwrap();
-		levels.push_str(lvl);
-		levels.push_str(",");
-		builder.parse(lvl);
-	}
+struct Logger {
+	logs: Arc<RotatingLogger>,
+	file: Option<Mutex<LogFile>>,
+	json: bool,
+	enable_color: bool,
+	isatty: bool,
+}
 
-	if let Some(ref s) = config.mode {
-		levels.push_str(s);
-		builder.parse(s);
+impl Log for Logger {
+	fn enabled(&self, metadata: &LogMetadata) -> bool {
+		self.logs.enabled(metadata.level(), metadata.target())
 	}
 
-	let isatty = stderr_isatty();
-	let enable_color = config.color && isatty;
-	let logs = Arc::new(RotatingLogger::new(levels));
-	let logger = logs.clone();
-
-	let maybe_file = match config.file.as_ref() {
-		Some(f) => Some(try!(File::create(f).map_err(|_| format!("Cannot write to log file given: {}", f)))),
-		None => None,
-	};
+	fn log(&self, record: &LogRecord) {
+		if !self.enabled(record.metadata()) {
+			return;
+		}
 
-	let format = move |record: &LogRecord| {
 		let timestamp = time::strftime("%Y-%m-%d %H:%M:%S %Z", &time::now()).unwrap();
 
 		let with_color = if max_log_level() <= LogLevelFilter::Info {
@@ -99,31 +93,104 @@ pub fn setup_log(config: &Config) -> Result<Arc<RotatingLogger>, String> {
 
 		let removed_color = kill_color(with_color.as_ref());
 
-		let ret = match enable_color {
-			true => with_color,
-			false => removed_color.clone(),
+		let ret = match (self.json, self.enable_color) {
+			(true, _) => format_json(record),
+			(false, true) => with_color,
+			(false, false) => removed_color.clone(),
 		};
 
-		if let Some(mut file) = maybe_file.as_ref() {
+		if let Some(ref file) = self.file {
 			// ignore errors - there's nothing we can do
-			let _ = file.write_all(removed_color.as_bytes());
-			let _ = file.write_all(b"\n");
+			let _ = file.lock().write_line(if self.json { &ret } else { &removed_color });
 		}
-		logger.append(removed_color);
-		if !isatty && record.level() <= LogLevel::Info && stdout_isatty() {
+		self.logs.append(removed_color);
+		if !self.isatty && record.level() <= LogLevel::Info && stdout_isatty() {
 			// duplicate INFO/WARN output to console
 			println!("{}", ret);
 		}
 
-		ret
-    };
+		let _ = writeln!(io::stderr(), "{}", ret);
+	}
+}
 
-	builder.format(format);
-	builder.init().unwrap();
+/// Sets up the logger
+pub fn setup_log(config: &Config) -> Result<Arc<RotatingLogger>, String> {
+	let mut levels = String::new();
+	let mut directives = parse_log_directives(DEFAULT_LEVELS).expect("default levels are valid; qed");
+
+	if let Ok(lvl) = env::var("RUST_LOG") {
+		levels.push_str(&lvl);
+		levels.push_str(",");
+		directives.extend(try!(parse_log_directives(&lvl)));
+	}
+
+	if let Some(ref s) = config.mode {
+		levels.push_str(s);
+		directives.extend(try!(parse_log_directives(s)));
+	}
+
+	let isatty = stderr_isatty();
+	let logs = Arc::new(RotatingLogger::with_directives(levels, directives));
+
+	let maybe_file = match config.file.as_ref() {
+		Some(f) => Some(Mutex::new(try!(LogFile::create(f, config.rotation.clone()).map_err(|_| format!("Cannot write to log file given: {}", f))))),
+		None => None,
+	};
+
+	let logger = Logger {
+		logs: logs.clone(),
+		file: maybe_file,
+		json: config.json,
+		enable_color: config.color && isatty,
+		isatty: isatty,
+	};
+
+	try!(rlog::set_logger(|max_level| {
+		logs.set_max_level_filter(max_level);
+		Box::new(logger)
+	}).map_err(|_| "Logger is already initialized".to_owned()));
 
 	Ok(logs)
 }
 
+/// Formats the record as a JSON object on a single line.
+fn format_json(record: &LogRecord) -> String {
+	let now = time::now_utc();
+	let timestamp = format!("{}.{:03}Z", time::strftime("%Y-%m-%dT%H:%M:%S", &now).unwrap(), now.tm_nsec / 1_000_000);
+	let thread = thread::current();
+	let location = record.location();
+	format!(
+		r#"{{"timestamp":"{}","level":"{}","target":{},"thread":{},"message":{},"fields":{{"module":{},"file":{},"line":{}}}}}"#,
+		timestamp,
+		record.level(),
+		json_string(record.target()),
+		thread.name().map_or_else(|| "null".to_owned(), json_string),
+		json_string(&kill_color(&format!("{}", record.args()))),
+		json_string(location.module_path()),
+		json_string(location.file()),
+		location.line(),
+	)
+}
+
+/// Quotes and escapes a string for JSON output.
+fn json_string(s: &str) -> String {
+	let mut out = String::with_capacity(s.len() + 2);
+	out.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+	out
+}
+
 fn kill_color(s: &str) -> String {
 	lazy_static! {
 		static ref RE: Regex = Regex::new("\x1b\\[[^m]+m").unwrap();
@@ -138,6 +205,13 @@ fn should_remove_colour() {
 	assert_eq!(after, "test");
 }
 
+#[test]
+fn should_escape_json_strings() {
+	assert_eq!(json_string("plain"), r#""plain""#);
+	assert_eq!(json_string("say \"hi\"\n\tC:\\"), r#""say \"hi\"\n\tC:\\""#);
+	assert_eq!(json_string("\x1b"), r#""\u001b""#);
+}
+
 #[test]
 fn should_remove_multiple_colour() {
 	let t = format!("{} {}", Colour::Red.bold().paint("test"), Colour::White.normal().paint("again"));
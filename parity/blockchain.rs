@@ -20,17 +20,21 @@ use std::io::{BufReader, BufRead};
 use std::time::{Instant, Duration};
 use std::thread::sleep;
 use std::sync::Arc;
+use std::collections::BTreeMap;
 use rustc_serialize::hex::FromHex;
+use serde_json::{self, Value};
 use ethcore_logger::{setup_log, Config as LogConfig};
 use io::{PanicHandler, ForwardPanic};
-use util::{ToPretty, Uint};
-use rlp::PayloadInfo;
+use util::{ToPretty, Uint, U256, H256, Bytes};
+use rlp::{self, PayloadInfo};
 use ethcore::service::ClientService;
-use ethcore::client::{Mode, DatabaseCompactionProfile, VMType, BlockImportError, BlockChainClient, BlockID};
+use ethcore::client::{Mode, DatabaseCompactionProfile, VMType, BlockImportError, BlockChainClient, BlockID, TransactionID, Client};
 use ethcore::error::ImportError;
 use ethcore::miner::Miner;
+use ethcore::views::BlockView;
+use ethcore_rpc::v1::types::{Block, BlockTransactions, Bytes as RpcBytes, Receipt, U256 as RpcU256, H256 as RpcH256};
 use cache::CacheConfig;
-use params::{SpecType, Pruning, Switch, tracing_switch_to_bool};
+use params::{SpecType, Pruning, Switch, tracing_switch_to_bool, fatdb_switch_to_bool};
 use informant::{Informant, MillisecondDuration};
 use io_handler::ImportIoHandler;
 use helpers::{to_client_config, execute_upgrades};
@@ -42,6 +46,7 @@ use fdlimit;
 pub enum DataFormat {
 	Hex,
 	Binary,
+	Json,
 }
 
 impl Default for DataFormat {
@@ -57,6 +62,7 @@ impl FromStr for DataFormat {
 		match s {
 			"binary" | "bin" => Ok(DataFormat::Binary),
 			"hex" => Ok(DataFormat::Hex),
+			"json" => Ok(DataFormat::Json),
 			x => Err(format!("Invalid format: {}", x))
 		}
 	}
@@ -66,6 +72,7 @@ impl FromStr for DataFormat {
 pub enum BlockchainCmd {
 	Import(ImportBlockchain),
 	Export(ExportBlockchain),
+	ExportState(ExportState),
 }
 
 #[derive(Debug, PartialEq)]
@@ -81,6 +88,7 @@ pub struct ImportBlockchain {
 	pub wal: bool,
 	pub mode: Mode,
 	pub tracing: Switch,
+	pub fat_db: bool,
 	pub vm_type: VMType,
 }
 
@@ -99,12 +107,29 @@ pub struct ExportBlockchain {
 	pub tracing: Switch,
 	pub from_block: BlockID,
 	pub to_block: BlockID,
+	pub receipts: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ExportState {
+	pub spec: SpecType,
+	pub logger_config: LogConfig,
+	pub cache_config: CacheConfig,
+	pub dirs: Directories,
+	pub file_path: Option<String>,
+	pub pruning: Pruning,
+	pub compaction: DatabaseCompactionProfile,
+	pub wal: bool,
+	pub mode: Mode,
+	pub tracing: Switch,
+	pub at: BlockID,
 }
 
 pub fn execute(cmd: BlockchainCmd) -> Result<String, String> {
 	match cmd {
 		BlockchainCmd::Import(import_cmd) => execute_import(import_cmd),
 		BlockchainCmd::Export(export_cmd) => execute_export(export_cmd),
+		BlockchainCmd::ExportState(export_cmd) => execute_export_state(export_cmd),
 	}
 }
 
@@ -143,6 +168,9 @@ fn execute_import(cmd: ImportBlockchain) -> Result<String, String> {
 	// select pruning algorithm
 	let algorithm = cmd.pruning.to_algorithm(&user_defaults);
 
+	// check if fatdb is on
+	let fat_db = try!(fatdb_switch_to_bool(cmd.fat_db, &user_defaults, algorithm));
+
 	// prepare client and snapshot paths.
 	let client_path = db_dirs.client_path(algorithm);
 	let snapshot_path = db_dirs.snapshot_path();
@@ -151,7 +179,7 @@ fn execute_import(cmd: ImportBlockchain) -> Result<String, String> {
 	try!(execute_upgrades(&db_dirs, algorithm, cmd.compaction.compaction_profile()));
 
 	// prepare client config
-	let client_config = to_client_config(&cmd.cache_config, cmd.mode, tracing, cmd.compaction, cmd.wal, cmd.vm_type, "".into(), algorithm);
+	let client_config = to_client_config(&cmd.cache_config, cmd.mode, tracing, cmd.compaction, cmd.wal, cmd.vm_type, "".into(), algorithm, fat_db);
 
 	// build client
 	let service = try!(ClientService::start(
@@ -234,12 +262,14 @@ fn execute_import(cmd: ImportBlockchain) -> Result<String, String> {
 				try!(do_import(bytes));
 			}
 		}
+		DataFormat::Json => return Err("Import of JSON exports is not supported.".into()),
 	}
 	client.flush_queue();
 
 	// save user defaults
 	user_defaults.pruning = algorithm;
 	user_defaults.tracing = tracing;
+	user_defaults.fat_db = fat_db;
 	try!(user_defaults.save(&user_defaults_path));
 
 	let report = client.report();
@@ -256,26 +286,28 @@ fn execute_import(cmd: ImportBlockchain) -> Result<String, String> {
 	).into())
 }
 
-fn execute_export(cmd: ExportBlockchain) -> Result<String, String> {
-	// Setup panic handler
-	let panic_handler = PanicHandler::new_in_arc();
-
-	// Setup logging
-	let _logger = setup_log(&cmd.logger_config);
-
+// shared portion of export commands: start the client service
+fn start_client(
+	dirs: Directories,
+	spec: SpecType,
+	pruning: Pruning,
+	tracing: Switch,
+	cache_config: CacheConfig,
+	mode: Mode,
+	compaction: DatabaseCompactionProfile,
+	wal: bool,
+) -> Result<ClientService, String> {
 	// create dirs used by parity
-	try!(cmd.dirs.create_dirs());
-
-	let format = cmd.format.unwrap_or_default();
+	try!(dirs.create_dirs());
 
 	// load spec file
-	let spec = try!(cmd.spec.spec());
+	let spec = try!(spec.spec());
 
 	// load genesis hash
 	let genesis_hash = spec.genesis_header().hash();
 
 	// database paths
-	let db_dirs = cmd.dirs.database(genesis_hash, spec.fork_name.clone());
+	let db_dirs = dirs.database(genesis_hash, spec.fork_name.clone());
 
 	// user defaults path
 	let user_defaults_path = db_dirs.user_defaults_path();
@@ -284,31 +316,46 @@ fn execute_export(cmd: ExportBlockchain) -> Result<String, String> {
 	let user_defaults = try!(UserDefaults::load(&user_defaults_path));
 
 	// check if tracing is on
-	let tracing = try!(tracing_switch_to_bool(cmd.tracing, &user_defaults));
+	let tracing = try!(tracing_switch_to_bool(tracing, &user_defaults));
 
 	fdlimit::raise_fd_limit();
 
 	// select pruning algorithm
-	let algorithm = cmd.pruning.to_algorithm(&user_defaults);
+	let algorithm = pruning.to_algorithm(&user_defaults);
+
+	// check if fatdb is on
+	let fat_db = try!(fatdb_switch_to_bool(false, &user_defaults, algorithm));
 
 	// prepare client and snapshot paths.
 	let client_path = db_dirs.client_path(algorithm);
 	let snapshot_path = db_dirs.snapshot_path();
 
 	// execute upgrades
-	try!(execute_upgrades(&db_dirs, algorithm, cmd.compaction.compaction_profile()));
+	try!(execute_upgrades(&db_dirs, algorithm, compaction.compaction_profile()));
 
 	// prepare client config
-	let client_config = to_client_config(&cmd.cache_config, cmd.mode, tracing, cmd.compaction, cmd.wal, VMType::default(), "".into(), algorithm);
+	let client_config = to_client_config(&cache_config, mode, tracing, compaction, wal, VMType::default(), "".into(), algorithm, fat_db);
 
-	let service = try!(ClientService::start(
+	ClientService::start(
 		client_config,
 		&spec,
 		&client_path,
 		&snapshot_path,
-		&cmd.dirs.ipc_path(),
+		&dirs.ipc_path(),
 		Arc::new(Miner::with_spec(&spec)),
-	).map_err(|e| format!("Client service error: {:?}", e)));
+	).map_err(|e| format!("Client service error: {:?}", e))
+}
+
+fn execute_export(cmd: ExportBlockchain) -> Result<String, String> {
+	// Setup panic handler
+	let panic_handler = PanicHandler::new_in_arc();
+
+	// Setup logging
+	let _logger = setup_log(&cmd.logger_config);
+
+	let format = cmd.format.unwrap_or_default();
+
+	let service = try!(start_client(cmd.dirs, cmd.spec, cmd.pruning, cmd.tracing, cmd.cache_config, cmd.mode, cmd.compaction, cmd.wal));
 
 	panic_handler.forward_from(&service);
 	let client = service.client();
@@ -321,25 +368,208 @@ fn execute_export(cmd: ExportBlockchain) -> Result<String, String> {
 	let from = try!(client.block_number(cmd.from_block).ok_or("From block could not be found"));
 	let to = try!(client.block_number(cmd.to_block).ok_or("To block could not be found"));
 
+	if format == DataFormat::Json {
+		out.write_all(b"[").expect("Couldn't write to stream.");
+	}
+
 	for i in from..(to + 1) {
 		let b = try!(client.block(BlockID::Number(i)).ok_or("Error exporting incomplete chain"));
 		match format {
 			DataFormat::Binary => { out.write(&b).expect("Couldn't write to stream."); }
 			DataFormat::Hex => { out.write_fmt(format_args!("{}", b.pretty())).expect("Couldn't write to stream."); }
+			DataFormat::Json => {
+				let block = try!(block_to_json(&*client, &b, cmd.receipts));
+				let separator = if i == from { "\n" } else { ",\n" };
+				out.write_fmt(format_args!("{}{}", separator, block)).expect("Couldn't write to stream.");
+			}
 		}
 	}
 
+	if format == DataFormat::Json {
+		out.write_all(b"\n]\n").expect("Couldn't write to stream.");
+	}
+
 	Ok("Export completed.".into())
 }
 
+fn execute_export_state(cmd: ExportState) -> Result<String, String> {
+	// Setup panic handler
+	let panic_handler = PanicHandler::new_in_arc();
+
+	// Setup logging
+	let _logger = setup_log(&cmd.logger_config);
+
+	let service = try!(start_client(cmd.dirs, cmd.spec, cmd.pruning, cmd.tracing, cmd.cache_config, cmd.mode, cmd.compaction, cmd.wal));
+
+	panic_handler.forward_from(&service);
+	let client = service.client();
+
+	let mut out: Box<io::Write> = match cmd.file_path {
+		Some(f) => Box::new(try!(fs::File::create(&f).map_err(|_| format!("Cannot write to file given: {}", f)))),
+		None => Box::new(io::stdout()),
+	};
+
+	let count = try!(write_state(&*client, cmd.at, &mut *out));
+	Ok(format!("Export completed. {} accounts exported.", count))
+}
+
+// write all accounts in the state of the given block as a chain specification `accounts` section.
+// accounts are written out as they are read from a single view of the state.
+fn write_state<W: io::Write + ?Sized>(client: &Client, at: BlockID, out: &mut W) -> Result<usize, String> {
+	out.write_all(b"{").expect("Couldn't write to stream.");
+	let mut first = true;
+	let count = try!(client.for_each_account(at, |address, account| {
+		let separator = if first { "\n" } else { ",\n" };
+		first = false;
+		let entry = serde_json::to_string(&account_to_json(account.balance, account.nonce, account.code.unwrap_or_default(), account.storage))
+			.expect("serialization of a JSON value cannot fail; qed");
+		out.write_fmt(format_args!("{}\"0x{:?}\": {}", separator, address, entry)).expect("Couldn't write to stream.");
+	}).ok_or("Cannot export state: the state of the given block is not available or the database was not built with --fat-db"));
+	out.write_all(b"\n}\n").expect("Couldn't write to stream.");
+
+	Ok(count)
+}
+
+// format a block with decoded transactions, and optionally their receipts, as JSON.
+fn block_to_json<C: BlockChainClient + ?Sized>(client: &C, bytes: &[u8], with_receipts: bool) -> Result<String, String> {
+	let block_view = BlockView::new(bytes);
+	let view = block_view.header_view();
+	let hash = view.sha3();
+	let total_difficulty = try!(client.block_total_difficulty(BlockID::Hash(hash.clone())).ok_or("Error exporting incomplete chain"));
+
+	let block = Block {
+		hash: Some(hash.clone().into()),
+		size: Some(bytes.len().into()),
+		parent_hash: view.parent_hash().into(),
+		uncles_hash: view.uncles_hash().into(),
+		author: view.author().into(),
+		miner: view.author().into(),
+		state_root: view.state_root().into(),
+		transactions_root: view.transactions_root().into(),
+		receipts_root: view.receipts_root().into(),
+		number: Some(view.number().into()),
+		gas_used: view.gas_used().into(),
+		gas_limit: view.gas_limit().into(),
+		logs_bloom: view.log_bloom().into(),
+		timestamp: view.timestamp().into(),
+		difficulty: view.difficulty().into(),
+		total_difficulty: total_difficulty.into(),
+		seal_fields: view.seal().into_iter().map(|f| rlp::decode(&f)).map(RpcBytes::new).collect(),
+		uncles: block_view.uncle_hashes().into_iter().map(Into::into).collect(),
+		transactions: BlockTransactions::Full(block_view.localized_transactions().into_iter().map(Into::into).collect()),
+		extra_data: RpcBytes::new(view.extra_data()),
+	};
+
+	let mut json = serde_json::to_value(&block);
+	if with_receipts {
+		let mut receipts = Vec::with_capacity(block_view.transactions_count());
+		for i in 0..block_view.transactions_count() {
+			let receipt = try!(client.transaction_receipt(TransactionID::Location(BlockID::Hash(hash.clone()), i))
+				.ok_or_else(|| format!("Missing receipt of transaction {} in block {}", i, view.number())));
+			receipts.push(Receipt::from(receipt));
+		}
+		if let Value::Object(ref mut map) = json {
+			map.insert("receipts".to_owned(), serde_json::to_value(&receipts));
+		}
+	}
+
+	Ok(serde_json::to_string(&json).expect("serialization of a JSON value cannot fail; qed"))
+}
+
+// format an account as an entry of the chain specification `accounts` section.
+fn account_to_json(balance: U256, nonce: U256, code: Bytes, storage: BTreeMap<H256, H256>) -> Value {
+	let mut map = BTreeMap::new();
+	map.insert("balance".to_owned(), serde_json::to_value(&RpcU256::from(balance)));
+	map.insert("nonce".to_owned(), serde_json::to_value(&RpcU256::from(nonce)));
+	if !code.is_empty() {
+		map.insert("code".to_owned(), serde_json::to_value(&RpcBytes::new(code)));
+	}
+	if !storage.is_empty() {
+		let storage = storage.into_iter()
+			.map(|(key, value)| (format!("0x{:?}", key), serde_json::to_value(&RpcH256::from(value))))
+			.collect();
+		map.insert("storage".to_owned(), Value::Object(storage));
+	}
+	Value::Object(map)
+}
+
 #[cfg(test)]
 mod test {
-	use super::DataFormat;
+	use std::collections::BTreeMap;
+	use std::sync::Arc;
+	use serde_json::{self, Value};
+	use devtools::RandomTempPath;
+	use io::IoChannel;
+	use util::{U256, H256};
+	use util::journaldb::Algorithm;
+	use util::kvdb::DatabaseConfig;
+	use util::trie::TrieSpec;
+	use ethcore::client::{BlockChainClient, BlockID, Client, ClientConfig};
+	use ethcore::db::NUM_COLUMNS;
+	use ethcore::miner::Miner;
+	use ethcore::spec::Spec;
+	use ethcore::views::HeaderView;
+	use super::{DataFormat, account_to_json, write_state};
 
 	#[test]
 	fn test_data_format_parsing() {
 		assert_eq!(DataFormat::Binary, "binary".parse().unwrap());
 		assert_eq!(DataFormat::Binary, "bin".parse().unwrap());
 		assert_eq!(DataFormat::Hex, "hex".parse().unwrap());
+		assert_eq!(DataFormat::Json, "json".parse().unwrap());
+	}
+
+	#[test]
+	fn test_account_to_json() {
+		// given
+		let mut storage = BTreeMap::new();
+		storage.insert(H256::from(1), H256::from(42));
+
+		// when
+		let plain = account_to_json(U256::from(1000), U256::zero(), vec![], BTreeMap::new());
+		let contract = account_to_json(U256::zero(), U256::from(1), vec![0x60, 0x00], storage);
+
+		// then
+		assert_eq!(serde_json::to_string(&plain).unwrap(), r#"{"balance":"0x3e8","nonce":"0x0"}"#);
+		assert_eq!(serde_json::to_string(&contract).unwrap(), r#"{"balance":"0x0","code":"0x6000","nonce":"0x1","storage":{"0x0000000000000000000000000000000000000000000000000000000000000001":"0x000000000000000000000000000000000000000000000000000000000000002a"}}"#);
+	}
+
+	#[test]
+	fn exported_state_reproduces_state_root() {
+		// given
+		let mut spec_json: Value = serde_json::from_str(include_str!("../ethcore/res/null_morden.json")).unwrap();
+		if let Value::Object(ref mut spec) = spec_json {
+			if let Some(&mut Value::Object(ref mut accounts)) = spec.get_mut("accounts") {
+				let contract = r#"{ "balance": "0x10", "nonce": "0x1", "code": "0x60016000", "storage": { "0x1": "0x2a", "0x2": "0x3" } }"#;
+				accounts.insert("00000000000000000000000000000000000000aa".to_owned(), serde_json::from_str(contract).unwrap());
+			}
+		}
+
+		let spec = Spec::load(serde_json::to_string(&spec_json).unwrap().as_bytes()).unwrap();
+		let dir = RandomTempPath::new();
+		let mut config = ClientConfig::default();
+		config.trie_spec = TrieSpec::Fat;
+		config.pruning = Algorithm::Archive;
+		let client = Client::new(
+			config,
+			&spec,
+			dir.as_path(),
+			Arc::new(Miner::with_spec(&spec)),
+			IoChannel::disconnected(),
+			&DatabaseConfig::with_columns(NUM_COLUMNS)
+		).unwrap();
+
+		// when
+		let mut dump = Vec::new();
+		let count = write_state(&*client, BlockID::Latest, &mut dump).unwrap();
+		if let Value::Object(ref mut spec) = spec_json {
+			spec.insert("accounts".to_owned(), serde_json::from_slice(&dump).unwrap());
+		}
+		let forked = Spec::load(serde_json::to_string(&spec_json).unwrap().as_bytes()).unwrap();
+
+		// then
+		let header = client.block_header(BlockID::Latest).unwrap();
+		assert_eq!(count, 6);
+		assert_eq!(forked.state_root(), HeaderView::new(&header).state_root());
 	}
 }
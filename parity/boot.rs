@@ -20,13 +20,14 @@ use nanoipc;
 use ipc;
 use std;
 use std::sync::Arc;
+use std::time::Duration;
 use hypervisor::HypervisorServiceClient;
 use hypervisor::service::IpcModuleId;
 use ctrlc::CtrlC;
 use std::sync::atomic::{AtomicBool, Ordering};
 use nanoipc::{IpcInterface, GuardedSocket, NanoSocket};
 use ipc::WithSocket;
-use ethcore_logger::{Config as LogConfig, setup_log};
+use ethcore_logger::{Config as LogConfig, Rotation as LogRotation, setup_log};
 use docopt::Docopt;
 
 #[derive(Debug)]
@@ -94,6 +95,10 @@ Usage:
                            format as RUST_LOG.
   --log-file FILENAME      Specify a filename into which logging should be
                            directed.
+  --log-max-size BYTES     Rotate the log file once it grows to BYTES.
+  --log-max-age SECONDS    Rotate the log file once it is SECONDS old.
+  --log-keep NUM           Number of rotated log files to keep [default: 5].
+  --log-format FORMAT      Format of log output, text or json.
   --no-color               Don't use terminal color codes in output.
 ", svc_name, svc_name);
 
@@ -101,6 +106,10 @@ Usage:
 	struct Args {
 		flag_logging: Option<String>,
 		flag_log_file: Option<String>,
+		flag_log_max_size: Option<u64>,
+		flag_log_max_age: Option<u64>,
+		flag_log_keep: usize,
+		flag_log_format: Option<String>,
 		flag_no_color: bool,
 	}
 
@@ -110,6 +119,12 @@ Usage:
 				color: self.flag_no_color || cfg!(windows),
 				mode: self.flag_logging.clone(),
 				file: self.flag_log_file.clone(),
+				json: self.flag_log_format.as_ref().map_or(false, |f| f == "json"),
+				rotation: LogRotation {
+					max_size: self.flag_log_max_size,
+					max_age: self.flag_log_max_age.map(Duration::from_secs),
+					keep: self.flag_log_keep,
+				},
 			}
 		}
 	}
@@ -21,6 +21,7 @@ const MIN_DB_CACHE_MB: u32 = 2;
 const MIN_BLOCK_QUEUE_SIZE_LIMIT_MB: u32 = 16;
 const DEFAULT_BLOCK_QUEUE_SIZE_LIMIT_MB: u32 = 50;
 const DEFAULT_TRACE_CACHE_SIZE: u32 = 20;
+const DEFAULT_STATE_CACHE_SIZE: u32 = 25;
 
 /// Configuration for application cache sizes.
 /// All	values are represented in MB.
@@ -37,11 +38,13 @@ pub struct CacheConfig {
 	queue: u32,
 	/// Size of traces cache.
 	traces: u32,
+	/// Size of the shared state cache.
+	state: u32,
 }
 
 impl Default for CacheConfig {
 	fn default() -> Self {
-		CacheConfig::new(64, 8, DEFAULT_BLOCK_QUEUE_SIZE_LIMIT_MB)
+		CacheConfig::new(64, 8, DEFAULT_BLOCK_QUEUE_SIZE_LIMIT_MB, DEFAULT_STATE_CACHE_SIZE)
 	}
 }
 
@@ -49,20 +52,22 @@ impl CacheConfig {
 	/// Creates new cache config with cumulative size equal `total`.
 	pub fn new_with_total_cache_size(total: u32) -> Self {
 		CacheConfig {
-			db: total * 7 / 8,
-			blockchain: total / 8,
+			db: total * 7 / 10,
+			blockchain: total / 10,
 			queue: DEFAULT_BLOCK_QUEUE_SIZE_LIMIT_MB,
 			traces: DEFAULT_TRACE_CACHE_SIZE,
+			state: total * 2 / 10,
 		}
 	}
 
 	/// Creates new cache config with gitven details.
-	pub fn new(db: u32, blockchain: u32, queue: u32) -> Self {
+	pub fn new(db: u32, blockchain: u32, queue: u32, state: u32) -> Self {
 		CacheConfig {
 			db: db,
 			blockchain: blockchain,
 			queue: queue,
 			traces: DEFAULT_TRACE_CACHE_SIZE,
+			state: state,
 		}
 	}
 
@@ -90,6 +95,11 @@ impl CacheConfig {
 	pub fn traces(&self) -> u32 {
 		self.traces
 	}
+
+	/// Size of the state cache.
+	pub fn state(&self) -> u32 {
+		self.state
+	}
 }
 
 #[cfg(test)]
@@ -99,21 +109,22 @@ mod tests {
 	#[test]
 	fn test_cache_config_constructor() {
 		let config = CacheConfig::new_with_total_cache_size(200);
-		assert_eq!(config.db, 175);
-		assert_eq!(config.blockchain(), 25);
+		assert_eq!(config.db, 140);
+		assert_eq!(config.blockchain(), 20);
 		assert_eq!(config.queue(), 50);
+		assert_eq!(config.state(), 40);
 	}
 
 	#[test]
 	fn test_cache_config_db_cache_sizes() {
 		let config = CacheConfig::new_with_total_cache_size(400);
-		assert_eq!(config.db, 350);
-		assert_eq!(config.db_blockchain_cache_size(), 12);
-		assert_eq!(config.db_state_cache_size(), 262);
+		assert_eq!(config.db, 280);
+		assert_eq!(config.db_blockchain_cache_size(), 10);
+		assert_eq!(config.db_state_cache_size(), 210);
 	}
 
 	#[test]
 	fn test_cache_config_default() {
-		assert_eq!(CacheConfig::default(), CacheConfig::new(64, 8, super::DEFAULT_BLOCK_QUEUE_SIZE_LIMIT_MB));
+		assert_eq!(CacheConfig::default(), CacheConfig::new(64, 8, super::DEFAULT_BLOCK_QUEUE_SIZE_LIMIT_MB, super::DEFAULT_STATE_CACHE_SIZE));
 	}
 }
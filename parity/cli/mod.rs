@@ -26,19 +26,32 @@ usage! {
 		cmd_new: bool,
 		cmd_list: bool,
 		cmd_export: bool,
+		cmd_blocks: bool,
+		cmd_state: bool,
 		cmd_import: bool,
 		cmd_signer: bool,
 		cmd_new_token: bool,
+		cmd_list_tokens: bool,
+		cmd_revoke_token: bool,
 		cmd_snapshot: bool,
+		cmd_verify: bool,
 		cmd_restore: bool,
 		cmd_ui: bool,
 		cmd_tools: bool,
 		cmd_hash: bool,
+		cmd_ethash: bool,
+		cmd_dag: bool,
+		cmd_db: bool,
+		cmd_check: bool,
+		cmd_repair: bool,
+		cmd_reset: bool,
 
 		// Arguments
 		arg_pid_file: String,
 		arg_file: Option<String>,
 		arg_path: Vec<String>,
+		arg_token_name: Option<String>,
+		arg_block: String,
 
 		// Flags
 		// -- Legacy Options
@@ -102,6 +115,8 @@ usage! {
 			or |c: &Config| otry!(c.signer).path.clone(),
 		// NOTE [todr] For security reasons don't put this to config files
 		flag_signer_no_validation: bool = false, or |_| None,
+		flag_token_scope: String = "confirm", or |_| None,
+		flag_token_expiry: Option<String> = None, or |_| None,
 
 		// -- Networking Options
 		flag_no_network: bool = false,
@@ -126,6 +141,18 @@ usage! {
 			or |c: &Config| otry!(c.network).reserved_peers.clone().map(Some),
 		flag_reserved_only: bool = false,
 			or |c: &Config| otry!(c.network).reserved_only.clone(),
+		flag_allow_ips: String = "all",
+			or |c: &Config| otry!(c.network).allow_ips.clone(),
+		flag_allow_ip_ranges: Option<String> = None,
+			or |c: &Config| otry!(c.network).allow_ip_ranges.clone().map(|vec| Some(vec.join(","))),
+		flag_deny_ip_ranges: Option<String> = None,
+			or |c: &Config| otry!(c.network).deny_ip_ranges.clone().map(|vec| Some(vec.join(","))),
+		flag_max_upload: u64 = 0u64,
+			or |c: &Config| otry!(c.network).max_upload.clone(),
+		flag_max_download: u64 = 0u64,
+			or |c: &Config| otry!(c.network).max_download.clone(),
+		flag_max_serve: u64 = 0u64,
+			or |c: &Config| otry!(c.network).max_serve.clone(),
 
 		// -- API and Console Options
 		// RPC
@@ -199,18 +226,42 @@ usage! {
 			or |c: &Config| otry!(c.mining).remove_solved.clone(),
 		flag_notify_work: Option<String> = None,
 			or |c: &Config| otry!(c.mining).notify_work.clone().map(|vec| Some(vec.join(","))),
+		flag_mine: bool = false,
+			or |c: &Config| otry!(c.mining).mine.clone(),
+		flag_mining_threads: Option<usize> = None,
+			or |c: &Config| otry!(c.mining).mining_threads.clone().map(Some),
+		flag_mining_dag: bool = false,
+			or |c: &Config| otry!(c.mining).mining_dag.clone(),
+
+		// -- Stratum Options
+		flag_stratum: bool = false,
+			or |c: &Config| otry!(c.stratum).enable.clone(),
+		flag_stratum_interface: String = "local",
+			or |c: &Config| otry!(c.stratum).interface.clone(),
+		flag_stratum_port: u16 = 8008u16,
+			or |c: &Config| otry!(c.stratum).port.clone(),
+		flag_stratum_secret: Option<String> = None,
+			or |c: &Config| otry!(c.stratum).secret.clone().map(Some),
+		flag_stratum_difficulty: String = "100000000",
+			or |c: &Config| otry!(c.stratum).difficulty.clone(),
+		flag_stratum_share_time: u64 = 10u64,
+			or |c: &Config| otry!(c.stratum).share_time.clone(),
 
 		// -- Footprint Options
 		flag_tracing: String = "auto",
 			or |c: &Config| otry!(c.footprint).tracing.clone(),
 		flag_pruning: String = "auto",
 			or |c: &Config| otry!(c.footprint).pruning.clone(),
+		flag_pruning_history: u64 = 1200u64,
+			or |c: &Config| otry!(c.footprint).pruning_history.clone(),
 		flag_cache_size_db: u32 = 64u32,
 			or |c: &Config| otry!(c.footprint).cache_size_db.clone(),
 		flag_cache_size_blocks: u32 = 8u32,
 			or |c: &Config| otry!(c.footprint).cache_size_blocks.clone(),
 		flag_cache_size_queue: u32 = 50u32,
 			or |c: &Config| otry!(c.footprint).cache_size_queue.clone(),
+		flag_cache_size_state: u32 = 25u32,
+			or |c: &Config| otry!(c.footprint).cache_size_state.clone(),
 		flag_cache_size: Option<u32> = None,
 			or |c: &Config| otry!(c.footprint).cache_size.clone().map(Some),
 		flag_fast_and_loose: bool = false,
@@ -219,16 +270,27 @@ usage! {
 			or |c: &Config| otry!(c.footprint).db_compaction.clone(),
 		flag_fat_db: bool = false,
 			or |c: &Config| otry!(c.footprint).fat_db.clone(),
+		flag_reorg_history: u64 = 10000u64,
+			or |c: &Config| otry!(c.footprint).reorg_history.clone(),
+		flag_block_history: Option<u64> = None,
+			or |c: &Config| otry!(c.footprint).block_history.clone().map(Some),
+		flag_address_index: bool = false,
+			or |c: &Config| otry!(c.footprint).address_index.clone(),
 
 		// -- Import/Export Options
 		flag_from: String = "1", or |_| None,
 		flag_to: String = "latest", or |_| None,
 		flag_format: Option<String> = None, or |_| None,
+		flag_receipts: bool = false, or |_| None,
 
 		// -- Snapshot Optons
 		flag_at: String = "latest", or |_| None,
 		flag_no_periodic_snapshot: bool = false,
 			or |c: &Config| otry!(c.snapshots).disable_periodic.clone(),
+		flag_snapshot_history: usize = 0usize,
+			or |c: &Config| otry!(c.snapshots).history.clone(),
+		flag_incremental_snapshots: usize = 0usize,
+			or |c: &Config| otry!(c.snapshots).incremental.clone(),
 
 		// -- Virtual Machine Options
 		flag_jitvm: bool = false,
@@ -240,6 +302,14 @@ usage! {
 			or |c: &Config| otry!(c.misc).logging.clone().map(Some),
 		flag_log_file: Option<String> = None,
 			or |c: &Config| otry!(c.misc).log_file.clone().map(Some),
+		flag_log_format: String = "text",
+			or |c: &Config| otry!(c.misc).log_format.clone(),
+		flag_log_max_size: Option<u64> = None,
+			or |c: &Config| otry!(c.misc).log_max_size.clone().map(Some),
+		flag_log_max_age: Option<String> = None,
+			or |c: &Config| otry!(c.misc).log_max_age.clone().map(Some),
+		flag_log_keep: usize = 5usize,
+			or |c: &Config| otry!(c.misc).log_keep.clone(),
 		flag_no_color: bool = false,
 			or |c: &Config| otry!(c.misc).color.map(|c| !c).clone(),
 	}
@@ -256,6 +326,7 @@ struct Config {
 	ipc: Option<Ipc>,
 	dapps: Option<Dapps>,
 	mining: Option<Mining>,
+	stratum: Option<Stratum>,
 	footprint: Option<Footprint>,
 	snapshots: Option<Snapshots>,
 	vm: Option<VM>,
@@ -302,6 +373,12 @@ struct Network {
 	node_key: Option<String>,
 	reserved_peers: Option<String>,
 	reserved_only: Option<bool>,
+	allow_ips: Option<String>,
+	allow_ip_ranges: Option<Vec<String>>,
+	deny_ip_ranges: Option<Vec<String>>,
+	max_upload: Option<u64>,
+	max_download: Option<u64>,
+	max_serve: Option<u64>,
 }
 
 #[derive(Default, Debug, PartialEq, RustcDecodable)]
@@ -350,24 +427,44 @@ struct Mining {
 	tx_queue_size: Option<usize>,
 	remove_solved: Option<bool>,
 	notify_work: Option<Vec<String>>,
+	mine: Option<bool>,
+	mining_threads: Option<usize>,
+	mining_dag: Option<bool>,
+}
+
+#[derive(Default, Debug, PartialEq, RustcDecodable)]
+struct Stratum {
+	enable: Option<bool>,
+	interface: Option<String>,
+	port: Option<u16>,
+	secret: Option<String>,
+	difficulty: Option<String>,
+	share_time: Option<u64>,
 }
 
 #[derive(Default, Debug, PartialEq, RustcDecodable)]
 struct Footprint {
 	tracing: Option<String>,
 	pruning: Option<String>,
+	pruning_history: Option<u64>,
 	fast_and_loose: Option<bool>,
 	cache_size: Option<u32>,
 	cache_size_db: Option<u32>,
 	cache_size_blocks: Option<u32>,
 	cache_size_queue: Option<u32>,
+	cache_size_state: Option<u32>,
 	db_compaction: Option<String>,
 	fat_db: Option<bool>,
+	reorg_history: Option<u64>,
+	block_history: Option<u64>,
+	address_index: Option<bool>,
 }
 
 #[derive(Default, Debug, PartialEq, RustcDecodable)]
 struct Snapshots {
 	disable_periodic: Option<bool>,
+	history: Option<usize>,
+	incremental: Option<usize>,
 }
 
 #[derive(Default, Debug, PartialEq, RustcDecodable)]
@@ -379,6 +476,10 @@ struct VM {
 struct Misc {
 	logging: Option<String>,
 	log_file: Option<String>,
+	log_format: Option<String>,
+	log_max_size: Option<u64>,
+	log_max_age: Option<String>,
+	log_keep: Option<usize>,
 	color: Option<bool>,
 }
 
@@ -437,19 +538,32 @@ mod tests {
 			cmd_new: false,
 			cmd_list: false,
 			cmd_export: false,
+			cmd_blocks: false,
+			cmd_state: false,
 			cmd_import: false,
 			cmd_signer: false,
 			cmd_new_token: false,
+			cmd_list_tokens: false,
+			cmd_revoke_token: false,
 			cmd_snapshot: false,
+			cmd_verify: false,
 			cmd_restore: false,
 			cmd_ui: false,
 			cmd_tools: false,
 			cmd_hash: false,
+			cmd_ethash: false,
+			cmd_dag: false,
+			cmd_db: false,
+			cmd_check: false,
+			cmd_repair: false,
+			cmd_reset: false,
 
 			// Arguments
 			arg_pid_file: "".into(),
 			arg_file: None,
 			arg_path: vec![],
+			arg_token_name: None,
+			arg_block: "".into(),
 
 			// -- Operating Options
 			flag_mode: "active".into(),
@@ -471,6 +585,8 @@ mod tests {
 			flag_signer_interface: "127.0.0.1".into(),
 			flag_signer_path: "$HOME/.parity/signer".into(),
 			flag_signer_no_validation: false,
+			flag_token_scope: "confirm".into(),
+			flag_token_expiry: None,
 
 			// -- Networking Options
 			flag_no_network: false,
@@ -484,6 +600,12 @@ mod tests {
 			flag_node_key: None,
 			flag_reserved_peers: Some("./path_to_file".into()),
 			flag_reserved_only: false,
+			flag_allow_ips: "all".into(),
+			flag_allow_ip_ranges: Some("".into()),
+			flag_deny_ip_ranges: Some("".into()),
+			flag_max_upload: 0u64,
+			flag_max_download: 0u64,
+			flag_max_serve: 0u64,
 
 			// -- API and Console Options
 			// RPC
@@ -525,26 +647,45 @@ mod tests {
 			flag_tx_queue_size: 1024usize,
 			flag_remove_solved: false,
 			flag_notify_work: Some("http://localhost:3001".into()),
+			flag_mine: false,
+			flag_mining_threads: Some(2),
+			flag_mining_dag: false,
+
+			// -- Stratum Options
+			flag_stratum: false,
+			flag_stratum_interface: "local".into(),
+			flag_stratum_port: 8008u16,
+			flag_stratum_secret: None,
+			flag_stratum_difficulty: "100000000".into(),
+			flag_stratum_share_time: 10u64,
 
 			// -- Footprint Options
 			flag_tracing: "auto".into(),
 			flag_pruning: "auto".into(),
+			flag_pruning_history: 1200u64,
 			flag_cache_size_db: 64u32,
 			flag_cache_size_blocks: 8u32,
 			flag_cache_size_queue: 50u32,
+			flag_cache_size_state: 25u32,
 			flag_cache_size: Some(128),
 			flag_fast_and_loose: false,
 			flag_db_compaction: "ssd".into(),
 			flag_fat_db: false,
+			flag_reorg_history: 10000u64,
+			flag_block_history: Some(100000u64),
+			flag_address_index: false,
 
 			// -- Import/Export Options
 			flag_from: "1".into(),
 			flag_to: "latest".into(),
 			flag_format: None,
+			flag_receipts: false,
 
 			// -- Snapshot Optons
 			flag_at: "latest".into(),
 			flag_no_periodic_snapshot: false,
+			flag_snapshot_history: 0usize,
+			flag_incremental_snapshots: 0usize,
 
 			// -- Virtual Machine Options
 			flag_jitvm: false,
@@ -581,6 +722,10 @@ mod tests {
 			flag_config: "$HOME/.parity/config.toml".into(),
 			flag_logging: Some("own_tx=trace".into()),
 			flag_log_file: Some("/var/log/parity.log".into()),
+			flag_log_format: "text".into(),
+			flag_log_max_size: Some(100),
+			flag_log_max_age: Some("daily".into()),
+			flag_log_keep: 5usize,
 			flag_no_color: false,
 			flag_no_config: false,
 		});
@@ -637,6 +782,12 @@ mod tests {
 				node_key: None,
 				reserved_peers: Some("./path/to/reserved_peers".into()),
 				reserved_only: Some(true),
+				allow_ips: None,
+				allow_ip_ranges: None,
+				deny_ip_ranges: None,
+				max_upload: None,
+				max_download: None,
+				max_serve: Some(512),
 			}),
 			rpc: Some(Rpc {
 				disable: Some(true),
@@ -677,20 +828,31 @@ mod tests {
 				extra_data: None,
 				remove_solved: None,
 				notify_work: None,
+				mine: None,
+				mining_threads: None,
+				mining_dag: None,
 			}),
+			stratum: None,
 			footprint: Some(Footprint {
 				tracing: Some("on".into()),
 				pruning: Some("fast".into()),
+				pruning_history: None,
 				fast_and_loose: None,
 				cache_size: None,
 				cache_size_db: Some(128),
 				cache_size_blocks: Some(16),
 				cache_size_queue: Some(100),
+				cache_size_state: None,
 				db_compaction: Some("ssd".into()),
 				fat_db: Some(true),
+				reorg_history: None,
+				block_history: None,
+				address_index: None,
 			}),
 			snapshots: Some(Snapshots {
 				disable_periodic: Some(true),
+				history: None,
+				incremental: None,
 			}),
 			vm: Some(VM {
 				jit: Some(false),
@@ -698,6 +860,10 @@ mod tests {
 			misc: Some(Misc {
 				logging: Some("own_tx=trace".into()),
 				log_file: Some("/var/log/parity.log".into()),
+				log_format: Some("json".into()),
+				log_max_size: None,
+				log_max_age: None,
+				log_keep: None,
 				color: Some(true),
 			})
 		});
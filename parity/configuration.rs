@@ -24,23 +24,24 @@ use util::{Hashable, U256, Uint, Bytes, version_data, Secret, Address};
 use util::log::Colour;
 use ethsync::{NetworkConfiguration, is_valid_node_url};
 use ethcore::client::{VMType, Mode};
-use ethcore::miner::MinerOptions;
+use ethcore::miner::{MinerOptions, StratumOptions, VardiffConfig, CpuMinerOptions};
 
 use rpc::{IpcConfiguration, HttpConfiguration};
 use ethcore_rpc::NetworkSettings;
 use cache::CacheConfig;
 use helpers::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_price, replace_home,
-geth_ipc_path, parity_ipc_path, to_bootnodes, to_addresses, to_address};
+geth_ipc_path, parity_ipc_path, to_bootnodes, to_addresses, to_address, to_ip_filter};
 use params::{ResealPolicy, AccountsConfig, GasPricerConfig, MinerExtras, SpecType};
-use ethcore_logger::Config as LogConfig;
+use ethcore_logger::{Config as LogConfig, Rotation as LogRotation};
 use dir::Directories;
 use dapps::Configuration as DappsConfiguration;
-use signer::Configuration as SignerConfiguration;
+use signer::{Configuration as SignerConfiguration, SignerCommand, NewToken, to_token_scope};
 use run::RunCmd;
-use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, DataFormat};
+use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, ExportState, DataFormat};
 use presale::ImportWallet;
 use account::{AccountCmd, NewAccount, ImportAccounts};
 use snapshot::{self, SnapshotCommand};
+use db::{self, DbCommand};
 
 #[derive(Debug, PartialEq)]
 pub enum Cmd {
@@ -49,9 +50,11 @@ pub enum Cmd {
 	Account(AccountCmd),
 	ImportPresaleWallet(ImportWallet),
 	Blockchain(BlockchainCmd),
-	SignerToken(String),
+	Signer(SignerCommand),
 	Snapshot(SnapshotCommand),
+	Db(DbCommand),
 	Hash(Option<String>),
+	EthashDag(u64),
 }
 
 #[derive(Debug, PartialEq)]
@@ -76,7 +79,7 @@ impl Configuration {
 		let vm_type = try!(self.vm_type());
 		let mode = try!(to_mode(&self.args.flag_mode, self.args.flag_mode_timeout, self.args.flag_mode_alarm));
 		let miner_options = try!(self.miner_options());
-		let logger_config = self.logger_config();
+		let logger_config = try!(self.logger_config());
 		let http_conf = try!(self.http_config());
 		let ipc_conf = try!(self.ipc_config());
 		let net_conf = try!(self.net_config());
@@ -91,14 +94,36 @@ impl Configuration {
 		let signer_port = self.signer_port();
 		let dapps_conf = self.dapps_config();
 		let signer_conf = self.signer_config();
+		let stratum = try!(self.stratum_options());
+		let cpu_miner = self.cpu_miner_options();
 		let format = try!(self.format());
 
 		let cmd = if self.args.flag_version {
 			Cmd::Version
-		} else if self.args.cmd_signer && self.args.cmd_new_token {
-			Cmd::SignerToken(dirs.signer)
+		} else if self.args.cmd_signer {
+			let signer_cmd = if self.args.cmd_new_token {
+				SignerCommand::NewToken(NewToken {
+					path: dirs.signer,
+					name: self.args.arg_token_name.clone(),
+					scope: try!(to_token_scope(&self.args.flag_token_scope)),
+					expiry: match self.args.flag_token_expiry {
+						Some(ref expiry) => Some(try!(to_duration(expiry))),
+						None => None,
+					},
+				})
+			} else if self.args.cmd_list_tokens {
+				SignerCommand::ListTokens(dirs.signer)
+			} else if self.args.cmd_revoke_token {
+				let name = try!(self.args.arg_token_name.clone().ok_or("Token name or id is required.".to_owned()));
+				SignerCommand::RevokeToken(dirs.signer, name)
+			} else {
+				unreachable!();
+			};
+			Cmd::Signer(signer_cmd)
 		} else if self.args.cmd_tools && self.args.cmd_hash {
 			Cmd::Hash(self.args.arg_file)
+		} else if self.args.cmd_tools && self.args.cmd_ethash && self.args.cmd_dag {
+			Cmd::EthashDag(try!(self.args.arg_block.parse().map_err(|_| format!("Invalid block number: {}", self.args.arg_block))))
 		} else if self.args.cmd_account {
 			let account_cmd = if self.args.cmd_new {
 				let new_acc = NewAccount {
@@ -140,10 +165,16 @@ impl Configuration {
 				wal: wal,
 				mode: mode,
 				tracing: tracing,
+				fat_db: self.args.flag_fat_db,
 				vm_type: vm_type,
 			};
 			Cmd::Blockchain(BlockchainCmd::Import(import_cmd))
-		} else if self.args.cmd_export {
+		} else if self.args.cmd_export && !self.args.cmd_state {
+			// `parity export [<file>]` is an alias for `parity export blocks [<file>]`.
+			if self.args.flag_receipts && format != Some(DataFormat::Json) {
+				return Err("Receipts can only be exported with --format json".into());
+			}
+
 			let export_cmd = ExportBlockchain {
 				spec: spec,
 				logger_config: logger_config,
@@ -158,8 +189,24 @@ impl Configuration {
 				tracing: tracing,
 				from_block: try!(to_block_id(&self.args.flag_from)),
 				to_block: try!(to_block_id(&self.args.flag_to)),
+				receipts: self.args.flag_receipts,
 			};
 			Cmd::Blockchain(BlockchainCmd::Export(export_cmd))
+		} else if self.args.cmd_export && self.args.cmd_state {
+			let export_cmd = ExportState {
+				spec: spec,
+				logger_config: logger_config,
+				cache_config: cache_config,
+				dirs: dirs,
+				file_path: self.args.arg_file.clone(),
+				pruning: pruning,
+				compaction: compaction,
+				wal: wal,
+				mode: mode,
+				tracing: tracing,
+				at: try!(to_block_id(&self.args.flag_at)),
+			};
+			Cmd::Blockchain(BlockchainCmd::ExportState(export_cmd))
 		} else if self.args.cmd_snapshot {
 			let snapshot_cmd = SnapshotCommand {
 				cache_config: cache_config,
@@ -172,7 +219,10 @@ impl Configuration {
 				compaction: compaction,
 				file_path: self.args.arg_file.clone(),
 				wal: wal,
-				kind: snapshot::Kind::Take,
+				kind: match self.args.cmd_verify {
+					true => snapshot::Kind::Verify,
+					false => snapshot::Kind::Take,
+				},
 				block_at: try!(to_block_id(&self.args.flag_at)),
 			};
 			Cmd::Snapshot(snapshot_cmd)
@@ -192,6 +242,27 @@ impl Configuration {
 				block_at: try!(to_block_id("latest")), // unimportant.
 			};
 			Cmd::Snapshot(restore_cmd)
+		} else if self.args.cmd_db {
+			let db_cmd = DbCommand {
+				cache_config: cache_config,
+				dirs: dirs,
+				spec: spec,
+				pruning: pruning,
+				pruning_history: try!(self.pruning_history()),
+				logger_config: logger_config,
+				mode: mode,
+				tracing: tracing,
+				compaction: compaction,
+				wal: wal,
+				kind: if self.args.cmd_repair {
+					db::Kind::Repair
+				} else if self.args.cmd_reset {
+					db::Kind::Reset(try!(to_block_id(&self.args.arg_block)))
+				} else {
+					db::Kind::Check
+				},
+			};
+			Cmd::Db(db_cmd)
 		} else {
 			let daemon = if self.args.cmd_daemon {
 				Some(self.args.arg_pid_file.clone())
@@ -216,6 +287,7 @@ impl Configuration {
 				miner_extras: try!(self.miner_extras()),
 				mode: mode,
 				tracing: tracing,
+				fat_db: self.args.flag_fat_db,
 				compaction: compaction,
 				wal: wal,
 				vm_type: vm_type,
@@ -229,6 +301,14 @@ impl Configuration {
 				name: self.args.flag_identity,
 				custom_bootnodes: self.args.flag_bootnodes.is_some(),
 				no_periodic_snapshot: self.args.flag_no_periodic_snapshot,
+				snapshot_history: self.args.flag_snapshot_history,
+				incremental_snapshots: self.args.flag_incremental_snapshots,
+				stratum: stratum,
+				cpu_miner: cpu_miner,
+				reorg_history: self.args.flag_reorg_history,
+				block_history: try!(self.block_history()),
+				address_index: self.args.flag_address_index,
+				pruning_history: try!(self.pruning_history()),
 			};
 			Cmd::Run(run_cmd)
 		};
@@ -277,16 +357,31 @@ impl Configuration {
 	fn cache_config(&self) -> CacheConfig {
 		match self.args.flag_cache_size.or(self.args.flag_cache) {
 			Some(size) => CacheConfig::new_with_total_cache_size(size),
-			None => CacheConfig::new(self.args.flag_cache_size_db, self.args.flag_cache_size_blocks, self.args.flag_cache_size_queue),
+			None => CacheConfig::new(self.args.flag_cache_size_db, self.args.flag_cache_size_blocks, self.args.flag_cache_size_queue, self.args.flag_cache_size_state),
 		}
 	}
 
-	fn logger_config(&self) -> LogConfig {
-		LogConfig {
+	fn logger_config(&self) -> Result<LogConfig, String> {
+		let json = match self.args.flag_log_format.as_str() {
+			"text" => false,
+			"json" => true,
+			other => return Err(format!("Invalid log format: {}", other)),
+		};
+		let max_age = match self.args.flag_log_max_age {
+			Some(ref age) => Some(try!(to_duration(age))),
+			None => None,
+		};
+		Ok(LogConfig {
 			mode: self.args.flag_logging.clone(),
 			color: !self.args.flag_no_color && !cfg!(windows),
 			file: self.args.flag_log_file.clone(),
-		}
+			json: json,
+			rotation: LogRotation {
+				max_size: self.args.flag_log_max_size.map(|mb| mb * 1024 * 1024),
+				max_age: max_age,
+				keep: self.args.flag_log_keep,
+			},
+		})
 	}
 
 	fn chain(&self) -> String {
@@ -401,6 +496,20 @@ impl Configuration {
 		}
 	}
 
+	fn pruning_history(&self) -> Result<u64, String> {
+		match self.args.flag_pruning_history {
+			0 => Err("Pruning history must be at least 1 block".into()),
+			history => Ok(history),
+		}
+	}
+
+	fn block_history(&self) -> Result<Option<u64>, String> {
+		match self.args.flag_block_history {
+			Some(0) => Err("Block history must be at least 1 block".into()),
+			history => Ok(history),
+		}
+	}
+
 	fn init_reserved_nodes(&self) -> Result<Vec<String>, String> {
 		use std::fs::File;
 
@@ -450,6 +559,10 @@ impl Configuration {
 		ret.net_config_path = Some(net_specific_path.to_str().unwrap().to_owned());
 		ret.reserved_nodes = try!(self.init_reserved_nodes());
 		ret.allow_non_reserved = !self.args.flag_reserved_only;
+		ret.ip_filter = try!(to_ip_filter(&self.args.flag_allow_ips, &self.args.flag_allow_ip_ranges, &self.args.flag_deny_ip_ranges));
+		ret.upload_limit = self.args.flag_max_upload * 1024;
+		ret.download_limit = self.args.flag_max_download * 1024;
+		ret.serve_limit = self.args.flag_max_serve * 1024;
 		Ok(ret)
 	}
 
@@ -588,6 +701,38 @@ impl Configuration {
 		}
 	}
 
+	fn stratum_options(&self) -> Result<Option<StratumOptions>, String> {
+		if !self.args.flag_stratum {
+			return Ok(None);
+		}
+
+		Ok(Some(StratumOptions {
+			listen_addr: match self.args.flag_stratum_interface.as_str() {
+				"local" => "127.0.0.1",
+				"all" => "0.0.0.0",
+				x => x,
+			}.into(),
+			port: self.args.flag_stratum_port,
+			secret: self.args.flag_stratum_secret.as_ref().map(|s| s.sha3()),
+			vardiff: VardiffConfig {
+				initial: try!(to_u256(&self.args.flag_stratum_difficulty)),
+				share_time: self.args.flag_stratum_share_time,
+				..Default::default()
+			},
+		}))
+	}
+
+	fn cpu_miner_options(&self) -> Option<CpuMinerOptions> {
+		if !self.args.flag_mine {
+			return None;
+		}
+
+		Some(CpuMinerOptions {
+			threads: self.args.flag_mining_threads.unwrap_or_else(::num_cpus::get),
+			use_dag: self.args.flag_mining_dag,
+		})
+	}
+
 	fn signer_interface(&self) -> String {
 		match self.args.flag_signer_interface.as_str() {
 			"local" => "127.0.0.1",
@@ -631,16 +776,20 @@ impl Configuration {
 mod tests {
 	use super::*;
 	use cli::Args;
-	use ethcore_rpc::NetworkSettings;
+	use ethcore_rpc::{NetworkSettings, TokenScope};
 	use ethcore::client::{VMType, BlockID};
 	use helpers::{replace_home, default_network_config};
 	use run::RunCmd;
-	use signer::Configuration as SignerConfiguration;
-	use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, DataFormat};
+	use signer::{Configuration as SignerConfiguration, SignerCommand, NewToken};
+	use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, ExportState, DataFormat};
 	use presale::ImportWallet;
 	use account::{AccountCmd, NewAccount, ImportAccounts};
+	use db::{self, DbCommand};
+	use snapshot::{self, SnapshotCommand};
 	use devtools::{RandomTempPath};
 	use std::io::Write;
+	use std::time::Duration;
+	use util::Hashable;
 	use std::fs::{File, create_dir};
 
 	#[derive(Debug, PartialEq)]
@@ -717,10 +866,76 @@ mod tests {
 			wal: true,
 			mode: Default::default(),
 			tracing: Default::default(),
+			fat_db: false,
 			vm_type: VMType::Interpreter,
 		})));
 	}
 
+	#[test]
+	fn test_command_db_repair() {
+		let args = vec!["parity", "db", "repair"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap(), Cmd::Db(DbCommand {
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			spec: Default::default(),
+			pruning: Default::default(),
+			pruning_history: 1200,
+			logger_config: Default::default(),
+			mode: Default::default(),
+			tracing: Default::default(),
+			compaction: Default::default(),
+			wal: true,
+			kind: db::Kind::Repair,
+		}));
+	}
+
+	#[test]
+	fn test_command_db_reset() {
+		let args = vec!["parity", "db", "reset", "1000", "--pruning-history", "5000"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap(), Cmd::Db(DbCommand {
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			spec: Default::default(),
+			pruning: Default::default(),
+			pruning_history: 5000,
+			logger_config: Default::default(),
+			mode: Default::default(),
+			tracing: Default::default(),
+			compaction: Default::default(),
+			wal: true,
+			kind: db::Kind::Reset(BlockID::Number(1000)),
+		}));
+	}
+
+	#[test]
+	fn test_command_ethash_dag() {
+		let args = vec!["parity", "tools", "ethash", "dag", "60000"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap(), Cmd::EthashDag(60000));
+	}
+
+	#[test]
+	fn test_command_snapshot_verify() {
+		let args = vec!["parity", "snapshot", "verify", "snapshot.pack"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap(), Cmd::Snapshot(SnapshotCommand {
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			spec: Default::default(),
+			pruning: Default::default(),
+			logger_config: Default::default(),
+			mode: Default::default(),
+			tracing: Default::default(),
+			compaction: Default::default(),
+			file_path: Some("snapshot.pack".into()),
+			wal: true,
+			kind: snapshot::Kind::Verify,
+			block_at: BlockID::Latest,
+		}));
+	}
+
 	#[test]
 	fn test_command_blockchain_export() {
 		let args = vec!["parity", "export", "blockchain.json"];
@@ -739,7 +954,9 @@ mod tests {
 			tracing: Default::default(),
 			from_block: BlockID::Number(1),
 			to_block: BlockID::Latest,
+			receipts: false,
 		})));
+		assert_eq!(parse(&["parity", "export", "blocks", "blockchain.json"]).into_command().unwrap(), parse(&args).into_command().unwrap());
 	}
 
 	#[test]
@@ -760,6 +977,49 @@ mod tests {
 			tracing: Default::default(),
 			from_block: BlockID::Number(1),
 			to_block: BlockID::Latest,
+			receipts: false,
+		})));
+	}
+
+	#[test]
+	fn test_command_blockchain_export_json_with_receipts() {
+		let args = vec!["parity", "export", "blocks", "--format", "json", "--receipts", "blockchain.json"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap(), Cmd::Blockchain(BlockchainCmd::Export(ExportBlockchain {
+			spec: Default::default(),
+			logger_config: Default::default(),
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			file_path: Some("blockchain.json".into()),
+			pruning: Default::default(),
+			format: Some(DataFormat::Json),
+			compaction: Default::default(),
+			wal: true,
+			mode: Default::default(),
+			tracing: Default::default(),
+			from_block: BlockID::Number(1),
+			to_block: BlockID::Latest,
+			receipts: true,
+		})));
+		assert!(parse(&["parity", "export", "blocks", "--receipts"]).into_command().is_err());
+	}
+
+	#[test]
+	fn test_command_state_export() {
+		let args = vec!["parity", "export", "state", "--at", "1000", "state.json"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap(), Cmd::Blockchain(BlockchainCmd::ExportState(ExportState {
+			spec: Default::default(),
+			logger_config: Default::default(),
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			file_path: Some("state.json".into()),
+			pruning: Default::default(),
+			compaction: Default::default(),
+			wal: true,
+			mode: Default::default(),
+			tracing: Default::default(),
+			at: BlockID::Number(1000),
 		})));
 	}
 
@@ -768,7 +1028,33 @@ mod tests {
 		let args = vec!["parity", "signer", "new-token"];
 		let conf = parse(&args);
 		let expected = replace_home("$HOME/.parity/signer");
-		assert_eq!(conf.into_command().unwrap(), Cmd::SignerToken(expected));
+		assert_eq!(conf.into_command().unwrap(), Cmd::Signer(SignerCommand::NewToken(NewToken {
+			path: expected,
+			name: None,
+			scope: TokenScope::Confirm,
+			expiry: None,
+		})));
+	}
+
+	#[test]
+	fn test_command_signer_new_named_token() {
+		let args = vec!["parity", "signer", "new-token", "laptop", "--token-scope", "view", "--token-expiry", "2hours"];
+		let conf = parse(&args);
+		let expected = replace_home("$HOME/.parity/signer");
+		assert_eq!(conf.into_command().unwrap(), Cmd::Signer(SignerCommand::NewToken(NewToken {
+			path: expected,
+			name: Some("laptop".into()),
+			scope: TokenScope::View,
+			expiry: Some(Duration::from_secs(2 * 60 * 60)),
+		})));
+	}
+
+	#[test]
+	fn test_command_signer_revoke_token() {
+		let args = vec!["parity", "signer", "revoke-token", "laptop"];
+		let conf = parse(&args);
+		let expected = replace_home("$HOME/.parity/signer");
+		assert_eq!(conf.into_command().unwrap(), Cmd::Signer(SignerCommand::RevokeToken(expected, "laptop".into())));
 	}
 
 	#[test]
@@ -792,6 +1078,7 @@ mod tests {
 			miner_extras: Default::default(),
 			mode: Default::default(),
 			tracing: Default::default(),
+			fat_db: false,
 			compaction: Default::default(),
 			wal: true,
 			vm_type: Default::default(),
@@ -805,9 +1092,88 @@ mod tests {
 			name: "".into(),
 			custom_bootnodes: false,
 			no_periodic_snapshot: false,
+			snapshot_history: 0,
+			incremental_snapshots: 0,
+			stratum: None,
+			cpu_miner: None,
+			reorg_history: 10000,
+			block_history: None,
+			address_index: false,
+			pruning_history: 1200,
 		}));
 	}
 
+	#[test]
+	fn should_parse_stratum_options() {
+		// given
+		let conf = parse(&["parity", "--stratum", "--stratum-interface", "all", "--stratum-secret", "pool", "--stratum-difficulty", "1000"]);
+
+		// when
+		let options = conf.stratum_options().unwrap().unwrap();
+
+		// then
+		assert_eq!(options.listen_addr, "0.0.0.0".to_owned());
+		assert_eq!(options.port, 8008);
+		assert_eq!(options.secret, Some("pool".sha3()));
+		assert_eq!(options.vardiff.initial, 1000.into());
+		assert_eq!(options.vardiff.share_time, 10);
+	}
+
+	#[test]
+	fn should_reject_zero_pruning_history() {
+		// given
+		let conf = parse(&["parity", "--pruning-history", "0"]);
+
+		// when
+		let cmd = conf.into_command();
+
+		// then
+		assert!(cmd.is_err());
+	}
+
+	#[test]
+	fn should_reject_zero_block_history() {
+		// given
+		let conf = parse(&["parity", "--block-history", "0"]);
+
+		// when
+		let cmd = conf.into_command();
+
+		// then
+		assert!(cmd.is_err());
+	}
+
+	#[test]
+	fn should_parse_logger_config() {
+		// given
+		let conf = parse(&["parity", "--log-format", "json", "--log-max-size", "10", "--log-max-age", "hourly", "--log-keep", "3"]);
+
+		// when
+		let config = conf.logger_config().unwrap();
+
+		// then
+		assert!(config.json);
+		assert_eq!(config.rotation, LogRotation {
+			max_size: Some(10 * 1024 * 1024),
+			max_age: Some(Duration::from_secs(3600)),
+			keep: 3,
+		});
+		assert!(parse(&["parity", "--log-format", "xml"]).logger_config().is_err());
+	}
+
+	#[test]
+	fn should_parse_cpu_miner_options() {
+		// given
+		let conf = parse(&["parity", "--mine", "--mining-threads", "3", "--mining-dag"]);
+
+		// when
+		let options = conf.cpu_miner_options().unwrap();
+
+		// then
+		assert_eq!(options, CpuMinerOptions { threads: 3, use_dag: true });
+		assert_eq!(parse(&["parity"]).cpu_miner_options(), None);
+	}
+
 	#[test]
 	fn should_parse_network_settings() {
 		// given
@@ -0,0 +1,194 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Database integrity check, repair and reset commands.
+
+use std::sync::Arc;
+use std::path::PathBuf;
+
+use ethcore_logger::{setup_log, Config as LogConfig};
+use ethcore::client::{Client, ClientConfig, Mode, DatabaseCompactionProfile, VMType, IntegrityReport, BlockChainClient, StateError};
+use ethcore::ids::BlockID;
+use ethcore::miner::Miner;
+use ethcore::spec::Spec;
+use ethcore::db::NUM_COLUMNS;
+use util::kvdb::DatabaseConfig;
+use io::IoChannel;
+
+use cache::CacheConfig;
+use params::{SpecType, Pruning, Switch, tracing_switch_to_bool, fatdb_switch_to_bool};
+use helpers::{to_client_config, execute_upgrades};
+use dir::Directories;
+use user_defaults::UserDefaults;
+use fdlimit;
+
+/// Kinds of database commands.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Kind {
+	/// Check integrity of the database.
+	Check,
+	/// Check integrity and repair inconsistencies.
+	Repair,
+	/// Rewind the best block to the given block.
+	Reset(BlockID),
+}
+
+/// Command for database integrity check or repair.
+#[derive(Debug, PartialEq)]
+pub struct DbCommand {
+	pub cache_config: CacheConfig,
+	pub dirs: Directories,
+	pub spec: SpecType,
+	pub pruning: Pruning,
+	pub pruning_history: u64,
+	pub logger_config: LogConfig,
+	pub mode: Mode,
+	pub tracing: Switch,
+	pub compaction: DatabaseCompactionProfile,
+	pub wal: bool,
+	pub kind: Kind,
+}
+
+fn print_report(report: &IntegrityReport) -> String {
+	let mut out = format!("Checked blocks up to #{}, chain is consistent up to #{}.", report.best_block, report.last_consistent);
+	for error in &report.errors {
+		out.push_str(&format!("\n  {}", error));
+	}
+	out
+}
+
+/// Client database opened by a database command.
+struct ClientDb {
+	spec: Spec,
+	config: ClientConfig,
+	path: PathBuf,
+	db_config: DatabaseConfig,
+}
+
+impl ClientDb {
+	/// Checks integrity without starting the client, so that damaged databases can be checked.
+	fn check(&self) -> Result<IntegrityReport, String> {
+		Client::check_database(&self.config, &self.path, &self.db_config).map_err(|e| format!("Client error: {:?}", e))
+	}
+
+	fn client(self) -> Result<Arc<Client>, String> {
+		let miner = Arc::new(Miner::with_spec(&self.spec));
+		Client::new(self.config, &self.spec, &self.path, miner, IoChannel::disconnected(), &self.db_config)
+			.map_err(|e| format!("Client error: {:?}", e))
+	}
+}
+
+impl DbCommand {
+	fn open_db(self) -> Result<ClientDb, String> {
+		// load spec file
+		let spec = try!(self.spec.spec());
+
+		// load genesis hash
+		let genesis_hash = spec.genesis_header().hash();
+
+		// database paths
+		let db_dirs = self.dirs.database(genesis_hash, spec.fork_name.clone());
+
+		// user defaults path
+		let user_defaults_path = db_dirs.user_defaults_path();
+
+		// load user defaults
+		let user_defaults = try!(UserDefaults::load(&user_defaults_path));
+
+		// check if tracing is on
+		let tracing = try!(tracing_switch_to_bool(self.tracing, &user_defaults));
+
+		fdlimit::raise_fd_limit();
+
+		// select pruning algorithm
+		let algorithm = self.pruning.to_algorithm(&user_defaults);
+
+		// check if fatdb is on
+		let fat_db = try!(fatdb_switch_to_bool(false, &user_defaults, algorithm));
+
+		// prepare client path
+		let client_path = db_dirs.client_path(algorithm);
+
+		// execute upgrades
+		try!(execute_upgrades(&db_dirs, algorithm, self.compaction.compaction_profile()));
+
+		// prepare client config
+		let mut client_config = to_client_config(&self.cache_config, self.mode, tracing, self.compaction, self.wal, VMType::default(), "".into(), algorithm, fat_db);
+		client_config.history = Some(self.pruning_history);
+
+		let mut db_config = DatabaseConfig::with_columns(NUM_COLUMNS);
+		db_config.compaction = client_config.db_compaction.compaction_profile();
+		db_config.wal = client_config.db_wal;
+
+		Ok(ClientDb {
+			spec: spec,
+			config: client_config,
+			path: client_path,
+			db_config: db_config,
+		})
+	}
+}
+
+fn check(db: ClientDb) -> Result<String, String> {
+	info!("Checking database integrity");
+	let report = try!(db.check());
+	match report.is_ok() {
+		true => Ok(print_report(&report)),
+		false => Err(print_report(&report)),
+	}
+}
+
+fn repair(db: ClientDb) -> Result<String, String> {
+	info!("Checking database integrity");
+	let report = try!(db.check());
+	if report.is_ok() {
+		return Ok(print_report(&report));
+	}
+
+	warn!("{}", print_report(&report));
+	let client = try!(db.client());
+	let best_block = client.repair_integrity(&report);
+	let report = client.check_integrity();
+	match report.is_ok() {
+		true => Ok(format!("Database repaired, best block is #{}.", best_block)),
+		false => Err(format!("Database could not be fully repaired.\n{}", print_report(&report))),
+	}
+}
+
+fn reset(client: &Client, id: BlockID) -> Result<String, String> {
+	match client.rewind(id) {
+		Ok(retracted) => Ok(format!("Best block reset to #{}, {} blocks retracted.", client.chain_info().best_block_number, retracted.len())),
+		Err(StateError::UnknownBlock) => Err("Block to reset to is not in the canonical chain.".into()),
+		Err(StateError::StatePruned) => Err("State of the block to reset to has been pruned. Try a more recent block.".into()),
+		Err(e) => Err(format!("{}", e)),
+	}
+}
+
+/// Execute this database command.
+pub fn execute(cmd: DbCommand) -> Result<String, String> {
+	// Setup logging
+	let _logger = setup_log(&cmd.logger_config);
+
+	let kind = cmd.kind.clone();
+	let db = try!(cmd.open_db());
+
+	match kind {
+		Kind::Check => check(db),
+		Kind::Repair => repair(db),
+		Kind::Reset(id) => reset(&*try!(db.client()), id),
+	}
+}
@@ -21,13 +21,14 @@ use std::path::Path;
 use std::fs::File;
 use util::{clean_0x, U256, Uint, Address, path, CompactionProfile};
 use util::journaldb::Algorithm;
+use util::trie::TrieSpec;
 use ethcore::client::{Mode, BlockID, VMType, DatabaseCompactionProfile, ClientConfig};
 use ethcore::miner::PendingSet;
 use cache::CacheConfig;
 use dir::DatabaseDirectories;
 use upgrade::upgrade;
 use migration::migrate;
-use ethsync::is_valid_node_url;
+use ethsync::{is_valid_node_url, IpFilterSettings};
 
 pub fn to_duration(s: &str) -> Result<Duration, String> {
 	to_seconds(s).map(Duration::from_secs)
@@ -166,6 +167,20 @@ pub fn to_bootnodes(bootnodes: &Option<String>) -> Result<Vec<String>, String> {
 	}
 }
 
+pub fn to_ip_filter(allow: &str, allow_ranges: &Option<String>, deny_ranges: &Option<String>) -> Result<IpFilterSettings, String> {
+	let to_ranges = |ranges: &Option<String>| match *ranges {
+		Some(ref x) => x.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).map(|s| s.to_owned()).collect(),
+		None => Vec::new(),
+	};
+	let filter = IpFilterSettings {
+		allow: allow.to_owned(),
+		allow_ranges: to_ranges(allow_ranges),
+		deny_ranges: to_ranges(deny_ranges),
+	};
+	try!(filter.clone().into_filter().map_err(|e| format!("{}", e)));
+	Ok(filter)
+}
+
 #[cfg(test)]
 pub fn default_network_config() -> ::ethsync::NetworkConfiguration {
 	use ethsync::NetworkConfiguration;
@@ -183,6 +198,14 @@ pub fn default_network_config() -> ::ethsync::NetworkConfiguration {
 		min_peers: 25,
 		reserved_nodes: Vec::new(),
 		allow_non_reserved: true,
+		ip_filter: IpFilterSettings {
+			allow: "all".into(),
+			allow_ranges: Vec::new(),
+			deny_ranges: Vec::new(),
+		},
+		upload_limit: 0,
+		download_limit: 0,
+		serve_limit: 0,
 	}
 }
 
@@ -196,6 +219,7 @@ pub fn to_client_config(
 		vm_type: VMType,
 		name: String,
 		pruning: Algorithm,
+		fat_db: bool,
 	) -> ClientConfig {
 	let mut client_config = ClientConfig::default();
 
@@ -214,6 +238,8 @@ pub fn to_client_config(
 	client_config.tracing.max_cache_size = cache_config.traces() as usize * mb;
 	// in bytes
 	client_config.tracing.pref_cache_size = cache_config.traces() as usize * 3 / 4 * mb;
+	// in bytes
+	client_config.state_cache_size = Some(cache_config.state() as usize * mb);
 
 	client_config.mode = mode;
 	client_config.tracing.enabled = tracing;
@@ -222,6 +248,10 @@ pub fn to_client_config(
 	client_config.db_wal = wal;
 	client_config.vm_type = vm_type;
 	client_config.name = name;
+	client_config.trie_spec = match fat_db {
+		true => TrieSpec::Fat,
+		false => TrieSpec::Secure,
+	};
 	client_config
 }
 
@@ -295,7 +325,7 @@ mod tests {
 	use util::{U256};
 	use ethcore::client::{Mode, BlockID};
 	use ethcore::miner::PendingSet;
-	use super::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_address, to_addresses, to_price, geth_ipc_path, to_bootnodes};
+	use super::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_address, to_addresses, to_price, geth_ipc_path, to_bootnodes, to_ip_filter};
 
 	#[test]
 	fn test_to_duration() {
@@ -411,5 +441,16 @@ mod tests {
 		assert_eq!(to_bootnodes(&Some(one_bootnode.into())), Ok(vec![one_bootnode.into()]));
 		assert_eq!(to_bootnodes(&Some(two_bootnodes.into())), Ok(vec![one_bootnode.into(), one_bootnode.into()]));
 	}
+
+	#[test]
+	fn test_to_ip_filter() {
+		let filter = to_ip_filter("private", &Some("8.8.8.8/32, ".into()), &None).unwrap();
+		assert_eq!(filter.allow, "private");
+		assert_eq!(filter.allow_ranges, vec!["8.8.8.8/32".to_owned()]);
+		assert!(filter.deny_ranges.is_empty());
+
+		assert!(to_ip_filter("everyone", &None, &None).is_err());
+		assert!(to_ip_filter("all", &None, &Some("10.0.0.0/40".into())).is_err());
+	}
 }
 
@@ -27,6 +27,7 @@ extern crate num_cpus;
 extern crate rustc_serialize;
 extern crate ethcore_devtools as devtools;
 extern crate ethcore;
+extern crate ethash;
 extern crate ethsync;
 extern crate env_logger;
 extern crate ethcore_logger;
@@ -103,6 +104,7 @@ mod account;
 mod blockchain;
 mod presale;
 mod snapshot;
+mod db;
 mod run;
 #[cfg(feature="ipc")]
 mod sync;
@@ -131,6 +133,14 @@ fn print_hash_of(maybe_file: Option<String>) -> Result<String, String> {
 	}
 }
 
+fn generate_dag(block_number: u64) -> Result<String, String> {
+	if ethash::Full::exists(block_number) {
+		return Ok(format!("DAG for epoch {} is already generated", block_number / ethash::ETHASH_EPOCH_LENGTH));
+	}
+	let path = try!(ethash::generate_dag(block_number, num_cpus::get()).map_err(|e| format!("DAG generation failed: {}", e)));
+	Ok(format!("{}", path.display()))
+}
+
 fn execute(command: Cmd) -> Result<String, String> {
 	match command {
 		Cmd::Run(run_cmd) => {
@@ -139,11 +149,13 @@ fn execute(command: Cmd) -> Result<String, String> {
 		},
 		Cmd::Version => Ok(Args::print_version()),
 		Cmd::Hash(maybe_file) => print_hash_of(maybe_file),
+		Cmd::EthashDag(block_number) => generate_dag(block_number),
 		Cmd::Account(account_cmd) => account::execute(account_cmd),
 		Cmd::ImportPresaleWallet(presale_cmd) => presale::execute(presale_cmd),
 		Cmd::Blockchain(blockchain_cmd) => blockchain::execute(blockchain_cmd),
-		Cmd::SignerToken(path) => signer::new_token(path),
+		Cmd::Signer(signer_cmd) => signer::execute(signer_cmd),
 		Cmd::Snapshot(snapshot_cmd) => snapshot::execute(snapshot_cmd),
+		Cmd::Db(db_cmd) => db::execute(db_cmd),
 	}
 }
 
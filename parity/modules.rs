@@ -108,8 +108,23 @@ fn sync_arguments(io_path: &str, sync_cfg: SyncConfig, net_cfg: NetworkConfigura
 		cli_args.push(mode.to_owned());
 	}
 	if let Some(ref file) = log_settings.file {
+		// sync process logs into its own file, so that both processes can rotate independently
 		cli_args.push("--log-file".to_owned());
-		cli_args.push(file.to_owned());
+		cli_args.push(format!("{}.sync", file));
+		if let Some(max_size) = log_settings.rotation.max_size {
+			cli_args.push("--log-max-size".to_owned());
+			cli_args.push(format!("{}", max_size));
+		}
+		if let Some(max_age) = log_settings.rotation.max_age {
+			cli_args.push("--log-max-age".to_owned());
+			cli_args.push(format!("{}", max_age.as_secs()));
+		}
+		cli_args.push("--log-keep".to_owned());
+		cli_args.push(format!("{}", log_settings.rotation.keep));
+	}
+	if log_settings.json {
+		cli_args.push("--log-format".to_owned());
+		cli_args.push("json".to_owned());
 	}
 
 	BootArgs::new().stdin(service_payload).cli(cli_args)
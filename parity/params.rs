@@ -252,11 +252,24 @@ pub fn tracing_switch_to_bool(switch: Switch, user_defaults: &UserDefaults) -> R
 	}
 }
 
+pub fn fatdb_switch_to_bool(switch: bool, user_defaults: &UserDefaults, algorithm: Algorithm) -> Result<bool, String> {
+	let fat_db = match (user_defaults.is_first_launch, switch, user_defaults.fat_db) {
+		(false, true, false) => return Err("FatDB resync required. Fat DB can only be enabled on a fresh database: remove the database and resync with `--fat-db --pruning=archive`".into()),
+		(_, true, _) => true,
+		(_, false, def) => def,
+	};
+
+	match (fat_db, algorithm) {
+		(true, Algorithm::Archive) | (false, _) => Ok(fat_db),
+		(true, _) => Err("Fat DB is not supported with the chosen pruning option. Please rerun with `--pruning=archive`".into()),
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use util::journaldb::Algorithm;
 	use user_defaults::UserDefaults;
-	use super::{SpecType, Pruning, ResealPolicy, Switch, tracing_switch_to_bool};
+	use super::{SpecType, Pruning, ResealPolicy, Switch, tracing_switch_to_bool, fatdb_switch_to_bool};
 
 	#[test]
 	fn test_spec_type_parsing() {
@@ -336,4 +349,22 @@ mod tests {
 		assert!(tracing_switch_to_bool(Switch::On, &user_defaults_with_tracing(false, true)).unwrap());
 		assert!(tracing_switch_to_bool(Switch::On, &user_defaults_with_tracing(false, false)).is_err());
 	}
+
+	fn user_defaults_with_fat_db(first_launch: bool, fat_db: bool) -> UserDefaults {
+		let mut ud = UserDefaults::default();
+		ud.is_first_launch = first_launch;
+		ud.fat_db = fat_db;
+		ud
+	}
+
+	#[test]
+	fn test_fatdb_switch_to_bool() {
+		assert!(!fatdb_switch_to_bool(false, &user_defaults_with_fat_db(true, false), Algorithm::OverlayRecent).unwrap());
+		assert!(fatdb_switch_to_bool(false, &user_defaults_with_fat_db(false, true), Algorithm::Archive).unwrap());
+		assert!(fatdb_switch_to_bool(true, &user_defaults_with_fat_db(true, false), Algorithm::Archive).unwrap());
+		assert!(fatdb_switch_to_bool(true, &user_defaults_with_fat_db(false, true), Algorithm::Archive).unwrap());
+
+		assert!(fatdb_switch_to_bool(true, &user_defaults_with_fat_db(false, false), Algorithm::Archive).is_err());
+		assert!(fatdb_switch_to_bool(true, &user_defaults_with_fat_db(true, false), Algorithm::OverlayRecent).is_err());
+	}
 }
@@ -26,7 +26,7 @@ use io::{MayPanic, ForwardPanic, PanicHandler};
 use ethcore::client::{Mode, DatabaseCompactionProfile, VMType, ChainNotify};
 use ethcore::service::ClientService;
 use ethcore::account_provider::AccountProvider;
-use ethcore::miner::{Miner, MinerService, ExternalMiner, MinerOptions};
+use ethcore::miner::{Miner, MinerService, ExternalMiner, MinerOptions, Stratum, StratumOptions, CpuMiner, CpuMinerOptions};
 use ethcore::snapshot;
 use ethsync::{SyncConfig, SyncProvider};
 use informant::Informant;
@@ -35,7 +35,7 @@ use rpc::{HttpServer, IpcServer, HttpConfiguration, IpcConfiguration};
 use signer::SignerServer;
 use dapps::WebappServer;
 use io_handler::ClientIoHandler;
-use params::{SpecType, Pruning, AccountsConfig, GasPricerConfig, MinerExtras, Switch, tracing_switch_to_bool};
+use params::{SpecType, Pruning, AccountsConfig, GasPricerConfig, MinerExtras, Switch, tracing_switch_to_bool, fatdb_switch_to_bool};
 use helpers::{to_client_config, execute_upgrades, passwords_from_files};
 use dir::Directories;
 use cache::CacheConfig;
@@ -72,6 +72,7 @@ pub struct RunCmd {
 	pub miner_extras: MinerExtras,
 	pub mode: Mode,
 	pub tracing: Switch,
+	pub fat_db: bool,
 	pub compaction: DatabaseCompactionProfile,
 	pub wal: bool,
 	pub vm_type: VMType,
@@ -85,6 +86,14 @@ pub struct RunCmd {
 	pub name: String,
 	pub custom_bootnodes: bool,
 	pub no_periodic_snapshot: bool,
+	pub snapshot_history: usize,
+	pub incremental_snapshots: usize,
+	pub stratum: Option<StratumOptions>,
+	pub cpu_miner: Option<CpuMinerOptions>,
+	pub reorg_history: u64,
+	pub block_history: Option<u64>,
+	pub address_index: bool,
+	pub pruning_history: u64,
 }
 
 pub fn execute(cmd: RunCmd) -> Result<(), String> {
@@ -102,6 +111,9 @@ pub fn execute(cmd: RunCmd) -> Result<(), String> {
 
 	// load spec
 	let spec = try!(cmd.spec.spec());
+	if cmd.cpu_miner.is_some() && spec.engine.name() != "Ethash" {
+		return Err(format!("Built-in CPU miner requires an Ethash chain, {} uses {}.", spec.name, spec.engine.name()));
+	}
 
 	// load genesis hash
 	let genesis_hash = spec.genesis_header().hash();
@@ -121,6 +133,9 @@ pub fn execute(cmd: RunCmd) -> Result<(), String> {
 	// select pruning algorithm
 	let algorithm = cmd.pruning.to_algorithm(&user_defaults);
 
+	// check if fatdb is on
+	let fat_db = try!(fatdb_switch_to_bool(cmd.fat_db, &user_defaults, algorithm));
+
 	// prepare client and snapshot paths.
 	let client_path = db_dirs.client_path(algorithm);
 	let snapshot_path = db_dirs.snapshot_path();
@@ -155,6 +170,9 @@ pub fn execute(cmd: RunCmd) -> Result<(), String> {
 	}
 	sync_config.fork_block = spec.fork_block();
 
+	// prepare signing policy (uses the same password files as unlocking)
+	let signing_policy = try!(signer::signing_policy(&cmd.signer_conf, cmd.acc_conf.password_files.clone()));
+
 	// prepare account provider
 	let account_provider = Arc::new(try!(prepare_account_provider(&cmd.dirs, cmd.acc_conf)));
 
@@ -167,7 +185,7 @@ pub fn execute(cmd: RunCmd) -> Result<(), String> {
 	miner.set_transactions_limit(cmd.miner_extras.transactions_limit);
 
 	// create client config
-	let client_config = to_client_config(
+	let mut client_config = to_client_config(
 		&cmd.cache_config,
 		cmd.mode,
 		tracing,
@@ -176,7 +194,18 @@ pub fn execute(cmd: RunCmd) -> Result<(), String> {
 		cmd.vm_type,
 		cmd.name,
 		algorithm,
+		fat_db,
 	);
+	client_config.reorg_history = cmd.reorg_history;
+	client_config.block_history = cmd.block_history;
+	client_config.address_index = cmd.address_index;
+	client_config.history = Some(cmd.pruning_history);
+	client_config.snapshot_history = cmd.snapshot_history;
+	client_config.incremental_snapshots = cmd.incremental_snapshots;
+	client_config.sealing_data_threads = match cmd.cpu_miner {
+		Some(ref options) if options.use_dag => options.threads,
+		_ => 0,
+	};
 
 	// set up bootnodes
 	let mut net_conf = cmd.net_conf;
@@ -207,6 +236,18 @@ pub fn execute(cmd: RunCmd) -> Result<(), String> {
 	// create external miner
 	let external_miner = Arc::new(ExternalMiner::default());
 
+	// start stratum server
+	if let Some(ref stratum) = cmd.stratum {
+		try!(Stratum::register(stratum, miner.clone(), Arc::downgrade(&client))
+			.map_err(|e| format!("Stratum start error: {:?}", e)));
+	}
+
+	// start built-in CPU miner
+	if let Some(ref cpu_miner) = cmd.cpu_miner {
+		try!(CpuMiner::register(cpu_miner, miner.clone(), Arc::downgrade(&client))
+			.map_err(|e| format!("CPU miner start error: {}", e)));
+	}
+
 	// create sync object
 	let (sync_provider, manage_network, chain_notify) = try!(modules::sync(
 		&mut hypervisor, sync_config, net_conf.into(), client.clone(), snapshot_service, &cmd.logger_config,
@@ -223,7 +264,7 @@ pub fn execute(cmd: RunCmd) -> Result<(), String> {
 	let signer_path = cmd.signer_conf.signer_path.clone();
 	let deps_for_rpc_apis = Arc::new(rpc_apis::Dependencies {
 		signer_port: cmd.signer_port,
-		signer_service: Arc::new(rpc_apis::SignerService::new(move || signer::new_token(signer_path.clone()))),
+		signer_service: Arc::new(rpc_apis::SignerService::with_policy(signer::SignerCodes::new(signer_path), signing_policy)),
 		client: client.clone(),
 		sync: sync_provider.clone(),
 		net: manage_network.clone(),
@@ -305,6 +346,7 @@ pub fn execute(cmd: RunCmd) -> Result<(), String> {
 	// save user defaults
 	user_defaults.pruning = algorithm;
 	user_defaults.tracing = tracing;
+	user_defaults.fat_db = fat_db;
 	try!(user_defaults.save(&user_defaults_path));
 
 	// Handle exit
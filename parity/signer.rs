@@ -17,15 +17,19 @@
 use std::io;
 use std::sync::Arc;
 use std::path::PathBuf;
+use std::time::Duration;
 use ansi_term::Colour;
 use io::{ForwardPanic, PanicHandler};
 use util::path::restrict_permissions_owner;
 use rpc_apis;
 use ethcore_signer as signer;
-use helpers::replace_home;
+use ethcore_rpc::{SigningPolicy, SignerTokens, SignerToken, TokenScope};
+use helpers::{replace_home, passwords_from_files};
 pub use ethcore_signer::Server as SignerServer;
 
 const CODES_FILENAME: &'static str = "authcodes";
+const RULES_FILENAME: &'static str = "rules.json";
+const AUDIT_FILENAME: &'static str = "audit.log";
 
 #[derive(Debug, PartialEq)]
 pub struct Configuration {
@@ -68,21 +72,135 @@ fn codes_path(path: String) -> PathBuf {
 	p
 }
 
+/// Loads auto-approval rules stored in signer directory.
+pub fn signing_policy(conf: &Configuration, password_files: Vec<String>) -> Result<SigningPolicy, String> {
+	let path = PathBuf::from(&conf.signer_path);
+	let passwords = try!(passwords_from_files(password_files));
+	SigningPolicy::load(path.join(RULES_FILENAME), path.join(AUDIT_FILENAME), passwords)
+		.map_err(|e| format!("Error loading signing rules: {}", e))
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SignerCommand {
+	NewToken(NewToken),
+	ListTokens(String),
+	RevokeToken(String, String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NewToken {
+	pub path: String,
+	pub name: Option<String>,
+	pub scope: TokenScope,
+	pub expiry: Option<Duration>,
+}
+
+pub fn to_token_scope(s: &str) -> Result<TokenScope, String> {
+	match s {
+		"view" => Ok(TokenScope::View),
+		"confirm" => Ok(TokenScope::Confirm),
+		_ => Err(format!("{}: Invalid token scope given. Must be one of: view, confirm.", s)),
+	}
+}
+
+pub fn execute(cmd: SignerCommand) -> Result<String, String> {
+	match cmd {
+		SignerCommand::NewToken(new_token) => new_named_token(new_token),
+		SignerCommand::ListTokens(path) => list_tokens(path),
+		SignerCommand::RevokeToken(path, name) => revoke_token(path, name),
+	}
+}
+
 pub fn new_token(path: String) -> Result<String, String> {
-	generate_new_token(path)
+	new_named_token(NewToken {
+		path: path,
+		name: None,
+		scope: TokenScope::Confirm,
+		expiry: None,
+	})
+}
+
+fn new_named_token(cmd: NewToken) -> Result<String, String> {
+	generate_new_token(cmd)
 		.map(|code| format!("This key code will authorise your System Signer UI: {}", Colour::White.bold().paint(code)))
 		.map_err(|err| format!("Error generating token: {:?}", err))
 }
 
-fn generate_new_token(path: String) -> io::Result<String> {
-	let path = codes_path(path);
+fn generate_new_token(cmd: NewToken) -> io::Result<String> {
+	let path = codes_path(cmd.path);
 	let mut codes = try!(signer::AuthCodes::from_file(&path));
-	let code = try!(codes.generate_new());
+	codes.clear_expired();
+	let name = cmd.name.unwrap_or_default();
+	let code = try!(codes.generate_named(&name, cmd.scope, cmd.expiry.map(|e| e.as_secs())));
 	try!(codes.to_file(&path));
 	trace!("New key code created: {}", Colour::White.bold().paint(&code[..]));
 	Ok(code)
 }
 
+fn list_tokens(path: String) -> Result<String, String> {
+	let tokens = try!(SignerCodes::new(path).list());
+	if tokens.is_empty() {
+		return Ok("No tokens found.".into());
+	}
+
+	let format_time = |t: Option<u64>| t.map_or_else(|| "-".to_owned(), |t| format!("{}", t));
+	let lines = tokens.into_iter().map(|token| format!(
+		"{id}\t{name}\t{scope:?}\tcreated: {created}\texpires: {expires}\tlast used: {used}",
+		id = token.id,
+		name = if token.name.is_empty() { "(unnamed)" } else { &token.name[..] },
+		scope = token.scope,
+		created = token.created_at,
+		expires = format_time(token.expires_at),
+		used = format_time(token.last_used_at),
+	)).collect::<Vec<_>>();
+	Ok(lines.join("\n"))
+}
+
+fn revoke_token(path: String, name: String) -> Result<String, String> {
+	match try!(SignerCodes::new(path).revoke(&name)) {
+		true => Ok(format!("Token {} revoked.", name)),
+		false => Err(format!("Token {} not found.", name)),
+	}
+}
+
+/// Signer tokens stored in `authcodes` file.
+pub struct SignerCodes {
+	path: String,
+}
+
+impl SignerCodes {
+	pub fn new(path: String) -> Self {
+		SignerCodes {
+			path: path,
+		}
+	}
+
+	fn update<F, T>(&self, f: F) -> Result<T, String> where F: FnOnce(&mut signer::AuthCodes) -> T {
+		let path = codes_path(self.path.clone());
+		let mut codes = try!(signer::AuthCodes::from_file(&path).map_err(|e| format!("Error reading tokens: {:?}", e)));
+		let res = f(&mut codes);
+		try!(codes.to_file(&path).map_err(|e| format!("Error saving tokens: {:?}", e)));
+		Ok(res)
+	}
+}
+
+impl SignerTokens for SignerCodes {
+	fn generate(&self) -> Result<String, String> {
+		new_token(self.path.clone())
+	}
+
+	fn list(&self) -> Result<Vec<SignerToken>, String> {
+		self.update(|codes| {
+			codes.clear_expired();
+			codes.codes().iter().cloned().map(Into::into).collect()
+		})
+	}
+
+	fn revoke(&self, name_or_id: &str) -> Result<bool, String> {
+		self.update(|codes| codes.revoke(name_or_id))
+	}
+}
+
 fn do_start(conf: Configuration, deps: Dependencies) -> Result<SignerServer, String> {
 	let addr = try!(format!("{}:{}", conf.interface, conf.port)
 		.parse()
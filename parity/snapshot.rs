@@ -16,21 +16,24 @@
 
 //! Snapshot and restoration commands.
 
+use std::collections::BTreeMap;
 use std::time::Duration;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use serde_json::{self, Value};
 use ethcore_logger::{setup_log, Config as LogConfig};
 use ethcore::snapshot::{Progress, RestorationStatus, SnapshotService as SS};
-use ethcore::snapshot::io::{SnapshotReader, PackedReader, PackedWriter};
+use ethcore::snapshot::io::{SnapshotReader, ChainedReader, PackedReader, PackedWriter};
 use ethcore::snapshot::service::Service as SnapshotService;
+use ethcore::snapshot::verify::{self, VerificationReport};
 use ethcore::service::ClientService;
 use ethcore::client::{Mode, DatabaseCompactionProfile, VMType};
 use ethcore::miner::Miner;
 use ethcore::ids::BlockID;
 
 use cache::CacheConfig;
-use params::{SpecType, Pruning, Switch, tracing_switch_to_bool};
+use params::{SpecType, Pruning, Switch, tracing_switch_to_bool, fatdb_switch_to_bool};
 use helpers::{to_client_config, execute_upgrades};
 use dir::Directories;
 use user_defaults::UserDefaults;
@@ -44,7 +47,9 @@ pub enum Kind {
 	/// Take a snapshot.
 	Take,
 	/// Restore a snapshot.
-	Restore
+	Restore,
+	/// Verify a snapshot without restoring it.
+	Verify,
 }
 
 /// Command for snapshot creation or restoration.
@@ -118,6 +123,23 @@ fn restore_using<R: SnapshotReader>(snapshot: Arc<SnapshotService>, reader: &R,
 	}
 }
 
+// format a verification report as JSON.
+fn report_to_json(report: &VerificationReport) -> String {
+	let mut map = BTreeMap::new();
+	map.insert("valid".to_owned(), Value::Bool(report.is_valid()));
+	map.insert("blockNumber".to_owned(), Value::U64(report.block_number));
+	map.insert("blockHash".to_owned(), Value::String(format!("0x{:?}", report.block_hash)));
+	map.insert("stateRoot".to_owned(), Value::String(format!("0x{:?}", report.state_root)));
+	map.insert("rebuiltStateRoot".to_owned(), Value::String(format!("0x{:?}", report.rebuilt_state_root)));
+	map.insert("stateChunks".to_owned(), Value::U64(report.state_chunks as u64));
+	map.insert("blockChunks".to_owned(), Value::U64(report.block_chunks as u64));
+	map.insert("blocks".to_owned(), Value::U64(report.blocks));
+	map.insert("firstBlock".to_owned(), report.first_block.map_or(Value::Null, Value::U64));
+	map.insert("errors".to_owned(), Value::Array(report.errors.iter().map(|e| Value::String(format!("{}", e))).collect()));
+
+	serde_json::to_string_pretty(&Value::Object(map)).expect("serialization of a JSON value cannot fail; qed")
+}
+
 impl SnapshotCommand {
 	// shared portion of snapshot commands: start the client service
 	fn start_service(self) -> Result<(ClientService, Arc<PanicHandler>), String> {
@@ -150,6 +172,9 @@ impl SnapshotCommand {
 		// select pruning algorithm
 		let algorithm = self.pruning.to_algorithm(&user_defaults);
 
+		// check if fatdb is on
+		let fat_db = try!(fatdb_switch_to_bool(false, &user_defaults, algorithm));
+
 		// prepare client and snapshot paths.
 		let client_path = db_dirs.client_path(algorithm);
 		let snapshot_path = db_dirs.snapshot_path();
@@ -158,7 +183,7 @@ impl SnapshotCommand {
 		try!(execute_upgrades(&db_dirs, algorithm, self.compaction.compaction_profile()));
 
 		// prepare client config
-		let client_config = to_client_config(&self.cache_config, self.mode, tracing, self.compaction, self.wal, VMType::default(), "".into(), algorithm);
+		let client_config = to_client_config(&self.cache_config, self.mode, tracing, self.compaction, self.wal, VMType::default(), "".into(), algorithm, fat_db);
 
 		let service = try!(ClientService::start(
 			client_config,
@@ -196,8 +221,14 @@ impl SnapshotCommand {
 
 			// attempting restoration with recovery will lead to deadlock
 			// as we currently hold a read lock on the service's reader.
-			match *snapshot.reader() {
-				Some(ref reader) => try!(restore_using(snapshot.clone(), reader, false)),
+			let reader = snapshot.reader();
+			let history = snapshot.history();
+			match *reader {
+				Some(ref reader) => {
+					// chunks shared with older snapshots are read from them.
+					let reader = ChainedReader::new(reader, history.iter().map(|r| r as &SnapshotReader).collect());
+					try!(restore_using(snapshot.clone(), &reader, false))
+				}
 				None => return Err("No local snapshot found.".into()),
 			}
 		}
@@ -205,6 +236,24 @@ impl SnapshotCommand {
 		Ok(())
 	}
 
+	/// Verify a snapshot file without touching the client database.
+	pub fn verify(self) -> Result<String, String> {
+		let file_path = try!(self.file_path.clone().ok_or("No file path provided.".to_owned()));
+		let _logger = setup_log(&self.logger_config);
+
+		let reader = try!(PackedReader::new(Path::new(&file_path))
+			.map_err(|e| format!("Couldn't open snapshot file: {}", e))
+			.and_then(|x| x.ok_or("Snapshot file has invalid format.".to_owned())));
+
+		info!("Verifying snapshot at block #{} (0x{:?})", reader.manifest().block_number, reader.manifest().block_hash);
+
+		let report = try!(verify::verify(&reader).map_err(|e| format!("Failed to verify snapshot: {}", e)));
+		match report.is_valid() {
+			true => Ok(report_to_json(&report)),
+			false => Err(report_to_json(&report)),
+		}
+	}
+
 	/// Take a snapshot from the head of the chain.
 	pub fn take_snapshot(self) -> Result<(), String> {
 		let file_path = try!(self.file_path.clone().ok_or("No file path provided.".to_owned()));
@@ -236,7 +285,7 @@ impl SnapshotCommand {
 			}
  		});
 
-		if let Err(e) = service.client().take_snapshot(writer, block_at, &*progress) {
+		if let Err(e) = service.client().take_snapshot(writer, block_at, None, &*progress) {
 			let _ = ::std::fs::remove_file(&file_path);
 			return Err(format!("Encountered fatal error while creating snapshot: {}", e));
 		}
@@ -255,6 +304,7 @@ pub fn execute(cmd: SnapshotCommand) -> Result<String, String> {
 	match cmd.kind {
 		Kind::Take => try!(cmd.take_snapshot()),
 		Kind::Restore => try!(cmd.restore()),
+		Kind::Verify => return cmd.verify(),
 	}
 
 	Ok(String::new())
@@ -19,7 +19,7 @@ use util::bytes::ToPretty;
 use ethcore::miner::MinerService;
 use ethcore::client::MiningBlockChainClient;
 use ethcore::transaction::{Action, SignedTransaction, Transaction};
-use ethcore::account_provider::AccountProvider;
+use ethcore::account_provider::{AccountProvider, Signature};
 use jsonrpc_core::{Error, Value, to_value};
 use v1::helpers::TransactionRequest;
 use v1::types::{H256 as RpcH256, H520 as RpcH520};
@@ -73,6 +73,27 @@ pub fn unlock_sign_and_dispatch<C, M>(client: &C, miner: &M, request: Transactio
 	dispatch_transaction(&*client, &*miner, signed_transaction).map(to_value)
 }
 
+/// Signs `hash` with the first of `passwords` which unlocks the account.
+pub fn sign_with_any_password(account_provider: &AccountProvider, address: Address, hash: H256, passwords: &[String]) -> Option<Signature> {
+	passwords.iter()
+		.filter_map(|pass| account_provider.sign_with_password(address, pass.clone(), hash).ok())
+		.next()
+}
+
+/// Signs the transaction with the first of `passwords` which unlocks the sender and dispatches it.
+/// Returns `None` if none of the passwords unlocks the sender.
+pub fn sign_with_any_password_and_dispatch<C, M>(client: &C, miner: &M, request: TransactionRequest, account_provider: &AccountProvider, passwords: &[String]) -> Option<Result<RpcH256, Error>>
+	where C: MiningBlockChainClient, M: MinerService {
+
+	let address = request.from;
+	let t = prepare_transaction(client, miner, request);
+	sign_with_any_password(account_provider, address, t.hash(), passwords).map(|signature| {
+		let signed_transaction = t.with_signature(signature);
+		trace!(target: "miner", "send_transaction: dispatching tx: {}", ::rlp::encode(&signed_transaction).to_vec().pretty());
+		dispatch_transaction(&*client, &*miner, signed_transaction)
+	})
+}
+
 pub fn sign_and_dispatch<C, M>(client: &C, miner: &M, request: TransactionRequest, account_provider: &AccountProvider, address: Address) -> Result<Value, Error>
 	where C: MiningBlockChainClient, M: MinerService {
 
@@ -23,6 +23,7 @@ macro_rules! rpc_unimplemented {
 use std::fmt;
 use ethcore::error::Error as EthcoreError;
 use ethcore::account_provider::{Error as AccountError};
+use ethcore::client::StateError;
 use fetch::FetchError;
 use jsonrpc_core::{Error, ErrorCode, Value};
 
@@ -43,6 +44,7 @@ mod codes {
 	pub const REQUEST_NOT_FOUND: i64 = -32042;
 	pub const COMPILATION_ERROR: i64 = -32050;
 	pub const FETCH_ERROR: i64 = -32060;
+	pub const NETWORK_ERROR: i64 = -32070;
 }
 
 pub fn unimplemented() -> Error {
@@ -93,6 +95,14 @@ pub fn compilation<T: fmt::Debug>(error: T) -> Error {
 	}
 }
 
+pub fn network_error<T: fmt::Debug>(error: &str, details: T) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::NETWORK_ERROR),
+		message: error.into(),
+		data: Some(Value::String(format!("{:?}", details))),
+	}
+}
+
 pub fn internal<T: fmt::Debug>(error: &str, data: T) -> Error {
 	Error {
 		code: ErrorCode::InternalError,
@@ -112,7 +122,47 @@ pub fn invalid_params<T: fmt::Debug>(param: &str, details: T) -> Error {
 pub fn state_pruned() -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::UNSUPPORTED_REQUEST),
-		message: "This request is not supported because your node is running with state pruning. Run with --pruning=archive.".into(),
+		message: "This request is not supported because your node is running with state pruning. Run with --pruning=archive or increase --pruning-history.".into(),
+		data: None
+	}
+}
+
+pub fn unknown_block() -> Error {
+	Error {
+		code: ErrorCode::InvalidParams,
+		message: "Unknown block.".into(),
+		data: None
+	}
+}
+
+pub fn from_state_error(error: StateError) -> Error {
+	match error {
+		StateError::StatePruned => state_pruned(),
+		StateError::UnknownBlock => unknown_block(),
+		StateError::MissingTrieNode(hash) => internal("Database is missing a trie node", hash),
+	}
+}
+
+pub fn history_pruned() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::UNSUPPORTED_REQUEST),
+		message: "Block body and receipts are not available because your node is running with ancient block pruning. Run without --block-history.".into(),
+		data: None
+	}
+}
+
+pub fn address_index_disabled() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::UNSUPPORTED_REQUEST),
+		message: "Transactions are not indexed by address. Run with --address-index to enable the index.".into(),
+		data: None
+	}
+}
+
+pub fn address_index_incomplete(unindexed: u64) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::UNSUPPORTED_REQUEST),
+		message: format!("Transactions are still being indexed by address. {} blocks left to index.", unindexed),
 		data: None
 	}
 }
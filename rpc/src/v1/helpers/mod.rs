@@ -28,11 +28,13 @@ mod poll_filter;
 mod requests;
 mod signer;
 mod signing_queue;
+mod signing_policy;
 mod network_settings;
 
 pub use self::poll_manager::PollManager;
 pub use self::poll_filter::{PollFilter, limit_logs};
 pub use self::requests::{TransactionRequest, FilledTransactionRequest, ConfirmationRequest, ConfirmationPayload, CallRequest};
 pub use self::signing_queue::{ConfirmationsQueue, ConfirmationPromise, ConfirmationResult, SigningQueue, QueueEvent};
-pub use self::signer::SignerService;
+pub use self::signer::{SignerService, SignerTokens, SignerToken, TokenScope};
+pub use self::signing_policy::{SigningPolicy, SigningRule, RuleAction, PolicyDecision};
 pub use self::network_settings::NetworkSettings;
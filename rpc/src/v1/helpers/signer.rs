@@ -17,27 +17,98 @@
 use std::sync::Arc;
 use std::ops::Deref;
 use v1::helpers::signing_queue::{ConfirmationsQueue};
+use v1::helpers::signing_policy::SigningPolicy;
+
+/// Scope of Signer UI authorization token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenScope {
+	/// Token allows only to view pending requests.
+	View,
+	/// Token allows to confirm and reject requests and to manage the signer.
+	Confirm,
+}
+
+/// Signer UI authorization token details (without the secret part).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignerToken {
+	/// Public identifier of the token (prefix of the hash of the code).
+	pub id: String,
+	/// Name of the token.
+	pub name: String,
+	/// Scope of the token.
+	pub scope: TokenScope,
+	/// Creation time (seconds since epoch).
+	pub created_at: u64,
+	/// Expiry time (seconds since epoch).
+	pub expires_at: Option<u64>,
+	/// Time the token was last used (seconds since epoch).
+	pub last_used_at: Option<u64>,
+}
+
+/// Store of Signer UI authorization tokens.
+pub trait SignerTokens: Send + Sync {
+	/// Generates new token.
+	fn generate(&self) -> Result<String, String>;
+
+	/// Lists all tokens.
+	fn list(&self) -> Result<Vec<SignerToken>, String>;
+
+	/// Revokes tokens with given name or id. Returns `false` if there was no such token.
+	fn revoke(&self, name_or_id: &str) -> Result<bool, String>;
+}
+
+impl<F> SignerTokens for F where F: Fn() -> Result<String, String> + Send + Sync {
+	fn generate(&self) -> Result<String, String> {
+		self()
+	}
+
+	fn list(&self) -> Result<Vec<SignerToken>, String> {
+		Ok(Vec::new())
+	}
+
+	fn revoke(&self, _name_or_id: &str) -> Result<bool, String> {
+		Ok(false)
+	}
+}
 
 /// Manages communication with Signer crate
 pub struct SignerService {
 	queue: Arc<ConfirmationsQueue>,
-	generate_new_token: Box<Fn() -> Result<String, String> + Send + Sync + 'static>,
+	policy: SigningPolicy,
+	tokens: Box<SignerTokens>,
 }
 
 impl SignerService {
 
-	/// Creates new Signer Service given function to generate new tokens.
-	pub fn new<F>(new_token: F) -> Self
-		where F: Fn() -> Result<String, String> + Send + Sync + 'static {
+	/// Creates new Signer Service given store of tokens.
+	pub fn new<T>(tokens: T) -> Self
+		where T: SignerTokens + 'static {
+		SignerService::with_policy(tokens, SigningPolicy::default())
+	}
+
+	/// Creates new Signer Service with auto-approval rules given by `policy`.
+	pub fn with_policy<T>(tokens: T, policy: SigningPolicy) -> Self
+		where T: SignerTokens + 'static {
 		SignerService {
 			queue: Arc::new(ConfirmationsQueue::default()),
-			generate_new_token: Box::new(new_token),
+			policy: policy,
+			tokens: Box::new(tokens),
 		}
 	}
 
 	/// Generates new token.
 	pub fn generate_token(&self) -> Result<String, String> {
-		(self.generate_new_token)()
+		self.tokens.generate()
+	}
+
+	/// Lists all authorization tokens.
+	pub fn tokens(&self) -> Result<Vec<SignerToken>, String> {
+		self.tokens.list()
+	}
+
+	/// Revokes authorization tokens with given name or id.
+	pub fn revoke_token(&self, name_or_id: &str) -> Result<bool, String> {
+		self.tokens.revoke(name_or_id)
 	}
 
 	/// Returns a reference to `ConfirmationsQueue`
@@ -45,6 +116,11 @@ impl SignerService {
 		self.queue.clone()
 	}
 
+	/// Returns a reference to `SigningPolicy`
+	pub fn policy(&self) -> &SigningPolicy {
+		&self.policy
+	}
+
 	#[cfg(test)]
 	/// Creates new Signer Service for tests.
 	pub fn new_test() -> Self {
@@ -0,0 +1,486 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signing policy: rules that auto-approve or auto-reject confirmation requests.
+
+use std::{fs, io};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::collections::HashMap;
+use serde_json;
+use time;
+use util::{Address, U256, H256, Uint, Bytes, Mutex, RwLock};
+use v1::helpers::FilledTransactionRequest;
+use v1::types::{SigningRule as RpcSigningRule, SigningRuleAction, SigningAuditEntry};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Action taken when a rule matches a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleAction {
+	/// Sign and dispatch the request without asking.
+	Approve,
+	/// Reject the request without asking.
+	Reject,
+}
+
+/// Auto-approval rule. All conditions that are set have to be satisfied for the rule to match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningRule {
+	/// Unique name of the rule.
+	pub name: String,
+	/// Action taken when the rule matches.
+	pub action: RuleAction,
+	/// Sender the rule applies to.
+	pub sender: Option<Address>,
+	/// Recipients allowlist. Contract creations never match.
+	pub recipients: Option<Vec<Address>>,
+	/// Maximal value of a single transaction.
+	pub max_value: Option<U256>,
+	/// Maximal gas of a single transaction.
+	pub max_gas: Option<U256>,
+	/// Maximal value approved by this rule per sender per day.
+	pub daily_limit: Option<U256>,
+	/// Allowed method selectors (first 4 bytes of transaction data).
+	pub methods: Option<Vec<Bytes>>,
+}
+
+impl SigningRule {
+	/// Checks all conditions of this rule except for the daily limit.
+	fn matches(&self, request: &FilledTransactionRequest) -> bool {
+		if self.sender.map_or(false, |sender| sender != request.from) {
+			return false;
+		}
+		if let Some(ref recipients) = self.recipients {
+			match request.to {
+				Some(ref to) if recipients.contains(to) => {},
+				_ => return false,
+			}
+		}
+		if self.max_value.map_or(false, |max| request.value > max) {
+			return false;
+		}
+		if self.max_gas.map_or(false, |max| request.gas > max) {
+			return false;
+		}
+		if let Some(ref methods) = self.methods {
+			if !methods.iter().any(|m| request.data.starts_with(m)) {
+				return false;
+			}
+		}
+		true
+	}
+
+	/// Checks if the rule applies to signing a message by `address`.
+	/// Only rules without conditions on transaction fields apply, since the signed hash
+	/// may as well be a hash of a transaction breaking them.
+	fn matches_sign(&self, address: &Address) -> bool {
+		self.sender.map_or(true, |sender| sender == *address)
+			&& self.recipients.is_none()
+			&& self.max_value.is_none()
+			&& self.max_gas.is_none()
+			&& self.daily_limit.is_none()
+			&& self.methods.is_none()
+	}
+}
+
+/// Decision taken by the policy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyDecision {
+	/// Request approved by rule with given name.
+	Approve(String),
+	/// Request rejected by rule with given name.
+	Reject(String),
+	/// No rule matched, request has to be confirmed manually.
+	Ask,
+}
+
+/// Ordered set of rules evaluated against every transaction and message signing request before
+/// it enters the confirmations queue. First matching rule wins. Every decision is appended to the audit log.
+pub struct SigningPolicy {
+	rules: RwLock<Vec<SigningRule>>,
+	/// Value spent today per (rule, sender): (day, value).
+	spent: Mutex<HashMap<(String, Address), (u64, U256)>>,
+	rules_path: Option<PathBuf>,
+	audit_path: Option<PathBuf>,
+	passwords: Vec<String>,
+}
+
+impl Default for SigningPolicy {
+	fn default() -> Self {
+		SigningPolicy {
+			rules: RwLock::new(Vec::new()),
+			spent: Mutex::new(HashMap::new()),
+			rules_path: None,
+			audit_path: None,
+			passwords: Vec::new(),
+		}
+	}
+}
+
+fn today() -> u64 {
+	time::get_time().sec as u64 / SECONDS_PER_DAY
+}
+
+// audit log entry of a request without a recipient, value and gas.
+fn audit_entry(rule: &str, action: RuleAction, from: Address, result: Result<H256, String>) -> SigningAuditEntry {
+	let (hash, error) = match result {
+		Ok(hash) => (Some(hash.into()), None),
+		Err(e) => (None, Some(e)),
+	};
+	SigningAuditEntry {
+		timestamp: time::get_time().sec as u64,
+		rule: rule.to_owned(),
+		action: match action {
+			RuleAction::Approve => SigningRuleAction::Approve,
+			RuleAction::Reject => SigningRuleAction::Reject,
+		},
+		from: from.into(),
+		to: None,
+		value: U256::zero().into(),
+		gas: U256::zero().into(),
+		hash: hash,
+		error: error,
+	}
+}
+
+impl SigningPolicy {
+	/// Loads rules from `rules_path` (if it exists) and restores today's spending from `audit_path`.
+	/// `passwords` are used to sign auto-approved requests.
+	pub fn load(rules_path: PathBuf, audit_path: PathBuf, passwords: Vec<String>) -> io::Result<Self> {
+		let rules = match fs::File::open(&rules_path) {
+			Ok(file) => {
+				let rules: Vec<RpcSigningRule> = try!(serde_json::from_reader(file)
+					.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid signing rules: {:?}", e))));
+				rules.into_iter().map(Into::into).collect()
+			},
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+			Err(e) => return Err(e),
+		};
+
+		let policy = SigningPolicy {
+			rules: RwLock::new(rules),
+			spent: Mutex::new(HashMap::new()),
+			rules_path: Some(rules_path),
+			audit_path: Some(audit_path),
+			passwords: passwords,
+		};
+		try!(policy.restore_spending());
+		Ok(policy)
+	}
+
+	#[cfg(test)]
+	/// Creates new in-memory policy using given passwords.
+	pub fn new_test(passwords: Vec<String>) -> Self {
+		SigningPolicy {
+			passwords: passwords,
+			..Default::default()
+		}
+	}
+
+	fn restore_spending(&self) -> io::Result<()> {
+		let path = match self.audit_path {
+			Some(ref path) => path,
+			None => return Ok(()),
+		};
+		let file = match fs::File::open(path) {
+			Ok(file) => file,
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+			Err(e) => return Err(e),
+		};
+
+		let today = today();
+		let mut spent = self.spent.lock();
+		for line in BufReader::new(file).lines() {
+			let line = try!(line);
+			let entry: SigningAuditEntry = match serde_json::from_str(&line) {
+				Ok(entry) => entry,
+				Err(_) => {
+					warn!(target: "signer", "Skipping invalid audit log entry: {}", line);
+					continue;
+				}
+			};
+			if entry.action != SigningRuleAction::Approve || entry.hash.is_none() || entry.timestamp / SECONDS_PER_DAY != today {
+				continue;
+			}
+			let value: U256 = entry.value.into();
+			let total = spent.entry((entry.rule, entry.from.into())).or_insert((today, U256::zero()));
+			total.1 = match total.1.overflowing_add(value) {
+				(sum, false) => sum,
+				(_, true) => U256::max_value(),
+			};
+		}
+		Ok(())
+	}
+
+	/// Returns all rules in evaluation order.
+	pub fn rules(&self) -> Vec<SigningRule> {
+		self.rules.read().clone()
+	}
+
+	/// Adds a rule at the end of the list, replacing a rule with the same name.
+	pub fn add_rule(&self, rule: SigningRule) -> io::Result<()> {
+		{
+			let mut rules = self.rules.write();
+			match rules.iter().position(|r| r.name == rule.name) {
+				Some(idx) => rules[idx] = rule,
+				None => rules.push(rule),
+			}
+		}
+		self.save()
+	}
+
+	/// Removes rule with given name. Returns `false` if there was no such rule.
+	pub fn remove_rule(&self, name: &str) -> io::Result<bool> {
+		let removed = {
+			let mut rules = self.rules.write();
+			let len = rules.len();
+			rules.retain(|r| r.name != name);
+			len != rules.len()
+		};
+		if removed {
+			try!(self.save());
+		}
+		Ok(removed)
+	}
+
+	fn save(&self) -> io::Result<()> {
+		if let Some(ref path) = self.rules_path {
+			let rules = self.rules().into_iter().map(RpcSigningRule::from).collect::<Vec<_>>();
+			let serialized = try!(serde_json::to_string_pretty(&rules)
+				.map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{:?}", e))));
+			let mut file = try!(fs::File::create(path));
+			try!(file.write_all(serialized.as_bytes()));
+		}
+		Ok(())
+	}
+
+	/// Passwords that can be used to sign auto-approved requests.
+	pub fn passwords(&self) -> &[String] {
+		&self.passwords
+	}
+
+	/// Evaluates rules against the request.
+	/// Value of approved requests is reserved against the daily limit of the matching rule.
+	pub fn evaluate(&self, request: &FilledTransactionRequest) -> PolicyDecision {
+		let rules = self.rules.read();
+		let mut spent = self.spent.lock();
+		let today = today();
+
+		for rule in rules.iter().filter(|r| r.matches(request)) {
+			match rule.action {
+				RuleAction::Reject => return PolicyDecision::Reject(rule.name.clone()),
+				RuleAction::Approve => {
+					if let Some(limit) = rule.daily_limit {
+						let total = spent.entry((rule.name.clone(), request.from)).or_insert((today, U256::zero()));
+						if total.0 != today {
+							*total = (today, U256::zero());
+						}
+						match total.1.overflowing_add(request.value) {
+							(new_total, false) if new_total <= limit => total.1 = new_total,
+							_ => continue,
+						}
+					}
+					return PolicyDecision::Approve(rule.name.clone());
+				},
+			}
+		}
+		PolicyDecision::Ask
+	}
+
+	/// Evaluates rules against a request to sign a message by `address`.
+	pub fn evaluate_sign(&self, address: &Address) -> PolicyDecision {
+		match self.rules.read().iter().find(|r| r.matches_sign(address)) {
+			Some(rule) if rule.action == RuleAction::Approve => PolicyDecision::Approve(rule.name.clone()),
+			Some(rule) => PolicyDecision::Reject(rule.name.clone()),
+			None => PolicyDecision::Ask,
+		}
+	}
+
+	/// Returns value reserved by `evaluate` for a request that could not be dispatched.
+	pub fn refund(&self, rule: &str, request: &FilledTransactionRequest) {
+		if let Some(total) = self.spent.lock().get_mut(&(rule.to_owned(), request.from)) {
+			total.1 = if total.1 > request.value { total.1 - request.value } else { U256::zero() };
+		}
+	}
+
+	/// Appends decision taken by `rule` to the audit log.
+	pub fn record(&self, rule: &str, action: RuleAction, request: &FilledTransactionRequest, result: Result<H256, String>) {
+		let mut entry = audit_entry(rule, action, request.from, result);
+		entry.to = request.to.map(Into::into);
+		entry.value = request.value.into();
+		entry.gas = request.gas.into();
+		self.append_audit(entry);
+	}
+
+	/// Appends decision taken by `rule` on a request to sign a message by `address` to the audit log.
+	/// `result` holds the hash of the signed message.
+	pub fn record_sign(&self, rule: &str, action: RuleAction, address: Address, result: Result<H256, String>) {
+		self.append_audit(audit_entry(rule, action, address, result));
+	}
+
+	fn append_audit(&self, entry: SigningAuditEntry) {
+		info!(target: "signer", "Signing policy: rule {} {:?} request from {:?}", entry.rule, entry.action, entry.from);
+
+		if let Some(ref path) = self.audit_path {
+			let res = serde_json::to_string(&entry)
+				.map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{:?}", e)))
+				.and_then(|line| {
+					let mut file = try!(fs::OpenOptions::new().create(true).append(true).open(path));
+					writeln!(file, "{}", line)
+				});
+			if let Err(e) = res {
+				warn!(target: "signer", "Unable to write signing policy audit log: {}", e);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+	use util::{Address, U256};
+	use devtools::RandomTempPath;
+	use v1::helpers::FilledTransactionRequest;
+	use super::{SigningPolicy, SigningRule, RuleAction, PolicyDecision};
+
+	fn request(value: u64) -> FilledTransactionRequest {
+		FilledTransactionRequest {
+			from: Address::from(1),
+			to: Some(Address::from(2)),
+			gas_price: 0.into(),
+			gas: 21_000.into(),
+			value: value.into(),
+			data: vec![0xa9, 0x05, 0x9c, 0xbb, 0x00],
+			nonce: None,
+		}
+	}
+
+	fn rule(name: &str, action: RuleAction) -> SigningRule {
+		SigningRule {
+			name: name.into(),
+			action: action,
+			sender: None,
+			recipients: None,
+			max_value: None,
+			max_gas: None,
+			daily_limit: None,
+			methods: None,
+		}
+	}
+
+	#[test]
+	fn should_ask_when_no_rule_matches() {
+		// given
+		let policy = SigningPolicy::default();
+		let mut r = rule("other", RuleAction::Approve);
+		r.sender = Some(Address::from(5));
+		policy.add_rule(r).unwrap();
+
+		// when
+		let decision = policy.evaluate(&request(10));
+
+		// then
+		assert_eq!(decision, PolicyDecision::Ask);
+	}
+
+	#[test]
+	fn should_apply_only_sender_rules_to_signing() {
+		// given
+		let policy = SigningPolicy::default();
+		let mut r1 = rule("limited", RuleAction::Approve);
+		r1.sender = Some(Address::from(1));
+		r1.max_value = Some(100.into());
+		policy.add_rule(r1).unwrap();
+		let mut r2 = rule("signer", RuleAction::Approve);
+		r2.sender = Some(Address::from(1));
+		policy.add_rule(r2).unwrap();
+
+		// when
+		let decision = policy.evaluate_sign(&Address::from(1));
+		let other = policy.evaluate_sign(&Address::from(2));
+
+		// then
+		assert_eq!(decision, PolicyDecision::Approve("signer".into()));
+		assert_eq!(other, PolicyDecision::Ask);
+	}
+
+	#[test]
+	fn should_use_first_matching_rule() {
+		// given
+		let policy = SigningPolicy::default();
+		let mut r1 = rule("small", RuleAction::Approve);
+		r1.max_value = Some(100.into());
+		r1.methods = Some(vec![vec![0xa9, 0x05, 0x9c, 0xbb]]);
+		policy.add_rule(r1).unwrap();
+		let mut r2 = rule("recipient", RuleAction::Reject);
+		r2.recipients = Some(vec![Address::from(2)]);
+		policy.add_rule(r2).unwrap();
+
+		// when
+		let small = policy.evaluate(&request(100));
+		let big = policy.evaluate(&request(101));
+
+		// then
+		assert_eq!(small, PolicyDecision::Approve("small".into()));
+		assert_eq!(big, PolicyDecision::Reject("recipient".into()));
+	}
+
+	#[test]
+	fn should_enforce_daily_limit() {
+		// given
+		let policy = SigningPolicy::default();
+		let mut r = rule("daily", RuleAction::Approve);
+		r.daily_limit = Some(U256::from(150));
+		policy.add_rule(r).unwrap();
+
+		// when
+		let first = policy.evaluate(&request(100));
+		let second = policy.evaluate(&request(100));
+		policy.refund("daily", &request(100));
+		let third = policy.evaluate(&request(100));
+
+		// then
+		assert_eq!(first, PolicyDecision::Approve("daily".into()));
+		assert_eq!(second, PolicyDecision::Ask);
+		assert_eq!(third, PolicyDecision::Approve("daily".into()));
+	}
+
+	#[test]
+	fn should_persist_rules_and_restore_spending() {
+		// given
+		let temp = RandomTempPath::new();
+		fs::create_dir_all(temp.as_path()).unwrap();
+		let rules_path = temp.as_path().join("rules.json");
+		let audit_path = temp.as_path().join("audit.log");
+		{
+			let policy = SigningPolicy::load(rules_path.clone(), audit_path.clone(), vec![]).unwrap();
+			let mut r = rule("daily", RuleAction::Approve);
+			r.daily_limit = Some(U256::from(150));
+			policy.add_rule(r).unwrap();
+			assert_eq!(policy.evaluate(&request(100)), PolicyDecision::Approve("daily".into()));
+			policy.record("daily", RuleAction::Approve, &request(100), Ok(5.into()));
+		}
+
+		// when
+		let policy = SigningPolicy::load(rules_path, audit_path, vec![]).unwrap();
+
+		// then
+		assert_eq!(policy.rules().len(), 1);
+		assert_eq!(policy.evaluate(&request(100)), PolicyDecision::Ask);
+		assert_eq!(policy.evaluate(&request(50)), PolicyDecision::Approve("daily".into()));
+	}
+}
@@ -32,7 +32,7 @@ use util::sha3::*;
 use util::{FromHex, Mutex};
 use rlp::{self, UntrustedRlp, View};
 use ethcore::account_provider::AccountProvider;
-use ethcore::client::{MiningBlockChainClient, BlockID, TransactionID, UncleID};
+use ethcore::client::{MiningBlockChainClient, BlockID, TransactionID, UncleID, StateError};
 use ethcore::header::Header as BlockHeader;
 use ethcore::block::IsBlock;
 use ethcore::views::*;
@@ -44,7 +44,7 @@ use self::ethash::SeedHashCompute;
 use v1::traits::Eth;
 use v1::types::{
 	Block, BlockTransactions, BlockNumber, Bytes, SyncStatus, SyncInfo,
-	Transaction, CallRequest, Index, Filter, Log, Receipt, Work,
+	Transaction, CallRequest, Index, Filter, Log, Receipt, Work, AccountProof,
 	H64 as RpcH64, H256 as RpcH256, H160 as RpcH160, U256 as RpcU256,
 };
 use v1::helpers::{CallRequest as CRequest, errors, limit_logs};
@@ -104,9 +104,17 @@ impl<C, S: ?Sized, M, EM> EthClient<C, S, M, EM> where
 		}
 	}
 
+	fn raw_block(&self, id: BlockID) -> Result<Option<Vec<u8>>, Error> {
+		let client = take_weak!(self.client);
+		match client.block(id.clone()) {
+			None if client.is_pruned(id) => Err(errors::history_pruned()),
+			block => Ok(block),
+		}
+	}
+
 	fn block(&self, id: BlockID, include_txs: bool) -> Result<Option<Block>, Error> {
 		let client = take_weak!(self.client);
-		match (client.block(id.clone()), client.block_total_difficulty(id)) {
+		match (try!(self.raw_block(id.clone())), client.block_total_difficulty(id)) {
 			(Some(bytes), Some(total_difficulty)) => {
 				let block_view = BlockView::new(&bytes);
 				let view = block_view.header_view();
@@ -142,9 +150,13 @@ impl<C, S: ?Sized, M, EM> EthClient<C, S, M, EM> where
 	}
 
 	fn transaction(&self, id: TransactionID) -> Result<Option<Transaction>, Error> {
-		match take_weak!(self.client).transaction(id) {
+		let client = take_weak!(self.client);
+		match client.transaction(id.clone()) {
 			Some(t) => Ok(Some(Transaction::from(t))),
-			None => Ok(None),
+			None => match id {
+				TransactionID::Location(block, _) if client.is_pruned(block) => Err(errors::history_pruned()),
+				_ => Ok(None),
+			},
 		}
 	}
 
@@ -198,6 +210,11 @@ impl<C, S: ?Sized, M, EM> EthClient<C, S, M, EM> where
 	}
 }
 
+/// Describes why state of given block could not be queried.
+fn state_error<C>(client: &C, id: BlockID) -> Error where C: MiningBlockChainClient {
+	errors::from_state_error(client.state_available(id).err().unwrap_or(StateError::StatePruned))
+}
+
 pub fn pending_logs<M>(miner: &M, filter: &EthcoreFilter) -> Vec<Log> where M: MinerService {
 	let receipts = miner.pending_receipts();
 
@@ -321,9 +338,13 @@ impl<C, S: ?Sized, M, EM> Eth for EthClient<C, S, M, EM> where
 		let address = address.into();
 		match num.0 {
 			BlockNumber::Pending => Ok(take_weak!(self.miner).balance(&*take_weak!(self.client), &address).into()),
-			id => match take_weak!(self.client).balance(&address, id.into()) {
-				Some(balance) => Ok(balance.into()),
-				None => Err(errors::state_pruned()),
+			id => {
+				let client = take_weak!(self.client);
+				let id = id.into();
+				match client.balance(&address, id.clone()) {
+					Some(balance) => Ok(balance.into()),
+					None => Err(state_error(&*client, id)),
+				}
 			}
 		}
 	}
@@ -334,9 +355,13 @@ impl<C, S: ?Sized, M, EM> Eth for EthClient<C, S, M, EM> where
 		let position: U256 = RpcU256::into(pos);
 		match num.0 {
 			BlockNumber::Pending => Ok(take_weak!(self.miner).storage_at(&*take_weak!(self.client), &address, &H256::from(position)).into()),
-			id => match take_weak!(self.client).storage_at(&address, &H256::from(position), id.into()) {
-				Some(s) => Ok(s.into()),
-				None => Err(errors::state_pruned()),
+			id => {
+				let client = take_weak!(self.client);
+				let id = id.into();
+				match client.storage_at(&address, &H256::from(position), id.clone()) {
+					Some(s) => Ok(s.into()),
+					None => Err(state_error(&*client, id)),
+				}
 			}
 		}
 	}
@@ -347,9 +372,13 @@ impl<C, S: ?Sized, M, EM> Eth for EthClient<C, S, M, EM> where
 		let address: Address = RpcH160::into(address);
 		match num.0 {
 			BlockNumber::Pending => Ok(take_weak!(self.miner).nonce(&*take_weak!(self.client), &address).into()),
-			id => match take_weak!(self.client).nonce(&address, id.into()) {
-				Some(nonce) => Ok(nonce.into()),
-				None => Err(errors::state_pruned()),
+			id => {
+				let client = take_weak!(self.client);
+				let id = id.into();
+				match client.nonce(&address, id.clone()) {
+					Some(nonce) => Ok(nonce.into()),
+					None => Err(state_error(&*client, id)),
+				}
 			}
 		}
 	}
@@ -357,7 +386,7 @@ impl<C, S: ?Sized, M, EM> Eth for EthClient<C, S, M, EM> where
 	fn block_transaction_count_by_hash(&self, hash: RpcH256) -> Result<Option<RpcU256>, Error> {
 		try!(self.active());
 		Ok(
-			take_weak!(self.client).block(BlockID::Hash(hash.into()))
+			try!(self.raw_block(BlockID::Hash(hash.into())))
 				.map(|bytes| BlockView::new(&bytes).transactions_count().into())
 		)
 	}
@@ -370,7 +399,7 @@ impl<C, S: ?Sized, M, EM> Eth for EthClient<C, S, M, EM> where
 				take_weak!(self.miner).status().transactions_in_pending_block.into()
 			)),
 			_ => Ok(
-				take_weak!(self.client).block(num.into())
+				try!(self.raw_block(num.into()))
 					.map(|bytes| BlockView::new(&bytes).transactions_count().into())
 				)
 		}
@@ -380,7 +409,7 @@ impl<C, S: ?Sized, M, EM> Eth for EthClient<C, S, M, EM> where
 		try!(self.active());
 
 		Ok(
-			take_weak!(self.client).block(BlockID::Hash(hash.into()))
+			try!(self.raw_block(BlockID::Hash(hash.into())))
 				.map(|bytes| BlockView::new(&bytes).uncles_count().into())
 		)
 	}
@@ -391,7 +420,7 @@ impl<C, S: ?Sized, M, EM> Eth for EthClient<C, S, M, EM> where
 		match num {
 			BlockNumber::Pending => Ok(Some(0.into())),
 			_ => Ok(
-				take_weak!(self.client).block(num.into())
+				try!(self.raw_block(num.into()))
 					.map(|bytes| BlockView::new(&bytes).uncles_count().into())
 			),
 		}
@@ -403,13 +432,27 @@ impl<C, S: ?Sized, M, EM> Eth for EthClient<C, S, M, EM> where
 		let address: Address = RpcH160::into(address);
 		match num.0 {
 			BlockNumber::Pending => Ok(take_weak!(self.miner).code(&*take_weak!(self.client), &address).map_or_else(Bytes::default, Bytes::new)),
-			_ => match take_weak!(self.client).code(&address, num.0.into()) {
-				Some(code) => Ok(code.map_or_else(Bytes::default, Bytes::new)),
-				None => Err(errors::state_pruned()),
+			id => {
+				let client = take_weak!(self.client);
+				let id = id.into();
+				match client.code(&address, id.clone()) {
+					Some(code) => Ok(code.map_or_else(Bytes::default, Bytes::new)),
+					None => Err(state_error(&*client, id)),
+				}
 			},
 		}
 	}
 
+	fn proof(&self, address: RpcH160, keys: Vec<RpcH256>, num: Trailing<BlockNumber>) -> Result<AccountProof, Error> {
+		try!(self.active());
+
+		let address: Address = RpcH160::into(address);
+		let keys = keys.into_iter().map(Into::into).collect();
+		take_weak!(self.client).prove_account(&address, keys, num.0.into())
+			.map(Into::into)
+			.map_err(errors::from_state_error)
+	}
+
 	fn block_by_hash(&self, hash: RpcH256, include_txs: bool) -> Result<Option<Block>, Error> {
 		try!(self.active());
 
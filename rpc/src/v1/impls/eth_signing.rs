@@ -23,8 +23,8 @@ use ethcore::client::MiningBlockChainClient;
 use util::{U256, Address, H256, Mutex};
 use transient_hashmap::TransientHashMap;
 use ethcore::account_provider::AccountProvider;
-use v1::helpers::{errors, SigningQueue, ConfirmationPromise, ConfirmationResult, ConfirmationPayload, TransactionRequest as TRequest, FilledTransactionRequest as FilledRequest, SignerService};
-use v1::helpers::dispatch::{default_gas_price, sign_and_dispatch};
+use v1::helpers::{errors, SigningQueue, ConfirmationPromise, ConfirmationResult, ConfirmationPayload, TransactionRequest as TRequest, FilledTransactionRequest as FilledRequest, SignerService, PolicyDecision, RuleAction};
+use v1::helpers::dispatch::{default_gas_price, sign_and_dispatch, sign_with_any_password, sign_with_any_password_and_dispatch};
 use v1::traits::EthSigning;
 use v1::types::{TransactionRequest, H160 as RpcH160, H256 as RpcH256, H520 as RpcH520, U256 as RpcU256, Bytes as RpcBytes};
 
@@ -87,6 +87,22 @@ impl<C, M> EthSigningQueueClient<C, M> where C: MiningBlockChainClient, M: Miner
 			}
 
 			let signer = take_weak!(self.signer);
+			let policy = signer.policy();
+			match policy.evaluate_sign(&address) {
+				PolicyDecision::Approve(rule) => match sign_with_any_password(&*accounts, address, msg, policy.passwords()) {
+					Some(signature) => {
+						policy.record_sign(&rule, RuleAction::Approve, address, Ok(msg));
+						return Ok(DispatchResult::Value(to_value(&RpcH520::from(signature))));
+					},
+					None => warn!(target: "signer", "Signing policy: no valid password for {:?}, asking for confirmation.", address),
+				},
+				PolicyDecision::Reject(rule) => {
+					policy.record_sign(&rule, RuleAction::Reject, address, Err("Rejected by signing policy.".into()));
+					return Err(errors::request_rejected());
+				},
+				PolicyDecision::Ask => {},
+			}
+
 			signer.add_request(ConfirmationPayload::Sign(address, msg))
 				.map(DispatchResult::Promise)
 				.map_err(|_| errors::request_rejected_limit())
@@ -107,6 +123,28 @@ impl<C, M> EthSigningQueueClient<C, M> where C: MiningBlockChainClient, M: Miner
 
 				let signer = take_weak!(self.signer);
 				let request = fill_optional_fields(request, &*client, &*miner);
+				let policy = signer.policy();
+				match policy.evaluate(&request) {
+					PolicyDecision::Approve(rule) => match sign_with_any_password_and_dispatch(&*client, &*miner, request.clone().into(), &*accounts, policy.passwords()) {
+						Some(result) => {
+							if result.is_err() {
+								policy.refund(&rule, &request);
+							}
+							policy.record(&rule, RuleAction::Approve, &request, result.clone().map(Into::into).map_err(|e| e.message));
+							return result.map(|hash| DispatchResult::Value(to_value(&hash)));
+						},
+						None => {
+							policy.refund(&rule, &request);
+							warn!(target: "signer", "Signing policy: no valid password for {:?}, asking for confirmation.", request.from);
+						},
+					},
+					PolicyDecision::Reject(rule) => {
+						policy.record(&rule, RuleAction::Reject, &request, Err("Rejected by signing policy.".into()));
+						return Err(errors::request_rejected());
+					},
+					PolicyDecision::Ask => {},
+				}
+
 				signer.add_request(ConfirmationPayload::Transaction(request))
 					.map(DispatchResult::Promise)
 					.map_err(|_| errors::request_rejected_limit())
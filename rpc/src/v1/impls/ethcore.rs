@@ -28,14 +28,17 @@ use ethkey::{Brain, Generator};
 use ethstore::random_phrase;
 use ethsync::{SyncProvider, ManageNetwork};
 use ethcore::miner::MinerService;
-use ethcore::client::{MiningBlockChainClient};
+use ethcore::client::{MiningBlockChainClient, TransactionID};
 
 use jsonrpc_core::{from_params, to_value, Value, Error, Params, Ready};
 use v1::traits::Ethcore;
-use v1::types::{Bytes, U256, H160, H256, H512, Peers, Transaction};
+use v1::types::{Bytes, U256, H160, H256, H512, Peers, PeerReputation, IpFilter, Traffic, Transaction, BlockNumber, Reorg, TransactionReorgs, AddressTransaction, Index, StratumWorker};
 use v1::helpers::{errors, SigningQueue, SignerService, NetworkSettings};
 use v1::helpers::params::expect_no_params;
 
+/// Maximal number of address transactions returned in a single page.
+const MAX_ADDRESS_TRANSACTIONS: usize = 1000;
+
 /// Ethcore implementation.
 pub struct EthcoreClient<C, M, S: ?Sized, F=FetchClient> where
 	C: MiningBlockChainClient,
@@ -168,13 +171,43 @@ impl<C, M, S: ?Sized, F> Ethcore for EthcoreClient<C, M, S, F> where
 		let sync_status = take_weak!(self.sync).status();
 		let net_config = take_weak!(self.net).network_config();
 
+		let peers = take_weak!(self.sync).peers();
+
 		Ok(to_value(&Peers {
 			active: sync_status.num_active_peers,
 			connected: sync_status.num_peers,
 			max: sync_status.current_max_peers(net_config.min_peers, net_config.max_peers),
+			peers: peers.into_iter().map(Into::into).collect(),
 		}))
 	}
 
+	fn net_peer_reputations(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		try!(expect_no_params(params));
+
+		let reputations = take_weak!(self.net).peer_reputations();
+		Ok(to_value(&reputations.into_iter().map(Into::into).collect::<Vec<PeerReputation>>()))
+	}
+
+	fn net_ip_filter(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		try!(expect_no_params(params));
+		Ok(to_value(&IpFilter::from(take_weak!(self.net).ip_filter())))
+	}
+
+	fn net_traffic(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		try!(expect_no_params(params));
+		Ok(to_value(&Traffic::from(take_weak!(self.net).traffic_stats())))
+	}
+
+	fn stratum_workers(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		try!(expect_no_params(params));
+		let workers = take_weak!(self.miner).stratum_workers();
+		Ok(to_value(&workers.into_iter().map(|(name, stats)| (name, stats.into())).collect::<BTreeMap<String, StratumWorker>>()))
+	}
+
 	fn net_port(&self, params: Params) -> Result<Value, Error> {
 		try!(self.active());
 		try!(expect_no_params(params));
@@ -266,6 +299,46 @@ impl<C, M, S: ?Sized, F> Ethcore for EthcoreClient<C, M, S, F> where
 		Ok(to_value(&take_weak!(self.miner).all_transactions().into_iter().map(Into::into).collect::<Vec<Transaction>>()))
 	}
 
+	fn reorgs(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(BlockNumber, BlockNumber)>(params).and_then(|(from, to)| {
+			match take_weak!(self.client).reorgs(from.into(), to.into()) {
+				Some(reorgs) => Ok(to_value(&reorgs.into_iter().map(Into::into).collect::<Vec<Reorg>>())),
+				None => Ok(Value::Null),
+			}
+		})
+	}
+
+	fn transaction_reorgs(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(H256,)>(params).and_then(|(hash,)| {
+			let client = take_weak!(self.client);
+			let hash = hash.into();
+			let reorgs = client.transaction_reorgs(&hash);
+			Ok(to_value(&TransactionReorgs {
+				retracted: !reorgs.is_empty(),
+				block_hash: client.transaction(TransactionID::Hash(hash)).map(|t| t.block_hash.into()),
+				reorgs: reorgs.into_iter().map(Into::into).collect(),
+			}))
+		})
+	}
+
+	fn transactions_by_address(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(H160, Index, Index)>(params).and_then(|(address, offset, limit)| {
+			let client = take_weak!(self.client);
+			let limit = ::std::cmp::min(limit.value(), MAX_ADDRESS_TRANSACTIONS);
+			let unindexed = client.address_index_unindexed();
+			if unindexed > 0 {
+				return Err(errors::address_index_incomplete(unindexed));
+			}
+			match client.address_transactions(&address.into(), offset.value(), limit) {
+				Some(transactions) => Ok(to_value(&transactions.into_iter().map(Into::into).collect::<Vec<AddressTransaction>>())),
+				None => Err(errors::address_index_disabled()),
+			}
+		})
+	}
+
 	fn hash_content(&self, params: Params, ready: Ready) {
 		let res = self.active().and_then(|_| from_params::<(String,)>(params));
 
@@ -16,14 +16,15 @@
 
 /// Ethcore-specific rpc interface for operations altering the settings.
 use std::sync::{Arc, Weak};
+use util::RotatingLogger;
 use jsonrpc_core::*;
 use ethcore::miner::MinerService;
 use ethcore::client::MiningBlockChainClient;
-use ethsync::ManageNetwork;
+use ethsync::{ManageNetwork, IpFilterSettings, BandwidthLimits};
 use v1::helpers::errors;
 use v1::helpers::params::expect_no_params;
 use v1::traits::EthcoreSet;
-use v1::types::{Bytes, H160, U256};
+use v1::types::{Bytes, H160, H256, U256, BlockNumber};
 
 /// Ethcore-specific rpc interface for operations altering the settings.
 pub struct EthcoreSetClient<C, M> where
@@ -33,17 +34,19 @@ pub struct EthcoreSetClient<C, M> where
 	client: Weak<C>,
 	miner: Weak<M>,
 	net: Weak<ManageNetwork>,
+	logger: Arc<RotatingLogger>,
 }
 
 impl<C, M> EthcoreSetClient<C, M> where
 	C: MiningBlockChainClient,
 	M: MinerService {
 	/// Creates new `EthcoreSetClient`.
-	pub fn new(client: &Arc<C>, miner: &Arc<M>, net: &Arc<ManageNetwork>) -> Self {
+	pub fn new(client: &Arc<C>, miner: &Arc<M>, net: &Arc<ManageNetwork>, logger: Arc<RotatingLogger>) -> Self {
 		EthcoreSetClient {
 			client: Arc::downgrade(client),
 			miner: Arc::downgrade(miner),
 			net: Arc::downgrade(net),
+			logger: logger,
 		}
 	}
 
@@ -54,6 +57,13 @@ impl<C, M> EthcoreSetClient<C, M> where
 	}
 }
 
+/// Checks that the peer is given by its node id (public key), optionally prefixed with `0x`.
+fn check_peer_id(peer: &str) -> Result<(), Error> {
+	peer.trim_left_matches("0x").parse::<::util::H512>()
+		.map(|_| ())
+		.map_err(|e| errors::invalid_params("Peer id", e))
+}
+
 impl<C, M> EthcoreSet for EthcoreSetClient<C, M> where
 	C: MiningBlockChainClient + 'static,
 	M: MinerService + 'static {
@@ -134,6 +144,66 @@ impl<C, M> EthcoreSet for EthcoreSetClient<C, M> where
 		})
 	}
 
+	fn unban_peer(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(String,)>(params).and_then(|(peer,)| {
+			try!(check_peer_id(&peer));
+			match take_weak!(self.net).unban_peer(peer) {
+				Ok(()) => Ok(to_value(&true)),
+				Err(e) => Err(errors::network_error("Couldn't unban peer", e)),
+			}
+		})
+	}
+
+	fn disconnect_peer(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(String,)>(params).and_then(|(peer,)| {
+			try!(check_peer_id(&peer));
+			match take_weak!(self.net).disconnect_peer(peer) {
+				Ok(()) => Ok(to_value(&true)),
+				Err(e) => Err(errors::network_error("Couldn't disconnect peer", e)),
+			}
+		})
+	}
+
+	fn ban_peer(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(String,)>(params).and_then(|(peer,)| {
+			try!(check_peer_id(&peer));
+			match take_weak!(self.net).ban_peer(peer) {
+				Ok(()) => Ok(to_value(&true)),
+				Err(e) => Err(errors::network_error("Couldn't ban peer", e)),
+			}
+		})
+	}
+
+	fn set_ip_filter(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(String, Vec<String>, Vec<String>)>(params).and_then(|(allow, allow_ranges, deny_ranges)| {
+			let filter = IpFilterSettings {
+				allow: allow,
+				allow_ranges: allow_ranges,
+				deny_ranges: deny_ranges,
+			};
+			match take_weak!(self.net).set_ip_filter(filter) {
+				Ok(()) => Ok(to_value(&true)),
+				Err(e) => Err(errors::invalid_params("IP filter", e)),
+			}
+		})
+	}
+
+	fn set_bandwidth_limits(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(u64, u64, u64)>(params).and_then(|(upload, download, serve)| {
+			take_weak!(self.net).set_bandwidth_limits(BandwidthLimits {
+				upload: upload,
+				download: download,
+				serve: serve,
+			});
+			Ok(to_value(&true))
+		})
+	}
+
 	fn drop_non_reserved_peers(&self, params: Params) -> Result<Value, Error> {
 		try!(self.active());
 		try!(expect_no_params(params));
@@ -148,6 +218,22 @@ impl<C, M> EthcoreSet for EthcoreSetClient<C, M> where
 		Ok(to_value(&true))
 	}
 
+	fn reset_chain(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(BlockNumber,)>(params).and_then(|(block,)| {
+			match take_weak!(self.client).rewind(block.into()) {
+				Ok(retracted) => Ok(to_value(&retracted.into_iter().map(Into::into).collect::<Vec<H256>>())),
+				Err(e) => Err(errors::from_state_error(e)),
+			}
+		})
+	}
+
+	fn clear_rewound_block(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		try!(expect_no_params(params));
+		Ok(to_value(&take_weak!(self.client).clear_rewound_block().map(H256::from)))
+	}
+
 	fn start_network(&self, params: Params) -> Result<Value, Error> {
 		try!(expect_no_params(params));
 		take_weak!(self.net).start_network();
@@ -159,4 +245,14 @@ impl<C, M> EthcoreSet for EthcoreSetClient<C, M> where
 		take_weak!(self.net).stop_network();
 		Ok(Value::Bool(true))
 	}
+
+	fn set_logs_levels(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(String,)>(params).and_then(|(levels,)| {
+			match self.logger.set_levels(&levels) {
+				Ok(()) => Ok(to_value(&true)),
+				Err(e) => Err(errors::invalid_params("Log levels", e)),
+			}
+		})
+	}
 }
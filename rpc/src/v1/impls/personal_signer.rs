@@ -22,7 +22,7 @@ use ethcore::account_provider::AccountProvider;
 use ethcore::client::MiningBlockChainClient;
 use ethcore::miner::MinerService;
 use v1::traits::PersonalSigner;
-use v1::types::{TransactionModification, ConfirmationRequest, U256};
+use v1::types::{TransactionModification, ConfirmationRequest, SignerToken, SigningRule, U256};
 use v1::helpers::{errors, SignerService, SigningQueue, ConfirmationPayload};
 use v1::helpers::params::expect_no_params;
 use v1::helpers::dispatch::{unlock_sign_and_dispatch, signature_with_password};
@@ -122,5 +122,60 @@ impl<C: 'static, M: 'static> PersonalSigner for SignerClient<C, M> where C: Mini
 			.map(|token| to_value(&token))
 			.map_err(|e| errors::token(e))
 	}
+
+	fn signer_tokens(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		try!(expect_no_params(params));
+		let signer = take_weak!(self.signer);
+		signer.tokens()
+			.map(|tokens| to_value(&tokens.into_iter().map(From::from).collect::<Vec<SignerToken>>()))
+			.map_err(|e| errors::token(e))
+	}
+
+	fn revoke_signer_token(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(String, )>(params).and_then(
+			|(name, )| {
+				let signer = take_weak!(self.signer);
+				signer.revoke_token(&name)
+					.map(|revoked| to_value(&revoked))
+					.map_err(|e| errors::token(e))
+			}
+		)
+	}
+
+	fn signing_rules(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		try!(expect_no_params(params));
+		let signer = take_weak!(self.signer);
+		Ok(to_value(&signer.policy().rules().into_iter().map(From::from).collect::<Vec<SigningRule>>()))
+	}
+
+	fn add_signing_rule(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(SigningRule, )>(params).and_then(
+			|(rule, )| {
+				if rule.methods.as_ref().map_or(false, |methods| methods.iter().any(|m| m.0.len() != 4)) {
+					return Err(errors::invalid_params("Method selectors have to be 4 bytes long", rule.methods));
+				}
+				let signer = take_weak!(self.signer);
+				signer.policy().add_rule(rule.into())
+					.map(|_| to_value(&true))
+					.map_err(|e| errors::internal("Unable to save signing rules", e))
+			}
+		)
+	}
+
+	fn remove_signing_rule(&self, params: Params) -> Result<Value, Error> {
+		try!(self.active());
+		from_params::<(String, )>(params).and_then(
+			|(name, )| {
+				let signer = take_weak!(self.signer);
+				signer.policy().remove_rule(&name)
+					.map(|removed| to_value(&removed))
+					.map_err(|e| errors::internal("Unable to save signing rules", e))
+			}
+		)
+	}
 }
 
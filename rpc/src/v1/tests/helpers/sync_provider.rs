@@ -17,7 +17,7 @@
 //! Test implementation of SyncProvider.
 
 use util::{RwLock, U256};
-use ethsync::{SyncProvider, SyncStatus, SyncState};
+use ethsync::{SyncProvider, SyncStatus, SyncState, PeerInfo};
 
 /// TestSyncProvider config.
 pub struct Config {
@@ -60,5 +60,23 @@ impl SyncProvider for TestSyncProvider {
 	fn status(&self) -> SyncStatus {
 		self.status.read().clone()
 	}
+
+	fn peers(&self) -> Vec<PeerInfo> {
+		vec![PeerInfo {
+			id: "01".into(),
+			remote_address: "127.0.0.1:30303".into(),
+			client_version: "Parity".into(),
+			protocol_version: 5,
+			capabilities: vec!["eth/63".into()],
+			bytes_sent: 1024,
+			bytes_received: 2048,
+			ping_ms: Some(50),
+			connected_secs: 60,
+			originated: true,
+			head: Some(U256::from(1).into()),
+			head_number: Some(1),
+			difficulty: Some(U256::from(131072)),
+		}]
+	}
 }
 
@@ -19,7 +19,7 @@ use std::sync::Arc;
 use jsonrpc_core::{IoHandler, to_value};
 use v1::impls::EthSigningQueueClient;
 use v1::traits::EthSigning;
-use v1::helpers::{SignerService, SigningQueue};
+use v1::helpers::{SignerService, SigningQueue, SigningPolicy, SigningRule, RuleAction};
 use v1::types::{H256 as RpcH256, H520 as RpcH520};
 use v1::tests::helpers::TestMinerService;
 use util::{Address, FixedHash, Uint, U256, H256, H520};
@@ -58,6 +58,36 @@ fn eth_signing() -> EthSigningTester {
 	EthSigningTester::default()
 }
 
+fn eth_signing_with_policy(passwords: Vec<String>) -> EthSigningTester {
+	let signer = Arc::new(SignerService::with_policy(|| Ok("new_token".into()), SigningPolicy::new_test(passwords)));
+	let client = Arc::new(TestBlockChainClient::default());
+	let miner = Arc::new(TestMinerService::default());
+	let accounts = Arc::new(AccountProvider::transient_provider());
+	let io = IoHandler::new();
+	io.add_delegate(EthSigningQueueClient::new(&signer, &client, &miner, &accounts).to_delegate());
+
+	EthSigningTester {
+		signer: signer,
+		client: client,
+		miner: miner,
+		accounts: accounts,
+		io: io,
+	}
+}
+
+fn policy_rule(name: &str, action: RuleAction, sender: Address) -> SigningRule {
+	SigningRule {
+		name: name.into(),
+		action: action,
+		sender: Some(sender),
+		recipients: None,
+		max_value: Some(U256::from(0x9184e72au64)),
+		max_gas: None,
+		daily_limit: None,
+		methods: None,
+	}
+}
+
 #[test]
 fn should_add_sign_to_queue() {
 	// given
@@ -263,3 +293,200 @@ fn should_dispatch_transaction_if_account_is_unlock() {
 	// then
 	assert_eq!(tester.io.handle_request_sync(&request), Some(response.to_owned()));
 }
+
+#[test]
+fn should_dispatch_transaction_approved_by_policy() {
+	// given
+	let tester = eth_signing_with_policy(vec!["wrong".into(), "test".into()]);
+	let acc = tester.accounts.new_account("test").unwrap();
+	tester.signer.policy().add_rule(policy_rule("bot", RuleAction::Approve, acc)).unwrap();
+
+	let t = Transaction {
+		nonce: U256::zero(),
+		gas_price: U256::from(0x9184e72a000u64),
+		gas: U256::from(0x76c0),
+		action: Action::Call(Address::from_str("d46e8dd67c5d32be8058bb8eb970870f07244567").unwrap()),
+		value: U256::from(0x9184e72au64),
+		data: vec![]
+	};
+	let signature = tester.accounts.sign_with_password(acc, "test".into(), t.hash()).unwrap();
+	let t = t.with_signature(signature);
+
+	// when
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_sendTransaction",
+		"params": [{
+			"from": ""#.to_owned() + format!("0x{:?}", acc).as_ref() + r#"",
+			"to": "0xd46e8dd67c5d32be8058bb8eb970870f07244567",
+			"gas": "0x76c0",
+			"gasPrice": "0x9184e72a000",
+			"value": "0x9184e72a"
+		}],
+		"id": 1
+	}"#;
+	let response = r#"{"jsonrpc":"2.0","result":""#.to_owned() + format!("0x{:?}", t.hash()).as_ref() + r#"","id":1}"#;
+
+	// then
+	assert_eq!(tester.io.handle_request_sync(&request), Some(response.to_owned()));
+	assert_eq!(tester.signer.requests().len(), 0);
+	assert_eq!(tester.miner.imported_transactions.lock().len(), 1);
+}
+
+#[test]
+fn should_reject_transaction_by_policy() {
+	// given
+	let tester = eth_signing_with_policy(vec![]);
+	let acc = Address::random();
+	tester.signer.policy().add_rule(policy_rule("deny", RuleAction::Reject, acc)).unwrap();
+
+	// when
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_sendTransaction",
+		"params": [{
+			"from": ""#.to_owned() + format!("0x{:?}", acc).as_ref() + r#"",
+			"to": "0xd46e8dd67c5d32be8058bb8eb970870f07244567",
+			"gas": "0x76c0",
+			"gasPrice": "0x9184e72a000",
+			"value": "0x9184e72a"
+		}],
+		"id": 1
+	}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32040,"message":"Request has been rejected.","data":null},"id":1}"#;
+
+	// then
+	assert_eq!(tester.io.handle_request_sync(&request), Some(response.to_owned()));
+	assert_eq!(tester.signer.requests().len(), 0);
+}
+
+#[test]
+fn should_add_transaction_to_queue_when_no_password_for_approved_request() {
+	// given
+	let tester = eth_signing_with_policy(vec!["wrong".into()]);
+	let acc = tester.accounts.new_account("test").unwrap();
+	tester.signer.policy().add_rule(policy_rule("bot", RuleAction::Approve, acc)).unwrap();
+
+	// when
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_sendTransaction",
+		"params": [{
+			"from": ""#.to_owned() + format!("0x{:?}", acc).as_ref() + r#"",
+			"to": "0xd46e8dd67c5d32be8058bb8eb970870f07244567",
+			"gas": "0x76c0",
+			"gasPrice": "0x9184e72a000",
+			"value": "0x9184e72a"
+		}],
+		"id": 1
+	}"#;
+
+	// then
+	tester.io.handle_request(&request).unwrap();
+	assert_eq!(tester.signer.requests().len(), 1);
+	assert_eq!(tester.miner.imported_transactions.lock().len(), 0);
+}
+
+#[test]
+fn should_sign_message_approved_by_policy() {
+	// given
+	let tester = eth_signing_with_policy(vec!["test".into()]);
+	let acc = tester.accounts.new_account("test").unwrap();
+	let mut rule = policy_rule("signer", RuleAction::Approve, acc);
+	rule.max_value = None;
+	tester.signer.policy().add_rule(rule).unwrap();
+	let msg = H256::from(5);
+	let signature = tester.accounts.sign_with_password(acc, "test".into(), msg).unwrap();
+
+	// when
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_sign",
+		"params": [
+			""#.to_owned() + format!("0x{:?}", acc).as_ref() + r#"",
+			"0x0000000000000000000000000000000000000000000000000000000000000005"
+		],
+		"id": 1
+	}"#;
+	let response = r#"{"jsonrpc":"2.0","result":""#.to_owned() + format!("0x{:?}", H520::from(signature)).as_ref() + r#"","id":1}"#;
+
+	// then
+	assert_eq!(tester.io.handle_request_sync(&request), Some(response.to_owned()));
+	assert_eq!(tester.signer.requests().len(), 0);
+}
+
+#[test]
+fn should_ask_to_sign_message_when_only_transaction_rules_match() {
+	// given
+	let tester = eth_signing_with_policy(vec!["test".into()]);
+	let acc = tester.accounts.new_account("test").unwrap();
+	tester.signer.policy().add_rule(policy_rule("bot", RuleAction::Approve, acc)).unwrap();
+
+	// when
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_sign",
+		"params": [
+			""#.to_owned() + format!("0x{:?}", acc).as_ref() + r#"",
+			"0x0000000000000000000000000000000000000000000000000000000000000005"
+		],
+		"id": 1
+	}"#;
+
+	// then
+	tester.io.handle_request(&request).unwrap();
+	assert_eq!(tester.signer.requests().len(), 1);
+}
+
+#[test]
+fn should_reject_sign_by_policy() {
+	// given
+	let tester = eth_signing_with_policy(vec![]);
+	let acc = Address::random();
+	let mut rule = policy_rule("deny", RuleAction::Reject, acc);
+	rule.max_value = None;
+	tester.signer.policy().add_rule(rule).unwrap();
+
+	// when
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_sign",
+		"params": [
+			""#.to_owned() + format!("0x{:?}", acc).as_ref() + r#"",
+			"0x0000000000000000000000000000000000000000000000000000000000000005"
+		],
+		"id": 1
+	}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32040,"message":"Request has been rejected.","data":null},"id":1}"#;
+
+	// then
+	assert_eq!(tester.io.handle_request_sync(&request), Some(response.to_owned()));
+	assert_eq!(tester.signer.requests().len(), 0);
+}
+
+#[test]
+fn should_add_transaction_to_queue_when_above_policy_limits() {
+	// given
+	let tester = eth_signing_with_policy(vec!["test".into()]);
+	let acc = tester.accounts.new_account("test").unwrap();
+	tester.signer.policy().add_rule(policy_rule("bot", RuleAction::Approve, acc)).unwrap();
+
+	// when
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_sendTransaction",
+		"params": [{
+			"from": ""#.to_owned() + format!("0x{:?}", acc).as_ref() + r#"",
+			"to": "0xd46e8dd67c5d32be8058bb8eb970870f07244567",
+			"gas": "0x76c0",
+			"gasPrice": "0x9184e72a000",
+			"value": "0x9184e72b"
+		}],
+		"id": 1
+	}"#;
+
+	// then
+	tester.io.handle_request(&request).unwrap();
+	assert_eq!(tester.signer.requests().len(), 1);
+	assert_eq!(tester.miner.imported_transactions.lock().len(), 0);
+}
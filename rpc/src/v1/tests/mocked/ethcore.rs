@@ -18,7 +18,8 @@ use std::sync::Arc;
 use util::log::RotatingLogger;
 use util::U256;
 use ethsync::ManageNetwork;
-use ethcore::client::{TestBlockChainClient};
+use ethcore::client::{TestBlockChainClient, Reorg, AddressTransaction};
+use ethcore::miner::WorkerStats;
 
 use jsonrpc_core::IoHandler;
 use v1::{Ethcore, EthcoreClient};
@@ -208,7 +209,75 @@ fn rpc_ethcore_net_peers() {
 	io.add_delegate(ethcore_client(&client, &miner, &sync, &net).to_delegate());
 
 	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_netPeers", "params":[], "id": 1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":{"active":0,"connected":120,"max":50},"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"active":0,"connected":120,"max":50,"peers":[{"bytesReceived":2048,"bytesSent":1024,"caps":["eth/63"],"connectedSecs":60,"difficulty":"0x20000","head":"0x0000000000000000000000000000000000000000000000000000000000000001","headNumber":"0x1","id":"0x01","name":"Parity","originated":true,"pingMs":50,"protocolVersion":5,"remoteAddress":"127.0.0.1:30303"}]},"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_net_peer_reputations() {
+	let miner = miner_service();
+	let client = client_service();
+	let sync = sync_provider();
+	let net = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_client(&client, &miner, &sync, &net).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_netPeerReputations", "params":[], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":[{"bannedUntil":null,"id":"0x01","score":-60}],"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_net_ip_filter() {
+	let miner = miner_service();
+	let client = client_service();
+	let sync = sync_provider();
+	let net = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_client(&client, &miner, &sync, &net).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_netIpFilter", "params":[], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"allow":"public","allowRanges":[],"denyRanges":["1.2.3.0/24"]},"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_net_traffic() {
+	let miner = miner_service();
+	let client = client_service();
+	let sync = sync_provider();
+	let net = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_client(&client, &miner, &sync, &net).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_netTraffic", "params":[], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"bytesReceived":20,"bytesSent":150,"limits":{"download":0,"serve":4096,"upload":0},"packets":[{"packetId":4,"protocol":"eth","receivedBytes":0,"receivedPackets":0,"sentBytes":150,"sentPackets":2}],"sessions":1},"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_stratum_workers() {
+	let miner = miner_service();
+	let client = client_service();
+	let sync = sync_provider();
+	let net = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_client(&client, &miner, &sync, &net).to_delegate());
+	miner.stratum_workers.write().insert("miner1".into(), WorkerStats {
+		accepted: 2,
+		rejected: 1,
+		stale: 0,
+		blocks: 1,
+		work: 2000.into(),
+		difficulty: 1000.into(),
+	});
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_stratumWorkers", "params":[], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"miner1":{"accepted":2,"blocks":1,"difficulty":"0x3e8","rejected":1,"stale":0,"work":"0x7d0"}},"id":1}"#;
 
 	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
 }
@@ -319,3 +388,106 @@ fn rpc_ethcore_pending_transactions() {
 
 	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
 }
+
+fn reorg() -> Reorg {
+	Reorg {
+		id: 0,
+		timestamp: 1,
+		depth: 1,
+		old_head: 2.into(),
+		old_number: 0,
+		new_head: 3.into(),
+		new_number: 0,
+		retracted_blocks: vec![2.into()],
+		retracted_transactions: vec![4.into()],
+		reincluded_transactions: vec![],
+	}
+}
+
+#[test]
+fn rpc_ethcore_reorgs() {
+	let miner = miner_service();
+	let client = client_service();
+	client.reorgs.write().push(reorg());
+	let sync = sync_provider();
+	let net = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_client(&client, &miner, &sync, &net).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_reorgs", "params":["earliest", "latest"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":[{"depth":"0x1","id":"0x0","newHead":"0x0000000000000000000000000000000000000000000000000000000000000003","newNumber":"0x0","oldHead":"0x0000000000000000000000000000000000000000000000000000000000000002","oldNumber":"0x0","reincludedTransactions":[],"retractedBlocks":["0x0000000000000000000000000000000000000000000000000000000000000002"],"retractedTransactions":["0x0000000000000000000000000000000000000000000000000000000000000004"],"timestamp":"0x1"}],"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_transaction_reorgs() {
+	let miner = miner_service();
+	let client = client_service();
+	client.reorgs.write().push(reorg());
+	let sync = sync_provider();
+	let net = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_client(&client, &miner, &sync, &net).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_transactionReorgs", "params":["0x0000000000000000000000000000000000000000000000000000000000000004"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"blockHash":null,"reorgs":[{"depth":"0x1","id":"0x0","newHead":"0x0000000000000000000000000000000000000000000000000000000000000003","newNumber":"0x0","oldHead":"0x0000000000000000000000000000000000000000000000000000000000000002","oldNumber":"0x0","reincludedTransactions":[],"retractedBlocks":["0x0000000000000000000000000000000000000000000000000000000000000002"],"retractedTransactions":["0x0000000000000000000000000000000000000000000000000000000000000004"],"timestamp":"0x1"}],"retracted":true},"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_transactions_by_address() {
+	let miner = miner_service();
+	let client = client_service();
+	*client.address_transactions.write() = Some((0..3).map(|i| AddressTransaction {
+		address: 5.into(),
+		block_number: i,
+		block_hash: 1.into(),
+		transaction_index: 0,
+		transaction_hash: 2.into(),
+		sent: true,
+		received: false,
+	}).collect());
+	let sync = sync_provider();
+	let net = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_client(&client, &miner, &sync, &net).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_transactionsByAddress", "params":["0x0000000000000000000000000000000000000005", "0x1", "0x1"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":[{"blockHash":"0x0000000000000000000000000000000000000000000000000000000000000001","blockNumber":"0x1","received":false,"sent":true,"transactionHash":"0x0000000000000000000000000000000000000000000000000000000000000002","transactionIndex":"0x0"}],"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_transactions_by_address_disabled() {
+	let miner = miner_service();
+	let client = client_service();
+	let sync = sync_provider();
+	let net = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_client(&client, &miner, &sync, &net).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_transactionsByAddress", "params":["0x0000000000000000000000000000000000000005", "0x0", "0xa"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"Transactions are not indexed by address. Run with --address-index to enable the index.","data":null},"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_transactions_by_address_incomplete() {
+	let miner = miner_service();
+	let client = client_service();
+	*client.address_transactions.write() = Some(Vec::new());
+	*client.address_index_unindexed.write() = 1000;
+	let sync = sync_provider();
+	let net = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_client(&client, &miner, &sync, &net).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_transactionsByAddress", "params":["0x0000000000000000000000000000000000000005", "0x0", "0xa"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"Transactions are still being indexed by address. 1000 blocks left to index.","data":null},"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
@@ -19,11 +19,11 @@ use std::str::FromStr;
 use jsonrpc_core::IoHandler;
 use v1::{EthcoreSet, EthcoreSetClient};
 use ethcore::miner::MinerService;
-use ethcore::client::TestBlockChainClient;
+use ethcore::client::{TestBlockChainClient, BlockChainClient, EachBlockWith, BlockID};
 use v1::tests::helpers::TestMinerService;
-use util::{U256, Address};
+use util::{U256, Address, RotatingLogger};
 use rustc_serialize::hex::FromHex;
-use super::manage_network::TestManageNetwork;
+use super::manage_network::{TestManageNetwork, KNOWN_PEER};
 use ethsync::ManageNetwork;
 
 fn miner_service() -> Arc<TestMinerService> {
@@ -39,7 +39,7 @@ fn network_service() -> Arc<TestManageNetwork> {
 }
 
 fn ethcore_set_client(client: &Arc<TestBlockChainClient>, miner: &Arc<TestMinerService>, net: &Arc<TestManageNetwork>) -> EthcoreSetClient<TestBlockChainClient, TestMinerService> {
-	EthcoreSetClient::new(client, miner, &(net.clone() as Arc<ManageNetwork>))
+	EthcoreSetClient::new(client, miner, &(net.clone() as Arc<ManageNetwork>), Arc::new(RotatingLogger::new("rpc=trace".to_owned())))
 }
 
 #[test]
@@ -116,3 +116,113 @@ fn rpc_ethcore_set_transactions_limit() {
 	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
 	assert_eq!(miner.transactions_limit(), 10_240_240);
 }
+
+#[test]
+fn rpc_ethcore_reset_chain() {
+	let miner = miner_service();
+	let client = client_service();
+	let network = network_service();
+	client.add_blocks(3, EachBlockWith::Nothing);
+	let second = client.block_hash(BlockID::Number(2)).unwrap();
+	let third = client.block_hash(BlockID::Number(3)).unwrap();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_set_client(&client, &miner, &network).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_resetChain", "params":["0x1"], "id": 1}"#;
+	let response = format!(r#"{{"jsonrpc":"2.0","result":["0x{}","0x{}"],"id":1}}"#, third.hex(), second.hex());
+
+	assert_eq!(io.handle_request_sync(request), Some(response));
+	assert_eq!(client.chain_info().best_block_number, 1);
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_clearRewoundBlock", "params":[], "id": 2}"#;
+	let response = format!(r#"{{"jsonrpc":"2.0","result":"0x{}","id":2}}"#, second.hex());
+	assert_eq!(io.handle_request_sync(request), Some(response));
+
+	let response = r#"{"jsonrpc":"2.0","result":null,"id":2}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_unban_peer() {
+	let miner = miner_service();
+	let client = client_service();
+	let network = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_set_client(&client, &miner, &network).to_delegate());
+
+	let request = format!(r#"{{"jsonrpc": "2.0", "method": "ethcore_unbanPeer", "params":["{}"], "id": 1}}"#, KNOWN_PEER);
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(&request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_disconnect_and_ban_peer() {
+	let miner = miner_service();
+	let client = client_service();
+	let network = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_set_client(&client, &miner, &network).to_delegate());
+
+	let request = format!(r#"{{"jsonrpc": "2.0", "method": "ethcore_disconnectPeer", "params":["{}"], "id": 1}}"#, KNOWN_PEER);
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request_sync(&request), Some(response.to_owned()));
+
+	let request = format!(r#"{{"jsonrpc": "2.0", "method": "ethcore_banPeer", "params":["{}"], "id": 1}}"#, KNOWN_PEER);
+	assert_eq!(io.handle_request_sync(&request), Some(response.to_owned()));
+
+	let request = format!(r#"{{"jsonrpc": "2.0", "method": "ethcore_banPeer", "params":["{}"], "id": 1}}"#, KNOWN_PEER.replace("1", "2"));
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32070,"message":"Couldn't ban peer","data":"\"Unknown peer\""},"id":1}"#;
+	assert_eq!(io.handle_request_sync(&request), Some(response.to_owned()));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_banPeer", "params":["0x01"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: Peer id","data":"InvalidHexLength"},"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_set_ip_filter() {
+	let miner = miner_service();
+	let client = client_service();
+	let network = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_set_client(&client, &miner, &network).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_setIpFilter", "params":["private", ["8.8.8.8"], ["10.1.0.0/16"]], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_setIpFilter", "params":["all", [], ["10.0.0.0/33"]], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: IP filter","data":"\"Network error (Invalid IP filter: 10.0.0.0/33)\""},"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_set_bandwidth_limits() {
+	let miner = miner_service();
+	let client = client_service();
+	let network = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_set_client(&client, &miner, &network).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_setBandwidthLimits", "params":[1048576, 0, 262144], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_set_logs_levels() {
+	let miner = miner_service();
+	let client = client_service();
+	let network = network_service();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore_set_client(&client, &miner, &network).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_devSetLogsLevels", "params":["sync=debug"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_devSetLogsLevels", "params":["sync=loud"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: Log levels","data":"\"Invalid log level: loud\""},"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use ethsync::{ManageNetwork, NetworkConfiguration};
+use ethsync::{ManageNetwork, NetworkConfiguration, PeerReputation, IpFilterSettings, TrafficStats, PacketTraffic, BandwidthLimits};
 
 pub struct TestManageNetwork;
 
@@ -27,4 +27,43 @@ impl ManageNetwork for TestManageNetwork {
 	fn start_network(&self) {}
 	fn stop_network(&self) {}
 	fn network_config(&self) -> NetworkConfiguration { NetworkConfiguration::new_local() }
+	fn peer_reputations(&self) -> Vec<PeerReputation> {
+		vec![PeerReputation { id: "01".into(), score: -60, banned_until: None }]
+	}
+	fn unban_peer(&self, peer: String) -> Result<(), String> { known_peer(peer) }
+	fn disconnect_peer(&self, peer: String) -> Result<(), String> { known_peer(peer) }
+	fn ban_peer(&self, peer: String) -> Result<(), String> { known_peer(peer) }
+	fn ip_filter(&self) -> IpFilterSettings {
+		IpFilterSettings { allow: "public".into(), allow_ranges: vec![], deny_ranges: vec!["1.2.3.0/24".into()] }
+	}
+	fn set_ip_filter(&self, filter: IpFilterSettings) -> Result<(), String> {
+		filter.into_filter().map(|_| ()).map_err(|e| format!("{}", e))
+	}
+	fn traffic_stats(&self) -> TrafficStats {
+		TrafficStats {
+			bytes_sent: 150,
+			bytes_received: 20,
+			sessions: 1,
+			packets: vec![PacketTraffic {
+				protocol: "eth".into(),
+				packet_id: 4,
+				sent_packets: 2,
+				sent_bytes: 150,
+				received_packets: 0,
+				received_bytes: 0,
+			}],
+			limits: BandwidthLimits { upload: 0, download: 0, serve: 4096 },
+		}
+	}
+	fn set_bandwidth_limits(&self, _limits: BandwidthLimits) { }
+}
+
+/// Node id of the only peer the network knows.
+pub const KNOWN_PEER: &'static str = "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001";
+
+fn known_peer(peer: String) -> Result<(), String> {
+	match peer == KNOWN_PEER || format!("0x{}", peer) == KNOWN_PEER {
+		true => Ok(()),
+		false => Err("Unknown peer".into()),
+	}
 }
@@ -223,3 +223,62 @@ fn should_generate_new_token() {
 	// then
 	assert_eq!(tester.io.handle_request_sync(&request), Some(response.to_owned()));
 }
+
+#[test]
+fn should_add_and_remove_signing_rules() {
+	// given
+	let tester = signer_tester();
+
+	// when
+	let add = r#"{
+		"jsonrpc":"2.0",
+		"method":"personal_addSigningRule",
+		"params":[{"name":"bot","action":"approve","sender":"0x0000000000000000000000000000000000000001","maxValue":"0x1"}],
+		"id":1
+	}"#;
+	let list = r#"{"jsonrpc":"2.0","method":"personal_signingRules","params":[],"id":1}"#;
+	let remove = r#"{"jsonrpc":"2.0","method":"personal_removeSigningRule","params":["bot"],"id":1}"#;
+	let list_response = concat!(
+		r#"{"jsonrpc":"2.0","result":[{"action":"approve","dailyLimit":null,"maxGas":null,"maxValue":"0x1","methods":null,"#,
+		r#""name":"bot","recipients":null,"sender":"0x0000000000000000000000000000000000000001"}],"id":1}"#
+	);
+
+	// then
+	assert_eq!(tester.io.handle_request_sync(&add), Some(r#"{"jsonrpc":"2.0","result":true,"id":1}"#.to_owned()));
+	assert_eq!(tester.io.handle_request_sync(&list), Some(list_response.to_owned()));
+	assert_eq!(tester.io.handle_request_sync(&remove), Some(r#"{"jsonrpc":"2.0","result":true,"id":1}"#.to_owned()));
+	assert_eq!(tester.signer.policy().rules().len(), 0);
+}
+
+#[test]
+fn should_not_add_signing_rule_with_invalid_selector() {
+	// given
+	let tester = signer_tester();
+
+	// when
+	let request = r#"{
+		"jsonrpc":"2.0",
+		"method":"personal_addSigningRule",
+		"params":[{"name":"bot","action":"approve","methods":["0xa9059c"]}],
+		"id":1
+	}"#;
+
+	// then
+	let response = tester.io.handle_request_sync(&request).unwrap();
+	assert!(response.contains(r#""code":-32602"#));
+	assert_eq!(tester.signer.policy().rules().len(), 0);
+}
+
+#[test]
+fn should_list_and_revoke_signer_tokens() {
+	// given
+	let tester = signer_tester();
+
+	// when
+	let list = r#"{"jsonrpc":"2.0","method":"personal_signerTokens","params":[],"id":1}"#;
+	let revoke = r#"{"jsonrpc":"2.0","method":"personal_revokeSignerToken","params":["alice"],"id":1}"#;
+
+	// then
+	assert_eq!(tester.io.handle_request_sync(&list), Some(r#"{"jsonrpc":"2.0","result":[],"id":1}"#.to_owned()));
+	assert_eq!(tester.io.handle_request_sync(&revoke), Some(r#"{"jsonrpc":"2.0","result":false,"id":1}"#.to_owned()));
+}
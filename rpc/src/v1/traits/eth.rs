@@ -19,7 +19,7 @@ use std::sync::Arc;
 use jsonrpc_core::*;
 
 use v1::types::{Block, BlockNumber, Bytes, CallRequest, Filter, FilterChanges, Index};
-use v1::types::{Log, Receipt, SyncStatus, Transaction, Work};
+use v1::types::{Log, Receipt, SyncStatus, Transaction, Work, AccountProof};
 use v1::types::{H64, H160, H256, U256};
 
 use v1::helpers::auto_args::{Trailing, Wrap};
@@ -99,6 +99,10 @@ build_rpc_trait! {
 		#[name("eth_getCode")]
 		fn code_at(&self, H160, Trailing<BlockNumber>) -> Result<Bytes, Error>;
 
+		/// Returns Merkle proof of the account and given storage keys at given time (block number).
+		#[name("eth_getProof")]
+		fn proof(&self, H160, Vec<H256>, Trailing<BlockNumber>) -> Result<AccountProof, Error>;
+
 		/// Sends signed transaction, returning its hash.
 		#[name("eth_sendRawTransaction")]
 		fn send_raw_transaction(&self, Bytes) -> Result<H256, Error>;
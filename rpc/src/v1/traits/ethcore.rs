@@ -48,6 +48,18 @@ pub trait Ethcore: Sized + Send + Sync + 'static {
 	/// Returns peers details
 	fn net_peers(&self, _: Params) -> Result<Value, Error>;
 
+	/// Returns reputations of known peers with non-zero score or an active ban
+	fn net_peer_reputations(&self, _: Params) -> Result<Value, Error>;
+
+	/// Returns address ranges peers are allowed to use
+	fn net_ip_filter(&self, _: Params) -> Result<Value, Error>;
+
+	/// Returns traffic totals, traffic by packet type and bandwidth limits
+	fn net_traffic(&self, _: Params) -> Result<Value, Error>;
+
+	/// Returns share accounting of workers connected to the stratum server, by worker name
+	fn stratum_workers(&self, _: Params) -> Result<Value, Error>;
+
 	/// Returns network port
 	fn net_port(&self, _: Params) -> Result<Value, Error>;
 
@@ -83,6 +95,15 @@ pub trait Ethcore: Sized + Send + Sync + 'static {
 	/// Returns all pending (current) transactions from transaction queue.
 	fn pending_transactions(&self, _: Params) -> Result<Value, Error>;
 
+	/// Returns chain reorganizations which happened between given blocks (inclusive).
+	fn reorgs(&self, _: Params) -> Result<Value, Error>;
+
+	/// Returns reorganizations which retracted given transaction and its current inclusion.
+	fn transaction_reorgs(&self, _: Params) -> Result<Value, Error>;
+
+	/// Returns a page of canonical transactions sent from or to given address, oldest first.
+	fn transactions_by_address(&self, _: Params) -> Result<Value, Error>;
+
 	/// Hash a file content under given URL.
 	fn hash_content(&self, _: Params, _: Ready);
 
@@ -99,6 +120,10 @@ pub trait Ethcore: Sized + Send + Sync + 'static {
 		delegate.add_method("ethcore_devLogsLevels", Ethcore::dev_logs_levels);
 		delegate.add_method("ethcore_netChain", Ethcore::net_chain);
 		delegate.add_method("ethcore_netPeers", Ethcore::net_peers);
+		delegate.add_method("ethcore_netPeerReputations", Ethcore::net_peer_reputations);
+		delegate.add_method("ethcore_netIpFilter", Ethcore::net_ip_filter);
+		delegate.add_method("ethcore_netTraffic", Ethcore::net_traffic);
+		delegate.add_method("ethcore_stratumWorkers", Ethcore::stratum_workers);
 		delegate.add_method("ethcore_netPort", Ethcore::net_port);
 		delegate.add_method("ethcore_rpcSettings", Ethcore::rpc_settings);
 		delegate.add_method("ethcore_nodeName", Ethcore::node_name);
@@ -110,6 +135,9 @@ pub trait Ethcore: Sized + Send + Sync + 'static {
 		delegate.add_method("ethcore_registryAddress", Ethcore::registry_address);
 		delegate.add_method("ethcore_encryptMessage", Ethcore::encrypt_message);
 		delegate.add_method("ethcore_pendingTransactions", Ethcore::pending_transactions);
+		delegate.add_method("ethcore_reorgs", Ethcore::reorgs);
+		delegate.add_method("ethcore_transactionReorgs", Ethcore::transaction_reorgs);
+		delegate.add_method("ethcore_transactionsByAddress", Ethcore::transactions_by_address);
 		delegate.add_async_method("ethcore_hashContent", Ethcore::hash_content);
 
 		delegate
@@ -49,18 +49,42 @@ pub trait EthcoreSet: Sized + Send + Sync + 'static {
 	/// Remove a reserved peer.
 	fn remove_reserved_peer(&self, _: Params) -> Result<Value, Error>;
 
+	/// Lift ban and reset reputation of a peer.
+	fn unban_peer(&self, _: Params) -> Result<Value, Error>;
+
+	/// Disconnect a peer.
+	fn disconnect_peer(&self, _: Params) -> Result<Value, Error>;
+
+	/// Ban a peer and disconnect it.
+	fn ban_peer(&self, _: Params) -> Result<Value, Error>;
+
+	/// Set address ranges peers are allowed to use.
+	fn set_ip_filter(&self, _: Params) -> Result<Value, Error>;
+
+	/// Set upload, download and serving bandwidth limits in bytes per second.
+	fn set_bandwidth_limits(&self, _: Params) -> Result<Value, Error>;
+
 	/// Drop all non-reserved peers.
 	fn drop_non_reserved_peers(&self, _: Params) -> Result<Value, Error>;
 
 	/// Accept non-reserved peers (default behavior)
 	fn accept_non_reserved_peers(&self, _: Params) -> Result<Value, Error>;
 
+	/// Rewind the best block to the given block. Returns hashes of retracted blocks.
+	fn reset_chain(&self, _: Params) -> Result<Value, Error>;
+
+	/// Allow importing the block retracted by the last rewind again. Returns its hash or null.
+	fn clear_rewound_block(&self, _: Params) -> Result<Value, Error>;
+
 	/// Start the network.
 	fn start_network(&self, _: Params) -> Result<Value, Error>;
 
 	/// Stop the network.
 	fn stop_network(&self, _: Params) -> Result<Value, Error>;
 
+	/// Applies log level directives, e.g. `sync=trace,network=debug`, on top of current ones
+	fn set_logs_levels(&self, _: Params) -> Result<Value, Error>;
+
 	/// Should be used to convert object to io delegate.
 	fn to_delegate(self) -> IoDelegate<Self> {
 		let mut delegate = IoDelegate::new(Arc::new(self));
@@ -73,8 +97,16 @@ pub trait EthcoreSet: Sized + Send + Sync + 'static {
 		delegate.add_method("ethcore_setTransactionsLimit", EthcoreSet::set_transactions_limit);
 		delegate.add_method("ethcore_addReservedPeer", EthcoreSet::add_reserved_peer);
 		delegate.add_method("ethcore_removeReservedPeer", EthcoreSet::remove_reserved_peer);
+		delegate.add_method("ethcore_unbanPeer", EthcoreSet::unban_peer);
+		delegate.add_method("ethcore_disconnectPeer", EthcoreSet::disconnect_peer);
+		delegate.add_method("ethcore_banPeer", EthcoreSet::ban_peer);
+		delegate.add_method("ethcore_setIpFilter", EthcoreSet::set_ip_filter);
+		delegate.add_method("ethcore_setBandwidthLimits", EthcoreSet::set_bandwidth_limits);
 		delegate.add_method("ethcore_dropNonReservedPeers", EthcoreSet::drop_non_reserved_peers);
 		delegate.add_method("ethcore_acceptNonReservedPeers", EthcoreSet::accept_non_reserved_peers);
+		delegate.add_method("ethcore_resetChain", EthcoreSet::reset_chain);
+		delegate.add_method("ethcore_clearRewoundBlock", EthcoreSet::clear_rewound_block);
+		delegate.add_method("ethcore_devSetLogsLevels", EthcoreSet::set_logs_levels);
 
 		delegate
 	}
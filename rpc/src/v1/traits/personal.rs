@@ -95,6 +95,21 @@ pub trait PersonalSigner: Sized + Send + Sync + 'static {
 	/// Generates new authorization token.
 	fn generate_token(&self, _: Params) -> Result<Value, Error>;
 
+	/// Lists Signer UI authorization tokens.
+	fn signer_tokens(&self, _: Params) -> Result<Value, Error>;
+
+	/// Revokes Signer UI authorization tokens with given name or id.
+	fn revoke_signer_token(&self, _: Params) -> Result<Value, Error>;
+
+	/// Returns auto-approval rules in evaluation order.
+	fn signing_rules(&self, _: Params) -> Result<Value, Error>;
+
+	/// Adds auto-approval rule (replacing a rule with the same name).
+	fn add_signing_rule(&self, _: Params) -> Result<Value, Error>;
+
+	/// Removes auto-approval rule with given name.
+	fn remove_signing_rule(&self, _: Params) -> Result<Value, Error>;
+
 	/// Should be used to convert object to io delegate.
 	fn to_delegate(self) -> IoDelegate<Self> {
 		let mut delegate = IoDelegate::new(Arc::new(self));
@@ -102,6 +117,11 @@ pub trait PersonalSigner: Sized + Send + Sync + 'static {
 		delegate.add_method("personal_confirmRequest", PersonalSigner::confirm_request);
 		delegate.add_method("personal_rejectRequest", PersonalSigner::reject_request);
 		delegate.add_method("personal_generateAuthorizationToken", PersonalSigner::generate_token);
+		delegate.add_method("personal_signerTokens", PersonalSigner::signer_tokens);
+		delegate.add_method("personal_revokeSignerToken", PersonalSigner::revoke_signer_token);
+		delegate.add_method("personal_signingRules", PersonalSigner::signing_rules);
+		delegate.add_method("personal_addSigningRule", PersonalSigner::add_signing_rule);
+		delegate.add_method("personal_removeSigningRule", PersonalSigner::remove_signing_rule);
 		delegate
 	}
 }
@@ -0,0 +1,81 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use v1::types::{Bytes, H160, H256, U256};
+use ethcore::client::{AccountProof as EthAccountProof, StorageProof as EthStorageProof};
+
+/// Proof of a storage entry
+#[derive(Debug, Serialize, PartialEq)]
+pub struct StorageProof {
+	/// Storage key
+	pub key: H256,
+	/// Value stored under the key
+	pub value: H256,
+	/// Storage trie nodes, starting from the root
+	pub proof: Vec<Bytes>,
+}
+
+impl From<EthStorageProof> for StorageProof {
+	fn from(p: EthStorageProof) -> Self {
+		StorageProof {
+			key: p.key.into(),
+			value: p.value.into(),
+			proof: p.proof.into_iter().map(Into::into).collect(),
+		}
+	}
+}
+
+/// Proof of an account and its storage entries
+#[derive(Debug, Serialize, PartialEq)]
+pub struct AccountProof {
+	/// Account address
+	pub address: H160,
+	/// Account balance
+	pub balance: U256,
+	/// Account nonce
+	pub nonce: U256,
+	/// Hash of the account code
+	#[serde(rename="codeHash")]
+	pub code_hash: H256,
+	/// Root of the account storage trie
+	#[serde(rename="storageHash")]
+	pub storage_hash: H256,
+	/// RLP of the account as stored in the state trie
+	#[serde(rename="accountRlp")]
+	pub account_rlp: Option<Bytes>,
+	/// State trie nodes, starting from the root
+	#[serde(rename="accountProof")]
+	pub account_proof: Vec<Bytes>,
+	/// Proofs of requested storage entries
+	#[serde(rename="storageProof")]
+	pub storage_proof: Vec<StorageProof>,
+}
+
+impl From<EthAccountProof> for AccountProof {
+	fn from(p: EthAccountProof) -> Self {
+		AccountProof {
+			address: p.address.into(),
+			balance: p.balance.into(),
+			nonce: p.nonce.into(),
+			code_hash: p.code_hash.into(),
+			storage_hash: p.storage_root.into(),
+			account_rlp: p.account_rlp.map(Into::into),
+			account_proof: p.account_proof.into_iter().map(Into::into).collect(),
+			storage_proof: p.storage_proof.into_iter().map(Into::into).collect(),
+		}
+	}
+}
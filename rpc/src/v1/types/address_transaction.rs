@@ -0,0 +1,75 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use v1::types::{H256, U256};
+use ethcore::client::AddressTransaction as EthAddressTransaction;
+
+/// Transaction sent from or to an address
+#[derive(Debug, Serialize, PartialEq)]
+pub struct AddressTransaction {
+	/// Block number
+	#[serde(rename="blockNumber")]
+	pub block_number: U256,
+	/// Block hash
+	#[serde(rename="blockHash")]
+	pub block_hash: H256,
+	/// Transaction index in the block
+	#[serde(rename="transactionIndex")]
+	pub transaction_index: U256,
+	/// Transaction hash
+	#[serde(rename="transactionHash")]
+	pub transaction_hash: H256,
+	/// Transaction was sent by the address
+	pub sent: bool,
+	/// Transaction was sent to the address or created a contract at the address
+	pub received: bool,
+}
+
+impl From<EthAddressTransaction> for AddressTransaction {
+	fn from(t: EthAddressTransaction) -> Self {
+		AddressTransaction {
+			block_number: t.block_number.into(),
+			block_hash: t.block_hash.into(),
+			transaction_index: t.transaction_index.into(),
+			transaction_hash: t.transaction_hash.into(),
+			sent: t.sent,
+			received: t.received,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use v1::types::{H256, U256};
+	use super::AddressTransaction;
+
+	#[test]
+	fn address_transaction_serialization() {
+		let t = AddressTransaction {
+			block_number: U256::from(5),
+			block_hash: H256::from(1),
+			transaction_index: U256::from(2),
+			transaction_hash: H256::from(3),
+			sent: true,
+			received: false,
+		};
+
+		let serialized = serde_json::to_string(&t).unwrap();
+		assert_eq!(serialized, r#"{"blockNumber":"0x5","blockHash":"0x0000000000000000000000000000000000000000000000000000000000000001","transactionIndex":"0x2","transactionHash":"0x0000000000000000000000000000000000000000000000000000000000000003","sent":true,"received":false}"#);
+	}
+}
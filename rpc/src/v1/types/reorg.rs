@@ -0,0 +1,111 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use v1::types::{H256, U256};
+use ethcore::client::Reorg as EthReorg;
+
+/// Chain reorganization
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Reorg {
+	/// Sequential id
+	pub id: U256,
+	/// Unix timestamp
+	pub timestamp: U256,
+	/// Number of retracted blocks
+	pub depth: U256,
+	/// Best block hash before reorganization
+	#[serde(rename="oldHead")]
+	pub old_head: H256,
+	/// Best block number before reorganization
+	#[serde(rename="oldNumber")]
+	pub old_number: U256,
+	/// Best block hash after reorganization
+	#[serde(rename="newHead")]
+	pub new_head: H256,
+	/// Best block number after reorganization
+	#[serde(rename="newNumber")]
+	pub new_number: U256,
+	/// Retracted blocks
+	#[serde(rename="retractedBlocks")]
+	pub retracted_blocks: Vec<H256>,
+	/// Transactions from retracted blocks
+	#[serde(rename="retractedTransactions")]
+	pub retracted_transactions: Vec<H256>,
+	/// Retracted transactions included in the new canonical blocks
+	#[serde(rename="reincludedTransactions")]
+	pub reincluded_transactions: Vec<H256>,
+}
+
+impl From<EthReorg> for Reorg {
+	fn from(r: EthReorg) -> Self {
+		Reorg {
+			id: r.id.into(),
+			timestamp: r.timestamp.into(),
+			depth: r.depth.into(),
+			old_head: r.old_head.into(),
+			old_number: r.old_number.into(),
+			new_head: r.new_head.into(),
+			new_number: r.new_number.into(),
+			retracted_blocks: r.retracted_blocks.into_iter().map(Into::into).collect(),
+			retracted_transactions: r.retracted_transactions.into_iter().map(Into::into).collect(),
+			reincluded_transactions: r.reincluded_transactions.into_iter().map(Into::into).collect(),
+		}
+	}
+}
+
+/// Reorganization history of a single transaction
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TransactionReorgs {
+	/// Was the transaction ever retracted
+	pub retracted: bool,
+	/// Hash of canonical block currently including the transaction
+	#[serde(rename="blockHash")]
+	pub block_hash: Option<H256>,
+	/// Reorganizations which retracted the transaction
+	pub reorgs: Vec<Reorg>,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use v1::types::{H256, U256};
+	use super::{Reorg, TransactionReorgs};
+
+	#[test]
+	fn transaction_reorgs_serialization() {
+		let s = r#"{"retracted":true,"blockHash":null,"reorgs":[{"id":"0x0","timestamp":"0x1","depth":"0x1","oldHead":"0x0000000000000000000000000000000000000000000000000000000000000002","oldNumber":"0x5","newHead":"0x0000000000000000000000000000000000000000000000000000000000000003","newNumber":"0x5","retractedBlocks":["0x0000000000000000000000000000000000000000000000000000000000000002"],"retractedTransactions":["0x0000000000000000000000000000000000000000000000000000000000000004"],"reincludedTransactions":[]}]}"#;
+
+		let reorgs = TransactionReorgs {
+			retracted: true,
+			block_hash: None,
+			reorgs: vec![Reorg {
+				id: U256::from(0),
+				timestamp: U256::from(1),
+				depth: U256::from(1),
+				old_head: H256::from(2),
+				old_number: U256::from(5),
+				new_head: H256::from(3),
+				new_number: U256::from(5),
+				retracted_blocks: vec![H256::from(2)],
+				retracted_transactions: vec![H256::from(4)],
+				reincluded_transactions: vec![],
+			}],
+		};
+
+		let serialized = serde_json::to_string(&reorgs).unwrap();
+		assert_eq!(serialized, s);
+	}
+}
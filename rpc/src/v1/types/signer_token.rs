@@ -0,0 +1,72 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signer UI authorization token
+
+use v1::helpers;
+
+/// Scope of the token
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum TokenScope {
+	/// View-only
+	#[serde(rename="view")]
+	View,
+	/// Allows confirming requests
+	#[serde(rename="confirm")]
+	Confirm,
+}
+
+impl From<helpers::TokenScope> for TokenScope {
+	fn from(s: helpers::TokenScope) -> Self {
+		match s {
+			helpers::TokenScope::View => TokenScope::View,
+			helpers::TokenScope::Confirm => TokenScope::Confirm,
+		}
+	}
+}
+
+/// Authorization token details
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SignerToken {
+	/// Token id
+	pub id: String,
+	/// Token name
+	pub name: String,
+	/// Token scope
+	pub scope: TokenScope,
+	/// Creation timestamp
+	#[serde(rename="createdAt")]
+	pub created_at: u64,
+	/// Expiry timestamp
+	#[serde(rename="expiresAt")]
+	pub expires_at: Option<u64>,
+	/// Last use timestamp
+	#[serde(rename="lastUsedAt")]
+	pub last_used_at: Option<u64>,
+}
+
+impl From<helpers::SignerToken> for SignerToken {
+	fn from(t: helpers::SignerToken) -> Self {
+		SignerToken {
+			id: t.id,
+			name: t.name,
+			scope: t.scope.into(),
+			created_at: t.created_at,
+			expires_at: t.expires_at,
+			last_used_at: t.last_used_at,
+		}
+	}
+}
@@ -0,0 +1,171 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Types used by the signing policy (auto-approval rules of Trusted Signer)
+
+use v1::types::{U256, H160, H256, Bytes};
+use v1::helpers;
+
+/// Action taken when a rule matches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SigningRuleAction {
+	/// Sign and dispatch the request without asking.
+	#[serde(rename="approve")]
+	Approve,
+	/// Reject the request without asking.
+	#[serde(rename="reject")]
+	Reject,
+}
+
+/// Auto-approval rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SigningRule {
+	/// Unique name of the rule
+	pub name: String,
+	/// Action
+	pub action: SigningRuleAction,
+	/// Sender the rule applies to
+	pub sender: Option<H160>,
+	/// Allowed recipients
+	pub recipients: Option<Vec<H160>>,
+	/// Maximal value of a single transaction
+	#[serde(rename="maxValue")]
+	pub max_value: Option<U256>,
+	/// Maximal gas of a single transaction
+	#[serde(rename="maxGas")]
+	pub max_gas: Option<U256>,
+	/// Maximal value sent per day
+	#[serde(rename="dailyLimit")]
+	pub daily_limit: Option<U256>,
+	/// Allowed method selectors (4 bytes)
+	pub methods: Option<Vec<Bytes>>,
+}
+
+impl From<helpers::SigningRule> for SigningRule {
+	fn from(r: helpers::SigningRule) -> Self {
+		SigningRule {
+			name: r.name,
+			action: match r.action {
+				helpers::RuleAction::Approve => SigningRuleAction::Approve,
+				helpers::RuleAction::Reject => SigningRuleAction::Reject,
+			},
+			sender: r.sender.map(Into::into),
+			recipients: r.recipients.map(|r| r.into_iter().map(Into::into).collect()),
+			max_value: r.max_value.map(Into::into),
+			max_gas: r.max_gas.map(Into::into),
+			daily_limit: r.daily_limit.map(Into::into),
+			methods: r.methods.map(|m| m.into_iter().map(Into::into).collect()),
+		}
+	}
+}
+
+impl Into<helpers::SigningRule> for SigningRule {
+	fn into(self) -> helpers::SigningRule {
+		helpers::SigningRule {
+			name: self.name,
+			action: match self.action {
+				SigningRuleAction::Approve => helpers::RuleAction::Approve,
+				SigningRuleAction::Reject => helpers::RuleAction::Reject,
+			},
+			sender: self.sender.map(Into::into),
+			recipients: self.recipients.map(|r| r.into_iter().map(Into::into).collect()),
+			max_value: self.max_value.map(Into::into),
+			max_gas: self.max_gas.map(Into::into),
+			daily_limit: self.daily_limit.map(Into::into),
+			methods: self.methods.map(|m| m.into_iter().map(Into::into).collect()),
+		}
+	}
+}
+
+/// Single entry of the signing policy audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SigningAuditEntry {
+	/// Unix timestamp of the decision
+	pub timestamp: u64,
+	/// Name of the rule that took the decision
+	pub rule: String,
+	/// Decision taken
+	pub action: SigningRuleAction,
+	/// Sender
+	pub from: H160,
+	/// Recipient
+	pub to: Option<H160>,
+	/// Value
+	pub value: U256,
+	/// Gas
+	pub gas: U256,
+	/// Hash of dispatched transaction
+	pub hash: Option<H256>,
+	/// Error message if the request could not be dispatched
+	pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use v1::helpers;
+	use super::*;
+
+	#[test]
+	fn should_deserialize_signing_rule() {
+		// given
+		let s = r#"{
+			"name": "bot",
+			"action": "approve",
+			"sender": "0x0000000000000000000000000000000000000001",
+			"maxValue": "0x3e8",
+			"methods": ["0xa9059cbb"]
+		}"#;
+
+		// when
+		let rule: SigningRule = serde_json::from_str(s).unwrap();
+		let rule: helpers::SigningRule = rule.into();
+
+		// then
+		assert_eq!(rule, helpers::SigningRule {
+			name: "bot".into(),
+			action: helpers::RuleAction::Approve,
+			sender: Some(1.into()),
+			recipients: None,
+			max_value: Some(1000.into()),
+			max_gas: None,
+			daily_limit: None,
+			methods: Some(vec![vec![0xa9, 0x05, 0x9c, 0xbb]]),
+		});
+	}
+
+	#[test]
+	fn should_serialize_signing_rule() {
+		// given
+		let rule = helpers::SigningRule {
+			name: "deny".into(),
+			action: helpers::RuleAction::Reject,
+			sender: None,
+			recipients: Some(vec![2.into()]),
+			max_value: None,
+			max_gas: None,
+			daily_limit: None,
+			methods: None,
+		};
+
+		// when
+		let res = serde_json::to_string(&SigningRule::from(rule));
+		let expected = r#"{"name":"deny","action":"reject","sender":null,"recipients":["0x0000000000000000000000000000000000000002"],"maxValue":null,"maxGas":null,"dailyLimit":null,"methods":null}"#;
+
+		// then
+		assert_eq!(res.unwrap(), expected.to_owned());
+	}
+}
@@ -15,7 +15,9 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use serde::{Serialize, Serializer};
-use v1::types::U256;
+use ethsync::{PeerReputation as EthPeerReputation, PeerInfo as EthPeerInfo, IpFilterSettings,
+	TrafficStats as EthTrafficStats, PacketTraffic as EthPacketTraffic, BandwidthLimits as EthBandwidthLimits};
+use v1::types::{U256, H256};
 
 /// Sync info
 #[derive(Default, Debug, Serialize, PartialEq)]
@@ -40,6 +42,196 @@ pub struct Peers {
 	pub connected: usize,
 	/// Max number of peers
 	pub max: u32,
+	/// Detailed information on connected peers
+	pub peers: Vec<PeerInfo>,
+}
+
+/// Connected peer info
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PeerInfo {
+	/// Node id
+	pub id: String,
+	/// Client version
+	pub name: String,
+	/// Negotiated capabilities
+	pub caps: Vec<String>,
+	/// RLPx protocol version
+	#[serde(rename="protocolVersion")]
+	pub protocol_version: u32,
+	/// Remote address
+	#[serde(rename="remoteAddress")]
+	pub remote_address: String,
+	/// Bytes sent to the peer
+	#[serde(rename="bytesSent")]
+	pub bytes_sent: u64,
+	/// Bytes received from the peer
+	#[serde(rename="bytesReceived")]
+	pub bytes_received: u64,
+	/// Ping delay in milliseconds
+	#[serde(rename="pingMs")]
+	pub ping_ms: Option<u64>,
+	/// Seconds since the connection was established
+	#[serde(rename="connectedSecs")]
+	pub connected_secs: u64,
+	/// True if the connection was originated by us
+	pub originated: bool,
+	/// Best block hash
+	pub head: Option<H256>,
+	/// Best block number
+	#[serde(rename="headNumber")]
+	pub head_number: Option<U256>,
+	/// Total difficulty
+	pub difficulty: Option<U256>,
+}
+
+impl From<EthPeerInfo> for PeerInfo {
+	fn from(p: EthPeerInfo) -> Self {
+		PeerInfo {
+			id: format!("0x{}", p.id),
+			name: p.client_version,
+			caps: p.capabilities,
+			protocol_version: p.protocol_version,
+			remote_address: p.remote_address,
+			bytes_sent: p.bytes_sent,
+			bytes_received: p.bytes_received,
+			ping_ms: p.ping_ms,
+			connected_secs: p.connected_secs,
+			originated: p.originated,
+			head: p.head.map(Into::into),
+			head_number: p.head_number.map(Into::into),
+			difficulty: p.difficulty.map(Into::into),
+		}
+	}
+}
+
+/// Peer reputation
+#[derive(Default, Debug, Serialize, PartialEq)]
+pub struct PeerReputation {
+	/// Node id
+	pub id: String,
+	/// Reputation score
+	pub score: i32,
+	/// Unix timestamp the ban expires at
+	#[serde(rename="bannedUntil")]
+	pub banned_until: Option<u64>,
+}
+
+impl From<EthPeerReputation> for PeerReputation {
+	fn from(r: EthPeerReputation) -> Self {
+		PeerReputation {
+			id: format!("0x{}", r.id),
+			score: r.score,
+			banned_until: r.banned_until,
+		}
+	}
+}
+
+/// Address ranges peers are allowed to use
+#[derive(Default, Debug, Serialize, PartialEq)]
+pub struct IpFilter {
+	/// Predefined ranges: all, public, private or none
+	pub allow: String,
+	/// Additional allowed CIDR ranges
+	#[serde(rename="allowRanges")]
+	pub allow_ranges: Vec<String>,
+	/// Denied CIDR ranges
+	#[serde(rename="denyRanges")]
+	pub deny_ranges: Vec<String>,
+}
+
+impl From<IpFilterSettings> for IpFilter {
+	fn from(f: IpFilterSettings) -> Self {
+		IpFilter {
+			allow: f.allow,
+			allow_ranges: f.allow_ranges,
+			deny_ranges: f.deny_ranges,
+		}
+	}
+}
+
+/// Bandwidth limits in bytes per second, zero means unlimited
+#[derive(Default, Debug, Serialize, PartialEq)]
+pub struct BandwidthLimits {
+	/// Upload limit
+	pub upload: u64,
+	/// Download limit
+	pub download: u64,
+	/// Limit for serving data requested by peers
+	pub serve: u64,
+}
+
+impl From<EthBandwidthLimits> for BandwidthLimits {
+	fn from(l: EthBandwidthLimits) -> Self {
+		BandwidthLimits {
+			upload: l.upload,
+			download: l.download,
+			serve: l.serve,
+		}
+	}
+}
+
+/// Traffic of a subprotocol packet type
+#[derive(Default, Debug, Serialize, PartialEq)]
+pub struct PacketTraffic {
+	/// Subprotocol name
+	pub protocol: String,
+	/// Packet id
+	#[serde(rename="packetId")]
+	pub packet_id: u32,
+	/// Packets sent
+	#[serde(rename="sentPackets")]
+	pub sent_packets: u64,
+	/// Bytes sent
+	#[serde(rename="sentBytes")]
+	pub sent_bytes: u64,
+	/// Packets received
+	#[serde(rename="receivedPackets")]
+	pub received_packets: u64,
+	/// Bytes received
+	#[serde(rename="receivedBytes")]
+	pub received_bytes: u64,
+}
+
+impl From<EthPacketTraffic> for PacketTraffic {
+	fn from(p: EthPacketTraffic) -> Self {
+		PacketTraffic {
+			protocol: p.protocol,
+			packet_id: p.packet_id,
+			sent_packets: p.sent_packets,
+			sent_bytes: p.sent_bytes,
+			received_packets: p.received_packets,
+			received_bytes: p.received_bytes,
+		}
+	}
+}
+
+/// Network traffic summary
+#[derive(Default, Debug, Serialize, PartialEq)]
+pub struct Traffic {
+	/// Total bytes sent
+	#[serde(rename="bytesSent")]
+	pub bytes_sent: u64,
+	/// Total bytes received
+	#[serde(rename="bytesReceived")]
+	pub bytes_received: u64,
+	/// Sessions created
+	pub sessions: u64,
+	/// Traffic by packet type
+	pub packets: Vec<PacketTraffic>,
+	/// Bandwidth limits in effect
+	pub limits: BandwidthLimits,
+}
+
+impl From<EthTrafficStats> for Traffic {
+	fn from(t: EthTrafficStats) -> Self {
+		Traffic {
+			bytes_sent: t.bytes_sent,
+			bytes_received: t.bytes_received,
+			sessions: t.sessions,
+			packets: t.packets.into_iter().map(Into::into).collect(),
+			limits: t.limits.into(),
+		}
+	}
 }
 
 /// Sync status
@@ -64,7 +256,7 @@ impl Serialize for SyncStatus {
 #[cfg(test)]
 mod tests {
 	use serde_json;
-	use super::{SyncInfo, SyncStatus, Peers};
+	use super::{SyncInfo, SyncStatus, Peers, PeerReputation, IpFilter};
 
 	#[test]
 	fn test_serialize_sync_info() {
@@ -77,7 +269,21 @@ mod tests {
 	fn test_serialize_peers() {
 		let t = Peers::default();
 		let serialized = serde_json::to_string(&t).unwrap();
-		assert_eq!(serialized, r#"{"active":0,"connected":0,"max":0}"#);
+		assert_eq!(serialized, r#"{"active":0,"connected":0,"max":0,"peers":[]}"#);
+	}
+
+	#[test]
+	fn test_serialize_peer_reputation() {
+		let t = PeerReputation { id: "0x01".into(), score: -60, banned_until: Some(1000) };
+		let serialized = serde_json::to_string(&t).unwrap();
+		assert_eq!(serialized, r#"{"id":"0x01","score":-60,"bannedUntil":1000}"#);
+	}
+
+	#[test]
+	fn test_serialize_ip_filter() {
+		let t = IpFilter { allow: "private".into(), allow_ranges: vec![], deny_ranges: vec!["10.0.0.0/8".into()] };
+		let serialized = serde_json::to_string(&t).unwrap();
+		assert_eq!(serialized, r#"{"allow":"private","allowRanges":[],"denyRanges":["10.0.0.0/8"]}"#);
 	}
 
 	#[test]
@@ -17,6 +17,7 @@
 use super::{H256, U256};
 
 use serde::{Serialize, Serializer};
+use ethcore::miner::WorkerStats;
 
 /// The result of an `eth_getWork` call: it differs based on an option
 /// whether to send the block number.
@@ -41,3 +42,33 @@ impl Serialize for Work {
 	}
 }
 
+
+/// Share accounting of a stratum worker.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct StratumWorker {
+	/// Number of accepted shares
+	pub accepted: u64,
+	/// Number of rejected shares
+	pub rejected: u64,
+	/// Number of shares submitted for unknown or outdated jobs
+	pub stale: u64,
+	/// Number of shares that sealed a block
+	pub blocks: u64,
+	/// Sum of difficulties of accepted shares
+	pub work: U256,
+	/// Current share difficulty
+	pub difficulty: U256,
+}
+
+impl From<WorkerStats> for StratumWorker {
+	fn from(s: WorkerStats) -> Self {
+		StratumWorker {
+			accepted: s.accepted,
+			rejected: s.rejected,
+			stale: s.stale,
+			blocks: s.blocks,
+			work: s.work.into(),
+			difficulty: s.difficulty.into(),
+		}
+	}
+}
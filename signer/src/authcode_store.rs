@@ -22,6 +22,7 @@ use std::fs;
 use std::path::Path;
 use std::time;
 use util::{H256, Hashable};
+use rpc::{SignerToken, TokenScope};
 
 /// Providing current time in seconds
 pub trait TimeProvider {
@@ -48,11 +49,118 @@ impl TimeProvider for DefaultTimeProvider {
 /// No of seconds the hash is valid
 const TIME_THRESHOLD: u64 = 7;
 const TOKEN_LENGTH: usize = 16;
+/// Length of the token id in bytes
+const TOKEN_ID_LENGTH: usize = 4;
 const INITIAL_TOKEN: &'static str = "initial";
+const SEPARATOR: char = ';';
+
+/// Single authorization code with its metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthCode {
+	/// Secret code
+	pub code: String,
+	/// Name of the code (empty for unnamed codes)
+	pub name: String,
+	/// Scope
+	pub scope: TokenScope,
+	/// Creation time
+	pub created_at: u64,
+	/// Expiry time
+	pub expires_at: Option<u64>,
+	/// Time of last successful authorization
+	pub last_used_at: Option<u64>,
+}
+
+impl AuthCode {
+	fn unnamed(code: String, created_at: u64) -> Self {
+		AuthCode {
+			code: code,
+			name: "".into(),
+			scope: TokenScope::Confirm,
+			created_at: created_at,
+			expires_at: None,
+			last_used_at: None,
+		}
+	}
+
+	/// Public identifier of the code (derived from its hash, so it doesn't reveal the code).
+	pub fn id(&self) -> String {
+		self.code.sha3().hex()[0..TOKEN_ID_LENGTH * 2].to_owned()
+	}
+
+	fn is_expired(&self, now: u64) -> bool {
+		self.expires_at.map_or(false, |expires| expires <= now)
+	}
+
+	/// Parses single line of codes file.
+	/// Lines without metadata (written by previous versions) are unnamed codes with full access.
+	fn from_line(line: &str) -> Option<Self> {
+		fn opt(v: &str) -> Option<u64> {
+			v.parse().ok()
+		}
+
+		let parts = line.split(SEPARATOR).collect::<Vec<_>>();
+		let code = match parts.get(0) {
+			Some(code) if code.len() >= TOKEN_LENGTH => code.to_string(),
+			_ => return None,
+		};
+		if parts.len() == 1 {
+			return Some(AuthCode::unnamed(code, 0));
+		}
+		if parts.len() != 6 {
+			return None;
+		}
+		let scope = match parts[2] {
+			"view" => TokenScope::View,
+			"confirm" => TokenScope::Confirm,
+			_ => return None,
+		};
+		Some(AuthCode {
+			code: code,
+			name: parts[1].into(),
+			scope: scope,
+			created_at: opt(parts[3]).unwrap_or(0),
+			expires_at: opt(parts[4]),
+			last_used_at: opt(parts[5]),
+		})
+	}
+
+	fn to_line(&self) -> String {
+		fn opt(v: Option<u64>) -> String {
+			v.map_or_else(|| "-".into(), |v| format!("{}", v))
+		}
+
+		let scope = match self.scope {
+			TokenScope::View => "view",
+			TokenScope::Confirm => "confirm",
+		};
+		format!("{code};{name};{scope};{created};{expires};{used}",
+			code = self.code,
+			name = self.name,
+			scope = scope,
+			created = self.created_at,
+			expires = opt(self.expires_at),
+			used = opt(self.last_used_at),
+		)
+	}
+}
+
+impl Into<SignerToken> for AuthCode {
+	fn into(self) -> SignerToken {
+		SignerToken {
+			id: self.id(),
+			name: self.name,
+			scope: self.scope,
+			created_at: self.created_at,
+			expires_at: self.expires_at,
+			last_used_at: self.last_used_at,
+		}
+	}
+}
 
 /// Manages authorization codes for `SignerUIs`
 pub struct AuthCodes<T: TimeProvider = DefaultTimeProvider> {
-	codes: Vec<String>,
+	codes: Vec<AuthCode>,
 	now: T,
 }
 
@@ -70,8 +178,7 @@ impl AuthCodes<DefaultTimeProvider> {
 			}
 		};
 		let codes = content.lines()
-			.filter(|f| f.len() >= TOKEN_LENGTH)
-			.map(String::from)
+			.filter_map(AuthCode::from_line)
 			.collect();
 		Ok(AuthCodes {
 			codes: codes,
@@ -86,46 +193,80 @@ impl<T: TimeProvider> AuthCodes<T> {
 	/// Writes all `AuthCodes` to a disk.
 	pub fn to_file(&self, file: &Path) -> io::Result<()> {
 		let mut file = try!(fs::File::create(file));
-		let content = self.codes.join("\n");
+		let content = self.codes.iter().map(AuthCode::to_line).collect::<Vec<_>>().join("\n");
 		file.write_all(content.as_bytes())
 	}
 
 	/// Creates a new `AuthCodes` store with given `TimeProvider`.
 	pub fn new(codes: Vec<String>, now: T) -> Self {
 		AuthCodes {
-			codes: codes,
+			codes: codes.into_iter().map(|code| AuthCode::unnamed(code, 0)).collect(),
 			now: now,
 		}
 	}
 
 	/// Checks if given hash is correct identifier of `SignerUI`
 	pub fn is_valid(&mut self, hash: &H256, time: u64) -> bool {
+		self.authorize(hash, time).is_some()
+	}
+
+	/// Checks if given hash is correct identifier of `SignerUI` and returns the scope of matching code.
+	/// Updates last usage time of the code.
+	pub fn authorize(&mut self, hash: &H256, time: u64) -> Option<TokenScope> {
+		self.authorize_code(hash, time).map(|code| code.scope)
+	}
+
+	/// Checks if given hash is correct identifier of `SignerUI` and returns the matching code.
+	/// Using the initial token returns the newly generated code.
+	/// Updates last usage time of the code.
+	pub fn authorize_code(&mut self, hash: &H256, time: u64) -> Option<AuthCode> {
 		let now = self.now.now();
 		// check time
 		if time >= now + TIME_THRESHOLD || time <= now - TIME_THRESHOLD {
 			warn!(target: "signer", "Received old authentication request. ({} vs {})", now, time);
-			return false;
+			return None;
 		}
 
-		let as_token = |code| format!("{}:{}", code, time).sha3();
+		let as_token = |code: &str| format!("{}:{}", code, time).sha3();
 
 		// Check if it's the initial token.
 		if self.is_empty() {
 			let initial = &as_token(INITIAL_TOKEN) == hash;
 			// Initial token can be used only once.
 			if initial {
-				let _ = self.generate_new();
+				return self.generate_new().ok().and_then(|_| self.codes.last().cloned());
 			}
-			return initial;
+			return None;
 		}
 
 		// look for code
+		self.codes.iter_mut()
+			.find(|code| !code.is_expired(now) && &as_token(&code.code) == hash)
+			.map(|code| {
+				code.last_used_at = Some(now);
+				code.clone()
+			})
+	}
+
+	/// Returns the scope of given secret code if it's still valid (not revoked nor expired).
+	pub fn scope_of(&self, code: &str) -> Option<TokenScope> {
+		let now = self.now.now();
 		self.codes.iter()
-			.any(|code| &as_token(code) == hash)
+			.find(|c| c.code == code && !c.is_expired(now))
+			.map(|c| c.scope)
 	}
 
 	/// Generates and returns a new code that can be used by `SignerUIs`
 	pub fn generate_new(&mut self) -> io::Result<String> {
+		self.generate_named("", TokenScope::Confirm, None)
+	}
+
+	/// Generates and returns a new named code with given scope, valid for `valid_for` seconds.
+	pub fn generate_named(&mut self, name: &str, scope: TokenScope, valid_for: Option<u64>) -> io::Result<String> {
+		if name.contains(SEPARATOR) || name.contains('\n') {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "Token name cannot contain `;` or new lines."));
+		}
+
 		let mut rng = try!(OsRng::new());
 		let code = rng.gen_ascii_chars().take(TOKEN_LENGTH).collect::<String>();
 		let readable_code = code.as_bytes()
@@ -134,10 +275,40 @@ impl<T: TimeProvider> AuthCodes<T> {
 			.collect::<Vec<String>>()
 			.join("-");
 		trace!(target: "signer", "New authentication token generated.");
-		self.codes.push(code);
+		let now = self.now.now();
+		self.codes.push(AuthCode {
+			code: code,
+			name: name.into(),
+			scope: scope,
+			created_at: now,
+			expires_at: valid_for.map(|v| now + v),
+			last_used_at: None,
+		});
 		Ok(readable_code)
 	}
 
+	/// Returns all codes.
+	pub fn codes(&self) -> &[AuthCode] {
+		&self.codes
+	}
+
+	/// Removes codes with given name or id. Returns `true` if any code was removed.
+	pub fn revoke(&mut self, name_or_id: &str) -> bool {
+		let len = self.codes.len();
+		self.codes.retain(|code| {
+			let by_name = !code.name.is_empty() && code.name == name_or_id;
+			let by_id = code.id() == name_or_id;
+			!by_name && !by_id
+		});
+		len != self.codes.len()
+	}
+
+	/// Removes all expired codes.
+	pub fn clear_expired(&mut self) {
+		let now = self.now.now();
+		self.codes.retain(|code| !code.is_expired(now));
+	}
+
 	/// Returns true if there are no tokens in this store
 	pub fn is_empty(&self) -> bool {
 		self.codes.is_empty()
@@ -149,6 +320,7 @@ impl<T: TimeProvider> AuthCodes<T> {
 mod tests {
 
 	use util::{H256, Hashable};
+	use rpc::TokenScope;
 	use super::*;
 
 	fn generate_hash(val: &str, time: u64) -> H256 {
@@ -199,6 +371,97 @@ mod tests {
 		assert_eq!(res, false);
 	}
 
+	#[test]
+	fn should_return_scope_of_named_code() {
+		// given
+		let time = 99;
+		let mut codes = AuthCodes::new(vec![], || 100);
+		let view = codes.generate_named("alice", TokenScope::View, None).unwrap().replace("-", "");
+		let confirm = codes.generate_named("bob", TokenScope::Confirm, None).unwrap().replace("-", "");
+
+		// when
+		let res1 = codes.authorize(&generate_hash(&view, time), time);
+		let res2 = codes.authorize(&generate_hash(&confirm, time), time);
+
+		// then
+		assert_eq!(res1, Some(TokenScope::View));
+		assert_eq!(res2, Some(TokenScope::Confirm));
+		assert_eq!(codes.codes()[0].last_used_at, Some(100));
+		assert_eq!(codes.codes()[0].created_at, 100);
+	}
+
+	#[test]
+	fn should_reject_expired_code() {
+		// given
+		let time = 99;
+		let mut codes = AuthCodes::new(vec![], || 100);
+		let code = codes.generate_named("alice", TokenScope::Confirm, Some(0)).unwrap().replace("-", "");
+
+		// when
+		let res = codes.is_valid(&generate_hash(&code, time), time);
+		codes.clear_expired();
+
+		// then
+		assert_eq!(res, false);
+		assert!(codes.is_empty());
+	}
+
+	#[test]
+	fn should_revoke_code_by_name_or_id() {
+		// given
+		let mut codes = AuthCodes::new(vec![], || 100);
+		codes.generate_named("alice", TokenScope::View, None).unwrap();
+		let bob = codes.generate_named("bob", TokenScope::View, None).unwrap().replace("-", "");
+		let bob_id = codes.codes()[1].id();
+
+		// when
+		let res1 = codes.revoke("alice");
+		let res2 = codes.revoke("carol");
+		let res3 = codes.revoke(&bob[0..8]);
+		let res4 = codes.revoke(&bob_id);
+
+		// then
+		assert_eq!(res1, true);
+		assert_eq!(res2, false);
+		assert_eq!(res3, false);
+		assert_eq!(res4, true);
+		assert!(codes.is_empty());
+	}
+
+	#[test]
+	fn should_not_return_scope_of_revoked_code() {
+		// given
+		let mut codes = AuthCodes::new(vec![], || 100);
+		let alice = codes.generate_named("alice", TokenScope::View, None).unwrap().replace("-", "");
+		assert_eq!(codes.scope_of(&alice), Some(TokenScope::View));
+
+		// when
+		codes.revoke("alice");
+
+		// then
+		assert_eq!(codes.scope_of(&alice), None);
+	}
+
+	#[test]
+	fn should_read_legacy_and_named_lines() {
+		// given
+		let legacy = "23521352asdfasdfadf";
+		let named = "abcdabcdabcdabcd;alice;view;100;200;-";
+
+		// when
+		let res1 = AuthCode::from_line(legacy).unwrap();
+		let res2 = AuthCode::from_line(named).unwrap();
+
+		// then
+		assert_eq!(res1.scope, TokenScope::Confirm);
+		assert_eq!(res1.name, "".to_owned());
+		assert_eq!(res2.to_line(), named.to_owned());
+		assert_eq!(res2.expires_at, Some(200));
+		assert_eq!(res2.last_used_at, None);
+		assert_eq!(res2.id(), "abcdabcdabcdabcd".sha3().hex()[0..8].to_owned());
+		assert_eq!(AuthCode::from_line("żółćżółćżółćżółć").unwrap().id().len(), 8);
+	}
+
 	#[test]
 	fn should_return_false_if_hash_is_valid_but_time_is_invalid() {
 		// given
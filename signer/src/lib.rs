@@ -51,6 +51,7 @@ extern crate ethcore_util as util;
 extern crate ethcore_io as io;
 extern crate ethcore_rpc as rpc;
 extern crate jsonrpc_core;
+extern crate serde_json;
 extern crate ws;
 #[cfg(feature = "ui")]
 extern crate parity_dapps_signer as signer;
@@ -19,7 +19,7 @@ use std::thread;
 use std::time::{self, Duration};
 use std::sync::Arc;
 use devtools::{http_client, RandomTempPath};
-use rpc::ConfirmationsQueue;
+use rpc::{ConfirmationsQueue, TokenScope};
 use util::Hashable;
 use rand;
 
@@ -156,6 +156,36 @@ fn should_allow_if_authorization_is_correct() {
 	assert_eq!(response.status, "HTTP/1.1 101 Switching Protocols".to_owned());
 }
 
+#[test]
+fn should_block_if_token_has_expired() {
+	// given
+	let (server, port, mut authcodes) = serve();
+	let code = authcodes.generate_named("expired", TokenScope::Confirm, Some(0)).unwrap().replace("-", "");
+	authcodes.to_file(&authcodes.path).unwrap();
+	let timestamp = time::UNIX_EPOCH.elapsed().unwrap().as_secs();
+
+	// when
+	let response = request(server,
+		&format!("\
+			GET / HTTP/1.1\r\n\
+			Host: 127.0.0.1:{}\r\n\
+			Connection: Close\r\n\
+			Sec-WebSocket-Key: x3JJHMbDL1EzLkh9GBhXDw==\r\n\
+			Sec-WebSocket-Protocol: {:?}_{}\r\n\
+			Sec-WebSocket-Version: 13\r\n\
+			\r\n\
+			{{}}
+		",
+		port,
+		format!("{}:{}", code, timestamp).sha3(),
+		timestamp,
+		)
+	);
+
+	// then
+	assert_eq!(response.status, "HTTP/1.1 403 FORBIDDEN".to_owned());
+}
+
 #[test]
 fn should_allow_initial_connection_but_only_once() {
 	// given
@@ -17,11 +17,13 @@
 //! Session handlers factory.
 
 use ws;
-use authcode_store::AuthCodes;
+use authcode_store::{AuthCodes, AuthCode};
 use std::path::{PathBuf, Path};
 use std::sync::Arc;
 use std::str::FromStr;
 use jsonrpc_core::IoHandler;
+use rpc::TokenScope;
+use serde_json;
 use util::{H256, Mutex, version};
 
 #[cfg(feature = "ui")]
@@ -59,33 +61,161 @@ fn origin_is_allowed(self_origin: &str, header: Option<&[u8]>) -> bool {
 	}
 }
 
-fn auth_is_valid(codes_path: &Path, protocols: ws::Result<Vec<&str>>) -> bool {
+fn auth_code(codes_path: &Path, protocols: ws::Result<Vec<&str>>) -> Option<AuthCode> {
 	match protocols {
 		Ok(ref protocols) if protocols.len() == 1 => {
-			protocols.iter().any(|protocol| {
-				let mut split = protocol.split('_');
-				let auth = split.next().and_then(|v| H256::from_str(v).ok());
-				let time = split.next().and_then(|v| u64::from_str_radix(v, 10).ok());
-
-				if let (Some(auth), Some(time)) = (auth, time) {
-					// Check if the code is valid
-					AuthCodes::from_file(codes_path)
-						.map(|mut codes| {
-							let res = codes.is_valid(&auth, time);
-							// make sure to save back authcodes - it might have been modified
-							if let Err(_) = codes.to_file(codes_path) {
-								warn!(target: "signer", "Couldn't save authorization codes to file.");
-							}
-							res
-						})
-						.unwrap_or(false)
-				} else {
-					false
-				}
-			})
+			let protocol = protocols[0];
+			let mut split = protocol.split('_');
+			let auth = split.next().and_then(|v| H256::from_str(v).ok());
+			let time = split.next().and_then(|v| u64::from_str_radix(v, 10).ok());
+
+			if let (Some(auth), Some(time)) = (auth, time) {
+				// Check if the code is valid
+				AuthCodes::from_file(codes_path)
+					.ok()
+					.and_then(|mut codes| {
+						let res = codes.authorize_code(&auth, time);
+						// make sure to save back authcodes - it might have been modified
+						if let Err(_) = codes.to_file(codes_path) {
+							warn!(target: "signer", "Couldn't save authorization codes to file.");
+						}
+						res
+					})
+			} else {
+				None
+			}
 		},
-		_ => false
+		_ => None
+	}
+}
+
+// Returns the scope of given code if it's still valid (the token was not revoked nor has expired).
+fn code_scope(codes_path: &Path, code: &str) -> Option<TokenScope> {
+	AuthCodes::from_file(codes_path)
+		.ok()
+		.and_then(|codes| codes.scope_of(code))
+}
+
+/// Read-only methods that can be called using tokens with `view` scope.
+/// Any method not listed here is denied.
+const VIEW_METHODS: &'static [&'static str] = &[
+	"web3_clientVersion",
+	"web3_sha3",
+	"net_version",
+	"net_peerCount",
+	"net_listening",
+	"rpc_modules",
+	"modules",
+	"eth_protocolVersion",
+	"eth_syncing",
+	"eth_coinbase",
+	"eth_mining",
+	"eth_hashrate",
+	"eth_gasPrice",
+	"eth_accounts",
+	"eth_blockNumber",
+	"eth_getBalance",
+	"eth_getStorageAt",
+	"eth_getProof",
+	"eth_getTransactionCount",
+	"eth_getBlockTransactionCountByHash",
+	"eth_getBlockTransactionCountByNumber",
+	"eth_getUncleCountByBlockHash",
+	"eth_getUncleCountByBlockNumber",
+	"eth_getCode",
+	"eth_call",
+	"eth_estimateGas",
+	"eth_getBlockByHash",
+	"eth_getBlockByNumber",
+	"eth_getTransactionByHash",
+	"eth_getTransactionByBlockHashAndIndex",
+	"eth_getTransactionByBlockNumberAndIndex",
+	"eth_getTransactionReceipt",
+	"eth_getUncleByBlockHashAndIndex",
+	"eth_getUncleByBlockNumberAndIndex",
+	"eth_getCompilers",
+	"eth_newFilter",
+	"eth_newBlockFilter",
+	"eth_newPendingTransactionFilter",
+	"eth_getFilterChanges",
+	"eth_getFilterLogs",
+	"eth_uninstallFilter",
+	"eth_getLogs",
+	"eth_checkRequest",
+	"ethcore_transactionsLimit",
+	"ethcore_minGasPrice",
+	"ethcore_extraData",
+	"ethcore_gasFloorTarget",
+	"ethcore_gasCeilTarget",
+	"ethcore_devLogs",
+	"ethcore_devLogsLevels",
+	"ethcore_netChain",
+	"ethcore_netPeers",
+	"ethcore_netPeerReputations",
+	"ethcore_netIpFilter",
+	"ethcore_netTraffic",
+	"ethcore_stratumWorkers",
+	"ethcore_netPort",
+	"ethcore_rpcSettings",
+	"ethcore_nodeName",
+	"ethcore_defaultExtraData",
+	"ethcore_gasPriceStatistics",
+	"ethcore_unsignedTransactionsCount",
+	"ethcore_registryAddress",
+	"ethcore_pendingTransactions",
+	"ethcore_reorgs",
+	"ethcore_transactionReorgs",
+	"ethcore_transactionsByAddress",
+	"personal_requestsToConfirm",
+	"personal_signingRules",
+	"personal_signerTokens",
+	"personal_listAccounts",
+	"personal_accountsInfo",
+	"personal_signerEnabled",
+	"personal_listGethAccounts",
+	"trace_filter",
+	"trace_get",
+	"trace_transaction",
+	"trace_block",
+	"trace_call",
+	"trace_rawTransaction",
+	"trace_replayTransaction",
+];
+
+fn method_is_allowed(scope: TokenScope, method: &str) -> bool {
+	match scope {
+		TokenScope::Confirm => true,
+		TokenScope::View => VIEW_METHODS.contains(&method),
+	}
+}
+
+/// Returns a JSON-RPC error response if any of the methods in the request is not allowed for given scope.
+fn check_scope(scope: TokenScope, req: &str) -> Option<String> {
+	let request: serde_json::Value = match serde_json::from_str(req) {
+		Ok(request) => request,
+		// Let the handler report parse errors.
+		Err(_) => return None,
+	};
+	let calls = match request {
+		serde_json::Value::Array(calls) => calls,
+		call => vec![call],
+	};
+	let forbidden = calls.iter().any(|call| {
+		call.find("method")
+			.and_then(|method| method.as_str())
+			.map_or(false, |method| !method_is_allowed(scope, method))
+	});
+	if !forbidden {
+		return None;
 	}
+	let id = match calls.len() {
+		1 => calls[0].find("id").map_or_else(|| "null".into(), |id| serde_json::to_string(id).unwrap_or_else(|_| "null".into())),
+		_ => "null".into(),
+	};
+	Some(format!(
+		r#"{{"jsonrpc":"2.0","error":{{"code":-32040,"message":"Token scope does not allow calling this method."}},"id":{}}}"#,
+		id
+	))
 }
 
 fn add_headers(mut response: ws::Response, mime: &str) -> ws::Response {
@@ -106,6 +236,8 @@ pub struct Session {
 	skip_origin_validation: bool,
 	self_origin: String,
 	authcodes_path: PathBuf,
+	// Secret code the session was authorized with.
+	code: Option<String>,
 	handler: Arc<IoHandler>,
 }
 
@@ -137,9 +269,12 @@ impl ws::Handler for Session {
 		// (styles file skips origin validation, so make sure to prevent WS connections on this resource)
 		if req.header("sec-websocket-key").is_some() && !is_styles_file {
 			// Check authorization
-			if !auth_is_valid(&self.authcodes_path, req.protocols()) {
-				info!(target: "signer", "Unauthorized connection to Signer API blocked.");
-				return Ok(error(ErrorType::Forbidden, "Not Authorized", "Request to this API was not authorized.", None));
+			match auth_code(&self.authcodes_path, req.protocols()) {
+				Some(code) => self.code = Some(code.code),
+				None => {
+					info!(target: "signer", "Unauthorized connection to Signer API blocked.");
+					return Ok(error(ErrorType::Forbidden, "Not Authorized", "Request to this API was not authorized.", None));
+				},
 			}
 
 			let protocols = req.protocols().expect("Existence checked by authorization.");
@@ -163,6 +298,18 @@ impl ws::Handler for Session {
 
 	fn on_message(&mut self, msg: ws::Message) -> ws::Result<()> {
 		let req = try!(msg.as_text());
+		// The token might have been revoked since the session was opened.
+		let scope = match self.code.as_ref().and_then(|code| code_scope(&self.authcodes_path, code)) {
+			Some(scope) => scope,
+			None => {
+				info!(target: "signer", "Closing session of revoked or expired token.");
+				return self.out.lock().close(ws::CloseCode::Policy);
+			},
+		};
+		if let Some(error) = check_scope(scope, req) {
+			info!(target: "signer", "Blocked call outside of token scope.");
+			return self.out.lock().send(error);
+		}
 		if let Some(async) = self.handler.handle_request(req) {
 			let out = self.out.clone();
 			async.on_result(move |result| {
@@ -204,6 +351,8 @@ impl ws::Factory for Factory {
 			skip_origin_validation: self.skip_origin_validation,
 			self_origin: self.self_origin.clone(),
 			authcodes_path: self.authcodes_path.clone(),
+			// Set during authorization.
+			code: None,
 		}
 	}
 }
@@ -228,3 +377,23 @@ fn error(error: ErrorType, title: &str, message: &str, details: Option<&str>) ->
 	};
 	add_headers(res, "text/html")
 }
+
+#[cfg(test)]
+mod tests {
+	use rpc::TokenScope;
+	use super::method_is_allowed;
+
+	#[test]
+	fn should_only_allow_read_only_methods_in_view_scope() {
+		assert!(method_is_allowed(TokenScope::View, "eth_getBalance"));
+		assert!(method_is_allowed(TokenScope::View, "personal_requestsToConfirm"));
+		assert!(!method_is_allowed(TokenScope::View, "eth_sendTransaction"));
+		assert!(!method_is_allowed(TokenScope::View, "eth_sign"));
+		assert!(!method_is_allowed(TokenScope::View, "ethcore_resetChain"));
+		assert!(!method_is_allowed(TokenScope::View, "ethcore_banPeer"));
+		assert!(!method_is_allowed(TokenScope::View, "ethcore_setIpFilter"));
+		assert!(!method_is_allowed(TokenScope::View, "personal_confirmRequest"));
+		assert!(!method_is_allowed(TokenScope::View, "unknown_method"));
+		assert!(method_is_allowed(TokenScope::Confirm, "eth_sendTransaction"));
+	}
+}
@@ -39,18 +39,55 @@ mod traits {
 	include!(concat!(env!("OUT_DIR"), "/traits.rs"));
 }
 
+mod vardiff;
+mod worker;
+
 pub use traits::{
 	JobDispatcher, PushWorkHandler, Error, ServiceConfiguration,
-	RemoteWorkHandler, RemoteJobDispatcher,
+	RemoteWorkHandler, RemoteJobDispatcher, Work, Share,
 };
+pub use vardiff::VardiffConfig;
+pub use worker::{WorkerStats, difficulty_to_target};
 
 use json_tcp_server::Server as JsonRpcServer;
-use jsonrpc_core::{IoHandler, Params, IoDelegate, to_value, from_params};
+use jsonrpc_core::{IoHandler, Params, IoDelegate, to_value, from_params, ErrorCode};
 use std::sync::Arc;
+use std::time;
 
 use std::net::SocketAddr;
+use std::str::FromStr;
 use std::collections::{HashSet, HashMap};
 use util::{H256, Hashable, RwLock, RwLockReadGuard};
+use worker::{Worker, ShareError};
+
+/// Stratum error codes
+mod codes {
+	pub const UNAUTHORIZED: i64 = 24;
+	pub const STALE_JOB: i64 = 21;
+	pub const DUPLICATE_SHARE: i64 = 22;
+	pub const LOW_DIFFICULTY: i64 = 23;
+	pub const INVALID_SHARE: i64 = 20;
+}
+
+fn error(code: i64, message: &str) -> jsonrpc_core::Error {
+	jsonrpc_core::Error {
+		code: ErrorCode::ServerError(code),
+		message: message.into(),
+		data: None,
+	}
+}
+
+fn now() -> u64 {
+	time::UNIX_EPOCH.elapsed().map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn parse_hash(s: &str) -> Result<H256, jsonrpc_core::Error> {
+	H256::from_str(s.trim_left_matches("0x")).map_err(|_| jsonrpc_core::Error::invalid_params())
+}
+
+fn parse_nonce(s: &str) -> Result<u64, jsonrpc_core::Error> {
+	u64::from_str_radix(s.trim_left_matches("0x"), 16).map_err(|_| jsonrpc_core::Error::invalid_params())
+}
 
 pub struct Stratum {
 	rpc_server: JsonRpcServer,
@@ -61,8 +98,12 @@ pub struct Stratum {
 	job_que: RwLock<HashSet<SocketAddr>>,
 	/// Payload manager
 	dispatcher: Arc<JobDispatcher>,
-	/// Authorized workers (socket - worker_id)
-	workers: Arc<RwLock<HashMap<SocketAddr, String>>>,
+	/// Authorized workers (socket - worker)
+	workers: RwLock<HashMap<SocketAddr, Worker>>,
+	/// Most recent work package
+	work: RwLock<Option<Work>>,
+	/// Share difficulty settings
+	vardiff: VardiffConfig,
 	/// Secret if any
 	secret: Option<H256>,
 }
@@ -72,6 +113,15 @@ impl Stratum {
 		addr: &SocketAddr,
 		dispatcher: Arc<JobDispatcher>,
 		secret: Option<H256>,
+	) -> Result<Arc<Stratum>, json_tcp_server::Error> {
+		Self::start_with_vardiff(addr, dispatcher, secret, VardiffConfig::default())
+	}
+
+	pub fn start_with_vardiff(
+		addr: &SocketAddr,
+		dispatcher: Arc<JobDispatcher>,
+		secret: Option<H256>,
+		vardiff: VardiffConfig,
 	) -> Result<Arc<Stratum>, json_tcp_server::Error> {
 		let handler = Arc::new(IoHandler::new());
 		let server = try!(JsonRpcServer::new(addr, &handler));
@@ -81,13 +131,18 @@ impl Stratum {
 			subscribers: RwLock::new(Vec::new()),
 			job_que: RwLock::new(HashSet::new()),
 			dispatcher: dispatcher,
-			workers: Arc::new(RwLock::new(HashMap::new())),
+			workers: RwLock::new(HashMap::new()),
+			work: RwLock::new(None),
+			vardiff: vardiff,
 			secret: secret,
 		});
 
 		let mut delegate = IoDelegate::<Stratum>::new(stratum.clone());
 		delegate.add_method("miner.subscribe", Stratum::subscribe);
 		delegate.add_method("miner.authorize", Stratum::authorize);
+		delegate.add_method("mining.subscribe", Stratum::subscribe);
+		delegate.add_method("mining.authorize", Stratum::authorize);
+		delegate.add_method("mining.submit", Stratum::submit);
 		stratum.handler.add_delegate(delegate);
 
 		try!(stratum.rpc_server.run_async());
@@ -96,8 +151,6 @@ impl Stratum {
 	}
 
 	fn subscribe(&self, _params: Params) -> std::result::Result<jsonrpc_core::Value, jsonrpc_core::Error> {
-		use std::str::FromStr;
-
 		if let Some(context) = self.rpc_server.request_context() {
 			self.subscribers.write().push(context.socket_addr);
 			self.job_que.write().insert(context.socket_addr);
@@ -124,7 +177,9 @@ impl Stratum {
 				}
 			}
 			if let Some(context) = self.rpc_server.request_context() {
-				self.workers.write().insert(context.socket_addr, worker_id);
+				trace!(target: "stratum", "Worker {} authorized from {:?}", worker_id, context.socket_addr);
+				self.workers.write().insert(context.socket_addr, Worker::new(worker_id, self.vardiff.clone(), now()));
+				self.push_job(&context.socket_addr);
 				to_value(&true)
 			}
 			else {
@@ -134,19 +189,131 @@ impl Stratum {
 		})
 	}
 
+	/// Handles share submission: `[worker, job_id, nonce, pow_hash, mix_hash]`
+	fn submit(&self, params: Params) -> std::result::Result<jsonrpc_core::Value, jsonrpc_core::Error> {
+		let (worker_id, job_id, nonce, pow_hash, mix_hash) = try!(from_params::<(String, String, String, String, String)>(params));
+		let nonce = try!(parse_nonce(&nonce));
+		let pow_hash = try!(parse_hash(&pow_hash));
+		let mix_hash = try!(parse_hash(&mix_hash));
+
+		let socket_addr = match self.rpc_server.request_context() {
+			Some(context) => context.socket_addr,
+			None => return Err(error(codes::UNAUTHORIZED, "Unauthorized worker")),
+		};
+
+		let (share_difficulty, block_difficulty, number) = {
+			let mut workers = self.workers.write();
+			let worker = match workers.get_mut(&socket_addr) {
+				Some(worker) => worker,
+				None => return Err(error(codes::UNAUTHORIZED, "Unauthorized worker")),
+			};
+			if worker.name != worker_id {
+				return Err(error(codes::UNAUTHORIZED, "Unauthorized worker"));
+			}
+
+			let job = worker.note_share(&job_id, &pow_hash, nonce)
+				.map(|job| (job.difficulty, job.work.difficulty, job.work.number));
+			match job {
+				Ok(job) => job,
+				Err(ShareError::Duplicate) => {
+					worker.on_rejected();
+					return Err(error(codes::DUPLICATE_SHARE, "Duplicate share"));
+				},
+				Err(ShareError::Stale) => {
+					worker.on_stale();
+					return Err(error(codes::STALE_JOB, "Job not found"));
+				},
+			}
+		};
+
+		let share = Share {
+			pow_hash: pow_hash,
+			nonce: nonce,
+			mix_hash: mix_hash,
+			difficulty: block_difficulty,
+			number: number,
+		};
+		// Don't hold the lock: sealing a block triggers new work notification.
+		let result = self.dispatcher.submit(share);
+
+		let mut workers = self.workers.write();
+		let worker = match workers.get_mut(&socket_addr) {
+			Some(worker) => worker,
+			None => return Err(error(codes::UNAUTHORIZED, "Unauthorized worker")),
+		};
+		match result {
+			Ok(ref difficulty) if *difficulty >= share_difficulty => {
+				let block = *difficulty >= block_difficulty;
+				trace!(target: "stratum", "Share accepted from {} (block: {})", worker.name, block);
+				worker.on_accepted(share_difficulty, block);
+				Ok(to_value(&true))
+			},
+			Ok(_) => {
+				worker.on_rejected();
+				Err(error(codes::LOW_DIFFICULTY, "Low difficulty share"))
+			},
+			Err(e) => {
+				trace!(target: "stratum", "Invalid share from {}: {:?}", worker.name, e);
+				worker.on_rejected();
+				Err(error(codes::INVALID_SHARE, &format!("Invalid share: {:?}", e)))
+			},
+		}
+	}
+
 	pub fn subscribers(&self) -> RwLockReadGuard<Vec<SocketAddr>> {
 		self.subscribers.read()
 	}
 
+	/// Returns share accounting aggregated by worker name.
+	pub fn workers(&self) -> HashMap<String, WorkerStats> {
+		let mut stats: HashMap<String, WorkerStats> = HashMap::new();
+		for worker in self.workers.read().values() {
+			stats.entry(worker.name.clone()).or_insert_with(Default::default).merge(&worker.stats());
+		}
+		stats
+	}
+
+	/// Current work package
+	fn current_work(&self) -> Option<Work> {
+		if let Some(ref work) = *self.work.read() {
+			return Some(work.clone());
+		}
+		let work = self.dispatcher.work();
+		*self.work.write() = work.clone();
+		work
+	}
+
+	/// Issues new job for the worker connected from `socket_addr` and sends it.
+	fn push_job(&self, socket_addr: &SocketAddr) {
+		let work = match self.current_work() {
+			Some(work) => work,
+			None => return,
+		};
+		let payload = match self.workers.write().get_mut(socket_addr) {
+			Some(worker) => Self::notify_payload(worker.new_job(&work, now())),
+			None => return,
+		};
+		if let Err(e) = self.rpc_server.push_message(socket_addr, payload.as_bytes()) {
+			warn!(target: "stratum", "Error pushing job to {:?}: {:?}", socket_addr, e);
+		}
+	}
+
+	fn notify_payload(job: &worker::Job) -> String {
+		format!(
+			r#"{{"jsonrpc":"2.0","method":"mining.notify","params":["{}","0x{}","0x{}","0x{}","0x{:x}",true]}}"#,
+			job.id, job.work.pow_hash.hex(), job.work.seed_hash.hex(), job.target().hex(), job.work.number
+		)
+	}
+
 	pub fn maintain(&self) {
-		let mut job_que = self.job_que.write();
-		let workers = self.workers.read();
-		for socket_addr in job_que.drain() {
-			if let Some(worker_id) = workers.get(&socket_addr) {
-				let job_payload = self.dispatcher.job(worker_id.to_owned());
-				job_payload.map(
-					|json| self.rpc_server.push_message(&socket_addr, json.as_bytes())
-				);
+		let job_que = self.job_que.write().drain().collect::<Vec<_>>();
+		for socket_addr in job_que {
+			let worker_id = self.workers.read().get(&socket_addr).map(|worker| worker.name.clone());
+			if let Some(worker_id) = worker_id {
+				match self.dispatcher.job(worker_id) {
+					Some(json) => { let _ = self.rpc_server.push_message(&socket_addr, json.as_bytes()); },
+					None => self.push_job(&socket_addr),
+				}
 			}
 			else {
 				trace!(
@@ -161,7 +328,7 @@ impl Stratum {
 impl PushWorkHandler for Stratum {
 	fn push_work_all(&self, payload: String) -> Result<(), Error> {
 		let workers = self.workers.read();
-		println!("pushing work for {} workers", workers.len());
+		trace!(target: "stratum", "pushing work for {} workers", workers.len());
 		for (ref addr, _) in workers.iter() {
 			try!(self.rpc_server.push_message(addr, payload.as_bytes()));
 		}
@@ -192,12 +359,26 @@ impl PushWorkHandler for Stratum {
 		}
 		Ok(())
 	}
+
+	fn push_new_work(&self, work: Work) -> Result<(), Error> {
+		*self.work.write() = Some(work.clone());
+		let now = now();
+		let payloads = self.workers.write().iter_mut()
+			.map(|(addr, worker)| (*addr, Self::notify_payload(worker.new_job(&work, now))))
+			.collect::<Vec<_>>();
+		trace!(target: "stratum", "pushing new work #{} for {} workers", work.number, payloads.len());
+		for (addr, payload) in payloads {
+			try!(self.rpc_server.push_message(&addr, payload.as_bytes()));
+		}
+		Ok(())
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use std::str::FromStr;
+	use util::{U256, H256};
 	use std::net::SocketAddr;
 	use std::sync::{Arc, RwLock};
 	use std::thread;
@@ -232,6 +413,11 @@ mod tests {
 		let _ = *LOG_DUMMY;
 	}
 
+	/// Local address with a port that is currently free
+	fn free_addr() -> SocketAddr {
+		::std::net::TcpListener::bind("127.0.0.1:0").and_then(|listener| listener.local_addr()).unwrap()
+	}
+
 	pub fn dummy_request(addr: &SocketAddr, buf: &[u8]) -> Vec<u8> {
 		use std::io::{Read, Write};
 		use mio::*;
@@ -386,4 +572,100 @@ mod tests {
 
 		assert_eq!(2, result.read().unwrap().len());
 	}
+
+	struct FakeMiner;
+
+	impl JobDispatcher for FakeMiner {
+		fn work(&self) -> Option<Work> {
+			Some(Work {
+				pow_hash: 1.into(),
+				seed_hash: 2.into(),
+				difficulty: 1_000_000.into(),
+				number: 1,
+			})
+		}
+
+		// share difficulty equals to nonce
+		fn submit(&self, share: Share) -> Result<U256, Error> {
+			Ok(share.nonce.into())
+		}
+	}
+
+	fn submit_request(nonce: &str, id: u64) -> String {
+		format!(
+			r#"{{"jsonrpc": "2.0", "method": "mining.submit", "params": ["miner1", "0", "{}", "0x{}", "0x{}"], "id": {}}}"#,
+			nonce, H256::from(1).hex(), H256::from(3).hex(), id
+		)
+	}
+
+	#[test]
+	fn sends_job_and_accounts_shares() {
+		init_log();
+
+		let addr = free_addr();
+		let vardiff = VardiffConfig {
+			initial: 1000.into(),
+			min: 1.into(),
+			..Default::default()
+		};
+		let stratum = Stratum::start_with_vardiff(&addr, Arc::new(FakeMiner), None, vardiff).unwrap();
+
+		let result = Arc::new(RwLock::new(Vec::<String>::new()));
+		let _stop = dummy_async_waiter(
+			&addr,
+			vec![
+				r#"{"jsonrpc": "2.0", "method": "mining.authorize", "params": ["miner1", ""], "id": 1}"#.to_owned(),
+				// accepted share
+				submit_request("0x3e8", 2),
+				// duplicated share
+				submit_request("0x3e8", 3),
+				// low difficulty share
+				submit_request("0x5", 4),
+				// block
+				submit_request("0xf4240", 5),
+			],
+			result.clone(),
+		);
+		::std::thread::park_timeout(::std::time::Duration::from_millis(1000));
+
+		let responses = result.read().unwrap().join("");
+		assert!(responses.contains(r#""method":"mining.notify","params":["0","#));
+		let stats = stratum.workers();
+		let stats = stats.get("miner1").unwrap();
+		assert_eq!(stats.accepted, 2);
+		assert_eq!(stats.rejected, 2);
+		assert_eq!(stats.blocks, 1);
+		assert_eq!(stats.work, 2000.into());
+	}
+
+	#[test]
+	fn notifies_workers_about_new_work() {
+		init_log();
+
+		let addr = free_addr();
+		let stratum = Stratum::start(&addr, Arc::new(VoidManager), None).unwrap();
+
+		let result = Arc::new(RwLock::new(Vec::<String>::new()));
+		let _stop = dummy_async_waiter(
+			&addr,
+			vec![
+				r#"{"jsonrpc": "2.0", "method": "mining.authorize", "params": ["miner1", ""], "id": 1}"#.to_owned(),
+			],
+			result.clone(),
+		);
+		::std::thread::park_timeout(::std::time::Duration::from_millis(150));
+
+		stratum.push_new_work(Work {
+			pow_hash: 1.into(),
+			seed_hash: 2.into(),
+			difficulty: 1_000_000.into(),
+			number: 10,
+		}).unwrap();
+		::std::thread::park_timeout(::std::time::Duration::from_millis(150));
+
+		let result = result.read().unwrap();
+		assert_eq!(2, result.len());
+		assert!(result[1].contains(r#""0x0000000000000000000000000000000000000000000000000000000000000001""#));
+		assert!(result[1].contains(r#""0xa",true]"#));
+	}
 }
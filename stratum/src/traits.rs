@@ -16,7 +16,7 @@
 
 use std;
 use std::error::Error as StdError;
-use util::H256;
+use util::{H256, U256};
 use ipc::IpcConfig;
 
 #[derive(Debug, Clone, Binary)]
@@ -24,6 +24,7 @@ pub enum Error {
 	NoWork,
 	NoWorkers,
 	Io(String),
+	Dispatch(String),
 }
 
 impl From<std::io::Error> for Error {
@@ -42,6 +43,11 @@ pub trait JobDispatcher: Send + Sync {
 	fn difficulty(&self) -> Option<String> { None }
 	// json for job update given worker_id (payload manager should split job!)
 	fn job(&self, _worker_id: String) -> Option<String> { None }
+	// current work package
+	fn work(&self) -> Option<Work> { None }
+	// checks submitted share and seals the block if share is good enough
+	// returns difficulty reached by the share
+	fn submit(&self, _share: Share) -> Result<U256, Error> { Err(Error::NoWork) }
 }
 
 #[derive(Ipc)]
@@ -53,6 +59,37 @@ pub trait PushWorkHandler: Send + Sync {
 
 	/// push the work packages worker-wise (`payload`: json of pow-specific set of work specification)
 	fn push_work(&self, payloads: Vec<String>) -> Result<(), Error>;
+
+	/// push new work package to all authorized workers, issuing new job for every worker
+	fn push_new_work(&self, work: Work) -> Result<(), Error>;
+}
+
+/// Ethereum work package
+#[derive(Debug, Clone, PartialEq, Binary)]
+pub struct Work {
+	/// Hash of the header without seal
+	pub pow_hash: H256,
+	/// Seed hash of the epoch
+	pub seed_hash: H256,
+	/// Difficulty of the block
+	pub difficulty: U256,
+	/// Number of the block
+	pub number: u64,
+}
+
+/// Solution submitted by the worker
+#[derive(Debug, Clone, PartialEq, Binary)]
+pub struct Share {
+	/// Hash of the header without seal
+	pub pow_hash: H256,
+	/// Nonce
+	pub nonce: u64,
+	/// Mix hash
+	pub mix_hash: H256,
+	/// Difficulty of the block the share was mined for
+	pub difficulty: U256,
+	/// Number of the block the share was mined for
+	pub number: u64,
 }
 
 #[derive(Binary)]
@@ -0,0 +1,200 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Variable share difficulty.
+//! Adjusts share difficulty of a worker so that it submits a share every `share_time` seconds.
+
+use util::{U256, Uint};
+
+/// Maximal factor of single difficulty adjustment.
+const MAX_ADJUSTMENT: u64 = 4;
+
+/// Variable difficulty configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VardiffConfig {
+	/// Share difficulty of newly authorized worker
+	pub initial: U256,
+	/// Minimal share difficulty
+	pub min: U256,
+	/// Maximal share difficulty
+	pub max: U256,
+	/// Expected number of seconds between shares
+	pub share_time: u64,
+	/// Number of seconds between difficulty adjustments
+	pub retarget_time: u64,
+}
+
+impl Default for VardiffConfig {
+	fn default() -> Self {
+		VardiffConfig {
+			initial: 100_000_000.into(),
+			min: 1_000_000.into(),
+			max: U256::max_value(),
+			share_time: 10,
+			retarget_time: 60,
+		}
+	}
+}
+
+/// Share difficulty of a single worker.
+#[derive(Debug)]
+pub struct Vardiff {
+	config: VardiffConfig,
+	difficulty: U256,
+	shares: u64,
+	since: u64,
+}
+
+impl Vardiff {
+	/// Creates new difficulty tracker with initial difficulty, `now` is a timestamp in seconds.
+	pub fn new(config: VardiffConfig, now: u64) -> Self {
+		let difficulty = Self::clamp(&config, config.initial);
+		Vardiff {
+			config: config,
+			difficulty: difficulty,
+			shares: 0,
+			since: now,
+		}
+	}
+
+	/// Current share difficulty.
+	pub fn difficulty(&self) -> U256 {
+		self.difficulty
+	}
+
+	/// Records accepted share.
+	pub fn on_share(&mut self) {
+		self.shares += 1;
+	}
+
+	/// Adjusts difficulty if retarget time has passed.
+	/// Returns `true` if difficulty has changed.
+	pub fn retarget(&mut self, now: u64) -> bool {
+		let elapsed = now.saturating_sub(self.since);
+		if elapsed < self.config.retarget_time || elapsed == 0 {
+			return false;
+		}
+
+		// difficulty * (shares * share_time) / elapsed, but no more than MAX_ADJUSTMENT times up or down
+		let expected = self.shares.saturating_mul(self.config.share_time);
+		let max_adjustment = U256::from(MAX_ADJUSTMENT);
+		let new_difficulty = if expected >= elapsed.saturating_mul(MAX_ADJUSTMENT) {
+			match self.difficulty.overflowing_mul(max_adjustment) {
+				(_, true) => U256::max_value(),
+				(difficulty, false) => difficulty,
+			}
+		} else if expected.saturating_mul(MAX_ADJUSTMENT) <= elapsed {
+			self.difficulty / max_adjustment
+		} else {
+			match self.difficulty.overflowing_mul(U256::from(expected)) {
+				(_, true) => self.difficulty / U256::from(elapsed) * U256::from(expected),
+				(difficulty, false) => difficulty / U256::from(elapsed),
+			}
+		};
+		let new_difficulty = Self::clamp(&self.config, new_difficulty);
+
+		self.shares = 0;
+		self.since = now;
+		let changed = new_difficulty != self.difficulty;
+		self.difficulty = new_difficulty;
+		changed
+	}
+
+	fn clamp(config: &VardiffConfig, difficulty: U256) -> U256 {
+		if difficulty < config.min {
+			config.min
+		} else if difficulty > config.max {
+			config.max
+		} else {
+			difficulty
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use util::U256;
+	use super::{Vardiff, VardiffConfig};
+
+	fn config() -> VardiffConfig {
+		VardiffConfig {
+			initial: 1000.into(),
+			min: 10.into(),
+			max: 100_000.into(),
+			share_time: 10,
+			retarget_time: 60,
+		}
+	}
+
+	#[test]
+	fn should_not_retarget_before_retarget_time() {
+		// given
+		let mut vardiff = Vardiff::new(config(), 0);
+		vardiff.on_share();
+
+		// when
+		let res = vardiff.retarget(59);
+
+		// then
+		assert_eq!(res, false);
+		assert_eq!(vardiff.difficulty(), 1000.into());
+	}
+
+	#[test]
+	fn should_increase_difficulty_if_shares_are_too_frequent() {
+		// given
+		let mut vardiff = Vardiff::new(config(), 0);
+		for _ in 0..12 {
+			vardiff.on_share();
+		}
+
+		// when
+		let res = vardiff.retarget(60);
+
+		// then
+		assert_eq!(res, true);
+		assert_eq!(vardiff.difficulty(), 2000.into());
+	}
+
+	#[test]
+	fn should_decrease_difficulty_without_shares() {
+		// given
+		let mut vardiff = Vardiff::new(config(), 0);
+
+		// when
+		for i in 1..5 {
+			vardiff.retarget(i * 60);
+		}
+
+		// then
+		assert_eq!(vardiff.difficulty(), U256::from(10));
+	}
+
+	#[test]
+	fn should_limit_single_adjustment() {
+		// given
+		let mut vardiff = Vardiff::new(config(), 0);
+		for _ in 0..1000 {
+			vardiff.on_share();
+		}
+
+		// when
+		vardiff.retarget(60);
+
+		// then
+		assert_eq!(vardiff.difficulty(), 4000.into());
+	}
+}
@@ -0,0 +1,281 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Jobs and share accounting of a single worker.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use util::{U256, H256, Uint};
+use traits::Work;
+use vardiff::{Vardiff, VardiffConfig};
+
+/// Number of recent jobs a worker can submit shares for.
+const MAX_JOBS: usize = 4;
+
+/// Converts difficulty to the target boundary (`2^256 / difficulty`).
+pub fn difficulty_to_target(difficulty: &U256) -> H256 {
+	if *difficulty <= U256::one() {
+		U256::max_value().into()
+	} else {
+		(((U256::one() << 255) / *difficulty) << 1).into()
+	}
+}
+
+/// Share accounting of a worker.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WorkerStats {
+	/// Number of accepted shares
+	pub accepted: u64,
+	/// Number of shares rejected because of low difficulty, invalid or duplicated solution
+	pub rejected: u64,
+	/// Number of shares submitted for unknown or outdated jobs
+	pub stale: u64,
+	/// Number of shares that sealed a block
+	pub blocks: u64,
+	/// Sum of difficulties of accepted shares
+	pub work: U256,
+	/// Current share difficulty
+	pub difficulty: U256,
+}
+
+impl WorkerStats {
+	/// Merges stats of two connections of the same worker.
+	pub fn merge(&mut self, other: &WorkerStats) {
+		self.accepted += other.accepted;
+		self.rejected += other.rejected;
+		self.stale += other.stale;
+		self.blocks += other.blocks;
+		self.work = self.work.overflowing_add(other.work).0;
+		self.difficulty = ::std::cmp::max(self.difficulty, other.difficulty);
+	}
+}
+
+/// Reason of rejecting a share before its solution is verified.
+#[derive(Debug, PartialEq)]
+pub enum ShareError {
+	/// Job is unknown, outdated or issued for different work
+	Stale,
+	/// Nonce was already submitted for the same work
+	Duplicate,
+}
+
+/// Job issued to a worker.
+#[derive(Debug)]
+pub struct Job {
+	/// Job id
+	pub id: String,
+	/// Work package
+	pub work: Work,
+	/// Share difficulty
+	pub difficulty: U256,
+}
+
+impl Job {
+	/// Share target boundary
+	pub fn target(&self) -> H256 {
+		difficulty_to_target(&self.difficulty)
+	}
+}
+
+/// Authorized worker.
+#[derive(Debug)]
+pub struct Worker {
+	/// Worker name
+	pub name: String,
+	vardiff: Vardiff,
+	jobs: VecDeque<Job>,
+	/// Submitted nonces by work package. Jobs for the same work share the nonces.
+	nonces: HashMap<H256, HashSet<u64>>,
+	next_job: u64,
+	stats: WorkerStats,
+}
+
+impl Worker {
+	/// Creates new worker, `now` is a timestamp in seconds.
+	pub fn new(name: String, vardiff: VardiffConfig, now: u64) -> Self {
+		Worker {
+			name: name,
+			vardiff: Vardiff::new(vardiff, now),
+			jobs: VecDeque::new(),
+			nonces: HashMap::new(),
+			next_job: 0,
+			stats: WorkerStats::default(),
+		}
+	}
+
+	/// Issues new job for given work. Share difficulty is never higher than block difficulty.
+	pub fn new_job(&mut self, work: &Work, now: u64) -> &Job {
+		self.vardiff.retarget(now);
+		let difficulty = ::std::cmp::min(self.vardiff.difficulty(), work.difficulty);
+		let id = format!("{:x}", self.next_job);
+		self.next_job += 1;
+
+		self.jobs.push_back(Job {
+			id: id,
+			work: work.clone(),
+			difficulty: difficulty,
+		});
+		while self.jobs.len() > MAX_JOBS {
+			self.jobs.pop_front();
+		}
+
+		let outdated = self.nonces.keys()
+			.filter(|hash| !self.jobs.iter().any(|job| job.work.pow_hash == **hash))
+			.cloned()
+			.collect::<Vec<_>>();
+		for hash in outdated {
+			self.nonces.remove(&hash);
+		}
+		self.jobs.back().expect("Job was just inserted; qed")
+	}
+
+	/// Records share submitted for given job and returns the job.
+	/// Jobs issued for the same work share the submitted nonces.
+	pub fn note_share(&mut self, job_id: &str, pow_hash: &H256, nonce: u64) -> Result<&Job, ShareError> {
+		let index = match self.jobs.iter().position(|job| job.id == job_id && job.work.pow_hash == *pow_hash) {
+			Some(index) => index,
+			None => return Err(ShareError::Stale),
+		};
+		if !self.nonces.entry(pow_hash.clone()).or_insert_with(HashSet::new).insert(nonce) {
+			return Err(ShareError::Duplicate);
+		}
+		Ok(&self.jobs[index])
+	}
+
+	/// Records share accepted with given share difficulty.
+	pub fn on_accepted(&mut self, difficulty: U256, block: bool) {
+		self.vardiff.on_share();
+		self.stats.accepted += 1;
+		self.stats.work = self.stats.work.overflowing_add(difficulty).0;
+		if block {
+			self.stats.blocks += 1;
+		}
+	}
+
+	/// Records rejected share.
+	pub fn on_rejected(&mut self) {
+		self.stats.rejected += 1;
+	}
+
+	/// Records share for unknown job.
+	pub fn on_stale(&mut self) {
+		self.stats.stale += 1;
+	}
+
+	/// Returns share accounting of this worker.
+	pub fn stats(&self) -> WorkerStats {
+		WorkerStats {
+			difficulty: self.vardiff.difficulty(),
+			..self.stats.clone()
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use util::U256;
+	use traits::Work;
+	use vardiff::VardiffConfig;
+	use super::{Worker, ShareError};
+
+	fn work(difficulty: u64) -> Work {
+		Work {
+			pow_hash: 1.into(),
+			seed_hash: 2.into(),
+			difficulty: difficulty.into(),
+			number: 1,
+		}
+	}
+
+	#[test]
+	fn should_issue_unique_job_ids_and_forget_old_jobs() {
+		// given
+		let mut worker = Worker::new("miner1".into(), VardiffConfig::default(), 0);
+		let first = worker.new_job(&work(1_000_000_000), 0).id.clone();
+
+		// when
+		let ids = (0..4).map(|_| worker.new_job(&work(1_000_000_000), 0).id.clone()).collect::<Vec<_>>();
+
+		// then
+		assert_eq!(first, "0".to_owned());
+		assert_eq!(ids, vec!["1".to_owned(), "2".to_owned(), "3".to_owned(), "4".to_owned()]);
+		assert_eq!(worker.note_share("0", &1.into(), 1).err(), Some(ShareError::Stale));
+		assert!(worker.note_share("4", &1.into(), 1).is_ok());
+	}
+
+	#[test]
+	fn should_limit_share_difficulty_to_block_difficulty() {
+		// given
+		let mut worker = Worker::new("miner1".into(), VardiffConfig::default(), 0);
+
+		// when
+		let difficulty = worker.new_job(&work(1000), 0).difficulty;
+
+		// then
+		assert_eq!(difficulty, U256::from(1000));
+	}
+
+	#[test]
+	fn should_reject_duplicated_nonce() {
+		// given
+		let mut worker = Worker::new("miner1".into(), VardiffConfig::default(), 0);
+		worker.new_job(&work(1000), 0);
+
+		// when
+		let first = worker.note_share("0", &1.into(), 5).is_ok();
+		let second = worker.note_share("0", &1.into(), 5).err();
+		let other_work = worker.note_share("0", &2.into(), 6).err();
+
+		// then
+		assert_eq!(first, true);
+		assert_eq!(second, Some(ShareError::Duplicate));
+		assert_eq!(other_work, Some(ShareError::Stale));
+	}
+
+	#[test]
+	fn should_reject_nonce_submitted_under_other_job_for_same_work() {
+		// given
+		let mut worker = Worker::new("miner1".into(), VardiffConfig::default(), 0);
+		worker.new_job(&work(1000), 0);
+		worker.new_job(&work(1000), 0);
+
+		// when
+		let first = worker.note_share("0", &1.into(), 5).is_ok();
+		let second = worker.note_share("1", &1.into(), 5).err();
+		let other_nonce = worker.note_share("1", &1.into(), 6).is_ok();
+
+		// then
+		assert_eq!(first, true);
+		assert_eq!(second, Some(ShareError::Duplicate));
+		assert_eq!(other_nonce, true);
+	}
+
+	#[test]
+	fn should_forget_nonces_of_outdated_work() {
+		// given
+		let mut worker = Worker::new("miner1".into(), VardiffConfig::default(), 0);
+		worker.new_job(&work(1000), 0);
+		worker.note_share("0", &1.into(), 5).unwrap();
+
+		// when
+		for _ in 0..3 {
+			worker.new_job(&Work { pow_hash: 3.into(), ..work(1000) }, 0);
+		}
+		worker.new_job(&work(1000), 0);
+
+		// then
+		assert!(worker.note_share("4", &1.into(), 5).is_ok());
+	}
+}
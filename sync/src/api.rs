@@ -17,8 +17,9 @@
 use std::sync::Arc;
 use std::str;
 use network::{NetworkProtocolHandler, NetworkService, NetworkContext, PeerId,
-	NetworkConfiguration as BasicNetworkConfiguration, NonReservedPeerMode, NetworkError};
-use util::{U256, H256};
+	NetworkConfiguration as BasicNetworkConfiguration, NonReservedPeerMode, NetworkError, IpFilter};
+use util::{U256, H256, Hashable};
+use rlp::{RlpStream, Stream};
 use io::{TimerToken};
 use ethcore::client::{BlockChainClient, ChainNotify};
 use ethcore::snapshot::SnapshotService;
@@ -61,6 +62,39 @@ binary_fixed_size!(SyncStatus);
 pub trait SyncProvider: Send + Sync {
 	/// Get sync status
 	fn status(&self) -> SyncStatus;
+	/// Get information about connected peers
+	fn peers(&self) -> Vec<PeerInfo>;
+}
+
+#[derive(Binary, Debug, Clone, PartialEq)]
+/// Connected peer information
+pub struct PeerInfo {
+	/// Node id
+	pub id: String,
+	/// Remote address
+	pub remote_address: String,
+	/// Client version
+	pub client_version: String,
+	/// RLPx protocol version
+	pub protocol_version: u32,
+	/// Negotiated capabilities, e.g. "eth/63"
+	pub capabilities: Vec<String>,
+	/// Bytes sent to the peer
+	pub bytes_sent: u64,
+	/// Bytes received from the peer
+	pub bytes_received: u64,
+	/// Ping delay in milliseconds
+	pub ping_ms: Option<u64>,
+	/// Seconds since the connection was established
+	pub connected_secs: u64,
+	/// True if the connection was originated by us
+	pub originated: bool,
+	/// Best block hash as known to the sync
+	pub head: Option<H256>,
+	/// Best block number as known to the sync
+	pub head_number: Option<u64>,
+	/// Total difficulty as known to the sync
+	pub difficulty: Option<U256>,
 }
 
 /// Ethereum network protocol handler
@@ -77,7 +111,9 @@ impl EthSync {
 	/// Creates and register protocol with the network service
 	pub fn new(config: SyncConfig, chain: Arc<BlockChainClient>, snapshot_service: Arc<SnapshotService>, network_config: NetworkConfiguration) -> Result<Arc<EthSync>, NetworkError> {
 		let chain_sync = ChainSync::new(config, &*chain);
-		let service = try!(NetworkService::new(try!(network_config.into_basic())));
+		let mut network_config = try!(network_config.into_basic());
+		network_config.discovery_topic = Some(chain_topic(&chain.chain_info().genesis_hash, &config.network_id));
+		let service = try!(NetworkService::new(network_config));
 		let sync = Arc::new(EthSync{
 			network: service,
 			handler: Arc::new(SyncProtocolHandler { sync: RwLock::new(chain_sync), chain: chain, snapshot_service: snapshot_service }),
@@ -88,6 +124,14 @@ impl EthSync {
 	}
 }
 
+/// Discovery topic identifying peers on the same chain: hash of genesis and network id.
+fn chain_topic(genesis: &H256, network_id: &U256) -> H256 {
+	let mut s = RlpStream::new_list(2);
+	s.append(genesis);
+	s.append(network_id);
+	s.out().sha3()
+}
+
 #[derive(Ipc)]
 #[ipc(client_ident="SyncClient")]
 impl SyncProvider for EthSync {
@@ -95,6 +139,30 @@ impl SyncProvider for EthSync {
 	fn status(&self) -> SyncStatus {
 		self.handler.sync.write().status()
 	}
+
+	/// Get information about connected peers
+	fn peers(&self) -> Vec<PeerInfo> {
+		let peers = self.network.peers_info();
+		let sync = self.handler.sync.read();
+		peers.into_iter().map(|(peer_id, info)| {
+			let head = sync.peer_head(peer_id);
+			PeerInfo {
+				id: info.id.hex(),
+				remote_address: info.remote_address.map_or_else(|| "Unknown".to_owned(), |a| a.to_string()),
+				client_version: info.client_version,
+				protocol_version: info.protocol_version,
+				capabilities: info.capabilities.iter().map(|&(ref p, v)| format!("{}/{}", str::from_utf8(&p[..]).unwrap_or("???"), v)).collect(),
+				bytes_sent: info.bytes_sent,
+				bytes_received: info.bytes_received,
+				ping_ms: info.ping_ms,
+				connected_secs: info.connected_secs,
+				originated: info.originated,
+				head: head.as_ref().map(|h| h.0.clone()),
+				head_number: head.as_ref().and_then(|h| h.1),
+				difficulty: head.as_ref().and_then(|h| h.2),
+			}
+		}).collect()
+	}
 }
 
 struct SyncProtocolHandler {
@@ -126,6 +194,7 @@ impl NetworkProtocolHandler for SyncProtocolHandler {
 	fn timeout(&self, io: &NetworkContext, _timer: TimerToken) {
 		self.sync.write().maintain_peers(&mut NetSyncIo::new(io, &*self.chain, &*self.snapshot_service));
 		self.sync.write().maintain_sync(&mut NetSyncIo::new(io, &*self.chain, &*self.snapshot_service));
+		self.sync.write().send_delayed_responses(&mut NetSyncIo::new(io, &*self.chain, &*self.snapshot_service));
 		self.sync.write().propagate_new_transactions(&mut NetSyncIo::new(io, &*self.chain, &*self.snapshot_service));
 	}
 }
@@ -181,6 +250,22 @@ pub trait ManageNetwork : Send + Sync {
 	fn stop_network(&self);
 	/// Query the current configuration of the network
 	fn network_config(&self) -> NetworkConfiguration;
+	/// Reputations of known peers with non-zero score or an active ban
+	fn peer_reputations(&self) -> Vec<PeerReputation>;
+	/// Lift ban and reset reputation of a peer given by its node id
+	fn unban_peer(&self, peer: String) -> Result<(), String>;
+	/// Disconnect a peer given by its node id
+	fn disconnect_peer(&self, peer: String) -> Result<(), String>;
+	/// Ban a peer given by its node id and disconnect it
+	fn ban_peer(&self, peer: String) -> Result<(), String>;
+	/// Address ranges peers are currently allowed to use
+	fn ip_filter(&self) -> IpFilterSettings;
+	/// Replace the address filter, disconnecting peers that are no longer allowed
+	fn set_ip_filter(&self, filter: IpFilterSettings) -> Result<(), String>;
+	/// Traffic totals, per packet type counters and bandwidth limits in effect
+	fn traffic_stats(&self) -> TrafficStats;
+	/// Change bandwidth limits in bytes per second, zero means unlimited
+	fn set_bandwidth_limits(&self, limits: BandwidthLimits);
 }
 
 
@@ -218,6 +303,144 @@ impl ManageNetwork for EthSync {
 	fn network_config(&self) -> NetworkConfiguration {
 		NetworkConfiguration::from(self.network.config().clone())
 	}
+
+	fn peer_reputations(&self) -> Vec<PeerReputation> {
+		self.network.node_reputations().into_iter().map(|r| PeerReputation {
+			id: r.id.hex(),
+			score: r.score,
+			banned_until: r.banned_until,
+		}).collect()
+	}
+
+	fn unban_peer(&self, peer: String) -> Result<(), String> {
+		self.network.unban_node(&peer).map_err(|e| format!("{:?}", e))
+	}
+
+	fn disconnect_peer(&self, peer: String) -> Result<(), String> {
+		self.network.disconnect_node(&peer).map_err(|e| format!("{:?}", e))
+	}
+
+	fn ban_peer(&self, peer: String) -> Result<(), String> {
+		self.network.ban_node(&peer).map_err(|e| format!("{:?}", e))
+	}
+
+	fn ip_filter(&self) -> IpFilterSettings {
+		IpFilterSettings::from(self.network.ip_filter())
+	}
+
+	fn set_ip_filter(&self, filter: IpFilterSettings) -> Result<(), String> {
+		let filter = try!(filter.into_filter().map_err(|e| format!("{}", e)));
+		self.network.set_ip_filter(filter);
+		Ok(())
+	}
+
+	fn traffic_stats(&self) -> TrafficStats {
+		let stats = self.network.stats();
+		TrafficStats {
+			bytes_sent: stats.send() as u64,
+			bytes_received: stats.recv() as u64,
+			sessions: stats.sessions() as u64,
+			packets: self.network.packet_stats().to_vec().into_iter().map(|(protocol, packet_id, p)| PacketTraffic {
+				protocol: String::from_utf8_lossy(&protocol).into_owned(),
+				packet_id: packet_id as u32,
+				sent_packets: p.sent_packets,
+				sent_bytes: p.sent_bytes,
+				received_packets: p.received_packets,
+				received_bytes: p.received_bytes,
+			}).collect(),
+			limits: BandwidthLimits {
+				upload: stats.upload().rate() as u64,
+				download: stats.download().rate() as u64,
+				serve: stats.serve().rate() as u64,
+			},
+		}
+	}
+
+	fn set_bandwidth_limits(&self, limits: BandwidthLimits) {
+		self.network.set_bandwidth_limits(limits.upload as usize, limits.download as usize, limits.serve as usize);
+	}
+}
+
+#[derive(Binary, Debug, Clone, PartialEq, Eq)]
+/// Bandwidth limits in bytes per second, zero means unlimited
+pub struct BandwidthLimits {
+	/// Upload limit for all peers
+	pub upload: u64,
+	/// Download limit for all peers
+	pub download: u64,
+	/// Limit for responses to data requested by peers
+	pub serve: u64,
+}
+
+#[derive(Binary, Debug, Clone, PartialEq, Eq)]
+/// Traffic counters for a subprotocol packet type
+pub struct PacketTraffic {
+	/// Subprotocol name
+	pub protocol: String,
+	/// Packet id within the subprotocol
+	pub packet_id: u32,
+	/// Number of packets sent
+	pub sent_packets: u64,
+	/// Bytes sent
+	pub sent_bytes: u64,
+	/// Number of packets received
+	pub received_packets: u64,
+	/// Bytes received
+	pub received_bytes: u64,
+}
+
+#[derive(Binary, Debug, Clone, PartialEq, Eq)]
+/// Network traffic summary
+pub struct TrafficStats {
+	/// Total bytes sent
+	pub bytes_sent: u64,
+	/// Total bytes received
+	pub bytes_received: u64,
+	/// Total number of sessions created
+	pub sessions: u64,
+	/// Traffic by subprotocol packet type
+	pub packets: Vec<PacketTraffic>,
+	/// Bandwidth limits in effect
+	pub limits: BandwidthLimits,
+}
+
+#[derive(Binary, Debug, Clone, PartialEq, Eq)]
+/// Address ranges peers are allowed to connect from and to
+pub struct IpFilterSettings {
+	/// Predefined ranges: `all`, `public`, `private` or `none`
+	pub allow: String,
+	/// Additional CIDR ranges to allow
+	pub allow_ranges: Vec<String>,
+	/// CIDR ranges to deny, takes precedence over allowed ones
+	pub deny_ranges: Vec<String>,
+}
+
+impl IpFilterSettings {
+	/// Parse into network address filter.
+	pub fn into_filter(self) -> Result<IpFilter, NetworkError> {
+		IpFilter::parse(&self.allow, &self.allow_ranges, &self.deny_ranges)
+	}
+}
+
+impl From<IpFilter> for IpFilterSettings {
+	fn from(other: IpFilter) -> Self {
+		IpFilterSettings {
+			allow: format!("{}", other.predefined),
+			allow_ranges: other.custom_allow.iter().map(|n| format!("{}", n)).collect(),
+			deny_ranges: other.custom_block.iter().map(|n| format!("{}", n)).collect(),
+		}
+	}
+}
+
+#[derive(Binary, Debug, Clone, PartialEq, Eq)]
+/// Reputation of a known peer
+pub struct PeerReputation {
+	/// Node id
+	pub id: String,
+	/// Reputation score
+	pub score: i32,
+	/// Unix timestamp the ban expires at, if banned
+	pub banned_until: Option<u64>,
 }
 
 #[derive(Binary, Debug, Clone, PartialEq, Eq)]
@@ -249,6 +472,14 @@ pub struct NetworkConfiguration {
 	pub reserved_nodes: Vec<String>,
 	/// The non-reserved peer mode.
 	pub allow_non_reserved: bool,
+	/// Address ranges peers are allowed to use.
+	pub ip_filter: IpFilterSettings,
+	/// Upload limit in bytes per second, zero means unlimited
+	pub upload_limit: u64,
+	/// Download limit in bytes per second, zero means unlimited
+	pub download_limit: u64,
+	/// Limit for serving data to peers in bytes per second, zero means unlimited
+	pub serve_limit: u64,
 }
 
 impl NetworkConfiguration {
@@ -270,7 +501,7 @@ impl NetworkConfiguration {
 		Ok(())
 	}
 
-	pub fn into_basic(self) -> Result<BasicNetworkConfiguration, AddrParseError> {
+	pub fn into_basic(self) -> Result<BasicNetworkConfiguration, NetworkError> {
 
 		Ok(BasicNetworkConfiguration {
 			config_path: self.config_path,
@@ -286,6 +517,11 @@ impl NetworkConfiguration {
 			min_peers: self.min_peers,
 			reserved_nodes: self.reserved_nodes,
 			non_reserved_mode: if self.allow_non_reserved { NonReservedPeerMode::Accept } else { NonReservedPeerMode::Deny },
+			ip_filter: try!(self.ip_filter.into_filter()),
+			discovery_topic: None,
+			upload_limit: self.upload_limit as usize,
+			download_limit: self.download_limit as usize,
+			serve_limit: self.serve_limit as usize,
 		})
 	}
 }
@@ -306,6 +542,10 @@ impl From<BasicNetworkConfiguration> for NetworkConfiguration {
 			min_peers: other.min_peers,
 			reserved_nodes: other.reserved_nodes,
 			allow_non_reserved: match other.non_reserved_mode { NonReservedPeerMode::Accept => true, _ => false } ,
+			ip_filter: IpFilterSettings::from(other.ip_filter),
+			upload_limit: other.upload_limit as u64,
+			download_limit: other.download_limit as u64,
+			serve_limit: other.serve_limit as u64,
 		}
 	}
 }
@@ -123,6 +123,7 @@ const MAX_ROUND_PARENTS: usize = 32;
 const MAX_NEW_HASHES: usize = 64;
 const MAX_TX_TO_IMPORT: usize = 512;
 const MAX_NEW_BLOCK_AGE: BlockNumber = 20;
+const MAX_DELAYED_RESPONSES: usize = 256;
 
 const STATUS_PACKET: u8 = 0x00;
 const NEW_BLOCK_HASHES_PACKET: u8 = 0x01;
@@ -148,6 +149,14 @@ const FORK_HEADER_TIMEOUT_SEC: f64 = 3f64;
 const SNAPSHOT_MANIFEST_TIMEOUT_SEC: f64 = 3f64;
 const SNAPSHOT_DATA_TIMEOUT_SEC: f64 = 10f64;
 
+// Peer reputation score changes.
+// A single bad block gets a peer disconnected, but only a repeated offence gets it banned.
+const SCORE_BAD_BLOCK: i32 = -60;
+const SCORE_INVALID_RESPONSE: i32 = -20;
+const SCORE_TIMEOUT: i32 = -10;
+const SCORE_USELESS_RESPONSE: i32 = -2;
+const SCORE_USEFUL_RESPONSE: i32 = 1;
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 /// Sync state
 pub enum SyncState {
@@ -273,6 +282,8 @@ struct PeerInfo {
 	snapshot_hash: Option<H256>,
 	/// Best snapshot block number
 	snapshot_number: Option<BlockNumber>,
+	/// Hashes of older snapshots the peer still serves
+	older_snapshot_hashes: Vec<H256>,
 }
 
 impl PeerInfo {
@@ -280,6 +291,10 @@ impl PeerInfo {
 		self.confirmation == ForkConfirmation::Confirmed && !self.expired
 	}
 
+	fn has_snapshot(&self, hash: &H256) -> bool {
+		self.snapshot_hash.as_ref() == Some(hash) || self.older_snapshot_hashes.contains(hash)
+	}
+
 	fn is_allowed(&self) -> bool {
 		self.confirmation != ForkConfirmation::Unconfirmed && !self.expired
 	}
@@ -320,6 +335,8 @@ pub struct ChainSync {
 	fork_block: Option<(BlockNumber, H256)>,
 	/// Snapshot downloader.
 	snapshot: Snapshot,
+	/// Responses held back by the serving bandwidth limit, in order of arrival.
+	delayed_responses: VecDeque<(PeerId, PacketId, Bytes)>,
 }
 
 type RlpResponseResult = Result<Option<(PacketId, RlpStream)>, PacketDecodeError>;
@@ -345,6 +362,7 @@ impl ChainSync {
 			network_id: config.network_id,
 			fork_block: config.fork_block,
 			snapshot: Snapshot::new(),
+			delayed_responses: VecDeque::new(),
 		}
 	}
 
@@ -370,6 +388,11 @@ impl ChainSync {
 		}
 	}
 
+	/// Best block hash, number and total difficulty of a peer as known to the sync.
+	pub fn peer_head(&self, peer_id: PeerId) -> Option<(H256, Option<BlockNumber>, Option<U256>)> {
+		self.peers.get(&peer_id).map(|p| (p.latest_hash.clone(), p.latest_number, p.difficulty))
+	}
+
 	/// Abort all sync activity
 	pub fn abort(&mut self, io: &mut SyncIo) {
 		self.restart(io);
@@ -455,6 +478,7 @@ impl ChainSync {
 			asking_snapshot_data: None,
 			snapshot_hash: if protocol_version == 64 { Some(try!(r.val_at(5))) } else { None },
 			snapshot_number: if protocol_version == 64 { Some(try!(r.val_at(6))) } else { None },
+			older_snapshot_hashes: if protocol_version == 64 && r.item_count() > 7 { try!(r.val_at(7)) } else { Vec::new() },
 		};
 
 		trace!(target: "sync", "New peer {} (protocol: {}, network: {:?}, difficulty: {:?}, latest:{}, genesis:{})", peer_id, peer.protocol_version, peer.network_id, peer.difficulty, peer.latest_hash, peer.genesis);
@@ -577,6 +601,7 @@ impl ChainSync {
 				},
 				BlockStatus::Bad => {
 					warn!(target: "sync", "Bad header {} ({}) from {}: {}, state = {:?}", number, hash, peer_id, io.peer_info(peer_id), self.state);
+					io.adjust_peer_score(peer_id, SCORE_BAD_BLOCK);
 					io.disable_peer(peer_id);
 					return Ok(());
 				},
@@ -590,13 +615,17 @@ impl ChainSync {
 		// Disable the peer for this syncing round if it gives invalid chain
 		if !valid_response {
 			trace!(target: "sync", "{} Disabled for invalid headers response", peer_id);
+			io.adjust_peer_score(peer_id, SCORE_INVALID_RESPONSE);
 			io.disable_peer(peer_id);
 		}
 
 		if headers.is_empty() {
 			// Peer does not have any new subchain heads, deactivate it nd try with another
 			trace!(target: "sync", "{} Disabled for no data", peer_id);
+			io.adjust_peer_score(peer_id, SCORE_USELESS_RESPONSE);
 			io.disable_peer(peer_id);
+		} else if valid_response {
+			io.adjust_peer_score(peer_id, SCORE_USEFUL_RESPONSE);
 		}
 		match self.state {
 			SyncState::ChainHead => {
@@ -691,12 +720,16 @@ impl ChainSync {
 			Err(BlockImportError::Import(ImportError::AlreadyQueued)) => {
 				trace!(target: "sync", "New block already queued {:?}", h);
 			},
+			Err(BlockImportError::Import(ImportError::Rewound)) => {
+				trace!(target: "sync", "New block retracted by a rewind {:?}", h);
+			},
 			Ok(_) => {
 				if header.number() == self.last_imported_block + 1 {
 					self.last_imported_block = header.number();
 					self.last_imported_hash = header.hash();
 				}
 				trace!(target: "sync", "New block queued {:?} ({})", h, header.number());
+				io.adjust_peer_score(peer_id, SCORE_USEFUL_RESPONSE);
 			},
 			Err(BlockImportError::Block(BlockError::UnknownParent(p))) => {
 				unknown = true;
@@ -704,6 +737,7 @@ impl ChainSync {
 			},
 			Err(e) => {
 				debug!(target: "sync", "Bad new block {:?} : {:?}", h, e);
+				io.adjust_peer_score(peer_id, SCORE_BAD_BLOCK);
 				io.disable_peer(peer_id);
 			}
 		};
@@ -778,6 +812,7 @@ impl ChainSync {
 				},
 				BlockStatus::Bad => {
 					debug!(target: "sync", "Bad new block hash {:?}", hash);
+					io.adjust_peer_score(peer_id, SCORE_BAD_BLOCK);
 					io.disable_peer(peer_id);
 					return Ok(());
 				}
@@ -809,6 +844,7 @@ impl ChainSync {
 		let manifest = match ManifestData::from_rlp(manifest_rlp.as_raw()) {
 			Err(e) => {
 				trace!(target: "sync", "{}: Ignored bad manifest: {:?}", peer_id, e);
+				io.adjust_peer_score(peer_id, SCORE_INVALID_RESPONSE);
 				io.disconnect_peer(peer_id);
 				self.continue_sync(io);
 				return Ok(());
@@ -865,6 +901,7 @@ impl ChainSync {
 			}
 			Err(()) => {
 				trace!(target: "sync", "{}: Got bad snapshot chunk", peer_id);
+				io.adjust_peer_score(peer_id, SCORE_INVALID_RESPONSE);
 				io.disconnect_peer(peer_id);
 				self.continue_sync(io);
 				return Ok(());
@@ -892,6 +929,7 @@ impl ChainSync {
 			self.active_peers.remove(&peer);
 			self.continue_sync(io);
 		}
+		self.delayed_responses.retain(|&(p, _, _)| p != peer);
 	}
 
 	/// Called when a new peer is connected
@@ -941,7 +979,7 @@ impl ChainSync {
 			trace!(target: "sync", "Skipping deactivated peer");
 			return;
 		}
-		let (peer_latest, peer_difficulty, peer_snapshot_number, peer_snapshot_hash) = {
+		let (peer_latest, peer_difficulty, peer_snapshot_number, peer_has_our_snapshot) = {
 			let peer = self.peers.get_mut(&peer_id).unwrap();
 			if peer.asking != PeerAsking::Nothing || !peer.can_sync() {
 				return;
@@ -954,7 +992,8 @@ impl ChainSync {
 				trace!(target: "sync", "Waiting for the snapshot restoration");
 				return;
 			}
-			(peer.latest_hash.clone(), peer.difficulty.clone(), peer.snapshot_number.as_ref().cloned(), peer.snapshot_hash.as_ref().cloned())
+			let has_our_snapshot = self.snapshot.snapshot_hash().map_or(false, |hash| peer.has_snapshot(&hash));
+			(peer.latest_hash.clone(), peer.difficulty.clone(), peer.snapshot_number.as_ref().cloned(), has_our_snapshot)
 		};
 		let chain_info = io.chain().chain_info();
 		let td = chain_info.pending_total_difficulty;
@@ -990,7 +1029,8 @@ impl ChainSync {
 					}
 				},
 				SyncState::SnapshotData => {
-					if peer_snapshot_hash.is_some() && peer_snapshot_hash == self.snapshot.snapshot_hash() {
+					// peers which rotated the snapshot still serve it from their history.
+					if peer_has_our_snapshot {
 						self.request_snapshot_data(io, peer_id);
 					}
 				},
@@ -1126,6 +1166,10 @@ impl ChainSync {
 					trace!(target: "sync", "Block already queued {:?}", h);
 					self.block_imported(&h, number, &parent);
 				},
+				Err(BlockImportError::Import(ImportError::Rewound)) => {
+					debug!(target: "sync", "Block {:?} retracted by a rewind, not importing it", h);
+					break;
+				},
 				Ok(_) => {
 					trace!(target: "sync", "Block queued {:?}", h);
 					imported.insert(h.clone());
@@ -1271,7 +1315,7 @@ impl ChainSync {
 	/// Send Status message
 	fn send_status(&mut self, io: &mut SyncIo, peer: PeerId) -> Result<(), NetworkError> {
 		let pv64 = io.eth_protocol_version(peer) >= 64;
-		let mut packet = RlpStream::new_list(if pv64 { 7 } else { 5 });
+		let mut packet = RlpStream::new_list(if pv64 { 8 } else { 5 });
 		let chain = io.chain().chain_info();
 		packet.append(&(io.eth_protocol_version(peer) as u32));
 		packet.append(&self.network_id);
@@ -1282,8 +1326,10 @@ impl ChainSync {
 			let manifest = io.snapshot_service().manifest();
 			let block_number = manifest.as_ref().map_or(0, |m| m.block_number);
 			let manifest_hash = manifest.map_or(H256::new(), |m| m.into_rlp().sha3());
+			let older_hashes: Vec<H256> = io.snapshot_service().older_manifests().into_iter().map(|m| m.into_rlp().sha3()).collect();
 			packet.append(&manifest_hash);
 			packet.append(&block_number);
+			packet.append(&older_hashes);
 		}
 		io.respond(STATUS_PACKET, packet.out())
 	}
@@ -1363,9 +1409,18 @@ impl ChainSync {
 		let mut added = 0usize;
 		let mut data = Bytes::new();
 		for i in 0..count {
-			if let Some(mut hdr) = io.chain().block_body(BlockID::Hash(try!(r.val_at::<H256>(i)))) {
-				data.append(&mut hdr);
-				added += 1;
+			let hash = try!(r.val_at::<H256>(i));
+			match io.chain().block_body(BlockID::Hash(hash)) {
+				Some(mut hdr) => {
+					data.append(&mut hdr);
+					added += 1;
+				},
+				None if io.chain().is_pruned(BlockID::Hash(hash)) => {
+					// bodies must be returned in requested order, so stop at the first pruned one
+					trace!(target: "sync", "{} -> GetBlockBodies: body of {} is pruned", peer_id, hash);
+					break;
+				},
+				None => {},
 			}
 		}
 		let mut rlp = RlpStream::new_list(added);
@@ -1409,11 +1464,19 @@ impl ChainSync {
 		let mut added_receipts = 0usize;
 		let mut data = Bytes::new();
 		for i in 0..count {
-			if let Some(mut receipts_bytes) = io.chain().block_receipts(&try!(rlp.val_at::<H256>(i))) {
-				data.append(&mut receipts_bytes);
-				added_receipts += receipts_bytes.len();
-				added_headers += 1;
-				if added_receipts > MAX_RECEIPTS_TO_SEND { break; }
+			let hash = try!(rlp.val_at::<H256>(i));
+			match io.chain().block_receipts(&hash) {
+				Some(mut receipts_bytes) => {
+					data.append(&mut receipts_bytes);
+					added_receipts += receipts_bytes.len();
+					added_headers += 1;
+					if added_receipts > MAX_RECEIPTS_TO_SEND { break; }
+				},
+				None if io.chain().is_pruned(BlockID::Hash(hash)) => {
+					trace!(target: "sync", "{} -> GetReceipts: receipts of {} are pruned", peer_id, hash);
+					break;
+				},
+				None => {},
 			}
 		}
 		let mut rlp_result = RlpStream::new_list(added_headers);
@@ -1425,11 +1488,20 @@ impl ChainSync {
 	fn return_snapshot_manifest(io: &SyncIo, r: &UntrustedRlp, peer_id: PeerId) -> RlpResponseResult {
 		let count = r.item_count();
 		trace!(target: "sync", "{} -> GetSnapshotManifest", peer_id);
-		if count != 0 {
-			debug!(target: "sync", "Invalid GetSnapshotManifest request, ignoring.");
-			return Ok(None);
-		}
-		let rlp = match io.snapshot_service().manifest() {
+		// the latest manifest is returned unless an older one is asked for by hash.
+		let manifest = match count {
+			0 => io.snapshot_service().manifest(),
+			1 => {
+				let hash: H256 = try!(r.val_at(0));
+				let service = io.snapshot_service();
+				service.manifest().into_iter().chain(service.older_manifests()).find(|m| m.clone().into_rlp().sha3() == hash)
+			}
+			_ => {
+				debug!(target: "sync", "Invalid GetSnapshotManifest request, ignoring.");
+				return Ok(None);
+			}
+		};
+		let rlp = match manifest {
 			Some(manifest) => {
 				trace!(target: "sync", "{} <- SnapshotManifest", peer_id);
 				let mut rlp = RlpStream::new_list(1);
@@ -1461,7 +1533,7 @@ impl ChainSync {
 		Ok(Some((SNAPSHOT_DATA_PACKET, rlp)))
 	}
 
-	fn return_rlp<FRlp, FError>(io: &mut SyncIo, rlp: &UntrustedRlp, peer: PeerId, rlp_func: FRlp, error_func: FError) -> Result<(), PacketDecodeError>
+	fn return_rlp<FRlp, FError>(sync: &RwLock<ChainSync>, io: &mut SyncIo, rlp: &UntrustedRlp, peer: PeerId, rlp_func: FRlp, error_func: FError) -> Result<(), PacketDecodeError>
 		where FRlp : Fn(&SyncIo, &UntrustedRlp, PeerId) -> RlpResponseResult,
 			FError : FnOnce(NetworkError) -> String
 	{
@@ -1469,7 +1541,21 @@ impl ChainSync {
 		match response {
 			Err(e) => Err(e),
 			Ok(Some((packet_id, rlp_stream))) => {
-				io.respond(packet_id, rlp_stream.out()).unwrap_or_else(
+				let data = rlp_stream.out();
+				// over the serving limit the response waits in the queue, behind any response delayed earlier.
+				// The exclusive lock is only taken to queue it; it's not held while responding.
+				let queued = !sync.read().delayed_responses.is_empty();
+				if queued || !io.try_serve(data.len()) {
+					let mut sync = sync.write();
+					if sync.delayed_responses.len() >= MAX_DELAYED_RESPONSES {
+						trace!(target: "sync", "{}: Too many delayed responses, dropping response {}", peer, packet_id);
+						return Ok(());
+					}
+					trace!(target: "sync", "{}: Serving limit reached, delaying response {}", peer, packet_id);
+					sync.delayed_responses.push_back((peer, packet_id, data));
+					return Ok(());
+				}
+				io.respond(packet_id, data).unwrap_or_else(
 					|e| debug!(target: "sync", "{:?}", error_func(e)));
 				Ok(())
 			}
@@ -1477,31 +1563,43 @@ impl ChainSync {
 		}
 	}
 
+	/// Send responses held back by the serving limit as far as the limit allows now.
+	pub fn send_delayed_responses(&mut self, io: &mut SyncIo) {
+		while let Some(size) = self.delayed_responses.front().map(|&(_, _, ref data)| data.len()) {
+			if !io.try_serve(size) {
+				break;
+			}
+			let (peer, packet_id, data) = self.delayed_responses.pop_front().expect("front is Some; qed");
+			io.send(peer, packet_id, data).unwrap_or_else(
+				|e| debug!(target: "sync", "{}: Error sending delayed response: {:?}", peer, e));
+		}
+	}
+
 	/// Dispatch incoming requests and responses
 	pub fn dispatch_packet(sync: &RwLock<ChainSync>, io: &mut SyncIo, peer: PeerId, packet_id: u8, data: &[u8]) {
 		let rlp = UntrustedRlp::new(data);
 		let result = match packet_id {
-			GET_BLOCK_BODIES_PACKET => ChainSync::return_rlp(io, &rlp, peer,
+			GET_BLOCK_BODIES_PACKET => ChainSync::return_rlp(sync, io, &rlp, peer,
 				ChainSync::return_block_bodies,
 				|e| format!("Error sending block bodies: {:?}", e)),
 
-			GET_BLOCK_HEADERS_PACKET => ChainSync::return_rlp(io, &rlp, peer,
+			GET_BLOCK_HEADERS_PACKET => ChainSync::return_rlp(sync, io, &rlp, peer,
 				ChainSync::return_block_headers,
 				|e| format!("Error sending block headers: {:?}", e)),
 
-			GET_RECEIPTS_PACKET => ChainSync::return_rlp(io, &rlp, peer,
+			GET_RECEIPTS_PACKET => ChainSync::return_rlp(sync, io, &rlp, peer,
 				ChainSync::return_receipts,
 				|e| format!("Error sending receipts: {:?}", e)),
 
-			GET_NODE_DATA_PACKET => ChainSync::return_rlp(io, &rlp, peer,
+			GET_NODE_DATA_PACKET => ChainSync::return_rlp(sync, io, &rlp, peer,
 				ChainSync::return_node_data,
 				|e| format!("Error sending nodes: {:?}", e)),
 
-			GET_SNAPSHOT_MANIFEST_PACKET => ChainSync::return_rlp(io, &rlp, peer,
+			GET_SNAPSHOT_MANIFEST_PACKET => ChainSync::return_rlp(sync, io, &rlp, peer,
 				ChainSync::return_snapshot_manifest,
 				|e| format!("Error sending snapshot manifest: {:?}", e)),
 
-			GET_SNAPSHOT_DATA_PACKET => ChainSync::return_rlp(io, &rlp, peer,
+			GET_SNAPSHOT_DATA_PACKET => ChainSync::return_rlp(sync, io, &rlp, peer,
 				ChainSync::return_snapshot_data,
 				|e| format!("Error sending snapshot data: {:?}", e)),
 
@@ -1555,6 +1653,7 @@ impl ChainSync {
 			};
 			if timeout {
 				trace!(target:"sync", "Timeout {}", peer_id);
+				io.adjust_peer_score(*peer_id, SCORE_TIMEOUT);
 				io.disconnect_peer(*peer_id);
 				aborting.push(*peer_id);
 			}
@@ -1898,6 +1997,56 @@ mod tests {
 		assert_eq!(1, io.queue.len());
 	}
 
+	#[test]
+	fn delays_responses_over_serving_limit() {
+		// given
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(5, EachBlockWith::Nothing);
+		let mut queue = VecDeque::new();
+		let sync = RwLock::new(dummy_sync_with_peer(H256::new(), &client));
+		let ss = TestSnapshotService::new();
+		let mut io = TestIo::new(&mut client, &ss, &mut queue, Some(0));
+		let mut request = RlpStream::new_list(4);
+		request.append(&0u64);
+		request.append(&5u64);
+		request.append(&0u64);
+		request.append(&0u64);
+		let request = request.out();
+
+		// when
+		io.can_serve = false;
+		ChainSync::dispatch_packet(&sync, &mut io, 0, super::GET_BLOCK_HEADERS_PACKET, &request);
+		io.can_serve = true;
+		ChainSync::dispatch_packet(&sync, &mut io, 0, super::GET_BLOCK_HEADERS_PACKET, &request);
+
+		// then
+		assert_eq!(io.queue.len(), 0);
+		sync.write().send_delayed_responses(&mut io);
+		assert_eq!(io.queue.len(), 2);
+		assert!(io.queue.iter().all(|p| p.packet_id == super::BLOCK_HEADERS_PACKET && p.recipient == 0));
+	}
+
+	#[test]
+	fn return_block_bodies_stops_at_pruned() {
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(20, EachBlockWith::Nothing);
+		*client.pruned_number.write() = Some(5);
+		let mut queue = VecDeque::new();
+		let ss = TestSnapshotService::new();
+		let io = TestIo::new(&mut client, &ss, &mut queue, None);
+
+		let mut request = RlpStream::new_list(3);
+		request.append(&io.chain.block_hash(BlockID::Number(10)).unwrap());
+		request.append(&io.chain.block_hash(BlockID::Number(3)).unwrap());
+		request.append(&io.chain.block_hash(BlockID::Number(11)).unwrap());
+
+		let result = ChainSync::return_block_bodies(&io, &UntrustedRlp::new(&request.out()), 0);
+
+		let (_, rlp) = result.unwrap().unwrap();
+		let out = rlp.out();
+		assert_eq!(UntrustedRlp::new(&out).item_count(), 1);
+	}
+
 	#[test]
 	fn return_block_headers() {
 		use ethcore::views::HeaderView;
@@ -2012,6 +2161,7 @@ mod tests {
 				confirmation: super::ForkConfirmation::Confirmed,
 				snapshot_number: None,
 				snapshot_hash: None,
+				older_snapshot_hashes: Vec::new(),
 				asking_snapshot_data: None,
 			});
 		sync
@@ -2210,6 +2360,35 @@ mod tests {
 		assert!(result.is_err());
 	}
 
+	#[test]
+	fn reads_older_snapshots_from_status() {
+		let mut client = TestBlockChainClient::new();
+		let mut queue = VecDeque::new();
+		let mut sync = ChainSync::new(SyncConfig::default(), &client);
+		let ss = TestSnapshotService::new();
+		let older = H256::random();
+		let status = {
+			let chain = client.chain_info();
+			let mut packet = RlpStream::new_list(8);
+			packet.append(&64u32)
+				.append(&sync.network_id)
+				.append(&chain.total_difficulty)
+				.append(&chain.best_block_hash)
+				.append(&chain.genesis_hash)
+				.append(&H256::random())
+				.append(&10u64)
+				.append(&vec![older]);
+			packet.out()
+		};
+		let mut io = TestIo::new(&mut client, &ss, &mut queue, None);
+
+		sync.on_peer_status(&mut io, 0, &UntrustedRlp::new(&status)).unwrap();
+
+		let peer = sync.peers.get(&0).unwrap();
+		assert!(peer.has_snapshot(&older));
+		assert!(!peer.has_snapshot(&H256::random()));
+	}
+
 	#[test]
 	fn handles_peer_new_block() {
 		let mut client = TestBlockChainClient::new();
@@ -144,6 +144,9 @@ mod test {
 			state_root: H256::new(),
 			block_number: 42,
 			block_hash: H256::new(),
+			base: None,
+			subtree_digests: Vec::new(),
+			subtree_chunks: Vec::new(),
 		};
 		let mhash = manifest.clone().into_rlp().sha3();
 		(manifest, mhash, state_chunks, block_chunks)
@@ -20,16 +20,21 @@ use ethcore::snapshot::SnapshotService;
 
 /// IO interface for the syning handler.
 /// Provides peer connection management and an interface to the blockchain client.
-// TODO: ratings
 pub trait SyncIo {
 	/// Disable a peer
 	fn disable_peer(&mut self, peer_id: PeerId);
 	/// Disconnect peer
 	fn disconnect_peer(&mut self, peer_id: PeerId);
+	/// Change peer reputation score
+	fn adjust_peer_score(&mut self, _peer_id: PeerId, _delta: i32) {}
 	/// Respond to current request with a packet. Can be called from an IO handler for incoming packet.
 	fn respond(&mut self, packet_id: PacketId, data: Vec<u8>) -> Result<(), NetworkError>;
 	/// Send a packet to a peer.
 	fn send(&mut self, peer_id: PeerId, packet_id: PacketId, data: Vec<u8>) -> Result<(), NetworkError>;
+	/// Check if a response of the given size fits into the serving bandwidth limit.
+	fn try_serve(&mut self, _size: usize) -> bool {
+		true
+	}
 	/// Get the blockchain
 	fn chain(&self) -> &BlockChainClient;
 	/// Get the snapshot service.
@@ -75,6 +80,10 @@ impl<'s, 'h> SyncIo for NetSyncIo<'s, 'h> {
 		self.network.disconnect_peer(peer_id);
 	}
 
+	fn adjust_peer_score(&mut self, peer_id: PeerId, delta: i32) {
+		self.network.adjust_peer_score(peer_id, delta);
+	}
+
 	fn respond(&mut self, packet_id: PacketId, data: Vec<u8>) -> Result<(), NetworkError>{
 		self.network.respond(packet_id, data)
 	}
@@ -83,6 +92,10 @@ impl<'s, 'h> SyncIo for NetSyncIo<'s, 'h> {
 		self.network.send(peer_id, packet_id, data)
 	}
 
+	fn try_serve(&mut self, size: usize) -> bool {
+		self.network.try_serve(size)
+	}
+
 	fn chain(&self) -> &BlockChainClient {
 		self.chain
 	}
@@ -29,6 +29,7 @@ pub struct TestIo<'p> {
 	pub snapshot_service: &'p TestSnapshotService,
 	pub queue: &'p mut VecDeque<TestPacket>,
 	pub sender: Option<PeerId>,
+	pub can_serve: bool,
 }
 
 impl<'p> TestIo<'p> {
@@ -37,7 +38,8 @@ impl<'p> TestIo<'p> {
 			chain: chain,
 			snapshot_service: ss,
 			queue: queue,
-			sender: sender
+			sender: sender,
+			can_serve: true,
 		}
 	}
 }
@@ -71,6 +73,10 @@ impl<'p> SyncIo for TestIo<'p> {
 		Ok(())
 	}
 
+	fn try_serve(&mut self, _size: usize) -> bool {
+		self.can_serve
+	}
+
 	fn chain(&self) -> &BlockChainClient {
 		self.chain
 	}
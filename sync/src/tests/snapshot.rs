@@ -51,6 +51,9 @@ impl TestSnapshotService {
 			state_root: H256::new(),
 			block_number: block_number,
 			block_hash: block_hash,
+			base: None,
+			subtree_digests: Vec::new(),
+			subtree_chunks: Vec::new(),
 		};
 		let mut chunks: HashMap<H256, Bytes> = state_chunks.into_iter().map(|data| (data.sha3(), data)).collect();
 		chunks.extend(block_chunks.into_iter().map(|data| (data.sha3(), data)));
@@ -69,6 +72,10 @@ impl SnapshotService for TestSnapshotService {
 		self.manifest.as_ref().cloned()
 	}
 
+	fn older_manifests(&self) -> Vec<ManifestData> {
+		Vec::new()
+	}
+
 	fn chunk(&self, hash: H256) -> Option<Bytes> {
 		self.chunks.get(&hash).cloned()
 	}
@@ -0,0 +1,154 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Bandwidth limiting.
+
+use std::cmp::min;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use parking_lot::Mutex;
+use time;
+
+struct Bucket {
+	/// Bytes that can be transferred right now.
+	allowance: usize,
+	/// Time of the last refill.
+	refilled_ns: u64,
+}
+
+/// Token bucket limiting throughput to a number of bytes per second.
+/// Allows bursts of up to one second worth of traffic. Zero rate means no limit.
+pub struct RateLimiter {
+	rate: AtomicUsize,
+	bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+	/// Create a new limiter allowing `rate` bytes per second.
+	pub fn new(rate: usize) -> RateLimiter {
+		RateLimiter {
+			rate: AtomicUsize::new(rate),
+			bucket: Mutex::new(Bucket {
+				allowance: rate,
+				refilled_ns: time::precise_time_ns(),
+			}),
+		}
+	}
+
+	/// Get the limit in bytes per second. Zero means unlimited.
+	pub fn rate(&self) -> usize {
+		self.rate.load(Ordering::Relaxed)
+	}
+
+	/// Change the limit. Starts over with a full bucket.
+	pub fn set_rate(&self, rate: usize) {
+		let mut bucket = self.bucket.lock();
+		self.rate.store(rate, Ordering::Relaxed);
+		bucket.allowance = rate;
+		bucket.refilled_ns = time::precise_time_ns();
+	}
+
+	fn refill(bucket: &mut Bucket, rate: usize) {
+		let now = time::precise_time_ns();
+		let added = (now.saturating_sub(bucket.refilled_ns) as f64 * rate as f64 / 1_000_000_000f64) as usize;
+		// keep the timestamp while the increment rounds down to nothing, so that frequent calls don't starve the bucket.
+		if added > 0 {
+			bucket.allowance = min(rate, bucket.allowance.saturating_add(added));
+			bucket.refilled_ns = now;
+		}
+	}
+
+	/// Take up to `wanted` bytes out of the budget. Returns the number of bytes that may be transferred.
+	pub fn take(&self, wanted: usize) -> usize {
+		let rate = self.rate();
+		if rate == 0 {
+			return wanted;
+		}
+		let mut bucket = self.bucket.lock();
+		RateLimiter::refill(&mut bucket, rate);
+		let allowed = min(wanted, bucket.allowance);
+		bucket.allowance -= allowed;
+		allowed
+	}
+
+	/// Return bytes taken but not transferred.
+	pub fn refund(&self, size: usize) {
+		let rate = self.rate();
+		if rate == 0 || size == 0 {
+			return;
+		}
+		let mut bucket = self.bucket.lock();
+		bucket.allowance = min(rate, bucket.allowance.saturating_add(size));
+	}
+
+	/// Take exactly `size` bytes if the budget allows. Transfers larger than the burst size
+	/// are allowed once the bucket is full.
+	pub fn try_take(&self, size: usize) -> bool {
+		let rate = self.rate();
+		if rate == 0 {
+			return true;
+		}
+		let mut bucket = self.bucket.lock();
+		RateLimiter::refill(&mut bucket, rate);
+		if bucket.allowance >= size {
+			bucket.allowance -= size;
+			true
+		} else if bucket.allowance == rate {
+			bucket.allowance = 0;
+			true
+		} else {
+			false
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::RateLimiter;
+
+	#[test]
+	fn unlimited_by_default() {
+		let limiter = RateLimiter::new(0);
+		assert_eq!(limiter.take(1 << 30), 1 << 30);
+		assert!(limiter.try_take(1 << 30));
+	}
+
+	#[test]
+	fn limits_burst() {
+		// given
+		let limiter = RateLimiter::new(1000);
+
+		// when
+		let first = limiter.take(600);
+		let second = limiter.take(600);
+		limiter.refund(100);
+
+		// then
+		assert_eq!(first, 600);
+		assert!(second >= 400 && second < 600);
+		assert!(!limiter.try_take(500));
+		assert!(limiter.try_take(100));
+	}
+
+	#[test]
+	fn allows_large_transfer_with_full_bucket() {
+		let limiter = RateLimiter::new(1000);
+		assert!(limiter.try_take(5000));
+		assert!(!limiter.try_take(5000));
+		limiter.set_rate(2000);
+		assert_eq!(limiter.take(5000), 2000);
+	}
+}
@@ -62,6 +62,10 @@ pub struct GenericConnection<Socket: GenericSocket> {
 	stats: Arc<NetworkStats>,
 	/// Registered flag
 	registered: AtomicBool,
+	/// Last read was held back by the download limit
+	read_throttled: bool,
+	/// Last write was held back by the upload limit
+	write_throttled: bool,
 }
 
 impl<Socket: GenericSocket> GenericConnection<Socket> {
@@ -80,8 +84,20 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
 		}
 		let sock_ref = <Socket as Read>::by_ref(&mut self.socket);
 		loop {
-			let max = self.rec_size - self.rec_buf.len();
-			match sock_ref.take(max as u64).try_read_buf(&mut self.rec_buf) {
+			let max = self.stats.download().take(self.rec_size - self.rec_buf.len());
+			if max == 0 {
+				trace!(target:"network", "{}: Read throttled", self.token);
+				self.read_throttled = true;
+				return Ok(None);
+			}
+			self.read_throttled = false;
+			let result = sock_ref.take(max as u64).try_read_buf(&mut self.rec_buf);
+			let read = match result {
+				Ok(Some(size)) => size,
+				_ => 0,
+			};
+			self.stats.download().refund(max - read);
+			match result {
 				Ok(Some(size)) if size != 0  => {
 					self.stats.inc_recv(size);
 					trace!(target:"network", "{}: Read {} of {} bytes", self.token, self.rec_buf.len(), self.rec_size);
@@ -119,6 +135,17 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
 		self.interest.is_writable()
 	}
 
+	/// Check if the last read was held back by the download limit.
+	/// Such connections have to be polled again as the socket won't signal new readiness.
+	pub fn is_read_throttled(&self) -> bool {
+		self.read_throttled
+	}
+
+	/// Check if the last write was held back by the upload limit.
+	pub fn is_write_throttled(&self) -> bool {
+		self.write_throttled
+	}
+
 	/// Writable IO handler. Called when the socket is ready to send.
 	pub fn writable<Message>(&mut self, io: &IoContext<Message>) -> Result<WriteStatus, NetworkError> where Message: Send + Clone {
 		if self.send_queue.is_empty() {
@@ -127,23 +154,41 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
 		{
 			let buf = self.send_queue.front_mut().unwrap();
 			let send_size = buf.get_ref().len();
-			if (buf.position() as usize) >= send_size {
+			let position = buf.position() as usize;
+			if position >= send_size {
 				warn!(target:"net", "Unexpected connection data");
 				return Ok(WriteStatus::Complete)
 			}
-			match self.socket.try_write_buf(buf) {
-				Ok(Some(size)) if (buf.position() as usize) < send_size => {
+			let allowed = self.stats.upload().take(send_size - position);
+			if allowed == 0 {
+				trace!(target:"network", "{}: Write throttled", self.token);
+				self.write_throttled = true;
+				return Ok(WriteStatus::Ongoing)
+			}
+			self.write_throttled = false;
+			let result = self.socket.try_write(&buf.get_ref()[position..position + allowed]);
+			match result {
+				Ok(Some(size)) if position + size < send_size => {
+					self.stats.upload().refund(allowed - size);
 					self.stats.inc_send(size);
+					buf.set_position((position + size) as u64);
 					Ok(WriteStatus::Ongoing)
 				},
-				Ok(Some(size)) if (buf.position() as usize) == send_size => {
+				Ok(Some(size)) if position + size == send_size => {
 					self.stats.inc_send(size);
+					buf.set_position(send_size as u64);
 					trace!(target:"network", "{}: Wrote {} bytes", self.token, send_size);
 					Ok(WriteStatus::Complete)
 				},
 				Ok(Some(_)) => { panic!("Wrote past buffer");},
-				Ok(None) => Ok(WriteStatus::Ongoing),
-				Err(e) => try!(Err(e))
+				Ok(None) => {
+					self.stats.upload().refund(allowed);
+					Ok(WriteStatus::Ongoing)
+				},
+				Err(e) => {
+					self.stats.upload().refund(allowed);
+					try!(Err(e))
+				}
 			}
 		}.and_then(|r| {
 			if r == WriteStatus::Complete {
@@ -173,6 +218,8 @@ impl Connection {
 			interest: EventSet::hup() | EventSet::readable(),
 			stats: stats,
 			registered: AtomicBool::new(false),
+			read_throttled: false,
+			write_throttled: false,
 		}
 	}
 
@@ -202,6 +249,8 @@ impl Connection {
 			interest: EventSet::hup(),
 			stats: self.stats.clone(),
 			registered: AtomicBool::new(false),
+			read_throttled: false,
+			write_throttled: false,
 		})
 	}
 
@@ -339,6 +388,11 @@ impl EncryptedConnection {
 		Ok(enc)
 	}
 
+	/// Size of an encrypted packet carrying a payload of given length, including header, padding and MACs.
+	pub fn packet_size(payload_len: usize) -> usize {
+		ENCRYPTED_HEADER_LEN + payload_len + (16 - (payload_len % 16)) % 16 + 16
+	}
+
 	/// Send a packet
 	pub fn send_packet<Message>(&mut self, io: &IoContext<Message>, payload: &[u8]) -> Result<(), NetworkError> where Message: Send + Clone {
 		let mut header = RlpStream::new();
@@ -542,6 +596,8 @@ mod tests {
 				interest: EventSet::hup() | EventSet::readable(),
 				stats: Arc::<NetworkStats>::new(NetworkStats::new()),
 				registered: AtomicBool::new(false),
+				read_throttled: false,
+				write_throttled: false,
 			}
 		}
 	}
@@ -565,6 +621,8 @@ mod tests {
 				interest: EventSet::hup() | EventSet::readable(),
 				stats: Arc::<NetworkStats>::new(NetworkStats::new()),
 				registered: AtomicBool::new(false),
+				read_throttled: false,
+				write_throttled: false,
 			}
 		}
 	}
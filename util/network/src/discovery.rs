@@ -29,8 +29,8 @@ use node_table::*;
 use error::NetworkError;
 use io::{StreamToken, IoContext};
 use ethkey::{Secret, KeyPair, sign, recover};
+use ip_utils::IpFilter;
 
-use PROTOCOL_VERSION;
 
 const ADDRESS_BYTES_SIZE: u32 = 32;							// Size of address type in bytes.
 const ADDRESS_BITS: u32 = 8 * ADDRESS_BYTES_SIZE;			// Denoted by n in [Kademlia].
@@ -39,6 +39,7 @@ const DISCOVERY_MAX_STEPS: u16 = 8;							// Max iterations of discovery. (disco
 const BUCKET_SIZE: usize = 16;		// Denoted by k in [Kademlia]. Number of nodes stored in each bucket.
 const ALPHA: usize = 3;				// Denoted by \alpha in [Kademlia]. Number of concurrent FindNode requests.
 const MAX_DATAGRAM_SIZE: usize = 1280;
+const DISCOVERY_VERSION: u32 = 4;
 
 const PACKET_PING: u8 = 1;
 const PACKET_PONG: u8 = 2;
@@ -95,15 +96,21 @@ pub struct Discovery {
 	send_queue: VecDeque<Datagramm>,
 	check_timestamps: bool,
 	adding_nodes: Vec<NodeEntry>,
+	ip_filter: IpFilter,
+	topic: Option<H256>,
 }
 
 pub struct TableUpdates {
 	pub added: HashMap<NodeId, NodeEntry>,
 	pub removed: HashSet<NodeId>,
+	/// Topics advertised by nodes in ping and pong packets.
+	pub topics: HashMap<NodeId, H256>,
 }
 
 impl Discovery {
-	pub fn new(key: &KeyPair, listen: SocketAddr, public: NodeEndpoint, token: StreamToken) -> Discovery {
+	/// Create a new discovery instance. `topic` is advertised to other nodes in ping and pong packets
+	/// so they can tell which chain we are on before connecting.
+	pub fn new(key: &KeyPair, listen: SocketAddr, public: NodeEndpoint, token: StreamToken, ip_filter: IpFilter, topic: Option<H256>) -> Discovery {
 		let socket = UdpSocket::bound(&listen).expect("Error binding UDP socket");
 		Discovery {
 			id: key.public().clone(),
@@ -118,9 +125,24 @@ impl Discovery {
 			send_queue: VecDeque::new(),
 			check_timestamps: true,
 			adding_nodes: Vec::new(),
+			ip_filter: ip_filter,
+			topic: topic,
 		}
 	}
 
+	/// Replace the address filter applied to discovered nodes.
+	pub fn set_ip_filter(&mut self, ip_filter: IpFilter) {
+		self.ip_filter = ip_filter;
+	}
+
+	fn is_allowed(&self, endpoint: &NodeEndpoint) -> bool {
+		self.ip_filter.allows(&endpoint.address.ip())
+	}
+
+	fn is_allowed_source(&self, endpoint: &NodeEndpoint) -> bool {
+		self.is_allowed(endpoint) && (endpoint.is_global() || self.ip_filter.allows_private(&endpoint.address.ip()))
+	}
+
 	/// Add a new node to discovery table. Pings the node.
 	pub fn add_node(&mut self, e: NodeEntry) {
 		let endpoint = e.endpoint.clone();
@@ -232,7 +254,7 @@ impl Discovery {
 
 	fn ping(&mut self, node: &NodeEndpoint) {
 		let mut rlp = RlpStream::new_list(3);
-		rlp.append(&PROTOCOL_VERSION);
+		rlp.append(&DISCOVERY_VERSION);
 		self.public_endpoint.to_rlp_list(&mut rlp);
 		node.to_rlp_list(&mut rlp);
 		trace!(target: "discovery", "Sent Ping to {:?}", &node);
@@ -240,15 +262,23 @@ impl Discovery {
 	}
 
 	fn send_packet(&mut self, packet_id: u8, address: &SocketAddr, payload: &[u8]) {
+		// topic goes after the timestamp, where v4 nodes ignore extra list items.
+		let topic = match packet_id {
+			PACKET_PING | PACKET_PONG => self.topic.clone(),
+			_ => None,
+		};
 		let mut rlp = RlpStream::new();
 		rlp.append_raw(&[packet_id], 1);
 		let source = Rlp::new(payload);
-		rlp.begin_list(source.item_count() + 1);
+		rlp.begin_list(source.item_count() + 1 + topic.iter().count());
 		for i in 0 .. source.item_count() {
 			rlp.append_raw(source.at(i).as_raw(), 1);
 		}
 		let timestamp = time::get_time().sec as u32 + 60;
 		rlp.append(&timestamp);
+		if let Some(ref topic) = topic {
+			rlp.append(topic);
+		}
 
 		let bytes = rlp.drain();
 		let hash = bytes.as_ref().sha3();
@@ -386,6 +416,13 @@ impl Discovery {
 		Ok(())
 	}
 
+	fn topic_at(rlp: &UntrustedRlp, index: usize) -> Option<H256> {
+		match rlp.item_count() > index {
+			true => rlp.val_at(index).ok(),
+			false => None,
+		}
+	}
+
 	fn on_ping(&mut self, rlp: &UntrustedRlp, node: &NodeId, from: &SocketAddr) -> Result<Option<TableUpdates>, NetworkError> {
 		trace!(target: "discovery", "Got Ping from {:?}", &from);
 		let source = try!(NodeEndpoint::from_rlp(&try!(rlp.at(1))));
@@ -393,13 +430,20 @@ impl Discovery {
 		let timestamp: u64 = try!(rlp.val_at(3));
 		try!(self.check_timestamp(timestamp));
 		let mut added_map = HashMap::new();
+		let mut topics = HashMap::new();
 		let entry = NodeEntry { id: node.clone(), endpoint: source.clone() };
-		if !entry.endpoint.is_valid() || !entry.endpoint.is_global() {
+		if !entry.endpoint.is_valid() {
 			debug!(target: "discovery", "Got bad address: {:?}", entry);
 		}
+		else if !self.is_allowed_source(&entry.endpoint) {
+			debug!(target: "discovery", "Address not allowed: {:?}", entry);
+		}
 		else {
 			self.update_node(entry.clone());
 			added_map.insert(node.clone(), entry);
+			if let Some(topic) = Discovery::topic_at(rlp, 4) {
+				topics.insert(node.clone(), topic);
+			}
 		}
 		let hash = rlp.as_raw().sha3();
 		let mut response = RlpStream::new_list(2);
@@ -407,7 +451,7 @@ impl Discovery {
 		response.append(&hash);
 		self.send_packet(PACKET_PONG, from, &response.drain());
 
-		Ok(Some(TableUpdates { added: added_map, removed: HashSet::new() }))
+		Ok(Some(TableUpdates { added: added_map, removed: HashSet::new(), topics: topics }))
 	}
 
 	fn on_pong(&mut self, rlp: &UntrustedRlp, node: &NodeId, from: &SocketAddr) -> Result<Option<TableUpdates>, NetworkError> {
@@ -424,7 +468,14 @@ impl Discovery {
 		self.clear_ping(node);
 		let mut added_map = HashMap::new();
 		added_map.insert(node.clone(), entry);
-		Ok(None)
+		match Discovery::topic_at(rlp, 3) {
+			Some(topic) => {
+				let mut topics = HashMap::new();
+				topics.insert(node.clone(), topic);
+				Ok(Some(TableUpdates { added: HashMap::new(), removed: HashSet::new(), topics: topics }))
+			},
+			None => Ok(None),
+		}
 	}
 
 	fn on_find_node(&mut self, rlp: &UntrustedRlp, _node: &NodeId, from: &SocketAddr) -> Result<Option<TableUpdates>, NetworkError> {
@@ -470,6 +521,10 @@ impl Discovery {
 				debug!(target: "discovery", "Bad address: {:?}", endpoint);
 				continue;
 			}
+			if !self.is_allowed(&endpoint) {
+				debug!(target: "discovery", "Address not allowed: {:?}", endpoint);
+				continue;
+			}
 			let node_id: NodeId = try!(r.val_at(3));
 			if node_id == self.id {
 				continue;
@@ -479,7 +534,7 @@ impl Discovery {
 			self.ping(&entry.endpoint);
 			self.update_node(entry);
 		}
-		Ok(Some(TableUpdates { added: added, removed: HashSet::new() }))
+		Ok(Some(TableUpdates { added: added, removed: HashSet::new(), topics: HashMap::new() }))
 	}
 
 	fn check_expired(&mut self, force: bool) -> HashSet<NodeId> {
@@ -506,7 +561,7 @@ impl Discovery {
 		let removed = self.check_expired(false);
 		self.discover();
 		if !removed.is_empty() {
-			Some(TableUpdates { added: HashMap::new(), removed: removed })
+			Some(TableUpdates { added: HashMap::new(), removed: removed, topics: HashMap::new() })
 		} else { None }
 	}
 
@@ -539,6 +594,8 @@ mod tests {
 	use std::str::FromStr;
 	use rustc_serialize::hex::FromHex;
 	use ethkey::{Random, Generator};
+	use ip_utils::IpFilter;
+	use std::collections::HashSet;
 
 	#[test]
 	fn find_node() {
@@ -563,8 +620,8 @@ mod tests {
 		let key2 = Random.generate().unwrap();
 		let ep1 = NodeEndpoint { address: SocketAddr::from_str("127.0.0.1:40444").unwrap(), udp_port: 40444 };
 		let ep2 = NodeEndpoint { address: SocketAddr::from_str("127.0.0.1:40445").unwrap(), udp_port: 40445 };
-		let mut discovery1 = Discovery::new(&key1, ep1.address.clone(), ep1.clone(), 0);
-		let mut discovery2 = Discovery::new(&key2, ep2.address.clone(), ep2.clone(), 0);
+		let mut discovery1 = Discovery::new(&key1, ep1.address.clone(), ep1.clone(), 0, IpFilter::default(), None);
+		let mut discovery2 = Discovery::new(&key2, ep2.address.clone(), ep2.clone(), 0, IpFilter::default(), None);
 
 		let node1 = Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@127.0.0.1:7770").unwrap();
 		let node2 = Node::from_str("enode://b979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@127.0.0.1:7771").unwrap();
@@ -596,7 +653,7 @@ mod tests {
 	fn removes_expired() {
 		let key = Random.generate().unwrap();
 		let ep = NodeEndpoint { address: SocketAddr::from_str("127.0.0.1:40446").unwrap(), udp_port: 40447 };
-		let mut discovery = Discovery::new(&key, ep.address.clone(), ep.clone(), 0);
+		let mut discovery = Discovery::new(&key, ep.address.clone(), ep.clone(), 0, IpFilter::default(), None);
 		for _ in 0..1200 {
 			discovery.add_node(NodeEntry { id: NodeId::random(), endpoint: ep.clone() });
 		}
@@ -624,7 +681,7 @@ mod tests {
 	fn packets() {
 		let key = Random.generate().unwrap();
 		let ep = NodeEndpoint { address: SocketAddr::from_str("127.0.0.1:40447").unwrap(), udp_port: 40447 };
-		let mut discovery = Discovery::new(&key, ep.address.clone(), ep.clone(), 0);
+		let mut discovery = Discovery::new(&key, ep.address.clone(), ep.clone(), 0, IpFilter::default(), None);
 		discovery.check_timestamps = false;
 		let from = SocketAddr::from_str("99.99.99.99:40445").unwrap();
 
@@ -685,4 +742,94 @@ mod tests {
 		assert!(discovery.on_packet(&packet, from.clone()).is_ok());
 	}
 
+	struct TestNode {
+		discovery: Discovery,
+		table: NodeTable,
+		endpoint: NodeEndpoint,
+		id: NodeId,
+		chain: usize,
+	}
+
+	/// A local network of discovery nodes on several chains, exchanging datagrams in memory.
+	struct TestNet {
+		nodes: Vec<TestNode>,
+	}
+
+	impl TestNet {
+		/// Create `count` nodes alternating between `chains` chains. Nodes advertise their chain as topic if `topics` is set.
+		fn new(base_port: u16, count: usize, chains: usize, topics: bool) -> TestNet {
+			let nodes = (0..count).map(|i| {
+				let key = Random.generate().unwrap();
+				let port = base_port + i as u16;
+				let endpoint = NodeEndpoint { address: SocketAddr::from_str(&format!("127.0.0.1:{}", port)).unwrap(), udp_port: port };
+				let chain = i % chains;
+				let topic = match topics {
+					true => Some(H256::from(chain as u64 + 1)),
+					false => None,
+				};
+				let mut table = NodeTable::new(None);
+				table.set_topic(topic.clone());
+				TestNode {
+					discovery: Discovery::new(&key, endpoint.address.clone(), endpoint.clone(), 0, IpFilter::default(), topic),
+					table: table,
+					endpoint: endpoint,
+					id: key.public().clone(),
+					chain: chain,
+				}
+			}).collect();
+			TestNet { nodes: nodes }
+		}
+
+		/// Bootstrap all nodes from the first one and run discovery for a number of rounds.
+		fn run(&mut self, rounds: usize) {
+			let boot = NodeEntry { id: self.nodes[0].id.clone(), endpoint: self.nodes[0].endpoint.clone() };
+			for node in self.nodes.iter_mut().skip(1) {
+				node.discovery.add_node(boot.clone());
+			}
+			for _ in 0..rounds {
+				for i in 0..self.nodes.len() {
+					let from = self.nodes[i].endpoint.address.clone();
+					let datagrams: Vec<_> = self.nodes[i].discovery.send_queue.drain(..).collect();
+					for datagram in datagrams {
+						if let Some(node) = self.nodes.iter_mut().find(|n| n.endpoint.address == datagram.address) {
+							if let Ok(Some(updates)) = node.discovery.on_packet(&datagram.payload, from.clone()) {
+								node.table.update(updates, &HashSet::new());
+							}
+						}
+					}
+				}
+				for node in &mut self.nodes {
+					if let Some(updates) = node.discovery.round() {
+						node.table.update(updates, &HashSet::new());
+					}
+				}
+			}
+		}
+
+		/// Number of peers from other chains among the first `slots` connection candidates of a node.
+		fn wasted_slots(&self, node: usize, slots: usize) -> usize {
+			let chain = self.nodes[node].chain;
+			self.nodes[node].table.nodes().iter().take(slots)
+				.filter(|id| self.nodes.iter().find(|n| &n.id == *id).map_or(true, |n| n.chain != chain))
+				.count()
+		}
+	}
+
+	#[test]
+	fn topics_prefer_same_chain() {
+		// given
+		let mut plain = TestNet::new(40510, 12, 2, false);
+		let mut topics = TestNet::new(40530, 12, 2, true);
+
+		// when
+		plain.run(10);
+		topics.run(10);
+
+		// then
+		for i in 0..12 {
+			assert!(topics.nodes[i].table.nodes().len() >= 5);
+			assert_eq!(topics.wasted_slots(i, 5), 0);
+			assert!(topics.wasted_slots(i, 5) <= plain.wasted_slots(i, 5));
+		}
+	}
 }
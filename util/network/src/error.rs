@@ -96,6 +96,10 @@ pub enum NetworkError {
 	PeerNotFound,
 	/// Peer is diconnected.
 	Disconnect(DisconnectReason),
+	/// Packet payload exceeds the maximal allowed size.
+	OversizedPacket,
+	/// Malformed IP filter or address range.
+	InvalidIpFilter(String),
 	/// Util error.
 	Util(UtilError),
 	/// Socket IO error.
@@ -118,6 +122,8 @@ impl fmt::Display for NetworkError {
 			Expired => "Expired message".into(),
 			PeerNotFound => "Peer not found".into(),
 			Disconnect(ref reason) => format!("Peer disconnected: {}", reason),
+			OversizedPacket => "Packet is too large".into(),
+			InvalidIpFilter(ref filter) => format!("Invalid IP filter: {}", filter),
 			Io(ref err) => format!("Socket I/O error: {}", err),
 			AddressParse(ref err) => format!("{}", err),
 			AddressResolve(Some(ref err)) => format!("{}", err),
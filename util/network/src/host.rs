@@ -29,16 +29,16 @@ use mio::*;
 use mio::tcp::*;
 use util::hash::*;
 use util::Hashable;
-use util::version;
+use util::{version, UtilError};
 use rlp::*;
-use session::{Session, SessionData};
+use session::{Session, SessionData, PeerInfo};
 use error::*;
 use io::*;
 use {NetworkProtocolHandler, NonReservedPeerMode, PROTOCOL_VERSION};
 use node_table::*;
-use stats::NetworkStats;
+use stats::{NetworkStats, PacketCounters};
 use discovery::{Discovery, TableUpdates, NodeEntry};
-use ip_utils::{map_external_address, select_public_address};
+use ip_utils::{map_external_address, select_public_address, IpFilter};
 use util::path::restrict_permissions_owner;
 use parking_lot::{Mutex, RwLock};
 
@@ -48,6 +48,8 @@ const MAX_SESSIONS: usize = 1024 + MAX_HANDSHAKES;
 const MAX_HANDSHAKES: usize = 80;
 const MAX_HANDSHAKES_PER_ROUND: usize = 32;
 const MAINTENANCE_TIMEOUT: u64 = 1000;
+// Interval for resuming connections held back by bandwidth limits.
+const THROTTLE_TIMEOUT: u64 = 100;
 
 #[derive(Debug, PartialEq, Clone)]
 /// Network service configuration
@@ -78,6 +80,16 @@ pub struct NetworkConfiguration {
 	pub reserved_nodes: Vec<String>,
 	/// The non-reserved peer mode.
 	pub non_reserved_mode: NonReservedPeerMode,
+	/// Address ranges peers are allowed to connect from and to.
+	pub ip_filter: IpFilter,
+	/// Topic advertised via discovery, e.g. identifying the chain. Peers with the same topic are preferred.
+	pub discovery_topic: Option<H256>,
+	/// Upload limit in bytes per second. Zero means unlimited.
+	pub upload_limit: usize,
+	/// Download limit in bytes per second. Zero means unlimited.
+	pub download_limit: usize,
+	/// Limit for serving data requested by peers in bytes per second. Zero means unlimited.
+	pub serve_limit: usize,
 }
 
 impl Default for NetworkConfiguration {
@@ -103,6 +115,11 @@ impl NetworkConfiguration {
 			max_peers: 50,
 			reserved_nodes: Vec::new(),
 			non_reserved_mode: NonReservedPeerMode::Accept,
+			ip_filter: IpFilter::default(),
+			discovery_topic: None,
+			upload_limit: 0,
+			download_limit: 0,
+			serve_limit: 0,
 		}
 	}
 
@@ -129,6 +146,7 @@ const DISCOVERY: usize = SYS_TIMER + 3;
 const DISCOVERY_REFRESH: usize = SYS_TIMER + 4;
 const DISCOVERY_ROUND: usize = SYS_TIMER + 5;
 const NODE_TABLE: usize = SYS_TIMER + 6;
+const THROTTLE: usize = SYS_TIMER + 7;
 const FIRST_SESSION: usize = 0;
 const LAST_SESSION: usize = FIRST_SESSION + MAX_SESSIONS - 1;
 const USER_TIMER: usize = LAST_SESSION + 256;
@@ -166,6 +184,13 @@ pub enum NetworkIoMessage {
 	Disconnect(PeerId),
 	/// Disconnect and temporary disable peer.
 	DisablePeer(PeerId),
+	/// Change reputation score of a node.
+	AdjustPeerScore {
+		/// Node public key.
+		node: NodeId,
+		/// Score change.
+		delta: i32,
+	},
 	/// Network has been started with the host as the given enode.
 	NetworkStarted(String),
 }
@@ -198,6 +223,7 @@ pub struct NetworkContext<'s> {
 	session: Option<SharedSession>,
 	session_id: Option<StreamToken>,
 	_reserved_peers: &'s HashSet<NodeId>,
+	stats: &'s NetworkStats,
 }
 
 impl<'s> NetworkContext<'s> {
@@ -205,7 +231,7 @@ impl<'s> NetworkContext<'s> {
 	fn new(io: &'s IoContext<NetworkIoMessage>,
 		protocol: ProtocolId,
 		session: Option<SharedSession>, sessions: Arc<RwLock<Slab<SharedSession>>>,
-		reserved_peers: &'s HashSet<NodeId>) -> NetworkContext<'s> {
+		reserved_peers: &'s HashSet<NodeId>, stats: &'s NetworkStats) -> NetworkContext<'s> {
 		let id = session.as_ref().map(|s| s.lock().token());
 		NetworkContext {
 			io: io,
@@ -214,6 +240,7 @@ impl<'s> NetworkContext<'s> {
 			session: session,
 			sessions: sessions,
 			_reserved_peers: reserved_peers,
+			stats: stats,
 		}
 	}
 
@@ -241,6 +268,12 @@ impl<'s> NetworkContext<'s> {
 		self.send(self.session_id.unwrap(), packet_id, data)
 	}
 
+	/// Check if a response of the given size may be served within the serving bandwidth limit.
+	/// Takes the size out of the budget if so.
+	pub fn try_serve(&self, size: usize) -> bool {
+		self.stats.serve().try_take(size)
+	}
+
 	/// Get an IoChannel.
 	pub fn io_channel(&self) -> IoChannel<NetworkIoMessage> {
 		self.io.channel()
@@ -259,6 +292,15 @@ impl<'s> NetworkContext<'s> {
 			.unwrap_or_else(|e| warn!("Error sending network IO message: {:?}", e));
 	}
 
+	/// Change reputation score of a peer. Peers with low score get disconnected and eventually banned.
+	pub fn adjust_peer_score(&self, peer: PeerId, delta: i32) {
+		let node = self.resolve_session(peer).and_then(|s| s.lock().id().cloned());
+		if let Some(node) = node {
+			self.io.message(NetworkIoMessage::AdjustPeerScore { node: node, delta: delta })
+				.unwrap_or_else(|e| warn!("Error sending network IO message: {:?}", e));
+		}
+	}
+
 	/// Check if the session is still active.
 	pub fn is_expired(&self) -> bool {
 		self.session.as_ref().map_or(false, |s| s.lock().expired())
@@ -387,6 +429,8 @@ impl Host {
 
 		let boot_nodes = config.boot_nodes.clone();
 		let reserved_nodes = config.reserved_nodes.clone();
+		let mut nodes = NodeTable::new(path);
+		nodes.set_topic(config.discovery_topic.clone());
 
 		let mut host = Host {
 			info: RwLock::new(HostInfo {
@@ -402,7 +446,7 @@ impl Host {
 			discovery: Mutex::new(None),
 			tcp_listener: Mutex::new(tcp_listener),
 			sessions: Arc::new(RwLock::new(Slab::new_starting_at(FIRST_SESSION, MAX_SESSIONS))),
-			nodes: RwLock::new(NodeTable::new(path)),
+			nodes: RwLock::new(nodes),
 			handlers: RwLock::new(HashMap::new()),
 			timers: RwLock::new(HashMap::new()),
 			timer_counter: RwLock::new(USER_TIMER),
@@ -489,6 +533,75 @@ impl Host {
 		Ok(())
 	}
 
+	/// Traffic by subprotocol and packet id, of both closed and live sessions.
+	pub fn packet_stats(&self) -> PacketCounters {
+		let mut packets = self.stats.closed_packets();
+		for session in self.sessions.read().iter() {
+			packets.merge(session.lock().packets());
+		}
+		packets
+	}
+
+	/// Replace the address filter and disconnect peers it no longer allows.
+	pub fn set_ip_filter(&self, filter: IpFilter, io: &IoContext<NetworkIoMessage>) {
+		self.info.write().config.ip_filter = filter.clone();
+		if let Some(ref mut discovery) = *self.discovery.lock() {
+			discovery.set_ip_filter(filter.clone());
+		}
+		let mut to_kill = Vec::new();
+		for e in self.sessions.read().iter() {
+			let mut s = e.lock();
+			let allowed = s.remote_addr().map(|a| filter.allows(&a.ip())).unwrap_or(true);
+			if !allowed {
+				s.disconnect(io, DisconnectReason::UselessPeer);
+				to_kill.push(s.token());
+			}
+		}
+		for p in to_kill {
+			trace!(target: "network", "Disconnecting filtered peer: {}", p);
+			self.kill_connection(p, io, false);
+		}
+	}
+
+	/// Reputations of known nodes with non-zero score or an active ban.
+	pub fn node_reputations(&self) -> Vec<NodeReputation> {
+		self.nodes.read().reputations()
+	}
+
+	/// Lift ban and reset reputation of a node given by its public key.
+	pub fn unban_node(&self, id: &str) -> Result<(), NetworkError> {
+		let id = try!(parse_node_id(id));
+		match self.nodes.write().unban(&id) {
+			true => Ok(()),
+			false => Err(NetworkError::PeerNotFound),
+		}
+	}
+
+	/// Ban a node given by its public key and disconnect it.
+	pub fn ban_node(&self, id: &str, io: &IoContext<NetworkIoMessage>) -> Result<(), NetworkError> {
+		let id = try!(parse_node_id(id));
+		self.nodes.write().ban(&id);
+		self.disconnect_sessions(&id, io);
+		Ok(())
+	}
+
+	/// Disconnect a node given by its public key. Reconnect can be attempted later.
+	pub fn disconnect_node(&self, id: &str, io: &IoContext<NetworkIoMessage>) -> Result<(), NetworkError> {
+		let id = try!(parse_node_id(id));
+		match self.disconnect_sessions(&id, io) {
+			0 => Err(NetworkError::PeerNotFound),
+			_ => Ok(()),
+		}
+	}
+
+	/// Information about connected peers.
+	pub fn peers_info(&self) -> Vec<(PeerId, PeerInfo)> {
+		self.sessions.read().iter().filter_map(|s| {
+			let s = s.lock();
+			s.peer_info().map(|info| (s.token(), info))
+		}).collect()
+	}
+
 	pub fn client_version() -> String {
 		version()
 	}
@@ -556,7 +669,7 @@ impl Host {
 			if info.config.discovery_enabled && info.config.non_reserved_mode == NonReservedPeerMode::Accept {
 				let mut udp_addr = local_endpoint.address.clone();
 				udp_addr.set_port(local_endpoint.udp_port);
-				Some(Discovery::new(&info.keys, udp_addr, public_endpoint, DISCOVERY))
+				Some(Discovery::new(&info.keys, udp_addr, public_endpoint, DISCOVERY, info.config.ip_filter.clone(), info.config.discovery_topic.clone()))
 			} else { None }
 		};
 
@@ -578,6 +691,40 @@ impl Host {
 		self.connect_peers(io);
 	}
 
+	// poll sessions held back by bandwidth limits, their sockets won't signal readiness again.
+	fn resume_throttled(&self, io: &IoContext<NetworkIoMessage>) {
+		let throttled: Vec<(StreamToken, bool, bool)> = self.sessions.read().iter().filter_map(|s| {
+			let s = s.lock();
+			match (s.is_read_throttled(), s.is_write_throttled()) {
+				(false, false) => None,
+				(read, write) => Some((s.token(), read, write)),
+			}
+		}).collect();
+		for (token, read, write) in throttled {
+			if write {
+				self.session_writable(token, io);
+			}
+			if read {
+				self.session_readable(token, io);
+			}
+		}
+	}
+
+	// disconnect all sessions with the given node. returns number of sessions disconnected.
+	fn disconnect_sessions(&self, id: &NodeId, io: &IoContext<NetworkIoMessage>) -> usize {
+		let sessions: Vec<SharedSession> = self.sessions.read().iter().filter(|s| s.lock().id() == Some(id)).cloned().collect();
+		for session in &sessions {
+			let token = {
+				let mut s = session.lock();
+				s.disconnect(io, DisconnectReason::DisconnectRequested);
+				s.token()
+			};
+			trace!(target: "network", "Disconnecting node {}: session {}", id, token);
+			self.kill_connection(token, io, false);
+		}
+		sessions.len()
+	}
+
 	fn have_session(&self, id: &NodeId) -> bool {
 		self.sessions.read().iter().any(|e| e.lock().info.id == Some(id.clone()))
 	}
@@ -647,8 +794,12 @@ impl Host {
 			Vec::new()
 		});
 
+		// skip filtered addresses before picking candidates so they don't use up the round.
+		let ip_filter = self.info.read().config.ip_filter.clone();
+		let is_allowed = |id: &NodeId| self.nodes.read().get(id).map_or(false, |n| ip_filter.allows(&n.endpoint.address.ip()));
+
 		let mut started: usize = 0;
-		for id in nodes.filter(|ref id| !self.have_session(id) && !self.connecting_to(id))
+		for id in nodes.filter(|ref id| !self.have_session(id) && !self.connecting_to(id) && is_allowed(id))
 			.take(min(MAX_HANDSHAKES_PER_ROUND, handshake_limit - handshake_count)) {
 			self.connect_peer(&id, io);
 			started += 1;
@@ -680,6 +831,10 @@ impl Host {
 					return;
 				}
 			};
+			if !self.info.read().config.ip_filter.allows(&address.ip()) {
+				trace!(target: "network", "Aborted connect. Address {} is filtered.", address);
+				return;
+			}
 			match TcpStream::connect(&address) {
 				Ok(socket) => socket,
 				Err(e) => {
@@ -722,7 +877,13 @@ impl Host {
 		loop {
 			let socket = match self.tcp_listener.lock().accept() {
 				Ok(None) => break,
-				Ok(Some((sock, _addr))) => sock,
+				Ok(Some((sock, addr))) => {
+					if !self.info.read().config.ip_filter.allows(&addr.ip()) {
+						trace!(target: "network", "Rejecting connection from filtered address {}", addr);
+						continue;
+					}
+					sock
+				},
 				Err(e) => {
 					warn!("Error accepting connection: {:?}", e);
 					break
@@ -778,6 +939,13 @@ impl Host {
 					},
 					Ok(SessionData::Ready) => {
 						self.num_sessions.fetch_add(1, AtomicOrdering::SeqCst);
+						if let Some(id) = s.id().cloned() {
+							if self.nodes.read().is_banned(&id) && !self.reserved_nodes.read().contains(&id) {
+								trace!(target: "network", "Rejecting banned peer: {}", id);
+								s.disconnect(io, DisconnectReason::UselessPeer);
+								return;
+							}
+						}
 						if !s.info.originated {
 							let session_count = self.session_count();
 							let (max_peers, reserved_only) = {
@@ -832,12 +1000,12 @@ impl Host {
 			let h = handlers.get(&p).unwrap().clone();
 			self.stats.inc_sessions();
 			let reserved = self.reserved_nodes.read();
-			h.connected(&NetworkContext::new(io, p, session.clone(), self.sessions.clone(), &reserved), &token);
+			h.connected(&NetworkContext::new(io, p, session.clone(), self.sessions.clone(), &reserved, &self.stats), &token);
 		}
 		for (p, packet_id, data) in packet_data {
 			let h = handlers.get(&p).unwrap().clone();
 			let reserved = self.reserved_nodes.read();
-			h.read(&NetworkContext::new(io, p, session.clone(), self.sessions.clone(), &reserved), &token, packet_id, &data[1..]);
+			h.read(&NetworkContext::new(io, p, session.clone(), self.sessions.clone(), &reserved, &self.stats), &token, packet_id, &data[1..]);
 		}
 	}
 
@@ -879,7 +1047,7 @@ impl Host {
 		for p in to_disconnect {
 			let h = self.handlers.read().get(&p).unwrap().clone();
 			let reserved = self.reserved_nodes.read();
-			h.disconnected(&NetworkContext::new(io, p, expired_session.clone(), self.sessions.clone(), &reserved), &token);
+			h.disconnected(&NetworkContext::new(io, p, expired_session.clone(), self.sessions.clone(), &reserved, &self.stats), &token);
 		}
 		if deregister {
 			io.deregister_stream(token).unwrap_or_else(|e| debug!("Error deregistering stream: {:?}", e));
@@ -909,7 +1077,7 @@ impl Host {
 	pub fn with_context<F>(&self, protocol: ProtocolId, io: &IoContext<NetworkIoMessage>, action: F) where F: Fn(&NetworkContext) {
 		let reserved = { self.reserved_nodes.read() };
 
-		let context = NetworkContext::new(io, protocol, None, self.sessions.clone(), &reserved);
+		let context = NetworkContext::new(io, protocol, None, self.sessions.clone(), &reserved, &self.stats);
 		action(&context);
 	}
 }
@@ -918,6 +1086,7 @@ impl IoHandler<NetworkIoMessage> for Host {
 	/// Initialize networking
 	fn initialize(&self, io: &IoContext<NetworkIoMessage>) {
 		io.register_timer(IDLE, MAINTENANCE_TIMEOUT).expect("Error registering Network idle timer");
+		io.register_timer(THROTTLE, THROTTLE_TIMEOUT).expect("Error registering Network throttle timer");
 		io.message(NetworkIoMessage::InitPublicInterface).unwrap_or_else(|e| warn!("Error sending IO notification: {:?}", e));
 		self.maintain_network(io)
 	}
@@ -966,6 +1135,7 @@ impl IoHandler<NetworkIoMessage> for Host {
 		}
 		match token {
 			IDLE => self.maintain_network(io),
+			THROTTLE => self.resume_throttled(io),
 			FIRST_SESSION ... LAST_SESSION => self.connection_timeout(token, io),
 			DISCOVERY_REFRESH => {
 				self.discovery.lock().as_mut().unwrap().refresh();
@@ -980,14 +1150,16 @@ impl IoHandler<NetworkIoMessage> for Host {
 			},
 			NODE_TABLE => {
 				trace!(target: "network", "Refreshing node table");
-				self.nodes.write().clear_useless();
+				let mut nodes = self.nodes.write();
+				nodes.clear_useless();
+				nodes.decay_scores();
 			},
 			_ => match self.timers.read().get(&token).cloned() {
 				Some(timer) => match self.handlers.read().get(&timer.protocol).cloned() {
 					None => { warn!(target: "network", "No handler found for protocol: {:?}", timer.protocol) },
 					Some(h) => {
 						let reserved = self.reserved_nodes.read();
-						h.timeout(&NetworkContext::new(io, timer.protocol, None, self.sessions.clone(), &reserved), timer.token);
+						h.timeout(&NetworkContext::new(io, timer.protocol, None, self.sessions.clone(), &reserved, &self.stats), timer.token);
 					}
 				},
 				None => { warn!("Unknown timer token: {}", token); } // timer is not registerd through us
@@ -1007,7 +1179,7 @@ impl IoHandler<NetworkIoMessage> for Host {
 			} => {
 				let h = handler.clone();
 				let reserved = self.reserved_nodes.read();
-				h.initialize(&NetworkContext::new(io, *protocol, None, self.sessions.clone(), &reserved));
+				h.initialize(&NetworkContext::new(io, *protocol, None, self.sessions.clone(), &reserved, &self.stats));
 				self.handlers.write().insert(*protocol, h);
 				let mut info = self.info.write();
 				for v in versions {
@@ -1048,6 +1220,14 @@ impl IoHandler<NetworkIoMessage> for Host {
 				trace!(target: "network", "Disabling peer {}", peer);
 				self.kill_connection(*peer, io, false);
 			},
+			NetworkIoMessage::AdjustPeerScore { ref node, delta } => {
+				let reputation = self.nodes.write().adjust_score(node, delta);
+				if reputation == Reputation::Good || self.reserved_nodes.read().contains(node) {
+					return;
+				}
+				trace!(target: "network", "Disconnecting node {} on low reputation ({:?})", node, reputation);
+				self.disconnect_sessions(node, io);
+			},
 			NetworkIoMessage::InitPublicInterface =>
 				self.init_public_interface(io).unwrap_or_else(|e| warn!("Error initializing public interface: {:?}", e)),
 			_ => {}	// ignore others.
@@ -1097,6 +1277,10 @@ impl IoHandler<NetworkIoMessage> for Host {
 	}
 }
 
+fn parse_node_id(id: &str) -> Result<NodeId, NetworkError> {
+	Ok(try!(id.trim_left_matches("0x").parse().map_err(UtilError::from)))
+}
+
 fn save_key(path: &Path, key: &Secret) {
 	let mut path_buf = PathBuf::from(path);
 	if let Err(e) = fs::create_dir_all(path_buf.as_path()) {
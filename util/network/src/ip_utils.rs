@@ -18,9 +18,12 @@
 
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
 use std::io;
+use std::fmt;
+use std::str::FromStr;
 use igd::{PortMappingProtocol, search_gateway_from_timeout};
 use std::time::Duration;
 use node_table::{NodeEndpoint};
+use error::NetworkError;
 
 /// Socket address extension for rustc beta. To be replaces with now unstable API
 pub trait SocketAddrExt {
@@ -56,6 +59,175 @@ impl SocketAddrExt for Ipv6Addr {
 	}
 }
 
+/// A block of IP addresses in CIDR notation, e.g. `10.0.0.0/8` or `fc00::/7`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpNetwork {
+	addr: IpAddr,
+	prefix: u8,
+}
+
+impl IpNetwork {
+	/// Create a new network from base address and prefix length. Returns `None` if prefix is too long.
+	pub fn new(addr: IpAddr, prefix: u8) -> Option<IpNetwork> {
+		let max_prefix = match addr {
+			IpAddr::V4(_) => 32,
+			IpAddr::V6(_) => 128,
+		};
+		match prefix <= max_prefix {
+			true => Some(IpNetwork { addr: addr, prefix: prefix }),
+			false => None,
+		}
+	}
+
+	/// Check if the address belongs to this network. Addresses of a different family never match.
+	pub fn contains(&self, ip: &IpAddr) -> bool {
+		match (&self.addr, ip) {
+			(&IpAddr::V4(ref net), &IpAddr::V4(ref ip)) => prefix_matches(&net.octets(), &ip.octets(), self.prefix),
+			(&IpAddr::V6(ref net), &IpAddr::V6(ref ip)) => prefix_matches(&v6_octets(net), &v6_octets(ip), self.prefix),
+			_ => false,
+		}
+	}
+}
+
+fn v6_octets(ip: &Ipv6Addr) -> Vec<u8> {
+	ip.segments().iter().flat_map(|s| vec![(s >> 8) as u8, *s as u8]).collect()
+}
+
+fn prefix_matches(a: &[u8], b: &[u8], prefix: u8) -> bool {
+	let full = prefix as usize / 8;
+	let rest = prefix % 8;
+	if a[..full] != b[..full] {
+		return false;
+	}
+	if rest == 0 {
+		return true;
+	}
+	let mask = 0xffu8 << (8 - rest);
+	a[full] & mask == b[full] & mask
+}
+
+impl FromStr for IpNetwork {
+	type Err = NetworkError;
+
+	/// Parse `address/prefix`. A bare address is a single host network.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || NetworkError::InvalidIpFilter(s.to_owned());
+		let mut parts = s.trim().splitn(2, '/');
+		let addr: IpAddr = try!(parts.next().unwrap_or("").parse().map_err(|_| invalid()));
+		let prefix = match (parts.next(), addr) {
+			(Some(prefix), _) => try!(prefix.parse::<u8>().map_err(|_| invalid())),
+			(None, IpAddr::V4(_)) => 32,
+			(None, IpAddr::V6(_)) => 128,
+		};
+		IpNetwork::new(addr, prefix).ok_or_else(invalid)
+	}
+}
+
+impl fmt::Display for IpNetwork {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}/{}", self.addr, self.prefix)
+	}
+}
+
+/// Predefined address ranges peers are allowed to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllowIP {
+	/// Any address.
+	All,
+	/// Only private, loopback and other non-routable addresses. Suitable for consortium nodes.
+	Private,
+	/// Only globally routable addresses. Suitable for public nodes.
+	Public,
+	/// No address unless explicitly allowed by a custom range.
+	None,
+}
+
+impl FromStr for AllowIP {
+	type Err = NetworkError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"all" => Ok(AllowIP::All),
+			"private" => Ok(AllowIP::Private),
+			"public" => Ok(AllowIP::Public),
+			"none" => Ok(AllowIP::None),
+			_ => Err(NetworkError::InvalidIpFilter(s.to_owned())),
+		}
+	}
+}
+
+impl fmt::Display for AllowIP {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let s = match *self {
+			AllowIP::All => "all",
+			AllowIP::Private => "private",
+			AllowIP::Public => "public",
+			AllowIP::None => "none",
+		};
+		f.write_str(s)
+	}
+}
+
+/// Address filter applied to inbound connections, outbound connection attempts and discovered nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpFilter {
+	/// Predefined ranges to allow.
+	pub predefined: AllowIP,
+	/// Additional ranges to allow on top of the predefined ones.
+	pub custom_allow: Vec<IpNetwork>,
+	/// Ranges to deny. Takes precedence over everything else.
+	pub custom_block: Vec<IpNetwork>,
+}
+
+impl Default for IpFilter {
+	fn default() -> Self {
+		IpFilter {
+			predefined: AllowIP::All,
+			custom_allow: Vec::new(),
+			custom_block: Vec::new(),
+		}
+	}
+}
+
+impl IpFilter {
+	/// Build a filter from a predefined range name and lists of CIDR ranges.
+	pub fn parse(predefined: &str, allow: &[String], block: &[String]) -> Result<IpFilter, NetworkError> {
+		let parse_list = |list: &[String]| list.iter().map(|s| s.parse()).collect::<Result<Vec<IpNetwork>, _>>();
+		Ok(IpFilter {
+			predefined: try!(predefined.parse()),
+			custom_allow: try!(parse_list(allow)),
+			custom_block: try!(parse_list(block)),
+		})
+	}
+
+	/// Check if connections to or from the address are allowed.
+	pub fn allows(&self, ip: &IpAddr) -> bool {
+		if self.custom_block.iter().any(|net| net.contains(ip)) {
+			return false;
+		}
+		let global = match *ip {
+			IpAddr::V4(ref a) => a.is_global_s(),
+			IpAddr::V6(ref a) => a.is_global_s(),
+		};
+		let predefined = match self.predefined {
+			AllowIP::All => true,
+			AllowIP::Private => !global,
+			AllowIP::Public => global,
+			AllowIP::None => false,
+		};
+		predefined || self.custom_allow.iter().any(|net| net.contains(ip))
+	}
+
+	/// Check if the address is a private one the user explicitly asked for, either through
+	/// the `private` predefined range or a custom allow range.
+	pub fn allows_private(&self, ip: &IpAddr) -> bool {
+		if self.custom_block.iter().any(|net| net.contains(ip)) {
+			return false;
+		}
+		self.predefined == AllowIP::Private || self.custom_allow.iter().any(|net| net.contains(ip))
+	}
+}
+
 #[cfg(not(windows))]
 mod getinterfaces {
 	use std::{mem, io, ptr};
@@ -263,3 +435,57 @@ fn ipv6_properties() {
 	check("::", true,  false, true);
 	check("::1", false, true, false);
 }
+
+#[test]
+fn ip_network_contains() {
+	let net: IpNetwork = "10.0.0.0/8".parse().unwrap();
+	assert!(net.contains(&"10.1.2.3".parse().unwrap()));
+	assert!(!net.contains(&"11.0.0.1".parse().unwrap()));
+	assert!(!net.contains(&"::1".parse().unwrap()));
+
+	let net: IpNetwork = "172.16.0.0/12".parse().unwrap();
+	assert!(net.contains(&"172.31.255.255".parse().unwrap()));
+	assert!(!net.contains(&"172.32.0.0".parse().unwrap()));
+
+	let net: IpNetwork = "fc00::/7".parse().unwrap();
+	assert!(net.contains(&"fd12::1".parse().unwrap()));
+	assert!(!net.contains(&"fe80::1".parse().unwrap()));
+
+	let host: IpNetwork = "192.168.0.1".parse().unwrap();
+	assert_eq!(host.to_string(), "192.168.0.1/32");
+	assert!(host.contains(&"192.168.0.1".parse().unwrap()));
+	assert!(!host.contains(&"192.168.0.2".parse().unwrap()));
+
+	assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+	assert!("10.0.0/8".parse::<IpNetwork>().is_err());
+}
+
+#[test]
+fn ip_filter_allows() {
+	let public = IpFilter::parse("public", &[], &["1.2.3.0/24".into()]).unwrap();
+	assert!(public.allows(&"8.8.8.8".parse().unwrap()));
+	assert!(!public.allows(&"192.168.1.1".parse().unwrap()));
+	assert!(!public.allows(&"1.2.3.4".parse().unwrap()));
+
+	let private = IpFilter::parse("private", &["8.8.8.8/32".into()], &["10.1.0.0/16".into()]).unwrap();
+	assert!(private.allows(&"10.0.0.1".parse().unwrap()));
+	assert!(private.allows(&"8.8.8.8".parse().unwrap()));
+	assert!(!private.allows(&"10.1.0.1".parse().unwrap()));
+	assert!(!private.allows(&"8.8.4.4".parse().unwrap()));
+
+	assert!(IpFilter::default().allows(&"127.0.0.1".parse().unwrap()));
+	assert!(IpFilter::parse("some", &[], &[]).is_err());
+}
+
+#[test]
+fn ip_filter_allows_private() {
+	assert!(!IpFilter::default().allows_private(&"192.168.1.1".parse().unwrap()));
+
+	let private = IpFilter::parse("private", &[], &["10.1.0.0/16".into()]).unwrap();
+	assert!(private.allows_private(&"192.168.1.1".parse().unwrap()));
+	assert!(!private.allows_private(&"10.1.0.1".parse().unwrap()));
+
+	let custom = IpFilter::parse("all", &["192.168.0.0/16".into()], &[]).unwrap();
+	assert!(custom.allows_private(&"192.168.1.1".parse().unwrap()));
+	assert!(!custom.allows_private(&"10.0.0.1".parse().unwrap()));
+}
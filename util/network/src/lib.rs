@@ -86,6 +86,7 @@ mod service;
 mod error;
 mod node_table;
 mod stats;
+mod bandwidth;
 mod ip_utils;
 
 #[cfg(test)]
@@ -94,16 +95,21 @@ mod tests;
 pub use host::PeerId;
 pub use host::PacketId;
 pub use host::NetworkContext;
+pub use session::PeerInfo;
 pub use service::NetworkService;
 pub use host::NetworkIoMessage;
 pub use error::NetworkError;
 pub use host::NetworkConfiguration;
-pub use stats::NetworkStats;
+pub use stats::{NetworkStats, PacketStats, PacketCounters};
+pub use bandwidth::RateLimiter;
 
 use io::TimerToken;
-pub use node_table::is_valid_node_url;
+pub use node_table::{is_valid_node_url, NodeReputation};
+pub use ip_utils::{IpFilter, IpNetwork, AllowIP};
 
-const PROTOCOL_VERSION: u32 = 4;
+/// RLPx protocol version advertised in the hello packet.
+/// Version 5 adds snappy compression of packet payloads.
+const PROTOCOL_VERSION: u32 = 5;
 
 /// Network IO protocol handler. This needs to be implemented for each new subprotocol.
 /// All the handler function are called from within IO event loop.
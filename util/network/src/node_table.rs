@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::mem;
+use std::cmp::{min, max};
 use std::slice::from_raw_parts;
 use std::net::{SocketAddr, ToSocketAddrs, SocketAddrV4, SocketAddrV6, Ipv4Addr, Ipv6Addr};
 use std::hash::{Hash, Hasher};
@@ -37,6 +38,43 @@ pub use rustc_serialize::json::Json;
 /// Node public key
 pub type NodeId = H512;
 
+/// Lowest reputation score. Nodes dropping to it get temporarily banned.
+pub const MIN_SCORE: i32 = -100;
+/// Highest reputation score.
+pub const MAX_SCORE: i32 = 100;
+/// Connected nodes scoring below this get disconnected.
+pub const DISCONNECT_SCORE: i32 = -50;
+/// Time in seconds a node stays banned for.
+pub const BAN_DURATION_SEC: u64 = 3600;
+/// Points recovered by nodes with negative score on every `decay_scores` call.
+pub const SCORE_DECAY: i32 = 5;
+
+/// Outcome of a reputation change.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Reputation {
+	/// Node may stay connected.
+	Good,
+	/// Node should be disconnected.
+	Disconnect,
+	/// Node has been banned and should be disconnected.
+	Banned,
+}
+
+/// Reputation of a known node.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct NodeReputation {
+	/// Node public key.
+	pub id: NodeId,
+	/// Reputation score.
+	pub score: i32,
+	/// Unix timestamp the ban expires at, if banned.
+	pub banned_until: Option<u64>,
+}
+
+fn unix_now() -> u64 {
+	::time::get_time().sec as u64
+}
+
 #[derive(Debug, Clone)]
 /// Node address info
 pub struct NodeEndpoint {
@@ -136,6 +174,10 @@ pub struct Node {
 	pub peer_type: PeerType,
 	pub failures: u32,
 	pub last_attempted: Option<Tm>,
+	pub score: i32,
+	pub banned_until: Option<u64>,
+	/// Topic advertised by the node via discovery.
+	pub topic: Option<H256>,
 }
 
 impl Node {
@@ -146,8 +188,16 @@ impl Node {
 			peer_type: PeerType::Optional,
 			failures: 0,
 			last_attempted: None,
+			score: 0,
+			banned_until: None,
+			topic: None,
 		}
 	}
+
+	/// Check if the node is banned at the given time.
+	pub fn is_banned(&self, now: u64) -> bool {
+		self.banned_until.map_or(false, |until| until > now)
+	}
 }
 
 impl Display for Node {
@@ -177,6 +227,9 @@ impl FromStr for Node {
 			peer_type: PeerType::Optional,
 			last_attempted: None,
 			failures: 0,
+			score: 0,
+			banned_until: None,
+			topic: None,
 		})
 	}
 }
@@ -199,6 +252,7 @@ pub struct NodeTable {
 	nodes: HashMap<NodeId, Node>,
 	useless_nodes: HashSet<NodeId>,
 	path: Option<String>,
+	topic: Option<H256>,
 }
 
 impl NodeTable {
@@ -207,21 +261,44 @@ impl NodeTable {
 			path: path.clone(),
 			nodes: NodeTable::load(path),
 			useless_nodes: HashSet::new(),
+			topic: None,
+		}
+	}
+
+	/// Set our own topic. Nodes advertising it are preferred, nodes advertising a different one come last.
+	pub fn set_topic(&mut self, topic: Option<H256>) {
+		self.topic = topic;
+	}
+
+	fn topic_rank(&self, node: &Node) -> u8 {
+		match (self.topic.as_ref(), node.topic.as_ref()) {
+			(Some(ours), Some(theirs)) if ours == theirs => 0,
+			(Some(_), Some(_)) => 2,
+			_ => 1,
 		}
 	}
 
 	/// Add a node to table
 	pub fn add_node(&mut self, mut node: Node) {
-		// preserve failure counter
-		let failures = self.nodes.get(&node.id).map_or(0, |n| n.failures);
-		node.failures = failures;
+		// preserve failure counter and reputation
+		if let Some(n) = self.nodes.get(&node.id) {
+			node.failures = n.failures;
+			node.score = n.score;
+			node.banned_until = n.banned_until;
+			if node.topic.is_none() {
+				node.topic = n.topic.clone();
+			}
+		}
 		self.nodes.insert(node.id.clone(), node);
 	}
 
-	/// Returns node ids sorted by number of failures
+	/// Returns ids of nodes which are not banned, sorted by topic match, reputation score and number of failures
 	pub fn nodes(&self) -> Vec<NodeId> {
-		let mut refs: Vec<&Node> = self.nodes.values().filter(|n| !self.useless_nodes.contains(&n.id)).collect();
-		refs.sort_by(|a, b| a.failures.cmp(&b.failures));
+		let now = unix_now();
+		let mut refs: Vec<&Node> = self.nodes.values()
+			.filter(|n| !self.useless_nodes.contains(&n.id) && !n.is_banned(now) && n.endpoint.is_valid())
+			.collect();
+		refs.sort_by_key(|n| (self.topic_rank(n), -n.score, n.failures));
 		refs.iter().map(|n| n.id.clone()).collect()
 	}
 
@@ -231,6 +308,11 @@ impl NodeTable {
 		self.nodes.values().map(|n| NodeEntry { endpoint: n.endpoint.clone(), id: n.id.clone() }).collect()
 	}
 
+	/// Get particular node
+	pub fn get(&self, id: &NodeId) -> Option<&Node> {
+		self.nodes.get(id)
+	}
+
 	/// Get particular node
 	pub fn get_mut(&mut self, id: &NodeId) -> Option<&mut Node> {
 		self.nodes.get_mut(id)
@@ -242,8 +324,15 @@ impl NodeTable {
 			let mut entry = self.nodes.entry(node.id.clone()).or_insert_with(|| Node::new(node.id.clone(), node.endpoint.clone()));
 			entry.endpoint = node.endpoint;
 		}
+		for (id, topic) in update.topics.drain() {
+			if let Some(node) = self.nodes.get_mut(&id) {
+				node.topic = Some(topic);
+			}
+		}
+		let now = unix_now();
 		for r in update.removed {
-			if !reserved.contains(&r) {
+			// keep banned and badly scored nodes so that the reputation outlives rediscovery.
+			if !reserved.contains(&r) && !self.nodes.get(&r).map_or(false, |n| n.is_banned(now) || n.score < 0) {
 				self.nodes.remove(&r);
 			}
 		}
@@ -266,6 +355,76 @@ impl NodeTable {
 		self.useless_nodes.clear();
 	}
 
+	/// Change reputation score of a node. Nodes dropping to `MIN_SCORE` are banned for `BAN_DURATION_SEC`
+	/// and start over from `DISCONNECT_SCORE` once the ban expires.
+	pub fn adjust_score(&mut self, id: &NodeId, delta: i32) -> Reputation {
+		let node = match self.nodes.get_mut(id) {
+			Some(node) => node,
+			None => return Reputation::Good,
+		};
+		node.score = max(MIN_SCORE, min(MAX_SCORE, node.score.saturating_add(delta)));
+		if node.score <= MIN_SCORE {
+			node.score = DISCONNECT_SCORE;
+			node.banned_until = Some(unix_now() + BAN_DURATION_SEC);
+			Reputation::Banned
+		} else if node.score < DISCONNECT_SCORE {
+			Reputation::Disconnect
+		} else {
+			Reputation::Good
+		}
+	}
+
+	/// Move negative scores of nodes which are not banned towards zero by `SCORE_DECAY`.
+	pub fn decay_scores(&mut self) {
+		let now = unix_now();
+		for node in self.nodes.values_mut().filter(|n| n.score < 0 && !n.is_banned(now)) {
+			node.score = min(0, node.score + SCORE_DECAY);
+		}
+	}
+
+	/// Ban a node for `BAN_DURATION_SEC`. Unknown nodes are added with an unspecified endpoint,
+	/// which is updated if the node gets discovered.
+	pub fn ban(&mut self, id: &NodeId) {
+		let node = self.nodes.entry(id.clone()).or_insert_with(|| Node::new(id.clone(), NodeEndpoint {
+			address: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0)),
+			udp_port: 0,
+		}));
+		node.banned_until = Some(unix_now() + BAN_DURATION_SEC);
+	}
+
+	/// Check if a node is currently banned.
+	pub fn is_banned(&self, id: &NodeId) -> bool {
+		self.nodes.get(id).map_or(false, |n| n.is_banned(unix_now()))
+	}
+
+	/// Lift ban and reset reputation of a node. Returns false if the node is unknown.
+	pub fn unban(&mut self, id: &NodeId) -> bool {
+		self.useless_nodes.remove(id);
+		match self.nodes.get_mut(id) {
+			Some(node) => {
+				node.score = 0;
+				node.banned_until = None;
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Reputations of nodes with non-zero score or an active ban, worst first.
+	pub fn reputations(&self) -> Vec<NodeReputation> {
+		let now = unix_now();
+		let mut reputations: Vec<NodeReputation> = self.nodes.values()
+			.filter(|n| n.score != 0 || n.is_banned(now))
+			.map(|n| NodeReputation {
+				id: n.id.clone(),
+				score: n.score,
+				banned_until: n.banned_until.and_then(|until| if until > now { Some(until) } else { None }),
+			})
+			.collect();
+		reputations.sort_by_key(|r| r.score);
+		reputations
+	}
+
 	fn save(&self) {
 		if let Some(ref path) = self.path {
 			let mut path_buf = PathBuf::from(path);
@@ -277,10 +436,20 @@ impl NodeTable {
 			let mut json = String::new();
 			json.push_str("{\n");
 			json.push_str("\"nodes\": [\n");
-			let node_ids = self.nodes();
-			for i in 0 .. node_ids.len() {
-				let node = self.nodes.get(&node_ids[i]).unwrap();
-				json.push_str(&format!("\t{{ \"url\": \"{}\", \"failures\": {} }}{}\n", node, node.failures, if i == node_ids.len() - 1 {""} else {","}))
+			let now = unix_now();
+			let mut nodes: Vec<&Node> = self.nodes.values().filter(|n| !self.useless_nodes.contains(&n.id) || n.is_banned(now)).collect();
+			nodes.sort_by_key(|n| (-n.score, n.failures));
+			for i in 0 .. nodes.len() {
+				let node = nodes[i];
+				let ban = match node.is_banned(now) {
+					true => format!(", \"banned_until\": {}", node.banned_until.expect("banned nodes have ban expiry; qed")),
+					false => String::new(),
+				};
+				let topic = match node.topic {
+					Some(ref topic) => format!(", \"topic\": \"{}\"", topic.hex()),
+					None => String::new(),
+				};
+				json.push_str(&format!("\t{{ \"url\": \"{}\", \"failures\": {}, \"score\": {}{}{} }}{}\n", node, node.failures, node.score, ban, topic, if i == nodes.len() - 1 {""} else {","}))
 			}
 			json.push_str("]\n");
 			json.push_str("}");
@@ -331,6 +500,11 @@ impl NodeTable {
 							if let Some(failures) = n.get("failures").and_then(|f| f.as_u64()) {
 								node.failures = failures as u32;
 							}
+							if let Some(score) = n.get("score").and_then(|s| s.as_i64()) {
+								node.score = max(MIN_SCORE as i64, min(MAX_SCORE as i64, score)) as i32;
+							}
+							node.banned_until = n.get("banned_until").and_then(|b| b.as_u64());
+							node.topic = n.get("topic").and_then(|t| t.as_string()).and_then(|t| H256::from_str(t).ok());
 							nodes.insert(node.id.clone(), node);
 						}
 					}
@@ -360,6 +534,8 @@ mod tests {
 	use std::net::*;
 	use util::hash::*;
 	use devtools::*;
+	use std::collections::{HashMap, HashSet};
+	use discovery::{TableUpdates, NodeEntry};
 
 	#[test]
 	fn endpoint_parse() {
@@ -432,4 +608,128 @@ mod tests {
 			assert_eq!(r[1][..], id2[..]);
 		}
 	}
+
+	#[test]
+	fn table_reputation() {
+		let temp_path = RandomTempPath::create_dir();
+		let node1 = Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let node2 = Node::from_str("enode://b979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let node3 = Node::from_str("enode://c979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let id1 = H512::from_str("a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c").unwrap();
+		let id2 = H512::from_str("b979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c").unwrap();
+		let id3 = H512::from_str("c979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c").unwrap();
+		{
+			let mut table = NodeTable::new(Some(temp_path.as_path().to_str().unwrap().to_owned()));
+			table.add_node(node1);
+			table.add_node(node2);
+			table.add_node(node3);
+
+			assert_eq!(table.adjust_score(&id1, 10), Reputation::Good);
+			assert_eq!(table.adjust_score(&id2, -60), Reputation::Disconnect);
+			assert_eq!(table.adjust_score(&id3, -100), Reputation::Banned);
+
+			let r = table.nodes();
+			assert_eq!(r.len(), 2);
+			assert_eq!(r[0][..], id1[..]);
+			assert_eq!(r[1][..], id2[..]);
+			assert!(table.is_banned(&id3));
+		}
+
+		{
+			let mut table = NodeTable::new(Some(temp_path.as_path().to_str().unwrap().to_owned()));
+			let r = table.reputations();
+			assert_eq!(r.len(), 3);
+			assert_eq!((r[0].id, r[0].score), (id2, -60));
+			assert_eq!((r[1].id, r[1].score, r[1].banned_until.is_some()), (id3, DISCONNECT_SCORE, true));
+			assert_eq!((r[2].id, r[2].score), (id1, 10));
+
+			assert!(table.unban(&id3));
+			assert!(!table.is_banned(&id3));
+			assert_eq!(table.nodes().len(), 3);
+		}
+	}
+
+	#[test]
+	fn table_keeps_nodes_until_score_decays() {
+		let node1 = Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let node2 = Node::from_str("enode://b979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let node3 = Node::from_str("enode://c979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let id1 = node1.id.clone();
+		let id2 = node2.id.clone();
+		let id3 = node3.id.clone();
+		let mut table = NodeTable::new(None);
+		table.add_node(node1);
+		table.add_node(node2);
+		table.add_node(node3);
+		table.adjust_score(&id2, -2 * SCORE_DECAY);
+		table.ban(&id3);
+		let removed = || vec![id1.clone(), id2.clone(), id3.clone()].into_iter().collect::<HashSet<_>>();
+
+		table.update(TableUpdates { added: HashMap::new(), removed: removed(), topics: HashMap::new() }, &HashSet::new());
+		assert!(table.get(&id1).is_none());
+		assert_eq!(table.get(&id2).unwrap().score, -2 * SCORE_DECAY);
+		assert!(table.is_banned(&id3));
+
+		table.decay_scores();
+		table.decay_scores();
+		table.update(TableUpdates { added: HashMap::new(), removed: removed(), topics: HashMap::new() }, &HashSet::new());
+		assert!(table.get(&id2).is_none());
+		assert!(table.is_banned(&id3));
+	}
+
+	#[test]
+	fn table_bans_unknown_node() {
+		let node = Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let id = node.id.clone();
+		let mut table = NodeTable::new(None);
+
+		table.ban(&id);
+		assert!(table.is_banned(&id));
+		assert_eq!(table.reputations().len(), 1);
+
+		// discovery fills in the endpoint, but the ban stays
+		let mut added = HashMap::new();
+		added.insert(id.clone(), NodeEntry { id: id.clone(), endpoint: node.endpoint.clone() });
+		table.update(TableUpdates { added: added, removed: HashSet::new(), topics: HashMap::new() }, &HashSet::new());
+		assert!(table.is_banned(&id));
+		assert!(table.get(&id).unwrap().endpoint.is_valid());
+		assert!(table.nodes().is_empty());
+
+		assert!(table.unban(&id));
+		assert_eq!(table.nodes(), vec![id]);
+	}
+
+	#[test]
+	fn table_topics() {
+		let temp_path = RandomTempPath::create_dir();
+		let node1 = Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let node2 = Node::from_str("enode://b979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let node3 = Node::from_str("enode://c979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let id1 = node1.id.clone();
+		let id2 = node2.id.clone();
+		let id3 = node3.id.clone();
+		let ours = H256::from(1);
+		let theirs = H256::from(2);
+		{
+			let mut table = NodeTable::new(Some(temp_path.as_path().to_str().unwrap().to_owned()));
+			table.set_topic(Some(ours.clone()));
+			table.add_node(node1);
+			table.add_node(node2);
+			table.add_node(node3);
+			table.note_failure(&id3);
+
+			let mut topics = HashMap::new();
+			topics.insert(id1.clone(), theirs.clone());
+			topics.insert(id3.clone(), ours.clone());
+			table.update(TableUpdates { added: HashMap::new(), removed: HashSet::new(), topics: topics }, &HashSet::new());
+
+			assert_eq!(table.nodes(), vec![id3.clone(), id2.clone(), id1.clone()]);
+		}
+
+		{
+			let mut table = NodeTable::new(Some(temp_path.as_path().to_str().unwrap().to_owned()));
+			table.set_topic(Some(ours));
+			assert_eq!(table.nodes(), vec![id3, id2, id1]);
+		}
+	}
 }
@@ -16,8 +16,11 @@
 
 use {NetworkProtocolHandler, NetworkConfiguration, NonReservedPeerMode};
 use error::NetworkError;
-use host::{Host, NetworkContext, NetworkIoMessage, ProtocolId};
-use stats::NetworkStats;
+use host::{Host, NetworkContext, NetworkIoMessage, PeerId, ProtocolId};
+use session::PeerInfo;
+use node_table::NodeReputation;
+use stats::{NetworkStats, PacketCounters};
+use ip_utils::IpFilter;
 use io::*;
 use parking_lot::RwLock;
 use std::sync::Arc;
@@ -49,6 +52,7 @@ pub struct NetworkService {
 	panic_handler: Arc<PanicHandler>,
 	host_handler: Arc<HostHandler>,
 	config: NetworkConfiguration,
+	ip_filter: RwLock<IpFilter>,
 }
 
 impl NetworkService {
@@ -60,6 +64,9 @@ impl NetworkService {
 		panic_handler.forward_from(&io_service);
 
 		let stats = Arc::new(NetworkStats::new());
+		stats.upload().set_rate(config.upload_limit);
+		stats.download().set_rate(config.download_limit);
+		stats.serve().set_rate(config.serve_limit);
 		let host_info = Host::client_version();
 		Ok(NetworkService {
 			io_service: io_service,
@@ -67,6 +74,7 @@ impl NetworkService {
 			stats: stats,
 			panic_handler: panic_handler,
 			host: RwLock::new(None),
+			ip_filter: RwLock::new(config.ip_filter.clone()),
 			config: config,
 			host_handler: host_handler,
 		})
@@ -118,7 +126,9 @@ impl NetworkService {
 	pub fn start(&self) -> Result<(), NetworkError> {
 		let mut host = self.host.write();
 		if host.is_none() {
-			let h = Arc::new(try!(Host::new(self.config.clone(), self.stats.clone())));
+			let mut config = self.config.clone();
+			config.ip_filter = self.ip_filter.read().clone();
+			let h = Arc::new(try!(Host::new(config, self.stats.clone())));
 			try!(self.io_service.register_handler(h.clone()));
 			*host = Some(h);
 		}
@@ -161,6 +171,76 @@ impl NetworkService {
 		}
 	}
 
+	/// Get reputations of known nodes with non-zero score or an active ban.
+	pub fn node_reputations(&self) -> Vec<NodeReputation> {
+		let host = self.host.read();
+		host.as_ref().map_or_else(Vec::new, |h| h.node_reputations())
+	}
+
+	/// Lift ban and reset reputation of a node.
+	pub fn unban_node(&self, id: &str) -> Result<(), NetworkError> {
+		let host = self.host.read();
+		match *host {
+			Some(ref host) => host.unban_node(id),
+			None => Err(NetworkError::PeerNotFound),
+		}
+	}
+
+	/// Get information about connected peers.
+	pub fn peers_info(&self) -> Vec<(PeerId, PeerInfo)> {
+		let host = self.host.read();
+		host.as_ref().map_or_else(Vec::new, |h| h.peers_info())
+	}
+
+	/// Disconnect a node given by its public key.
+	pub fn disconnect_node(&self, id: &str) -> Result<(), NetworkError> {
+		let host = self.host.read();
+		match *host {
+			Some(ref host) => host.disconnect_node(id, &IoContext::new(self.io_service.channel(), 0)),
+			None => Err(NetworkError::PeerNotFound),
+		}
+	}
+
+	/// Ban a node given by its public key and disconnect it.
+	pub fn ban_node(&self, id: &str) -> Result<(), NetworkError> {
+		let host = self.host.read();
+		match *host {
+			Some(ref host) => host.ban_node(id, &IoContext::new(self.io_service.channel(), 0)),
+			None => Err(NetworkError::PeerNotFound),
+		}
+	}
+
+	/// Get traffic by subprotocol and packet id since the service was created.
+	pub fn packet_stats(&self) -> PacketCounters {
+		let host = self.host.read();
+		host.as_ref().map_or_else(|| self.stats.closed_packets(), |h| h.packet_stats())
+	}
+
+	/// Get the address filter currently in effect.
+	pub fn ip_filter(&self) -> IpFilter {
+		let host = self.host.read();
+		host.as_ref().map_or_else(|| self.ip_filter.read().clone(), |h| h.info.read().config.ip_filter.clone())
+	}
+
+	/// Replace the address filter. Connected peers that are no longer allowed are dropped.
+	/// The filter is kept across network restarts.
+	pub fn set_ip_filter(&self, filter: IpFilter) {
+		*self.ip_filter.write() = filter.clone();
+		let host = self.host.read();
+		if let Some(ref host) = *host {
+			let io_ctxt = IoContext::new(self.io_service.channel(), 0);
+			host.set_ip_filter(filter, &io_ctxt);
+		}
+	}
+
+	/// Change bandwidth limits in bytes per second, zero means unlimited. Takes effect immediately
+	/// and is kept across network restarts.
+	pub fn set_bandwidth_limits(&self, upload: usize, download: usize, serve: usize) {
+		self.stats.upload().set_rate(upload);
+		self.stats.download().set_rate(download);
+		self.stats.serve().set_rate(serve);
+	}
+
 	/// Set the non-reserved peer mode.
 	pub fn set_non_reserved_mode(&self, mode: NonReservedPeerMode) {
 		let host = self.host.read();
@@ -20,6 +20,7 @@ use std::sync::*;
 use mio::*;
 use mio::tcp::*;
 use util::hash::*;
+use util::snappy;
 use rlp::*;
 use connection::{EncryptedConnection, Packet, Connection};
 use handshake::Handshake;
@@ -27,11 +28,18 @@ use io::{IoContext, StreamToken};
 use error::{NetworkError, DisconnectReason};
 use host::*;
 use node_table::NodeId;
-use stats::NetworkStats;
+use stats::{NetworkStats, PacketCounters};
 use time;
 
 const PING_TIMEOUT_SEC: u64 = 30;
 const PING_INTERVAL_SEC: u64 = 30;
+// Lowest RLPx protocol version we can talk to.
+const MIN_PROTOCOL_VERSION: u32 = 4;
+// RLPx protocol version from which packet payloads are snappy compressed.
+const SNAPPY_PROTOCOL_VERSION: u32 = 5;
+// Maximal size of an uncompressed packet payload. Compressed packets which would
+// exceed this are rejected before decompression.
+const MAX_PAYLOAD_SIZE: usize = (1 << 24) - 1;
 
 /// Peer session over encrypted connection.
 /// When created waits for Hello packet exchange and signals ready state.
@@ -43,9 +51,15 @@ pub struct Session {
 	had_hello: bool,
 	/// Session is no longer active flag.
 	expired: bool,
+	/// Packet payloads are snappy compressed. Set after Hello packet exchange when both sides support it.
+	compression: bool,
 	ping_time_ns: u64,
 	pong_time_ns: Option<u64>,
 	state: State,
+	/// Shared network statistics
+	stats: Arc<NetworkStats>,
+	/// Traffic of this session by subprotocol and packet id
+	packets: PacketCounters,
 }
 
 enum State {
@@ -85,6 +99,37 @@ pub struct SessionInfo {
 	pub ping_ms: Option<u64>,
 	/// True if this session was originated by us.
 	pub originated: bool,
+	/// Bytes sent to the peer after the handshake, including packet framing.
+	pub bytes_sent: u64,
+	/// Bytes received from the peer after the handshake, including packet framing.
+	pub bytes_received: u64,
+	/// Time of the Hello packet exchange in nanoseconds.
+	pub connected_ns: u64,
+}
+
+/// Information about a connected peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerInfo {
+	/// Peer public key
+	pub id: NodeId,
+	/// Peer remote address
+	pub remote_address: Option<SocketAddr>,
+	/// Peer client ID
+	pub client_version: String,
+	/// Peer RLPx protocol version
+	pub protocol_version: u32,
+	/// Negotiated protocol capabilities and versions
+	pub capabilities: Vec<(ProtocolId, u8)>,
+	/// Peer ping delay in milliseconds
+	pub ping_ms: Option<u64>,
+	/// Bytes sent to the peer
+	pub bytes_sent: u64,
+	/// Bytes received from the peer
+	pub bytes_received: u64,
+	/// Seconds since the session was established
+	pub connected_secs: u64,
+	/// True if this session was originated by us.
+	pub originated: bool,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -133,7 +178,7 @@ impl Session {
 		nonce: &H256, stats: Arc<NetworkStats>, host: &HostInfo) -> Result<Session, NetworkError>
 		where Message: Send + Clone {
 		let originated = id.is_some();
-		let mut handshake = Handshake::new(token, id, socket, nonce, stats).expect("Can't create handshake");
+		let mut handshake = Handshake::new(token, id, socket, nonce, stats.clone()).expect("Can't create handshake");
 		try!(handshake.start(io, host, originated));
 		Ok(Session {
 			state: State::Handshake(handshake),
@@ -145,10 +190,16 @@ impl Session {
 				capabilities: Vec::new(),
 				ping_ms: None,
 				originated: originated,
+				bytes_sent: 0,
+				bytes_received: 0,
+				connected_ns: 0,
 			},
 			ping_time_ns: 0,
 			pong_time_ns: None,
 			expired: false,
+			compression: false,
+			stats: stats,
+			packets: PacketCounters::default(),
 		})
 	}
 
@@ -161,7 +212,9 @@ impl Session {
 		};
 		self.state = State::Session(connection);
 		try!(self.write_hello(io, host));
-		try!(self.send_ping(io));
+		// the first ping is sent once hello packets are exchanged and compression is settled.
+		// until then the peer is given the ping timeout to respond with hello.
+		self.ping_time_ns = time::precise_time_ns();
 		Ok(())
 	}
 
@@ -205,6 +258,21 @@ impl Session {
 		self.connection().remote_addr()
 	}
 
+	/// Traffic of this session by subprotocol and packet id.
+	pub fn packets(&self) -> &PacketCounters {
+		&self.packets
+	}
+
+	/// Check if reading is held back by the download limit.
+	pub fn is_read_throttled(&self) -> bool {
+		self.connection().is_read_throttled()
+	}
+
+	/// Check if writing is held back by the upload limit.
+	pub fn is_write_throttled(&self) -> bool {
+		self.connection().is_write_throttled()
+	}
+
 	/// Readable IO handler. Returns packet data if available.
 	pub fn readable<Message>(&mut self, io: &IoContext<Message>, host: &HostInfo) -> Result<SessionData, NetworkError>  where Message: Send + Sync + Clone {
 		if self.expired() {
@@ -244,6 +312,26 @@ impl Session {
 		}
 	}
 
+	/// Get information about the peer. Returns `None` until Hello packets are exchanged.
+	pub fn peer_info(&self) -> Option<PeerInfo> {
+		let id = match self.info.id {
+			Some(ref id) if self.had_hello => id.clone(),
+			_ => return None,
+		};
+		Some(PeerInfo {
+			id: id,
+			remote_address: self.remote_addr().ok(),
+			client_version: self.info.client_version.clone(),
+			protocol_version: self.info.protocol_version,
+			capabilities: self.info.capabilities.iter().map(|c| (c.protocol, c.version)).collect(),
+			ping_ms: self.info.ping_ms,
+			bytes_sent: self.info.bytes_sent,
+			bytes_received: self.info.bytes_received,
+			connected_secs: (time::precise_time_ns() - self.info.connected_ns) / 1000_000_000,
+			originated: self.info.originated,
+		})
+	}
+
 	/// Checks if peer supports given capability
 	pub fn have_capability(&self, protocol: [u8; 3]) -> bool {
 		self.info.capabilities.iter().any(|c| c.protocol == protocol)
@@ -297,7 +385,10 @@ impl Session {
 		let mut rlp = RlpStream::new();
 		rlp.append(&(pid as u32));
 		rlp.append_raw(data, 1);
-		self.send(io, rlp)
+		let sent = self.info.bytes_sent;
+		try!(self.send(io, rlp));
+		self.packets.inc_send(protocol, packet_id, (self.info.bytes_sent - sent) as usize);
+		Ok(())
 	}
 
 	/// Keep this session alive. Returns false if ping timeout happened
@@ -328,18 +419,25 @@ impl Session {
 		if packet.data.len() < 2 {
 			return Err(From::from(NetworkError::BadProtocol));
 		}
+		let size = EncryptedConnection::packet_size(packet.data.len());
+		self.info.bytes_received += size as u64;
 		let packet_id = packet.data[0];
 		if packet_id != PACKET_HELLO && packet_id != PACKET_DISCONNECT && !self.had_hello {
 			return Err(From::from(NetworkError::BadProtocol));
 		}
+		let data = match self.compression {
+			true => try!(Session::decompress(packet.data)),
+			false => packet.data,
+		};
 		match packet_id {
 			PACKET_HELLO => {
-				let rlp = UntrustedRlp::new(&packet.data[1..]); //TODO: validate rlp expected size
+				let rlp = UntrustedRlp::new(&data[1..]); //TODO: validate rlp expected size
 				try!(self.read_hello(io, &rlp, host));
+				try!(self.send_ping(io));
 				Ok(SessionData::Ready)
 			},
 			PACKET_DISCONNECT => {
-				let rlp = UntrustedRlp::new(&packet.data[1..]);
+				let rlp = UntrustedRlp::new(&data[1..]);
 				let reason: u8 = try!(rlp.val_at(0));
 				if self.had_hello {
 					debug!("Disconnected: {}: {:?}", self.token(), DisconnectReason::from_u8(reason));
@@ -370,7 +468,8 @@ impl Session {
 				// map to protocol
 				let protocol = self.info.capabilities[i].protocol;
 				let pid = packet_id - self.info.capabilities[i].id_offset;
-				Ok(SessionData::Packet { data: packet.data, protocol: protocol, packet_id: pid } )
+				self.packets.inc_recv(protocol, pid, size);
+				Ok(SessionData::Packet { data: data, protocol: protocol, packet_id: pid } )
 			},
 			_ => {
 				debug!(target: "network", "Unknown packet: {:?}", packet_id);
@@ -437,10 +536,13 @@ impl Session {
 			trace!(target: "network", "No common capabilities with peer.");
 			return Err(From::from(self.disconnect(io, DisconnectReason::UselessPeer)));
 		}
-		if protocol != host.protocol_version {
+		if protocol < MIN_PROTOCOL_VERSION {
 			trace!(target: "network", "Peer protocol version mismatch: {}", protocol);
 			return Err(From::from(self.disconnect(io, DisconnectReason::UselessPeer)));
 		}
+		self.info.protocol_version = protocol;
+		self.info.connected_ns = time::precise_time_ns();
+		self.compression = protocol >= SNAPPY_PROTOCOL_VERSION && host.protocol_version >= SNAPPY_PROTOCOL_VERSION;
 		self.had_hello = true;
 		Ok(())
 	}
@@ -476,16 +578,89 @@ impl Session {
 		Ok(rlp)
 	}
 
+	// compress packet payload, leaving the packet id intact.
+	fn compress(packet: Vec<u8>) -> Result<Vec<u8>, NetworkError> {
+		if packet.len() - 1 > MAX_PAYLOAD_SIZE {
+			return Err(NetworkError::OversizedPacket);
+		}
+		let mut compressed = Vec::with_capacity(1 + snappy::max_compressed_len(packet.len() - 1));
+		compressed.push(packet[0]);
+		compressed.extend_from_slice(&snappy::compress(&packet[1..]));
+		Ok(compressed)
+	}
+
+	// decompress packet payload, checking its declared size first.
+	fn decompress(packet: Vec<u8>) -> Result<Vec<u8>, NetworkError> {
+		let len = try!(snappy::decompressed_len(&packet[1..]).map_err(|_| NetworkError::BadProtocol));
+		if len > MAX_PAYLOAD_SIZE {
+			return Err(NetworkError::OversizedPacket);
+		}
+		let payload = try!(snappy::decompress(&packet[1..]).map_err(|_| NetworkError::BadProtocol));
+		let mut decompressed = Vec::with_capacity(1 + payload.len());
+		decompressed.push(packet[0]);
+		decompressed.extend_from_slice(&payload);
+		Ok(decompressed)
+	}
+
 	fn send<Message>(&mut self, io: &IoContext<Message>, rlp: RlpStream) -> Result<(), NetworkError> where Message: Send + Sync + Clone {
 		match self.state {
 			State::Handshake(_) => {
 				warn!(target:"network", "Unexpected send request");
 			},
 			State::Session(ref mut s) => {
-				try!(s.send_packet(io, &rlp.out()))
+				let packet = match self.compression {
+					true => try!(Session::compress(rlp.out())),
+					false => rlp.out(),
+				};
+				self.info.bytes_sent += EncryptedConnection::packet_size(packet.len()) as u64;
+				try!(s.send_packet(io, &packet))
 			},
 		}
 		Ok(())
 	}
 }
 
+impl Drop for Session {
+	fn drop(&mut self) {
+		self.stats.add_closed_packets(&self.packets);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Session, MAX_PAYLOAD_SIZE};
+	use error::NetworkError;
+
+	#[test]
+	fn compressed_packet_roundtrip() {
+		// given
+		let packet = vec![0x10u8; 1024];
+
+		// when
+		let compressed = Session::compress(packet.clone()).unwrap();
+
+		// then
+		assert_eq!(compressed[0], 0x10);
+		assert!(compressed.len() < packet.len());
+		assert_eq!(Session::decompress(compressed).unwrap(), packet);
+	}
+
+	#[test]
+	fn rejects_oversized_packets() {
+		// given
+		let packet = vec![0x10u8; MAX_PAYLOAD_SIZE + 2];
+		// snappy preamble declaring 32MB of uncompressed data.
+		let bomb = vec![0x10u8, 0x80, 0x80, 0x80, 0x10];
+
+		// then
+		match Session::compress(packet) {
+			Err(NetworkError::OversizedPacket) => {},
+			_ => panic!("Oversized packet should not be compressed"),
+		}
+		match Session::decompress(bomb) {
+			Err(NetworkError::OversizedPacket) => {},
+			_ => panic!("Oversized packet should not be decompressed"),
+		}
+	}
+}
+
@@ -16,9 +16,69 @@
 
 //! Network Statistics
 use std::sync::atomic::*;
+use std::collections::BTreeMap;
+use parking_lot::Mutex;
+use host::{ProtocolId, PacketId};
+use bandwidth::RateLimiter;
+
+/// Traffic counters for a single subprotocol packet type.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct PacketStats {
+	/// Number of packets sent
+	pub sent_packets: u64,
+	/// Bytes sent, including framing
+	pub sent_bytes: u64,
+	/// Number of packets received
+	pub received_packets: u64,
+	/// Bytes received, including framing
+	pub received_bytes: u64,
+}
+
+impl PacketStats {
+	fn add(&mut self, other: &PacketStats) {
+		self.sent_packets += other.sent_packets;
+		self.sent_bytes += other.sent_bytes;
+		self.received_packets += other.received_packets;
+		self.received_bytes += other.received_bytes;
+	}
+}
+
+/// Traffic counters by subprotocol and packet id. Owned by a single session so that
+/// accounting a packet does not contend with other sessions.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct PacketCounters {
+	packets: BTreeMap<(ProtocolId, PacketId), PacketStats>,
+}
+
+impl PacketCounters {
+	/// Account a subprotocol packet sent.
+	pub fn inc_send(&mut self, protocol: ProtocolId, packet_id: PacketId, size: usize) {
+		let stats = self.packets.entry((protocol, packet_id)).or_insert_with(PacketStats::default);
+		stats.sent_packets += 1;
+		stats.sent_bytes += size as u64;
+	}
+
+	/// Account a subprotocol packet received.
+	pub fn inc_recv(&mut self, protocol: ProtocolId, packet_id: PacketId, size: usize) {
+		let stats = self.packets.entry((protocol, packet_id)).or_insert_with(PacketStats::default);
+		stats.received_packets += 1;
+		stats.received_bytes += size as u64;
+	}
+
+	/// Add all counters of `other` to this one.
+	pub fn merge(&mut self, other: &PacketCounters) {
+		for (key, stats) in &other.packets {
+			self.packets.entry(*key).or_insert_with(PacketStats::default).add(stats);
+		}
+	}
+
+	/// Get traffic by subprotocol and packet id.
+	pub fn to_vec(&self) -> Vec<(ProtocolId, PacketId, PacketStats)> {
+		self.packets.iter().map(|(&(protocol, packet_id), stats)| (protocol, packet_id, stats.clone())).collect()
+	}
+}
 
 /// Network statistics structure
-#[derive(Default, Debug)]
 pub struct NetworkStats {
 	/// Bytes received
 	recv: AtomicUsize,
@@ -26,6 +86,20 @@ pub struct NetworkStats {
 	send: AtomicUsize,
 	/// Total number of sessions created
 	sessions: AtomicUsize,
+	/// Traffic by subprotocol and packet id of sessions that are gone
+	closed_packets: Mutex<PacketCounters>,
+	/// Upload limit for all connections
+	upload: RateLimiter,
+	/// Download limit for all connections
+	download: RateLimiter,
+	/// Limit for responses to data requests from peers
+	serve: RateLimiter,
+}
+
+impl Default for NetworkStats {
+	fn default() -> Self {
+		NetworkStats::new()
+	}
 }
 
 impl NetworkStats {
@@ -47,6 +121,11 @@ impl NetworkStats {
 		self.sessions.fetch_add(1, Ordering::Relaxed);
 	}
 
+	/// Keep the packet counters of a session that is going away.
+	pub fn add_closed_packets(&self, packets: &PacketCounters) {
+		self.closed_packets.lock().merge(packets);
+	}
+
 	/// Get bytes sent.
 	#[inline]
 	pub fn send(&self) -> usize {
@@ -65,12 +144,60 @@ impl NetworkStats {
 		self.sessions.load(Ordering::Relaxed)
 	}
 
+	/// Get traffic by subprotocol and packet id of closed sessions.
+	pub fn closed_packets(&self) -> PacketCounters {
+		self.closed_packets.lock().clone()
+	}
+
+	/// Upload limiter shared by all connections.
+	pub fn upload(&self) -> &RateLimiter {
+		&self.upload
+	}
+
+	/// Download limiter shared by all connections.
+	pub fn download(&self) -> &RateLimiter {
+		&self.download
+	}
+
+	/// Limiter for serving data requested by peers, on top of the upload limit.
+	pub fn serve(&self) -> &RateLimiter {
+		&self.serve
+	}
+
 	/// Create a new empty instance.
 	pub fn new() -> NetworkStats {
 		NetworkStats {
 			recv: AtomicUsize::new(0),
 			send: AtomicUsize::new(0),
 			sessions: AtomicUsize::new(0),
+			closed_packets: Mutex::new(PacketCounters::default()),
+			upload: RateLimiter::new(0),
+			download: RateLimiter::new(0),
+			serve: RateLimiter::new(0),
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{NetworkStats, PacketStats, PacketCounters};
+
+	#[test]
+	fn counts_packets() {
+		let stats = NetworkStats::new();
+		let mut session1 = PacketCounters::default();
+		let mut session2 = PacketCounters::default();
+		session1.inc_send(*b"eth", 4, 100);
+		session2.inc_send(*b"eth", 4, 50);
+		session1.inc_recv(*b"eth", 3, 20);
+		session2.inc_recv(*b"par", 0, 10);
+		stats.add_closed_packets(&session1);
+		stats.add_closed_packets(&session2);
+
+		assert_eq!(stats.closed_packets().to_vec(), vec![
+			(*b"eth", 3, PacketStats { sent_packets: 0, sent_bytes: 0, received_packets: 1, received_bytes: 20 }),
+			(*b"eth", 4, PacketStats { sent_packets: 2, sent_bytes: 150, received_packets: 0, received_bytes: 0 }),
+			(*b"par", 0, PacketStats { sent_packets: 0, sent_bytes: 0, received_packets: 1, received_bytes: 10 }),
+		]);
+	}
+}
@@ -223,6 +223,13 @@ impl JournalDB for ArchiveDB {
 
 	fn is_pruned(&self) -> bool { false }
 
+	fn revert_to_era(&mut self, batch: &mut DBTransaction, era: u64) {
+		if self.latest_era.map_or(false, |e| era < e) {
+			batch.put(self.column, &LATEST_ERA_KEY, &encode(&era));
+			self.latest_era = Some(era);
+		}
+	}
+
 	fn backing(&self) -> &Arc<Database> {
 		&self.backing
 	}
@@ -484,6 +491,30 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn revert_to_era() {
+		let temp = ::devtools::RandomTempPath::new();
+
+		{
+			let mut jdb = new_db(temp.as_path().as_path());
+			jdb.insert(b"foo");
+			jdb.commit_batch(0, &b"0".sha3(), None).unwrap();
+			jdb.insert(b"bar");
+			jdb.commit_batch(1, &b"1".sha3(), None).unwrap();
+			assert_eq!(jdb.latest_era(), Some(1));
+
+			let mut batch = jdb.backing().transaction();
+			jdb.revert_to_era(&mut batch, 0);
+			jdb.backing().write(batch).unwrap();
+			assert_eq!(jdb.latest_era(), Some(0));
+		}
+
+		{
+			let jdb = new_db(temp.as_path().as_path());
+			assert_eq!(jdb.latest_era(), Some(0));
+		}
+	}
+
 	#[test]
 	fn inject() {
 		let temp = ::devtools::RandomTempPath::new();
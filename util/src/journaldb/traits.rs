@@ -54,6 +54,10 @@ pub trait JournalDB: HashDB {
 	/// Whether this database is pruned.
 	fn is_pruned(&self) -> bool { true }
 
+	/// Move the latest era back to `era` after the canonical chain was rewound.
+	/// Journalled databases keep recent eras as possible forks, so by default nothing needs to be done.
+	fn revert_to_era(&mut self, _batch: &mut DBTransaction, _era: u64) {}
+
 	/// Get backing database.
 	fn backing(&self) -> &Arc<Database>;
 
@@ -197,6 +197,57 @@ impl<'a> Iterator for DatabaseIterator {
 	}
 }
 
+/// Iterator over flushed data with the unflushed changes applied, in ascending key order.
+struct OverlayIterator<I: Iterator<Item=(Box<[u8]>, Box<[u8]>)>> {
+	flushed: ::std::iter::Peekable<I>,
+	// `None` for deleted keys.
+	overlay: ::std::iter::Peekable<::std::collections::btree_map::IntoIter<Box<[u8]>, Option<Box<[u8]>>>>,
+}
+
+impl<I: Iterator<Item=(Box<[u8]>, Box<[u8]>)>> Iterator for OverlayIterator<I> {
+	type Item = (Box<[u8]>, Box<[u8]>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let order = match (self.flushed.peek(), self.overlay.peek()) {
+				(None, None) => return None,
+				(Some(_), None) => Ordering::Less,
+				(None, Some(_)) => Ordering::Greater,
+				(Some(&(ref flushed, _)), Some(&(ref overlay, _))) => flushed.cmp(overlay),
+			};
+
+			match order {
+				Ordering::Less => return self.flushed.next(),
+				// the unflushed change replaces the flushed value.
+				Ordering::Equal => { self.flushed.next(); },
+				Ordering::Greater => {},
+			}
+
+			if let Some((key, Some(value))) = self.overlay.next() {
+				return Some((key, value));
+			}
+		}
+	}
+}
+
+/// Options for the column family `col`.
+fn col_options(config: &DatabaseConfig, col: u32) -> Options {
+	// default cache size for columns not specified.
+	const DEFAULT_CACHE: usize = 2;
+
+	let mut opts = Options::new();
+	opts.set_compaction_style(DBCompactionStyle::DBUniversalCompaction);
+	opts.set_target_file_size_base(config.compaction.initial_file_size);
+	opts.set_target_file_size_multiplier(config.compaction.file_size_multiplier);
+
+	let cache_size = config.cache_sizes.get(&Some(col)).cloned().unwrap_or(DEFAULT_CACHE);
+	let mut block_opts = BlockBasedOptions::new();
+	// all goes to read cache.
+	block_opts.set_cache(Cache::new(cache_size * 1024 * 1024));
+	opts.set_block_based_table_factory(&block_opts);
+	opts
+}
+
 struct DBAndColumns {
 	db: DB,
 	cfs: Vec<Column>,
@@ -219,9 +270,6 @@ impl Database {
 
 	/// Open database file. Creates if it does not exist.
 	pub fn open(config: &DatabaseConfig, path: &str) -> Result<Database, String> {
-		// default cache size for columns not specified.
-		const DEFAULT_CACHE: usize = 2;
-
 		let mut opts = Options::new();
 		if let Some(rate_limit) = config.compaction.write_rate_limit {
 			try!(opts.set_parsed_options(&format!("rate_limiter_bytes_per_sec={}", rate_limit)));
@@ -239,26 +287,7 @@ impl Database {
 		opts.set_target_file_size_base(config.compaction.initial_file_size);
 		opts.set_target_file_size_multiplier(config.compaction.file_size_multiplier);
 
-		let mut cf_options = Vec::with_capacity(config.columns.unwrap_or(0) as usize);
-
-		for col in 0 .. config.columns.unwrap_or(0) {
-			let mut opts = Options::new();
-			opts.set_compaction_style(DBCompactionStyle::DBUniversalCompaction);
-			opts.set_target_file_size_base(config.compaction.initial_file_size);
-			opts.set_target_file_size_multiplier(config.compaction.file_size_multiplier);
-
-			let col_opt = config.columns.map(|_| col);
-
-			{
-				let cache_size = config.cache_sizes.get(&col_opt).cloned().unwrap_or(DEFAULT_CACHE);
-				let mut block_opts = BlockBasedOptions::new();
-				// all goes to read cache.
-				block_opts.set_cache(Cache::new(cache_size * 1024 * 1024));
-				opts.set_block_based_table_factory(&block_opts);
-			}
-
-			cf_options.push(opts);
-		}
+		let cf_options: Vec<_> = (0 .. config.columns.unwrap_or(0)).map(|col| col_options(config, col)).collect();
 
 		let mut write_opts = WriteOptions::new();
 		if !config.wal {
@@ -459,6 +488,54 @@ impl Database {
 		}
 	}
 
+	/// Get database iterator over keys starting with given prefix, in ascending order.
+	/// Changes written with `write_buffered` and not flushed yet are included.
+	pub fn iter_from_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Box<Iterator<Item=(Box<[u8]>, Box<[u8]>)>> {
+		let prefix = prefix.to_vec();
+		match *self.db.read() {
+			Some(DBAndColumns { ref db, ref cfs }) => {
+				let overlay: BTreeMap<Box<[u8]>, Option<Box<[u8]>>> = self.overlay.read()[Self::to_overlay_column(col)].iter()
+					.filter(|&(key, _)| key.starts_with(&prefix))
+					.map(|(key, state)| {
+						let value = match *state {
+							KeyState::Insert(ref value) | KeyState::InsertCompressed(ref value) => Some(value.clone().into_boxed_slice()),
+							KeyState::Delete => None,
+						};
+						(key.to_vec().into_boxed_slice(), value)
+					})
+					.collect();
+				let iter = col.map_or_else(|| db.iterator(IteratorMode::From(&prefix, Direction::Forward)),
+					|c| db.iterator_cf(cfs[c as usize], IteratorMode::From(&prefix, Direction::Forward)).unwrap());
+				Box::new(OverlayIterator {
+					flushed: iter.take_while(move |&(ref k, _)| k.starts_with(&prefix)).peekable(),
+					overlay: overlay.into_iter().peekable(),
+				})
+			},
+			None => Box::new(::std::iter::empty()),
+		}
+	}
+
+	/// Number of columns in the database, not counting the default one.
+	pub fn num_columns(&self) -> u32 {
+		self.db.read().as_ref().map_or(0, |db| db.cfs.len() as u32)
+	}
+
+	/// Append a new column to the database. Existing data is left in place.
+	pub fn add_column(&mut self) -> Result<(), String> {
+		let columns = match *self.db.write() {
+			Some(DBAndColumns { ref mut db, ref mut cfs }) => {
+				let col = cfs.len() as u32;
+				let cf = try!(db.create_cf(&format!("col{}", col), &col_options(&self.config, col)));
+				cfs.push(cf);
+				col + 1
+			}
+			None => return Err("Database is closed".into()),
+		};
+		self.overlay.write().push(HashMap::new());
+		self.config.columns = Some(columns);
+		Ok(())
+	}
+
 	/// Close the database
 	fn close(&self) {
 		*self.db.write() = None;
@@ -564,6 +641,28 @@ mod tests {
 		db.flush().unwrap();
 		assert!(db.get(None, &key3).unwrap().is_none());
 		assert_eq!(&*db.get(None, &key1).unwrap().unwrap(), b"horse");
+
+		let prefixed: Vec<_> = db.iter_from_prefix(None, &key2[0..1]).collect();
+		assert_eq!(prefixed.len(), 1);
+		assert_eq!(&*prefixed[0].0, &*key2);
+		assert_eq!(db.iter_from_prefix(None, &key3[0..1]).count(), 0);
+
+		let key4 = H256::from_str("03d69be41d0b7e40352fc85be1cd65eb03d40ef8427a0ca4596b1ead9a00e9fc").unwrap();
+		let mut transaction = db.transaction();
+		transaction.put(None, &key1, b"cow");
+		transaction.delete(None, &key2);
+		transaction.put(None, &key3, b"bird");
+		transaction.put(None, &key4, b"fish");
+		db.write_buffered(transaction);
+
+		let prefixed: Vec<_> = db.iter_from_prefix(None, &key1[0..1]).collect();
+		assert_eq!(prefixed.len(), 1);
+		assert_eq!(&*prefixed[0].1, b"cow");
+		let prefixed: Vec<_> = db.iter_from_prefix(None, &key2[0..1]).collect();
+		assert_eq!(prefixed.len(), 1);
+		assert_eq!(&*prefixed[0].0, &*key4);
+		assert_eq!(&*prefixed[0].1, b"fish");
+		assert_eq!(db.iter_from_prefix(None, &key3[0..1]).count(), 1);
 	}
 
 	#[test]
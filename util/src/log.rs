@@ -17,12 +17,13 @@
 //! Common log helper functions
 
 use std::env;
-use rlog::LogLevelFilter;
+use std::str::FromStr;
+use rlog::{LogLevel, LogLevelFilter, MaxLogLevelFilter};
 use env_logger::LogBuilder;
 use arrayvec::ArrayVec;
 pub use ansi_term::{Colour, Style};
 
-use parking_lot::{RwLock, RwLockReadGuard};
+use parking_lot::{Mutex, RwLock, RwLockReadGuard};
 
 lazy_static! {
 	static ref LOG_DUMMY: bool = {
@@ -47,33 +48,133 @@ pub fn init_log() {
 
 const LOG_SIZE : usize = 128;
 
+/// Log level for targets starting with `name`, or for all targets if there's no name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogDirective {
+	/// Target prefix
+	pub name: Option<String>,
+	/// Most verbose level logged
+	pub level: LogLevelFilter,
+}
+
+impl FromStr for LogDirective {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut parts = s.splitn(2, '=');
+		let (name, level) = match (parts.next().map(str::trim), parts.next().map(str::trim)) {
+			(Some(name), Some(level)) => {
+				let level = try!(level.parse().map_err(|_| format!("Invalid log level: {}", level)));
+				(Some(name.to_owned()), level)
+			},
+			// a bare level applies to all targets, a bare name enables everything for that target
+			(Some(part), None) => match part.parse() {
+				Ok(level) => (None, level),
+				Err(_) => (Some(part.to_owned()), LogLevelFilter::Trace),
+			},
+			_ => return Err(format!("Invalid log directive: {}", s)),
+		};
+		Ok(LogDirective {
+			name: name,
+			level: level,
+		})
+	}
+}
+
+/// Parses `RUST_LOG`-style directives, e.g. `info,sync=trace,network`.
+/// Regex filters after `/` are not supported and ignored.
+pub fn parse_log_directives(spec: &str) -> Result<Vec<LogDirective>, String> {
+	let spec = spec.split('/').next().unwrap_or("");
+	spec.split(',').map(str::trim).filter(|d| !d.is_empty()).map(str::parse).collect()
+}
+
 /// Logger implementation that keeps up to `LOG_SIZE` log elements.
 pub struct RotatingLogger {
 	/// Defined logger levels
-	levels: String,
+	levels: RwLock<String>,
+	/// Levels in effect, later directives override earlier ones for the same target
+	directives: RwLock<Vec<LogDirective>>,
+	/// Global max level handle of the installed logger
+	max_level: Mutex<Option<MaxLogLevelFilter>>,
 	/// Logs array. Latest log is always at index 0
 	logs: RwLock<ArrayVec<[String; LOG_SIZE]>>,
 }
 
 impl RotatingLogger {
 
-	/// Creates new `RotatingLogger` with given levels.
-	/// It does not enforce levels - it's just read only.
+	/// Creates new `RotatingLogger` with given levels. Invalid directives are ignored.
 	pub fn new(levels: String) -> Self {
+		let directives = levels.split(',').filter_map(|d| d.parse().ok()).collect();
+		RotatingLogger::with_directives(levels, directives)
+	}
+
+	/// Creates new `RotatingLogger` reporting `levels` and enforcing `directives`.
+	pub fn with_directives(levels: String, directives: Vec<LogDirective>) -> Self {
 		RotatingLogger {
-			levels: levels,
+			levels: RwLock::new(levels),
+			directives: RwLock::new(directives),
+			max_level: Mutex::new(None),
 			logs: RwLock::new(ArrayVec::<[_; LOG_SIZE]>::new()),
 		}
 	}
 
+	/// Checks whether a record with given level and target should be logged.
+	/// The directive with the longest matching target prefix decides.
+	pub fn enabled(&self, level: LogLevel, target: &str) -> bool {
+		let directives = self.directives.read();
+		let mut best: Option<&LogDirective> = None;
+		for directive in directives.iter() {
+			let matches = directive.name.as_ref().map_or(true, |name| target.starts_with(name.as_str()));
+			let longer = best.map_or(true, |b| directive.name.as_ref().map_or(0, |n| n.len()) >= b.name.as_ref().map_or(0, |n| n.len()));
+			if matches && longer {
+				best = Some(directive);
+			}
+		}
+		best.map_or(false, |d| level <= d.level)
+	}
+
+	/// Most verbose level enabled for any target.
+	pub fn max_level(&self) -> LogLevelFilter {
+		self.directives.read().iter().map(|d| d.level).max().unwrap_or(LogLevelFilter::Off)
+	}
+
+	/// Registers the global max level handle, kept in sync with the directives from now on.
+	pub fn set_max_level_filter(&self, filter: MaxLogLevelFilter) {
+		filter.set(self.max_level());
+		*self.max_level.lock() = Some(filter);
+	}
+
+	/// Applies `RUST_LOG`-style directives on top of the current ones.
+	pub fn set_levels(&self, spec: &str) -> Result<(), String> {
+		let new = try!(parse_log_directives(spec));
+		{
+			let mut directives = self.directives.write();
+			for directive in new {
+				directives.retain(|d| d.name != directive.name);
+				directives.push(directive);
+			}
+		}
+		{
+			let mut levels = self.levels.write();
+			if !levels.is_empty() && !levels.ends_with(',') {
+				levels.push(',');
+			}
+			levels.push_str(spec);
+		}
+		if let Some(ref filter) = *self.max_level.lock() {
+			filter.set(self.max_level());
+		}
+		Ok(())
+	}
+
 	/// Append new log entry
 	pub fn append(&self, log: String) {
 		self.logs.write().insert(0, log);
 	}
 
 	/// Return levels
-	pub fn levels(&self) -> &str {
-		&self.levels
+	pub fn levels(&self) -> String {
+		self.levels.read().clone()
 	}
 
 	/// Return logs
@@ -85,7 +186,8 @@ impl RotatingLogger {
 
 #[cfg(test)]
 mod test {
-	use super::RotatingLogger;
+	use rlog::{LogLevel, LogLevelFilter};
+	use super::{RotatingLogger, LogDirective, parse_log_directives};
 
 	fn logger() -> RotatingLogger {
 		RotatingLogger::new("test".to_owned())
@@ -118,5 +220,43 @@ mod test {
 		assert_eq!(logs[1], "a".to_owned());
 		assert_eq!(logs.len(), 2);
 	}
+
+	#[test]
+	fn should_parse_directives() {
+		assert_eq!(parse_log_directives("info,sync=trace, network").unwrap(), vec![
+			LogDirective { name: None, level: LogLevelFilter::Info },
+			LogDirective { name: Some("sync".into()), level: LogLevelFilter::Trace },
+			LogDirective { name: Some("network".into()), level: LogLevelFilter::Trace },
+		]);
+		assert!(parse_log_directives("sync=loud").is_err());
+	}
+
+	#[test]
+	fn should_use_most_specific_directive() {
+		// given
+		let logger = RotatingLogger::new("info,sync=warn,sync::chain=debug".to_owned());
+
+		// then
+		assert!(logger.enabled(LogLevel::Info, "miner"));
+		assert!(!logger.enabled(LogLevel::Info, "sync"));
+		assert!(logger.enabled(LogLevel::Debug, "sync::chain"));
+		assert_eq!(logger.max_level(), LogLevelFilter::Debug);
+	}
+
+	#[test]
+	fn should_change_levels_at_runtime() {
+		// given
+		let logger = RotatingLogger::new("info".to_owned());
+
+		// when
+		logger.set_levels("sync=trace").unwrap();
+		logger.set_levels("warn").unwrap();
+
+		// then
+		assert!(logger.enabled(LogLevel::Trace, "sync"));
+		assert!(!logger.enabled(LogLevel::Info, "miner"));
+		assert_eq!(logger.levels(), "info,sync=trace,warn");
+		assert!(logger.set_levels("sync=loud").is_err());
+	}
 }
 
@@ -122,6 +122,9 @@ pub trait Migration: 'static {
 	fn columns(&self) -> Option<u32>;
 	/// Version of the database after the migration.
 	fn version(&self) -> u32;
+	/// Whether the migration changes existing data. Migrations which only add
+	/// columns are applied to the database in place rather than copying it.
+	fn alters_existing(&self) -> bool { true }
 	/// Migrate a source to a destination.
 	fn migrate(&mut self, source: &Database, config: &Config, destination: &mut Database, col: Option<u32>) -> Result<(), Error>;
 }
@@ -155,6 +158,38 @@ impl<T: SimpleMigration> Migration for T {
 	}
 }
 
+/// A migration which changes the number of columns, leaving all existing data unchanged.
+///
+/// Adding columns is done in place. Copying is only needed when columns are removed.
+pub struct ChangeColumns {
+	/// The amount of columns before this migration.
+	pub pre_columns: Option<u32>,
+	/// The amount of columns after this migration.
+	pub post_columns: Option<u32>,
+	/// The version after this migration.
+	pub version: u32,
+}
+
+impl Migration for ChangeColumns {
+	fn pre_columns(&self) -> Option<u32> { self.pre_columns }
+	fn columns(&self) -> Option<u32> { self.post_columns }
+	fn version(&self) -> u32 { self.version }
+
+	fn alters_existing(&self) -> bool {
+		self.post_columns.unwrap_or(0) < self.pre_columns.unwrap_or(0)
+	}
+
+	fn migrate(&mut self, source: &Database, config: &Config, dest: &mut Database, col: Option<u32>) -> Result<(), Error> {
+		let mut batch = Batch::new(config, col);
+
+		for (key, value) in source.iter(col) {
+			try!(batch.insert(key.to_vec(), value.to_vec(), dest));
+		}
+
+		batch.commit(dest)
+	}
+}
+
 /// Get the path where all databases reside.
 fn database_path(path: &Path) -> PathBuf {
 	let mut temp_path = path.to_owned();
@@ -217,13 +252,18 @@ impl Manager {
 	}
 
 	/// Performs migration in order, starting with a source path, migrating between two temporary databases,
-	/// and producing a path where the final migration lives.
+	/// and producing a path where the final migration lives. Migrations which don't alter existing data
+	/// are applied in place, so the returned path is `old_path` when no copy was needed at all.
 	pub fn execute(&mut self, old_path: &Path, version: u32) -> Result<PathBuf, Error> {
 		let config = self.config.clone();
 		let migrations = self.migrations_from(version);
 		if migrations.is_empty() { return Err(Error::MigrationImpossible) };
 
-		let columns = migrations.iter().find(|m| m.version() == version).and_then(|m| m.pre_columns());
+		let columns = migrations.get(0).and_then(|m| m.pre_columns());
+		// an interrupted migration might have already added columns in place
+		// without updating the version, so the database is opened with any of them.
+		let mut possible_columns = vec![columns];
+		possible_columns.extend(migrations.iter().take_while(|m| !m.alters_existing()).map(|m| m.columns()));
 
 		let mut db_config = DatabaseConfig {
 			max_open_files: 64,
@@ -235,19 +275,27 @@ impl Manager {
 
 		let db_root = database_path(old_path);
 		let mut temp_idx = TempIndex::One;
-		let mut temp_path = temp_idx.path(&db_root);
+		let mut cur_path = old_path.to_owned();
 
 		// start with the old db.
 		let old_path_str = try!(old_path.to_str().ok_or(Error::MigrationImpossible));
-		let mut cur_db = try!(Database::open(&db_config, old_path_str).map_err(Error::Custom));
+		let mut cur_db = try!(Self::open_with_any_columns(&mut db_config, old_path_str, possible_columns));
 
 		for migration in migrations {
 			// Change number of columns in new db
 			let current_columns = db_config.columns;
 			db_config.columns = migration.columns();
 
+			if !migration.alters_existing() {
+				// only new columns are needed; create them in the current database.
+				while cur_db.num_columns() < db_config.columns.unwrap_or(0) {
+					try!(cur_db.add_column().map_err(Error::Custom));
+				}
+				continue;
+			}
+
 			// open the target temporary database.
-			temp_path = temp_idx.path(&db_root);
+			let temp_path = temp_idx.path(&db_root);
 			let temp_path_str = try!(temp_path.to_str().ok_or(Error::MigrationImpossible));
 			let mut new_db = try!(Database::open(&db_config, temp_path_str).map_err(Error::Custom));
 
@@ -264,12 +312,29 @@ impl Manager {
 			}
 			// next iteration, we will migrate from this db into the other temp.
 			cur_db = new_db;
+			cur_path = temp_path;
 			temp_idx.swap();
 
 			// remove the other temporary migration database.
 			let _ = fs::remove_dir_all(temp_idx.path(&db_root));
 		}
-		Ok(temp_path)
+		Ok(cur_path)
+	}
+
+	/// Opens the database with the first number of columns it can be opened with.
+	/// Sets that number in the config.
+	fn open_with_any_columns(db_config: &mut DatabaseConfig, path: &str, possible_columns: Vec<Option<u32>>) -> Result<Database, Error> {
+		let mut first_error = None;
+		for columns in possible_columns {
+			db_config.columns = columns;
+			match Database::open(db_config, path) {
+				Ok(db) => return Ok(db),
+				Err(err) => if first_error.is_none() {
+					first_error = Some(err);
+				},
+			}
+		}
+		Err(first_error.map_or(Error::MigrationImpossible, Error::Custom))
 	}
 
 	/// Returns true if migration is needed.
@@ -19,7 +19,7 @@
 //! are performed in temp sub-directories.
 
 use common::*;
-use migration::{Batch, Config, Error, SimpleMigration, Migration, Manager};
+use migration::{Batch, Config, Error, SimpleMigration, Migration, Manager, ChangeColumns};
 use kvdb::Database;
 
 use devtools::RandomTempPath;
@@ -226,3 +226,93 @@ fn pre_columns() {
 	// short of the one before it.
 	manager.execute(&db_path, 0).unwrap();
 }
+
+#[test]
+fn change_columns() {
+	use kvdb::DatabaseConfig;
+
+	let mut manager = Manager::new(Config::default());
+	manager.add_migration(ChangeColumns {
+		pre_columns: None,
+		post_columns: Some(4),
+		version: 1,
+	}).unwrap();
+
+	let dir = RandomTempPath::create_dir();
+	let db_path = db_path(dir.as_path());
+	make_db(&db_path, map![vec![1] => vec![1]]);
+
+	let new_path = manager.execute(&db_path, 0).unwrap();
+
+	// columns are added in place.
+	assert_eq!(new_path, db_path);
+	assert!(!dir.as_path().join("temp_migration_1").exists());
+
+	let config = DatabaseConfig::with_columns(Some(4));
+	let db = Database::open(&config, new_path.to_str().unwrap()).unwrap();
+	assert_eq!(db.num_columns(), 4);
+	assert_eq!(&db.get(None, &[1]).unwrap().unwrap()[..], &[1]);
+	assert!(db.get(Some(3), &[1]).unwrap().is_none());
+}
+
+#[test]
+fn adds_column_to_existing_columns() {
+	use kvdb::DatabaseConfig;
+
+	let mut manager = Manager::new(Config::default());
+	manager.add_migration(ChangeColumns {
+		pre_columns: Some(2),
+		post_columns: Some(3),
+		version: 1,
+	}).unwrap();
+
+	let dir = RandomTempPath::create_dir();
+	let db_path = db_path(dir.as_path());
+	{
+		let db = Database::open(&DatabaseConfig::with_columns(Some(2)), db_path.to_str().unwrap()).unwrap();
+		let mut batch = db.transaction();
+		batch.put(Some(1), &[1], &[2]);
+		db.write(batch).unwrap();
+	}
+
+	let new_path = manager.execute(&db_path, 0).unwrap();
+	assert_eq!(new_path, db_path);
+
+	let db = Database::open(&DatabaseConfig::with_columns(Some(3)), new_path.to_str().unwrap()).unwrap();
+	assert_eq!(&db.get(Some(1), &[1]).unwrap().unwrap()[..], &[2]);
+	assert!(db.get(Some(2), &[1]).unwrap().is_none());
+}
+
+#[test]
+fn resumes_interrupted_in_place_migration() {
+	use kvdb::DatabaseConfig;
+
+	let mut manager = Manager::new(Config::default());
+	manager.add_migration(ChangeColumns {
+		pre_columns: Some(2),
+		post_columns: Some(3),
+		version: 1,
+	}).unwrap();
+	manager.add_migration(ChangeColumns {
+		pre_columns: Some(3),
+		post_columns: Some(4),
+		version: 2,
+	}).unwrap();
+
+	let dir = RandomTempPath::create_dir();
+	let db_path = db_path(dir.as_path());
+	{
+		// the first migration added its column, but the version was not updated.
+		let db = Database::open(&DatabaseConfig::with_columns(Some(3)), db_path.to_str().unwrap()).unwrap();
+		let mut batch = db.transaction();
+		batch.put(Some(1), &[1], &[2]);
+		db.write(batch).unwrap();
+	}
+
+	let new_path = manager.execute(&db_path, 0).unwrap();
+	assert_eq!(new_path, db_path);
+
+	let db = Database::open(&DatabaseConfig::with_columns(Some(4)), new_path.to_str().unwrap()).unwrap();
+	assert_eq!(db.num_columns(), 4);
+	assert_eq!(&db.get(Some(1), &[1]).unwrap().unwrap()[..], &[2]);
+}
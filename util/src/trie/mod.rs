@@ -36,6 +36,8 @@ pub mod sectriedb;
 pub mod sectriedbmut;
 /// Trie query recording.
 pub mod recorder;
+/// Trie proof verification.
+pub mod proof;
 
 
 mod fatdb;
@@ -49,6 +51,7 @@ pub use self::sectriedb::SecTrieDB;
 pub use self::fatdb::{FatDB, FatDBIterator};
 pub use self::fatdbmut::FatDBMut;
 pub use self::recorder::Recorder;
+pub use self::proof::{verify_proof, verify_secure_proof};
 
 /// Trie Errors.
 ///
@@ -233,4 +236,9 @@ impl TrieFactory {
 			TrieSpec::Fat => Ok(Box::new(try!(FatDBMut::from_existing(db, root)))),
 		}
 	}
+
+	/// Returns true iff the trie DB is a fat DB (allows enumeration of keys).
+	pub fn is_fat(&self) -> bool {
+		self.spec == TrieSpec::Fat
+	}
 }
@@ -0,0 +1,106 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Verification of trie proofs.
+//!
+//! A proof is the list of trie nodes visited during a lookup, as collected by a `Recorder`.
+//! Since inline nodes are contained within their parents, the nodes alone are enough
+//! to repeat the lookup without access to the full database.
+
+use hash::H256;
+use sha3::Hashable;
+use hashdb::HashDB;
+use memorydb::MemoryDB;
+use super::{Trie, TrieDB, Result};
+use Bytes;
+
+/// Checks `proof` of `key` against the trie `root`.
+///
+/// Returns the value proven to be stored under the key or `None` if the proof shows
+/// the key is absent. Fails if the proof misses any node needed for the lookup.
+pub fn verify_proof(root: &H256, key: &[u8], proof: &[Bytes]) -> Result<Option<Bytes>> {
+	let mut db = MemoryDB::new();
+	for node in proof {
+		db.insert(node);
+	}
+
+	let trie = try!(TrieDB::new(&db, root));
+	let value = try!(trie.get(key));
+	Ok(value.map(|v| v.to_vec()))
+}
+
+/// Checks `proof` of `key` against the root of a secure trie, which stores values under
+/// hashed keys (e.g. the state trie or account storage tries).
+pub fn verify_secure_proof(root: &H256, key: &[u8], proof: &[Bytes]) -> Result<Option<Bytes>> {
+	verify_proof(root, &key.sha3(), proof)
+}
+
+#[cfg(test)]
+mod tests {
+	use memorydb::MemoryDB;
+	use hash::H256;
+	use super::super::{TrieDB, TrieDBMut, Trie, TrieMut, TrieError};
+	use super::super::recorder::{Recorder, BasicRecorder};
+	use super::verify_proof;
+
+	fn populate(db: &mut MemoryDB, root: &mut H256) {
+		let mut t = TrieDBMut::new(db, root);
+		t.insert(b"dog", b"cat").unwrap();
+		t.insert(b"lunch", b"time").unwrap();
+		t.insert(b"notdog", b"notcat").unwrap();
+		t.insert(b"hotdog", b"hotcat").unwrap();
+		t.insert(b"letter", b"confusion which is too long to be inlined").unwrap();
+		t.insert(b"pirate", b"aargh!").unwrap();
+	}
+
+	fn prove(db: &MemoryDB, root: &H256, key: &[u8]) -> Vec<Vec<u8>> {
+		let trie = TrieDB::new(db, root).unwrap();
+		let mut recorder = BasicRecorder::new();
+		trie.get_recorded(key, &mut recorder).unwrap();
+		recorder.drain().into_iter().map(|r| r.data).collect()
+	}
+
+	#[test]
+	fn should_verify_inclusion_and_exclusion() {
+		let mut db = MemoryDB::new();
+		let mut root = H256::new();
+		populate(&mut db, &mut root);
+
+		let proof = prove(&db, &root, b"pirate");
+		assert_eq!(verify_proof(&root, b"pirate", &proof).unwrap(), Some(b"aargh!".to_vec()));
+
+		let proof = prove(&db, &root, b"dogs");
+		assert_eq!(verify_proof(&root, b"dogs", &proof).unwrap(), None);
+	}
+
+	#[test]
+	fn should_reject_incomplete_proof() {
+		let mut db = MemoryDB::new();
+		let mut root = H256::new();
+		populate(&mut db, &mut root);
+
+		let mut proof = prove(&db, &root, b"letter");
+		proof.pop();
+		assert!(proof.len() > 0);
+		match *verify_proof(&root, b"letter", &proof).unwrap_err() {
+			TrieError::IncompleteDatabase(_) => {},
+			ref e => panic!("unexpected error: {}", e),
+		}
+
+		assert_eq!(*verify_proof(&H256::from(1), b"letter", &proof).unwrap_err(), TrieError::InvalidStateRoot(H256::from(1)));
+	}
+}